@@ -185,4 +185,74 @@ pub fn albums_bulk_add(access_token: &auth::AccessToken, album_id: String, media
     {
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+pub fn albums_bulk_remove(access_token: &auth::AccessToken, album_id: String, media_item_ids: Vec<String>) -> Result<(), GoogleApiError>
+{
+    let url = Url::parse(&format!("https://photoslibrary.googleapis.com/v1/albums/{}:batchRemoveMediaItems", album_id)).unwrap();
+
+    let mut list = List::new();
+    list.append(&format!("Authorization: Bearer {}", urlencoding::encode(&access_token.secret()))).unwrap();
+    list.append("Content-Type: application/json").unwrap();
+
+    let request = AlbumModifyRequest
+    {
+        media_item_ids,
+    };
+
+    let req_body = serde_json::to_vec(&request)?;
+
+    let mut data = Vec::new();
+    let mut handle = Easy::new();
+    handle.url(&url.to_string())?;
+    handle.post(true)?;
+    handle.post_fields_copy(&req_body)?;
+    handle.http_headers(list)?;
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|new_data| {
+            data.extend_from_slice(new_data);
+            Ok(new_data.len())
+        })?;
+        transfer.perform()?;
+    }
+
+    if !data.is_empty()
+    {
+        Err(GoogleApiError::new_unexpected_response(String::from_utf8_lossy(&data).to_string()))
+    }
+    else
+    {
+        Ok(())
+    }
+}
+
+pub fn media_item_download(media_item: &MediaItem) -> Result<Vec<u8>, GoogleApiError>
+{
+    // The base URL needs a suffix to select the original
+    // bytes - "=dv" for videos, or "=d" for photos
+
+    let suffix = if media_item.mime_type.starts_with("video/") { "=dv" } else { "=d" };
+
+    let url = Url::parse(&format!("{}{}", media_item.base_url, suffix)).unwrap();
+
+    let mut data = Vec::new();
+    let mut handle = Easy::new();
+    handle.url(&url.to_string())?;
+    handle.follow_location(true)?;
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|new_data| {
+            data.extend_from_slice(new_data);
+            Ok(new_data.len())
+        })?;
+        transfer.perform()?;
+    }
+
+    if handle.response_code()? != 200
+    {
+        return Err(GoogleApiError::new_unexpected_response(String::from_utf8_lossy(&data).to_string()));
+    }
+
+    Ok(data)
+}
@@ -1,8 +1,10 @@
+use chrono::{DateTime, Duration, Utc};
+use curl::easy::Easy;
 use oauth2::{
     AuthorizationCode, AuthUrl,
     ClientId, ClientSecret, CsrfToken,
     PkceCodeChallenge, PkceCodeVerifier,
-    RedirectUrl, ResponseType,
+    RedirectUrl, RefreshToken, ResponseType,
     Scope, StandardTokenResponse,
     TokenResponse, TokenUrl};
 use oauth2::basic::BasicClient;
@@ -22,7 +24,19 @@ enum ClientState
     StartedNew{client: BasicClient, csrf_token: CsrfToken, pkce_verifier: PkceCodeVerifier},
     GotCallback{client: BasicClient, code: AuthorizationCode, pkce_verifier: PkceCodeVerifier},
     Error{err: String},
-    Done{access_token: AccessToken},
+    Done{access_token: AccessToken, refresh_token: Option<String>, expires_at: Option<DateTime<Utc>>},
+}
+
+/// The status of the currently held token, suitable
+/// for display to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenStatus
+{
+    None,
+    InProgress,
+    Error{err: String},
+    Valid{expires_at: Option<DateTime<Utc>>, refreshable: bool},
+    Expired{refreshable: bool},
 }
 
 pub struct GoogleAuthClient
@@ -40,11 +54,26 @@ impl GoogleAuthClient
         }
     }
 
+    /// Returns the access token, but only if it
+    /// has not yet expired (or is about to expire).
     pub fn access_token(&self) -> Option<AccessToken>
     {
-        if let ClientState::Done{access_token} = &self.state
+        if let ClientState::Done{access_token, expires_at, ..} = &self.state
         {
-            Some(access_token.clone())
+            if !is_expired(expires_at)
+            {
+                return Some(access_token.clone());
+            }
+        }
+
+        None
+    }
+
+    pub fn refresh_token(&self) -> Option<String>
+    {
+        if let ClientState::Done{refresh_token, ..} = &self.state
+        {
+            refresh_token.clone()
         }
         else
         {
@@ -52,14 +81,33 @@ impl GoogleAuthClient
         }
     }
 
+    pub fn status(&self) -> TokenStatus
+    {
+        match &self.state
+        {
+            ClientState::None => TokenStatus::None,
+            ClientState::StartedNew{..}
+                | ClientState::GotCallback{..} => TokenStatus::InProgress,
+            ClientState::Error{err} => TokenStatus::Error{ err: err.clone() },
+            ClientState::Done{refresh_token, expires_at, ..} =>
+            {
+                let refreshable = refresh_token.is_some();
+
+                if is_expired(expires_at)
+                {
+                    TokenStatus::Expired{ refreshable }
+                }
+                else
+                {
+                    TokenStatus::Valid{ expires_at: *expires_at, refreshable }
+                }
+            },
+        }
+    }
+
     pub fn start_new(&mut self, setup: GoogleAuthSetup) -> String
     {
-        let client = BasicClient::new(
-            ClientId::new(setup.client_id),
-            Some(ClientSecret::new(setup.client_secret)),
-            AuthUrl::from_url(Url::parse("https://accounts.google.com/o/oauth2/v2/auth").unwrap()),
-            Some(TokenUrl::from_url(Url::parse("https://oauth2.googleapis.com/token").unwrap())))
-            .set_redirect_uri(RedirectUrl::from_url(Url::parse(&setup.redirect_url).unwrap()));
+        let client = create_client(setup);
 
         // Generate a PKCE challenge.
         let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
@@ -75,6 +123,8 @@ impl GoogleAuthClient
             .set_response_type(&ResponseType::new("code".to_owned()))
             .add_extra_param("access_type", "offline")
             .add_extra_param("include_granted_scopes", "true")
+            // Always ask for consent, so that a refresh token is returned
+            .add_extra_param("prompt", "consent")
             .url();
 
         self.state = ClientState::StartedNew{ client, csrf_token, pkce_verifier };
@@ -135,9 +185,117 @@ impl GoogleAuthClient
         }
     }
 
+    /// Creates an operation to obtain a new access token from
+    /// a refresh token - either the one currently held, or one
+    /// that has been restored from persistent storage.
+    pub fn refresh(&mut self, setup: GoogleAuthSetup, stored_refresh_token: Option<String>) -> RefreshOperation
+    {
+        let refresh_token = self.refresh_token().or(stored_refresh_token);
+
+        match refresh_token
+        {
+            Some(refresh_token) =>
+            {
+                RefreshOperation::Continue{ client: create_client(setup), refresh_token: RefreshToken::new(refresh_token) }
+            },
+            None =>
+            {
+                RefreshOperation::Error{ err: "No refresh token is available - please login again".to_owned() }
+            },
+        }
+    }
+
+    /// Forgets the current token, and returns an operation
+    /// to revoke the refresh token (or if none, the access token)
+    /// with the authentication server.
+    pub fn revoke(&mut self, stored_refresh_token: Option<String>) -> RevokeOperation
+    {
+        let token = match &self.state
+        {
+            ClientState::Done{refresh_token: Some(refresh_token), ..} => Some(refresh_token.clone()),
+            ClientState::Done{access_token, ..} => stored_refresh_token.or(Some(access_token.secret())),
+            _ => stored_refresh_token,
+        };
+
+        self.state = ClientState::None;
+
+        RevokeOperation{ token }
+    }
+
     pub fn save_token(&mut self, response: GoogleAuthTokenReponse)
     {
-        self.state = ClientState::Done{ access_token: AccessToken{ token: response.response.access_token().secret().clone() }};
+        // Refresh responses don't normally include a new refresh
+        // token - in this case keep using the existing one
+
+        let refresh_token = response.refresh_token()
+            .or(response.used_refresh_token.clone())
+            .or(self.refresh_token());
+
+        let expires_at = response.response.expires_in()
+            .and_then(|d| Duration::from_std(d).ok())
+            .map(|d| Utc::now() + d);
+
+        self.state = ClientState::Done
+        {
+            access_token: AccessToken{ token: response.response.access_token().secret().clone() },
+            refresh_token,
+            expires_at,
+        };
+    }
+
+    /// Saves a token obtained by refreshing - but only if the token
+    /// that was refreshed is still the one held, so that a login
+    /// started or completed meanwhile isn't replaced. Returns
+    /// false if the refreshed token was discarded.
+    pub fn save_refreshed_token(&mut self, response: GoogleAuthTokenReponse) -> bool
+    {
+        let still_current = match &self.state
+        {
+            ClientState::None
+                | ClientState::Error{..} => true,
+            ClientState::StartedNew{..}
+                | ClientState::GotCallback{..} => false,
+            ClientState::Done{refresh_token, expires_at, ..} =>
+            {
+                is_expired(expires_at)
+                    && (refresh_token.is_none() || *refresh_token == response.used_refresh_token)
+            },
+        };
+
+        if still_current
+        {
+            self.save_token(response);
+        }
+
+        still_current
+    }
+
+    pub fn set_error(&mut self, err: String)
+    {
+        self.state = ClientState::Error{ err };
+    }
+}
+
+fn create_client(setup: GoogleAuthSetup) -> BasicClient
+{
+    BasicClient::new(
+        ClientId::new(setup.client_id),
+        Some(ClientSecret::new(setup.client_secret)),
+        AuthUrl::from_url(Url::parse("https://accounts.google.com/o/oauth2/v2/auth").unwrap()),
+        Some(TokenUrl::from_url(Url::parse("https://oauth2.googleapis.com/token").unwrap())))
+        .set_redirect_uri(RedirectUrl::from_url(Url::parse(&setup.redirect_url).unwrap()))
+}
+
+fn is_expired(expires_at: &Option<DateTime<Utc>>) -> bool
+{
+    // Treat tokens that are about to expire as already
+    // expired, so that they aren't used for a long
+    // running operation that will fail part way through
+
+    match expires_at
+    {
+        Some(expires_at) => (*expires_at - Duration::minutes(5)) <= Utc::now(),
+        None => false,
     }
 }
 
@@ -169,7 +327,7 @@ impl ExchangeOperation
                     .set_pkce_verifier(pkce_verifier)
                     .request(http_client)
                     .map_err(|err| GoogleAuthError::Oauth2{err: format!("{}", err)})
-                    .map(|response| GoogleAuthTokenReponse{response})
+                    .map(|response| GoogleAuthTokenReponse{response, used_refresh_token: None})
             },
             ExchangeOperation::Error{err} =>
             {
@@ -179,6 +337,77 @@ impl ExchangeOperation
     }
 }
 
+pub enum RefreshOperation
+{
+    Continue{ client: BasicClient, refresh_token: RefreshToken },
+    Error{ err: String },
+}
+
+impl RefreshOperation
+{
+    pub fn blocking_execute(self) -> Result<GoogleAuthTokenReponse, GoogleAuthError>
+    {
+        match self
+        {
+            RefreshOperation::Continue{client, refresh_token} =>
+            {
+                client.exchange_refresh_token(&refresh_token)
+                    .request(http_client)
+                    .map_err(|err| GoogleAuthError::Oauth2{err: format!("{}", err)})
+                    .map(|response| GoogleAuthTokenReponse{response, used_refresh_token: Some(refresh_token.secret().clone())})
+            },
+            RefreshOperation::Error{err} =>
+            {
+                Err(GoogleAuthError::Other{err})
+            },
+        }
+    }
+}
+
+pub struct RevokeOperation
+{
+    token: Option<String>,
+}
+
+impl RevokeOperation
+{
+    pub fn blocking_execute(self) -> Result<(), GoogleAuthError>
+    {
+        if let Some(token) = self.token
+        {
+            let url = format!("https://oauth2.googleapis.com/revoke?token={}", urlencoding::encode(&token));
+
+            let mut data = Vec::new();
+            let mut handle = Easy::new();
+            let curl_err = |err: curl::Error| GoogleAuthError::Other{err: format!("{}", err)};
+
+            handle.url(&url).map_err(curl_err)?;
+            handle.post(true).map_err(curl_err)?;
+            handle.post_fields_copy(&[]).map_err(curl_err)?;
+            {
+                let mut transfer = handle.transfer();
+                transfer.write_function(|new_data| {
+                    data.extend_from_slice(new_data);
+                    Ok(new_data.len())
+                }).map_err(curl_err)?;
+                transfer.perform().map_err(curl_err)?;
+            }
+
+            let code = handle.response_code().map_err(curl_err)?;
+
+            // A 400 response means the token was already invalid,
+            // which is the outcome we were after anyway
+
+            if code != 200 && code != 400
+            {
+                return Err(GoogleAuthError::Oauth2{err: format!("Revoke failed with HTTP {}: {}", code, String::from_utf8_lossy(&data))});
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub enum GoogleAuthError
 {
@@ -190,6 +419,15 @@ pub enum GoogleAuthError
 pub struct GoogleAuthTokenReponse
 {
     response: StandardTokenResponse<oauth2::EmptyExtraTokenFields, oauth2::basic::BasicTokenType>,
+    used_refresh_token: Option<String>,
+}
+
+impl GoogleAuthTokenReponse
+{
+    pub fn refresh_token(&self) -> Option<String>
+    {
+        self.response.refresh_token().map(|t| t.secret().clone())
+    }
 }
 
 #[derive(Clone)]
@@ -8,6 +8,9 @@ pub struct ReverseGeocode
 {
     pub address: String,
     pub names: HashSet<String>,
+    /// The names of the most specific result, from the
+    /// broadest (usually the country) to the narrowest
+    pub hierarchy: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -67,6 +70,7 @@ pub fn reverse_geocode(api_key: &str, latitude: f64, longitude: f64) -> Result<R
     // Now, collect all useful names
 
     let mut names = HashSet::new();
+    let mut hierarchy = Vec::new();
 
     for r in results.iter()
     {
@@ -74,6 +78,8 @@ pub fn reverse_geocode(api_key: &str, latitude: f64, longitude: f64) -> Result<R
             || types_contains(&r.types, "postal_code")
             || types_contains(&r.types, "street_address")
         {
+            let mut result_names = Vec::new();
+
             for e in r.address_components.iter()
             {
                 if types_wanted(&e.types)
@@ -84,14 +90,31 @@ pub fn reverse_geocode(api_key: &str, latitude: f64, longitude: f64) -> Result<R
                         // Austrlian Admin level 2 are council names, with names like
                         // "Brisbane City" or "Cairns Regional" - we'll just use the short name.
 
-                        names.insert(e.short_name.clone());
+                        result_names.push(e.short_name.clone());
                     }
                     else
                     {
-                        names.insert(e.long_name.clone());
+                        result_names.push(e.long_name.clone());
+                    }
+                }
+            }
+
+            // Address components are listed from the narrowest
+            // to the broadest - the first useful result has
+            // the most detail, so it gives the hierarchy
+
+            if hierarchy.is_empty()
+            {
+                for name in result_names.iter().rev()
+                {
+                    if !hierarchy.contains(name)
+                    {
+                        hierarchy.push(name.clone());
                     }
                 }
             }
+
+            names.extend(result_names);
         }
     }
 
@@ -99,6 +122,7 @@ pub fn reverse_geocode(api_key: &str, latitude: f64, longitude: f64) -> Result<R
     {
         address,
         names,
+        hierarchy,
     })
 }
 
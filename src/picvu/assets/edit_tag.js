@@ -1,5 +1,6 @@
 document.addEventListener('DOMContentLoaded', (event) => {
 
     picvu.register_edit('edit-name');
+    picvu.register_edit('edit-parent');
     
 });
\ No newline at end of file
@@ -0,0 +1,205 @@
+use std::str::FromStr;
+
+use crate::analyse::img::MakeModel;
+use crate::analyse::tz::ExplicitTimezone;
+
+/// Corrects the taken time of photos and videos from a
+/// camera whose clock was set incorrectly, so photos from
+/// several cameras line up with each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockCorrection
+{
+    /// Matched (case-insensitive) against part of the "make model"
+    pub camera: String,
+    /// Only photos taken (before correction) in this range are corrected
+    pub date_range: Option<picvudb::data::DateRange>,
+    /// The amount to add to the camera's time
+    pub offset: chrono::Duration,
+}
+
+impl ClockCorrection
+{
+    pub fn matches(&self, make_model: &MakeModel, taken: &picvudb::data::Date) -> bool
+    {
+        let camera = format!("{} {}", make_model.make.trim(), make_model.model.trim()).to_lowercase();
+
+        if !camera.contains(&self.camera.to_lowercase())
+        {
+            return false;
+        }
+
+        if let Some(date_range) = &self.date_range
+        {
+            let local_date = taken.to_chrono_fixed_offset().naive_local().date();
+
+            if local_date < date_range.first_date()
+                || local_date > date_range.last_date()
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn apply(&self, taken: &picvudb::data::Date) -> picvudb::data::Date
+    {
+        shift_date(taken, self.offset)
+    }
+
+    /// Returns the corrected time using the first matching correction,
+    /// or None if none of the corrections apply.
+    pub fn correct(corrections: &Vec<ClockCorrection>, make_model: &MakeModel, taken: &picvudb::data::Date) -> Option<picvudb::data::Date>
+    {
+        corrections.iter()
+            .find(|c| c.matches(make_model, taken))
+            .map(|c| c.apply(taken))
+    }
+}
+
+/// Shifts the activity time of existing objects, and/or
+/// re-expresses it in a different timezone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeShift
+{
+    pub offset: chrono::Duration,
+    pub timezone: Option<ExplicitTimezone>,
+}
+
+impl TimeShift
+{
+    pub fn apply(&self, date: &picvudb::data::Date) -> picvudb::data::Date
+    {
+        let shifted = shift_date(date, self.offset);
+
+        match &self.timezone
+        {
+            Some(timezone) => timezone.adjust(&shifted),
+            None => shifted,
+        }
+    }
+
+    pub fn is_noop(&self) -> bool
+    {
+        self.offset == chrono::Duration::zero()
+            && self.timezone.is_none()
+    }
+}
+
+fn shift_date(date: &picvudb::data::Date, offset: chrono::Duration) -> picvudb::data::Date
+{
+    match date
+    {
+        picvudb::data::Date::Utc(utc) => picvudb::data::Date::from_chrono_utc(&(*utc + offset)),
+        picvudb::data::Date::FixedOffset(fixed) => picvudb::data::Date::from_chrono_fixed(&(*fixed + offset)),
+    }
+}
+
+/// Parses a correction in the form "camera; offset" or
+/// "camera; offset; date range", where the offset is
+/// "+HH:MM" or "+HH:MM:SS" - e.g. "W300; +00:37; 2019-06".
+impl FromStr for ClockCorrection
+{
+    type Err = ClockCorrectionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let parts = s.split(';').map(|p| p.trim()).collect::<Vec<_>>();
+
+        if parts.len() < 2
+            || parts.len() > 3
+            || parts[0].is_empty()
+        {
+            return Err(ClockCorrectionParseError);
+        }
+
+        let camera = parts[0].to_owned();
+        let offset = parse_offset(parts[1]).ok_or(ClockCorrectionParseError)?;
+
+        let date_range = match parts.get(2)
+        {
+            Some(dr) if !dr.is_empty() => Some(dr.parse().map_err(|_| ClockCorrectionParseError)?),
+            _ => None,
+        };
+
+        Ok(ClockCorrection{ camera, date_range, offset })
+    }
+}
+
+/// Parses a signed duration, as "+HH:MM" or "+HH:MM:SS"
+pub fn parse_offset(s: &str) -> Option<chrono::Duration>
+{
+    let (sign, rest) = if let Some(rest) = s.strip_prefix('+')
+    {
+        (1, rest)
+    }
+    else if let Some(rest) = s.strip_prefix('-')
+    {
+        (-1, rest)
+    }
+    else
+    {
+        return None;
+    };
+
+    let parts = rest.split(':').map(|p| p.parse::<i64>().ok()).collect::<Option<Vec<_>>>()?;
+
+    let (hours, mins, secs) = match parts.as_slice()
+    {
+        [hours, mins] => (*hours, *mins, 0),
+        [hours, mins, secs] => (*hours, *mins, *secs),
+        _ => return None,
+    };
+
+    if hours < 0 || mins < 0 || mins > 59 || secs < 0 || secs > 59
+    {
+        return None;
+    }
+
+    Some(chrono::Duration::seconds(sign * ((hours * 3600) + (mins * 60) + secs)))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClockCorrectionParseError;
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_clock_correction()
+    {
+        let correction = "W300; +00:37; 2019-06".parse::<ClockCorrection>().unwrap();
+        assert_eq!(correction.offset, chrono::Duration::minutes(37));
+
+        let make_model = MakeModel{ make: "NIKON".to_owned(), model: "COOLPIX W300".to_owned() };
+
+        let taken = picvudb::data::Date::from_rfc3339("2019-06-10T09:00:00+10:00").unwrap();
+        assert_eq!(
+            ClockCorrection::correct(&vec![correction.clone()], &make_model, &taken).map(|d| d.to_rfc3339()),
+            Some("2019-06-10T09:37:00+10:00".to_owned()));
+
+        let outside_range = picvudb::data::Date::from_rfc3339("2019-07-01T09:00:00+10:00").unwrap();
+        assert_eq!(ClockCorrection::correct(&vec![correction.clone()], &make_model, &outside_range), None);
+
+        let other_camera = MakeModel{ make: "Apple".to_owned(), model: "iPhone 8".to_owned() };
+        assert_eq!(ClockCorrection::correct(&vec![correction], &other_camera, &taken), None);
+
+        assert_eq!("iPhone; -1:02:03".parse::<ClockCorrection>().map(|c| c.offset), Ok(chrono::Duration::seconds(-3723)));
+        assert!("iPhone; 00:37".parse::<ClockCorrection>().is_err());
+        assert!("iPhone".parse::<ClockCorrection>().is_err());
+    }
+
+    #[test]
+    fn test_time_shift()
+    {
+        let date = picvudb::data::Date::from_rfc3339("2019-06-10T09:00:00+10:00").unwrap();
+
+        let shift = TimeShift{ offset: chrono::Duration::hours(-1), timezone: Some("-02:00".parse().unwrap()) };
+        assert_eq!(shift.apply(&date).to_rfc3339(), "2019-06-09T21:00:00-02:00");
+
+        let shift = TimeShift{ offset: chrono::Duration::minutes(5), timezone: None };
+        assert_eq!(shift.apply(&date).to_rfc3339(), "2019-06-10T09:05:00+10:00");
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::{Read, Write};
+
+use crate::analyse::warning::{Warning, WarningKind};
+
+// Documents are stored as attachments like any other file.
+// Their text is extracted so it can be searched, and PDFs
+// can have their first page rendered as a thumbnail. Both
+// use the optional poppler tools (pdftotext and pdftoppm).
+
+pub fn is_document_mime(mime: &mime::Mime) -> bool
+{
+    *mime == mime::APPLICATION_PDF
+        || mime.type_() == mime::TEXT
+}
+
+fn is_not_found(err: &std::io::Error) -> bool
+{
+    err.kind() == std::io::ErrorKind::NotFound
+}
+
+/// Returns the searchable text of a document, or None if
+/// it isn't a document, or the text can't be extracted.
+pub fn extract_text(bytes: &[u8], filename: &str, mime: &mime::Mime, warnings: &mut Vec<Warning>) -> Option<String>
+{
+    if !is_document_mime(mime)
+    {
+        return None;
+    }
+
+    let text = if mime.type_() == mime::TEXT
+    {
+        Some(String::from_utf8_lossy(bytes).to_string())
+    }
+    else
+    {
+        match run_pdftotext(bytes)
+        {
+            Ok(text) => text,
+            Err(err) if is_not_found(&err) =>
+            {
+                warnings.push(Warning::new(filename, WarningKind::DocumentToolsNotFound,
+                    "pdftotext is not installed - the document text can't be searched"));
+
+                None
+            },
+            Err(err) =>
+            {
+                warnings.push(Warning::new(filename, WarningKind::DocumentAnalysisError, format!("{:?}", err)));
+
+                None
+            },
+        }
+    };
+
+    text.map(|t| t.trim().to_owned())
+        .filter(|t| !t.is_empty())
+}
+
+fn run_pdftotext(bytes: &[u8]) -> Result<Option<String>, std::io::Error>
+{
+    let pdf_file = tempfile::Builder::new().suffix(".pdf").tempfile()?;
+    pdf_file.as_file().write_all(bytes)?;
+
+    let output = std::process::Command::new("pdftotext")
+        .arg("-q")
+        .arg("-enc")
+        .arg("UTF-8")
+        .arg(pdf_file.path())
+        .arg("-")
+        .output()?;
+
+    if !output.status.success()
+    {
+        return Ok(None);
+    }
+
+    Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+}
+
+/// Renders the first page of a PDF as a JPEG no larger than
+/// the thumbnail size. Returns None if pdftoppm isn't installed
+/// or can't read the document.
+pub fn render_pdf_thumbnail(bytes: &[u8], thumbnail_size: u32) -> Result<Option<Vec<u8>>, std::io::Error>
+{
+    let pdf_file = tempfile::Builder::new().suffix(".pdf").tempfile()?;
+    pdf_file.as_file().write_all(bytes)?;
+
+    // pdftoppm adds the extension to the output
+    // name itself, so it's given a directory
+
+    let out_dir = tempfile::tempdir()?;
+    let out_prefix = out_dir.path().join("page");
+
+    let output = std::process::Command::new("pdftoppm")
+        .arg("-q")
+        .arg("-jpeg")
+        .arg("-f")
+        .arg("1")
+        .arg("-l")
+        .arg("1")
+        .arg("-singlefile")
+        .arg("-scale-to")
+        .arg(thumbnail_size.to_string())
+        .arg(pdf_file.path())
+        .arg(&out_prefix)
+        .output();
+
+    match output
+    {
+        Ok(output) if output.status.success() =>
+        {
+            let mut bytes = Vec::new();
+            std::fs::File::open(out_prefix.with_extension("jpg"))?.read_to_end(&mut bytes)?;
+
+            Ok(Some(bytes))
+        },
+        Ok(_) => Ok(None),
+        Err(err) if is_not_found(&err) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_extract_text()
+    {
+        let mut warnings = Vec::new();
+        let markdown: mime::Mime = "text/markdown".parse().unwrap();
+
+        assert_eq!(extract_text(b"# Pancakes\n\nFlour, eggs, milk\n", "pancakes.md", &markdown, &mut warnings), Some("# Pancakes\n\nFlour, eggs, milk".to_owned()));
+        assert_eq!(extract_text(b"  \n", "empty.txt", &mime::TEXT_PLAIN, &mut warnings), None);
+        assert_eq!(extract_text(b"GIF89a", "image.gif", &mime::IMAGE_GIF, &mut warnings), None);
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+use std::io::Write;
+
+use crate::analyse::isobmff::{find_box, read_uint, BoxIter};
+
+// HEIF/HEIC files use the ISO Base Media File Format. The EXIF
+// data is stored as an "Exif" item in the "meta" box - the
+// "iinf" box gives the item's ID and the "iloc" box gives
+// where the item's data is stored in the file.
+
+pub fn is_heif(data: &[u8]) -> bool
+{
+    if data.len() < 12
+        || &data[4..8] != b"ftyp"
+    {
+        return false;
+    }
+
+    let brand = &data[8..12];
+
+    brand == b"heic"
+        || brand == b"heix"
+        || brand == b"heim"
+        || brand == b"heis"
+        || brand == b"hevc"
+        || brand == b"mif1"
+        || brand == b"msf1"
+}
+
+fn find_exif_item_id(iinf: &[u8]) -> Option<u32>
+{
+    let mut pos = 0;
+    let version = read_uint(iinf, &mut pos, 1)?;
+    pos += 3;
+
+    let _entry_count = read_uint(iinf, &mut pos, if version == 0 { 2 } else { 4 })?;
+
+    for (kind, infe) in BoxIter::new(iinf.get(pos..)?)
+    {
+        if kind != *b"infe"
+        {
+            continue;
+        }
+
+        let mut pos = 0;
+        let version = read_uint(infe, &mut pos, 1)?;
+        pos += 3;
+
+        if version < 2
+        {
+            // Older item info entries don't have an item type
+            continue;
+        }
+
+        let item_id = read_uint(infe, &mut pos, if version == 2 { 2 } else { 4 })?;
+        let _protection_index = read_uint(infe, &mut pos, 2)?;
+        let item_type = infe.get(pos..(pos + 4))?;
+
+        if item_type == b"Exif"
+        {
+            return Some(item_id as u32);
+        }
+    }
+
+    None
+}
+
+fn find_item_location(iloc: &[u8], item_id: u32) -> Option<Vec<(u64, u64)>>
+{
+    let mut pos = 0;
+    let version = read_uint(iloc, &mut pos, 1)?;
+    pos += 3;
+
+    let sizes = read_uint(iloc, &mut pos, 2)?;
+    let offset_size = ((sizes >> 12) & 0xF) as usize;
+    let length_size = ((sizes >> 8) & 0xF) as usize;
+    let base_offset_size = ((sizes >> 4) & 0xF) as usize;
+    let index_size = if version == 1 || version == 2 { (sizes & 0xF) as usize } else { 0 };
+
+    let item_count = read_uint(iloc, &mut pos, if version < 2 { 2 } else { 4 })?;
+
+    for _ in 0..item_count
+    {
+        let this_id = read_uint(iloc, &mut pos, if version < 2 { 2 } else { 4 })?;
+
+        let mut construction_method = 0;
+        if version == 1 || version == 2
+        {
+            construction_method = read_uint(iloc, &mut pos, 2)? & 0xF;
+        }
+
+        let _data_reference_index = read_uint(iloc, &mut pos, 2)?;
+        let base_offset = read_uint(iloc, &mut pos, base_offset_size)?;
+        let extent_count = read_uint(iloc, &mut pos, 2)?;
+
+        let mut extents = Vec::new();
+
+        for _ in 0..extent_count
+        {
+            let _extent_index = read_uint(iloc, &mut pos, index_size)?;
+            let extent_offset = read_uint(iloc, &mut pos, offset_size)?;
+            let extent_length = read_uint(iloc, &mut pos, length_size)?;
+
+            extents.push((base_offset.checked_add(extent_offset)?, extent_length));
+        }
+
+        if this_id == item_id as u64
+        {
+            // Only items stored at an offset in
+            // the file itself are supported
+
+            if construction_method != 0
+            {
+                return None;
+            }
+
+            return Some(extents);
+        }
+    }
+
+    None
+}
+
+/// Returns the TIFF formatted EXIF data stored in
+/// a HEIF/HEIC file, if it has any.
+pub fn extract_exif_tiff(data: &[u8]) -> Option<Vec<u8>>
+{
+    if !is_heif(data)
+    {
+        return None;
+    }
+
+    let meta = find_box(data, b"meta")?;
+
+    // The meta box is a full box - skip the version and flags
+
+    let meta_boxes = meta.get(4..)?;
+
+    let item_id = find_exif_item_id(find_box(meta_boxes, b"iinf")?)?;
+    let extents = find_item_location(find_box(meta_boxes, b"iloc")?, item_id)?;
+
+    let mut item = Vec::new();
+
+    for (offset, length) in extents
+    {
+        let start = offset as usize;
+        let end = if length == 0 { data.len() } else { start.checked_add(length as usize)? };
+
+        item.extend_from_slice(data.get(start..end)?);
+    }
+
+    // The Exif item starts with a 4-byte offset to the
+    // TIFF header (which normally skips an "Exif\0\0" prefix)
+
+    let mut pos = 0;
+    let tiff_offset = read_uint(&item, &mut pos, 4)? as usize;
+    let tiff = item.get((4 + tiff_offset)..)?;
+
+    if tiff.starts_with(b"II*\0")
+        || tiff.starts_with(b"MM\0*")
+    {
+        return Some(tiff.to_vec());
+    }
+
+    None
+}
+
+/// Decodes a HEIF/HEIC image by converting it to a
+/// JPEG using the external "heif-convert" tool.
+pub fn convert_to_jpeg(data: &[u8]) -> Result<Vec<u8>, std::io::Error>
+{
+    let heif_file = tempfile::Builder::new().suffix(".heic").tempfile()?;
+    heif_file.as_file().write_all(data)?;
+
+    let jpeg_file = tempfile::Builder::new().suffix(".jpg").tempfile()?;
+
+    let output = std::process::Command::new("heif-convert")
+        .arg(heif_file.path())
+        .arg(jpeg_file.path())
+        .output()?;
+
+    if !output.status.success()
+    {
+        return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("heif-convert failed: {}", String::from_utf8_lossy(&output.stderr))));
+    }
+
+    // Read the output by path, as the tool
+    // may replace the file rather than overwrite it
+
+    std::fs::read(jpeg_file.path())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::analyse::isobmff::make_box;
+
+    #[test]
+    fn test_extract_exif_tiff()
+    {
+        let tiff = b"MM\0*\0\0\0\x08rest".to_vec();
+
+        let mut exif_item = vec![0, 0, 0, 6];
+        exif_item.extend_from_slice(b"Exif\0\0");
+        exif_item.extend_from_slice(&tiff);
+
+        let infe = make_box(b"infe", &[2, 0, 0, 0, 0, 7, 0, 0, b'E', b'x', b'i', b'f']);
+        let mut iinf_contents = vec![0, 0, 0, 0, 0, 1];
+        iinf_contents.extend_from_slice(&infe);
+        let iinf = make_box(b"iinf", &iinf_contents);
+
+        let ftyp = make_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+
+        // iloc version 0, 4 byte offsets and lengths, one item with one extent.
+        // The offset is filled in once the size of the header is known.
+
+        let iloc_len = 8 + 4 + 2 + 2 + 2 + 2 + 2 + 4 + 4;
+        let meta_len = 8 + 4 + iinf.len() + iloc_len;
+        let offset = (ftyp.len() + meta_len) as u32;
+
+        let mut iloc_contents = vec![0, 0, 0, 0, 0x44, 0x00, 0, 1, 0, 7, 0, 0, 0, 1];
+        iloc_contents.extend_from_slice(&offset.to_be_bytes());
+        iloc_contents.extend_from_slice(&(exif_item.len() as u32).to_be_bytes());
+        let iloc = make_box(b"iloc", &iloc_contents);
+        assert_eq!(iloc.len(), iloc_len);
+
+        let mut meta_contents = vec![0, 0, 0, 0];
+        meta_contents.extend_from_slice(&iinf);
+        meta_contents.extend_from_slice(&iloc);
+        let meta = make_box(b"meta", &meta_contents);
+
+        let mut file = ftyp;
+        file.extend_from_slice(&meta);
+        file.extend_from_slice(&exif_item);
+
+        assert!(is_heif(&file));
+        assert_eq!(extract_exif_tiff(&file), Some(tiff));
+        assert_eq!(extract_exif_tiff(b"not a heif file"), None);
+    }
+
+    #[test]
+    fn test_item_location_overflow()
+    {
+        // iloc version 0, 8 byte offsets and base offsets, 4 byte
+        // lengths, one item whose offsets add up past u64::MAX
+
+        let mut iloc = vec![0, 0, 0, 0, 0x84, 0x80, 0, 1, 0, 7, 0, 0];
+        iloc.extend_from_slice(&u64::MAX.to_be_bytes());
+        iloc.extend_from_slice(&[0, 1]);
+        iloc.extend_from_slice(&1u64.to_be_bytes());
+        iloc.extend_from_slice(&[0, 0, 0, 4]);
+
+        assert_eq!(find_item_location(&iloc, 7), None);
+
+        iloc[12..20].copy_from_slice(&8u64.to_be_bytes());
+
+        assert_eq!(find_item_location(&iloc, 7), Some(vec![(9, 4)]));
+    }
+}
@@ -64,6 +64,7 @@ pub struct ImgAnalysis
     pub mime: mime::Mime,
     pub orientation: Option<Orientation>,
     pub orig_taken_naive: Option<chrono::NaiveDateTime>,
+    pub orig_taken_offset: Option<chrono::FixedOffset>,
     pub orig_digitized_naive: Option<chrono::NaiveDateTime>,
     pub gps_timestamp: Option<chrono::DateTime<chrono::Utc>>,
     pub orig_taken: Option<picvudb::data::Date>,
@@ -85,6 +86,87 @@ fn find_single(exif: &Vec<rexif::ExifEntry>, tag: rexif::ExifTag) -> Option<rexi
     None
 }
 
+// Tags from EXIF 2.31 that aren't known by the EXIF
+// library, so they have to be found by their tag number
+
+const EXIF_TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+const EXIF_TAG_OFFSET_TIME_DIGITIZED: u16 = 0x9012;
+const EXIF_TAG_SUB_SEC_TIME_ORIGINAL: u16 = 0x9291;
+const EXIF_TAG_SUB_SEC_TIME_DIGITIZED: u16 = 0x9292;
+
+fn find_single_ascii_by_number(exif: &Vec<rexif::ExifEntry>, tag: u16) -> Option<String>
+{
+    for entry in exif
+    {
+        if entry.ifd.tag == tag
+        {
+            if let rexif::TagValue::Ascii(s) = &entry.value
+            {
+                let s = s.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+
+                if !s.is_empty()
+                {
+                    return Some(s.to_owned());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Parses an EXIF OffsetTime value, e.g. "+10:00"
+fn parse_offset_time(s: &str) -> Result<chrono::FixedOffset, String>
+{
+    let err = || format!("Invalid EXIF offset time {:?}", s);
+
+    let bytes = s.as_bytes();
+
+    if bytes.len() != 6
+        || bytes[3] != b':'
+    {
+        return Err(err());
+    }
+
+    let sign = match bytes[0]
+    {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return Err(err()),
+    };
+
+    let hours: i32 = s[1..3].parse().map_err(|_| err())?;
+    let mins: i32 = s[4..6].parse().map_err(|_| err())?;
+
+    if hours > 23 || mins > 59
+    {
+        return Err(err());
+    }
+
+    chrono::FixedOffset::east_opt(sign * ((hours * 60) + mins) * 60).ok_or_else(err)
+}
+
+/// Adds an EXIF SubSecTime value (the digits after the
+/// decimal point, e.g. "045" is 45 milliseconds) to a time
+fn add_sub_sec_time(naive: &chrono::NaiveDateTime, sub_sec: &str) -> Result<chrono::NaiveDateTime, String>
+{
+    // Some cameras pad the value with spaces
+
+    let digits = sub_sec.trim();
+
+    if digits.is_empty()
+        || !digits.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(format!("Invalid EXIF sub-second time {:?}", sub_sec));
+    }
+
+    // Only nanosecond precision is kept
+
+    let digits = if digits.len() > 9 { &digits[..9] } else { digits };
+    let nanos: u32 = format!("{:0<9}", digits).parse().map_err(|_| format!("Invalid EXIF sub-second time {:?}", sub_sec))?;
+
+    Ok(*naive + chrono::Duration::nanoseconds(nanos as i64))
+}
+
 fn parse_urational(entry: &rexif::ExifEntry, factors: Vec<f64>, msg: String) -> Result<f64, String>
 {
     if let rexif::TagValue::URational(vec) = &entry.value
@@ -109,7 +191,14 @@ impl ImgAnalysis
 {
     pub fn decode(data: &Vec<u8>, file_name: &String, google_cache: Option<&GoogleCache>) -> Result<Option<(Self, Vec<Warning>)>, ImgAnalysisError>
     {
-        let (exif_result, mut exif_warnings) = rexif::parse_buffer_quiet(&data);
+        // HEIF/HEIC files store the EXIF data as TIFF inside
+        // the ISOBMFF container. Camera RAW files are TIFF
+        // based, so can be handled directly.
+
+        let heif_exif = crate::analyse::heif::extract_exif_tiff(data);
+        let exif_data = heif_exif.as_ref().unwrap_or(data);
+
+        let (exif_result, mut exif_warnings) = rexif::parse_buffer_quiet(exif_data);
 
         match exif_result
         {
@@ -200,6 +289,15 @@ impl ImgAnalysis
                             Ok(datetime_naive) =>
                             {
                                 orig_taken_naive = Some(datetime_naive);
+
+                                if let Some(sub_sec) = find_single_ascii_by_number(&exif.entries, EXIF_TAG_SUB_SEC_TIME_ORIGINAL)
+                                {
+                                    match add_sub_sec_time(&datetime_naive, &sub_sec)
+                                    {
+                                        Ok(with_sub_sec) => orig_taken_naive = Some(with_sub_sec),
+                                        Err(e) => exif_warnings.push(Warning::new(file_name, WarningKind::ImgExifAnalyse, e)),
+                                    }
+                                }
                             },
                             Err(e) =>
                             {
@@ -229,6 +327,15 @@ impl ImgAnalysis
                             Ok(datetime_naive) =>
                             {
                                 orig_digitized_naive = Some(datetime_naive);
+
+                                if let Some(sub_sec) = find_single_ascii_by_number(&exif.entries, EXIF_TAG_SUB_SEC_TIME_DIGITIZED)
+                                {
+                                    match add_sub_sec_time(&datetime_naive, &sub_sec)
+                                    {
+                                        Ok(with_sub_sec) => orig_digitized_naive = Some(with_sub_sec),
+                                        Err(e) => exif_warnings.push(Warning::new(file_name, WarningKind::ImgExifAnalyse, e)),
+                                    }
+                                }
                             },
                             Err(e) =>
                             {
@@ -241,6 +348,31 @@ impl ImgAnalysis
                     }
                 }
 
+                // Original Date/Time Offset. The digitized offset
+                // is used if there's no original offset, as they're
+                // normally written at the same time.
+
+                let mut orig_taken_offset = None;
+                {
+                    let offset_string = find_single_ascii_by_number(&exif.entries, EXIF_TAG_OFFSET_TIME_ORIGINAL)
+                        .or_else(|| find_single_ascii_by_number(&exif.entries, EXIF_TAG_OFFSET_TIME_DIGITIZED));
+
+                    if let Some(offset_string) = offset_string
+                    {
+                        match parse_offset_time(&offset_string)
+                        {
+                            Ok(offset) =>
+                            {
+                                orig_taken_offset = Some(offset);
+                            },
+                            Err(e) =>
+                            {
+                                exif_warnings.push(Warning::new(file_name, WarningKind::ImgExifAnalyse, e));
+                            },
+                        }
+                    }
+                }
+
                 // GPS Timestamp
                 let mut gps_timestamp = None;
                 {
@@ -276,12 +408,20 @@ impl ImgAnalysis
 
                 let mut orig_taken = None;
                 {
+                    // If the camera recorded the offset from UTC, then
+                    // that's exact, and there's no need to guess it
+
+                    if let (Some(taken_naive_ts), Some(offset)) = (&orig_taken_naive, &orig_taken_offset)
+                    {
+                        orig_taken = Some(crate::analyse::tz::ExplicitTimezone::new(*offset).from_local_assuming_tz(taken_naive_ts));
+                    }
+
                     // If the image has a location, then use the Google services
                     // to look up the timezone. We'll use the "orig_taken" or "GPS",
                     // and we'll just assume the "orig_taken" is a GPS timezone, because
                     // it should be pretty good, and worst case is we get the DST incorrect...
 
-                    if let (Some(gcache), Some(loc)) = (google_cache, &location)
+                    if let (Some(gcache), Some(loc), true) = (google_cache, &location, orig_taken.is_none())
                     {
                         if let Some(gps_ts) = &gps_timestamp
                         {
@@ -391,6 +531,7 @@ impl ImgAnalysis
                     mime,
                     orientation,
                     orig_taken_naive,
+                    orig_taken_offset,
                     orig_digitized_naive,
                     gps_timestamp,
                     orig_taken,
@@ -425,6 +566,331 @@ impl ImgAnalysis
     }
 }
 
+fn entry_to_f64(entry: &rexif::ExifEntry) -> Option<f64>
+{
+    match &entry.value
+    {
+        rexif::TagValue::URational(vec) =>
+        {
+            vec.first()
+                .filter(|r| r.denominator != 0)
+                .map(|r| (r.numerator as f64) / (r.denominator as f64))
+        },
+        rexif::TagValue::U16(vec) => vec.first().map(|v| *v as f64),
+        rexif::TagValue::U32(vec) => vec.first().map(|v| *v as f64),
+        _ => None,
+    }
+}
+
+fn extract_xmp(data: &[u8]) -> Option<String>
+{
+    const START: &[u8] = b"<x:xmpmeta";
+    const END: &[u8] = b"</x:xmpmeta>";
+
+    let start = data.windows(START.len()).position(|w| w == START)?;
+    let len = data[start..].windows(END.len()).position(|w| w == END)?;
+
+    Some(String::from_utf8_lossy(&data[start..(start + len + END.len())]).to_string())
+}
+
+/// Extracts all of the EXIF entries (and any XMP packet)
+/// from an image, so they can be stored and searched.
+pub fn extract_exif(data: &[u8]) -> Option<picvudb::data::AttachmentExif>
+{
+    let heif_exif = crate::analyse::heif::extract_exif_tiff(data);
+    let exif_data = heif_exif.as_deref().unwrap_or(data);
+
+    let entries = match rexif::parse_buffer_quiet(exif_data).0
+    {
+        Ok(exif) => exif.entries,
+        Err(_) => Vec::new(),
+    };
+
+    let xmp = extract_xmp(data);
+
+    if entries.is_empty()
+        && xmp.is_none()
+    {
+        return None;
+    }
+
+    let find_string = |tag: rexif::ExifTag| -> Option<String>
+    {
+        find_single(&entries, tag)
+            .map(|e| e.value_more_readable.trim().to_owned())
+            .filter(|s| !s.is_empty())
+    };
+
+    let find_f64 = |tag: rexif::ExifTag| -> Option<f64>
+    {
+        find_single(&entries, tag).and_then(|e| entry_to_f64(&e))
+    };
+
+    let gps_dop = match calc_location_and_dop(&entries)
+    {
+        Ok((_, dop)) => dop,
+        Err(_) => None,
+    };
+
+    Some(picvudb::data::AttachmentExif
+    {
+        make: find_string(rexif::ExifTag::Make),
+        model: find_string(rexif::ExifTag::Model),
+        exposure_time: find_string(rexif::ExifTag::ExposureTime),
+        aperture: find_f64(rexif::ExifTag::FNumber),
+        focal_length: find_f64(rexif::ExifTag::FocalLength),
+        iso: find_f64(rexif::ExifTag::ISOSpeedRatings).map(|i| i as u32),
+        gps_dop,
+        entries: entries.iter()
+            .filter(|e| e.tag != rexif::ExifTag::UnknownToMe)
+            .map(|e| picvudb::data::ExifEntry
+            {
+                tag: e.tag.to_string(),
+                value: e.value_more_readable.to_string(),
+            })
+            .collect(),
+        xmp,
+    })
+}
+
+/// Finds the JPEG images embedded in a file (e.g. the
+/// previews in a camera RAW file). The largest ones
+/// are returned first.
+pub fn extract_embedded_jpegs(data: &[u8]) -> Vec<&[u8]>
+{
+    let mut result = Vec::new();
+    let mut start = 0;
+
+    while let Some(offset) = data[start..].windows(3).position(|w| w == [0xFF, 0xD8, 0xFF])
+    {
+        let soi = start + offset;
+
+        match find_jpeg_end(data, soi)
+        {
+            Some(end) =>
+            {
+                result.push(&data[soi..end]);
+                start = end;
+            },
+            None =>
+            {
+                start = soi + 2;
+            },
+        }
+    }
+
+    result.sort_by(|a, b| b.len().cmp(&a.len()));
+
+    result
+}
+
+fn find_jpeg_end(data: &[u8], soi: usize) -> Option<usize>
+{
+    // Walk the marker segments up to the
+    // start of scan, then look for the end of image
+
+    let mut pos = soi + 2;
+
+    loop
+    {
+        if (pos + 4) > data.len()
+            || data[pos] != 0xFF
+        {
+            return None;
+        }
+
+        let marker = data[pos + 1];
+        let length = ((data[pos + 2] as usize) << 8) | (data[pos + 3] as usize);
+
+        pos += 2 + length;
+
+        if marker == 0xDA
+        {
+            break;
+        }
+    }
+
+    // Within the entropy coded data, 0xFF is always followed by
+    // a zero byte or a restart marker, unless it's a real marker
+
+    while (pos + 1) < data.len()
+    {
+        if data[pos] == 0xFF
+        {
+            let marker = data[pos + 1];
+
+            if marker == 0xD9
+            {
+                return Some(pos + 2);
+            }
+            else if (0xC0..=0xFE).contains(&marker)
+                && !(0xD0..=0xD7).contains(&marker)
+            {
+                // Another scan (for progressive JPEGs) or table -
+                // skip over its segment
+
+                if (pos + 4) > data.len()
+                {
+                    return None;
+                }
+
+                let length = ((data[pos + 2] as usize) << 8) | (data[pos + 3] as usize);
+                pos += 2 + length;
+                continue;
+            }
+        }
+
+        pos += 1;
+    }
+
+    None
+}
+
+/// Decodes an image. HEIF/HEIC files that the image crate
+/// can't decode are converted with an external tool. Camera
+/// RAW files are decoded from their largest embedded JPEG preview.
+pub fn load_image(data: &[u8]) -> Result<image::DynamicImage, image::ImageError>
+{
+    let direct = image::load_from_memory(data);
+
+    if crate::analyse::heif::is_heif(data)
+    {
+        if direct.is_err()
+        {
+            if let Ok(jpeg) = crate::analyse::heif::convert_to_jpeg(data)
+            {
+                if let Ok(image) = image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg)
+                {
+                    return Ok(image);
+                }
+            }
+        }
+
+        return direct;
+    }
+
+    // RAW files are TIFF based, but the image crate can
+    // only decode the small thumbnail in the first IFD (if
+    // anything) - so prefer a preview if it's larger
+
+    let is_tiff = data.starts_with(b"II*\0") || data.starts_with(b"MM\0*");
+
+    if direct.is_err() || is_tiff
+    {
+        for jpeg in extract_embedded_jpegs(data)
+        {
+            if let Ok(preview) = image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)
+            {
+                if let Ok(image) = &direct
+                {
+                    if (image.width() as u64 * image.height() as u64) >= (preview.width() as u64 * preview.height() as u64)
+                    {
+                        break;
+                    }
+                }
+
+                return Ok(preview);
+            }
+        }
+    }
+
+    direct
+}
+
+/// Rotates a decoded image so that it's upright.
+pub fn apply_orientation(image: image::DynamicImage, orientation: &Option<Orientation>) -> image::DynamicImage
+{
+    match orientation
+    {
+        None
+            | Some(Orientation::Straight) =>
+        {
+            image
+        },
+        Some(Orientation::UpsideDown) =>
+        {
+            image.rotate180()
+        }
+        Some(Orientation::RotatedLeft) =>
+        {
+            image.rotate90()
+        }
+        Some(Orientation::RotatedRight) =>
+        {
+            image.rotate270()
+        }
+    }
+}
+
+/// Applies an object's non-destructive adjustments. A user
+/// orientation replaces the EXIF orientation, and the crop
+/// is taken from the upright image.
+pub fn apply_adjustments(image: image::DynamicImage, exif_orientation: &Option<Orientation>, user_orientation: &Option<Orientation>, crop: &Option<picvudb::data::Crop>) -> image::DynamicImage
+{
+    let orientation = if user_orientation.is_some() { user_orientation } else { exif_orientation };
+
+    let image = apply_orientation(image, orientation);
+
+    match crop
+    {
+        None => image,
+        Some(crop) =>
+        {
+            let (x, y, dimensions) = crop.to_pixels(&picvudb::data::Dimensions::new(image.width(), image.height()));
+
+            image.crop_imm(x, y, dimensions.width, dimensions.height)
+        },
+    }
+}
+
+pub fn adjusted_dimensions(dimensions: &Option<picvudb::data::Dimensions>, exif_orientation: &Option<Orientation>, user_orientation: &Option<Orientation>, crop: &Option<picvudb::data::Crop>) -> Option<picvudb::data::Dimensions>
+{
+    dimensions.clone().map(|dimensions|
+    {
+        // Stored dimensions already have the EXIF orientation
+        // applied - so undo that before applying the user's
+
+        let dimensions = match user_orientation
+        {
+            None => dimensions,
+            Some(_) => dimensions.adjust_for_orientation(exif_orientation).adjust_for_orientation(user_orientation),
+        };
+
+        match crop
+        {
+            None => dimensions,
+            Some(crop) => crop.adjust_dimensions(&dimensions),
+        }
+    })
+}
+
+/// Renders an adjusted copy of an image as a JPEG, leaving
+/// the original bytes untouched.
+pub fn render_adjusted_jpeg(data: &[u8], file_name: &String, user_orientation: &Option<Orientation>, crop: &Option<picvudb::data::Crop>, max_size: Option<u32>) -> Result<Vec<u8>, image::ImageError>
+{
+    let exif_orientation =
+        ImgAnalysis::decode(&data.to_vec(), file_name, None)
+        .ok()
+        .flatten()
+        .map(|(analysis, _warnings)|{ analysis.orientation })
+        .flatten();
+
+    let image = load_image(data)?;
+
+    let image = apply_adjustments(image, &exif_orientation, user_orientation, crop);
+
+    let image = match max_size
+    {
+        None => image,
+        Some(size) => image.thumbnail(size, size),
+    };
+
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut cursor, image::ImageOutputFormat::Jpeg(100))?;
+
+    Ok(cursor.into_inner())
+}
+
 pub fn parse_mvimg_split(data: &Vec<u8>, file_name: &String) -> MvImgSplit
 {
     let mut result = MvImgSplit::Neither;
@@ -669,4 +1135,41 @@ mod tests
         test_time("2018:03:02 19:20:09", "2018:03:02 09:28:09.0 UTC", None);
         test_time("2018:03:02 19:20:09", "2018:03:02 09:12:09.0 UTC", None);
     }
+
+    #[test]
+    fn test_offset_and_sub_sec_time()
+    {
+        assert_eq!(parse_offset_time("+10:00"), Ok(chrono::FixedOffset::east(10 * 3600)));
+        assert_eq!(parse_offset_time("-03:30"), Ok(chrono::FixedOffset::west(3 * 3600 + 1800)));
+        assert!(parse_offset_time("10:00").is_err());
+        assert!(parse_offset_time("+25:00").is_err());
+
+        let naive = chrono::naive::NaiveDateTime::parse_from_str("2018:03:02 19:20:09", "%Y:%m:%d %H:%M:%S").unwrap();
+
+        assert_eq!(add_sub_sec_time(&naive, "045").unwrap().format("%S%.f").to_string(), "09.045");
+        assert_eq!(add_sub_sec_time(&naive, "5  ").unwrap().format("%S%.f").to_string(), "09.500");
+        assert!(add_sub_sec_time(&naive, "   ").is_err());
+
+        let taken = crate::analyse::tz::ExplicitTimezone::new(parse_offset_time("+10:00").unwrap())
+            .from_local_assuming_tz(&add_sub_sec_time(&naive, "25").unwrap());
+
+        assert_eq!(taken.to_rfc3339(), "2018-03-02T19:20:09.250+10:00");
+    }
+
+    #[test]
+    fn test_extract_embedded_jpegs()
+    {
+        let small: Vec<u8> = vec![0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02, 0x12, 0xFF, 0x00, 0x34, 0xFF, 0xD9];
+        let large: Vec<u8> = vec![0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x04, 0xFF, 0xD9, 0xFF, 0xDA, 0x00, 0x02, 0x12, 0xFF, 0xD0, 0x56, 0x78, 0xFF, 0xD9];
+
+        let mut data = b"II*\0raw".to_vec();
+        data.extend_from_slice(&small);
+        data.extend_from_slice(b"more raw data");
+        data.extend_from_slice(&large);
+        data.extend_from_slice(&[0xFF, 0xD8, 0xFF, 0xDA]);
+
+        let jpegs = extract_embedded_jpegs(&data);
+
+        assert_eq!(jpegs, vec![&large[..], &small[..]]);
+    }
 }
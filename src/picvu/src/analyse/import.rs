@@ -1,11 +1,23 @@
 use std::path::Path;
 
 use crate::analyse;
+use crate::analyse::clock::ClockCorrection;
 use crate::analyse::google::GoogleCache;
+use crate::analyse::tracklog::TrackLog;
 use crate::analyse::tz::ExplicitTimezone;
 use crate::analyse::warning::{Warning, WarningKind};
 use crate::bulk::export;
 
+/// What to do with files that aren't supported media
+/// or metadata files, and aren't ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnknownFileHandling
+{
+    Fail,
+    SkipWithWarning,
+    StoreAsDocument,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ImportOptions
 {
@@ -13,6 +25,10 @@ pub struct ImportOptions
     pub force_timezone: Option<ExplicitTimezone>,
     pub assume_notes: Option<picvudb::data::NotesMarkdown>,
     pub assume_location: Option<picvudb::data::Location>,
+    pub ignore_patterns: Vec<String>,
+    pub unknown_files: UnknownFileHandling,
+    pub clock_corrections: Vec<ClockCorrection>,
+    pub track_log: Option<TrackLog>,
 }
 
 impl Default for ImportOptions
@@ -25,8 +41,66 @@ impl Default for ImportOptions
             force_timezone: None,
             assume_notes: None,
             assume_location: None,
+            ignore_patterns: Vec::new(),
+            unknown_files: UnknownFileHandling::Fail,
+            clock_corrections: Vec::new(),
+            track_log: None,
+        }
+    }
+}
+
+impl ImportOptions
+{
+    /// Returns true if the file matches one of the ignore patterns.
+    /// Patterns containing a '/' are matched against the full
+    /// path, and others against just the file name.
+    pub fn is_ignored(&self, archive_path: &str, file_name: &str) -> bool
+    {
+        self.ignore_patterns.iter().any(|pattern|
+        {
+            let text = if pattern.contains('/') { archive_path } else { file_name };
+
+            glob_matches(pattern.as_bytes(), text.as_bytes())
+        })
+    }
+}
+
+/// Case-insensitive glob match, supporting '*' and '?' wildcards.
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool
+{
+    let mut p = 0;
+    let mut t = 0;
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len()
+    {
+        if p < pattern.len()
+            && (pattern[p] == b'?' || pattern[p].eq_ignore_ascii_case(&text[t]))
+        {
+            p += 1;
+            t += 1;
+        }
+        else if p < pattern.len()
+            && pattern[p] == b'*'
+        {
+            backtrack = Some((p, t));
+            p += 1;
+        }
+        else if let Some((star_p, star_t)) = backtrack
+        {
+            // Let the last star match one more character
+
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        }
+        else
+        {
+            return false;
         }
     }
+
+    pattern[p..].iter().all(|c| *c == b'*')
 }
 
 pub fn guess_mime_type_from_filename(filename: &String) -> Option<mime::Mime>
@@ -45,7 +119,7 @@ pub fn guess_mime_type_from_filename(filename: &String) -> Option<mime::Mime>
     {
         Some(mime::IMAGE_GIF)
     }
-    else if ext == "tif"
+    else if (ext == "tif") || (ext == "tiff")
     {
         Some("image/tiff".parse().unwrap())
     }
@@ -53,6 +127,22 @@ pub fn guess_mime_type_from_filename(filename: &String) -> Option<mime::Mime>
     {
         Some("image/webp".parse().unwrap())
     }
+    else if (ext == "heic") || (ext == "heif")
+    {
+        Some(format!("image/{}", ext).parse().unwrap())
+    }
+    else if ext == "cr2"
+    {
+        Some("image/x-canon-cr2".parse().unwrap())
+    }
+    else if ext == "nef"
+    {
+        Some("image/x-nikon-nef".parse().unwrap())
+    }
+    else if ext == "dng"
+    {
+        Some("image/x-adobe-dng".parse().unwrap())
+    }
     else if ext == "mp4"
     {
         Some(format!("{}/{}", mime::VIDEO.as_str(), mime::MP4.as_str()).parse().unwrap())
@@ -65,12 +155,183 @@ pub fn guess_mime_type_from_filename(filename: &String) -> Option<mime::Mime>
     {
         Some("video/x-matroska".parse().unwrap())
     }
+    else if ext == "mov"
+    {
+        Some("video/quicktime".parse().unwrap())
+    }
+    else if ext == "pdf"
+    {
+        Some(mime::APPLICATION_PDF)
+    }
+    else if ext == "txt"
+    {
+        Some(mime::TEXT_PLAIN)
+    }
+    else if (ext == "md") || (ext == "markdown")
+    {
+        Some("text/markdown".parse().unwrap())
+    }
     else
     {
         None
     }
 }
 
+/// Extensions of sidecar files, which hold extra
+/// information for the media file with the same stem.
+const SIDECAR_EXTENSIONS: [&str; 3] = ["xmp", "aae", "thm"];
+
+pub fn is_sidecar_file_name(file_name: &str) -> bool
+{
+    let ext = Path::new(file_name).extension().unwrap_or_default().to_str().unwrap_or_default().to_ascii_lowercase();
+
+    SIDECAR_EXTENSIONS.contains(&ext.as_str())
+}
+
+fn is_raw_mime(mime: &mime::Mime) -> bool
+{
+    mime.type_() == mime::IMAGE && mime.subtype().as_str().starts_with("x-")
+}
+
+/// Returns the key used to pair files that share a stem, such as
+/// "IMG_0001.CR2", "IMG_0001.JPG" and "IMG_0001.xmp" (or "IMG_0001.CR2.xmp").
+/// This is the path without its extension, ignoring case.
+pub fn pairing_stem(archive_path: &str) -> String
+{
+    let lower = archive_path.to_ascii_lowercase();
+    let mut stem = Path::new(&lower).with_extension("");
+
+    if is_sidecar_file_name(&lower)
+        && guess_mime_type_from_filename(&stem.to_string_lossy().to_string()).is_some()
+    {
+        stem = stem.with_extension("");
+    }
+
+    stem.to_string_lossy().to_string()
+}
+
+/// Orders a group of files that share a stem into the attachments of
+/// a single object. The primary attachment is the most easily viewed
+/// file, and the others are given a role based on their type.
+/// Returns indexes into the file names, in attachment order.
+pub fn order_paired_files(file_names: &Vec<String>) -> Vec<(usize, picvudb::data::AttachmentRole)>
+{
+    let rank = |file_name: &String| -> u32
+    {
+        match guess_mime_type_from_filename(file_name)
+        {
+            _ if is_sidecar_file_name(file_name) => 4,
+            Some(mime) if mime.type_() == mime::IMAGE && !is_raw_mime(&mime) => 0,
+            Some(mime) if mime.type_() == mime::IMAGE => 1,
+            Some(mime) if mime.type_() == mime::VIDEO => 2,
+            _ => 3,
+        }
+    };
+
+    let mut indexes = (0..file_names.len()).collect::<Vec<_>>();
+    indexes.sort_by_key(|i| (rank(&file_names[*i]), file_names[*i].clone()));
+
+    let primary_rank = match indexes.first()
+    {
+        Some(i) => rank(&file_names[*i]),
+        None => return Vec::new(),
+    };
+
+    indexes.iter().enumerate()
+        .map(|(pos, i)|
+        {
+            let role = if pos == 0
+            {
+                picvudb::data::AttachmentRole::Primary
+            }
+            else
+            {
+                match rank(&file_names[*i])
+                {
+                    1 => picvudb::data::AttachmentRole::Raw,
+                    2 if primary_rank < 2 => picvudb::data::AttachmentRole::Motion,
+                    3 | 4 => picvudb::data::AttachmentRole::Sidecar,
+                    _ => picvudb::data::AttachmentRole::Original,
+                }
+            };
+
+            (*i, role)
+        })
+        .collect()
+}
+
+/// Creates an additional attachment (such as a RAW file, or the movie
+/// of a live photo) for an object being imported. Only the file itself
+/// is analysed - the object's details come from the primary attachment.
+pub fn create_additional_attachment_for_import(
+    bytes: Vec<u8>,
+    file_name: &String,
+    role: picvudb::data::AttachmentRole,
+    opt_file_created_time: Option<picvudb::data::Date>,
+    opt_file_modified_time: Option<picvudb::data::Date>,
+    import_options: &ImportOptions,
+    warnings: &mut Vec<Warning>) -> picvudb::data::add::Attachment
+{
+    let now = picvudb::data::Date::now();
+
+    let mime = guess_mime_type_from_filename(file_name).unwrap_or(mime::APPLICATION_OCTET_STREAM);
+
+    let mut orientation = None;
+    let mut dimensions = None;
+    let mut duration = None;
+
+    if mime.type_() == mime::IMAGE
+    {
+        if let Ok(Some((analysis, _))) = analyse::img::ImgAnalysis::decode(&bytes, file_name, None)
+        {
+            orientation = analysis.orientation;
+        }
+
+        if let Ok(image) = analyse::img::load_image(&bytes)
+        {
+            dimensions = Some(picvudb::data::Dimensions::new(image.width(), image.height()));
+        }
+    }
+    else if mime.type_() == mime::VIDEO
+    {
+        match analyse::video::analyse_video(&bytes, file_name, Some(128), &import_options.assume_timezone, None, warnings)
+        {
+            Ok(info) =>
+            {
+                orientation = info.orientation;
+                dimensions = info.dimensions;
+                duration = info.duration;
+            },
+            Err(err) =>
+            {
+                warnings.push(Warning::new(file_name, WarningKind::VideoAnalysisError, format!("{:?}", err)));
+            },
+        }
+    }
+
+    if let Some(dim) = dimensions
+    {
+        dimensions = Some(dim.adjust_for_orientation(&orientation));
+    }
+
+    let text = analyse::document::extract_text(&bytes, file_name, &mime, warnings);
+
+    picvudb::data::add::Attachment
+    {
+        filename: file_name.clone(),
+        created: opt_file_created_time.unwrap_or(now.clone()),
+        modified: opt_file_modified_time.unwrap_or(now),
+        mime,
+        orientation,
+        dimensions,
+        duration,
+        exif: None,
+        text,
+        role,
+        bytes,
+    }
+}
+
 pub fn create_add_object_for_import(
     bytes: Vec<u8>,
     file_name: &String,
@@ -98,21 +359,44 @@ pub fn create_add_object_for_import(
                 kind: tag.kind,
                 rating: tag.rating,
                 censor: tag.censor,
+                parent: tag.parent,
             });
         }
         
-        // Construct the Add Object request
-    
-        let attachment = picvudb::data::add::Attachment
+        // Construct the Add Object request. Notes are
+        // exported without any attachment at all.
+
+        let attachment = match picvu_export.attachment
         {
-            filename: picvu_export.attachment.filename,
-            created: picvu_export.attachment.created,
-            modified: picvu_export.attachment.modified,
-            mime: picvu_export.attachment.mime.parse().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid MIME for {}: {:?}", file_name, e)))?,
-            orientation: picvu_export.attachment.orientation,
-            dimensions: picvu_export.attachment.dimensions,
-            duration: picvu_export.attachment.duration,
-            bytes: bytes,
+            None => None,
+            Some(export_attachment) =>
+            {
+                // The EXIF data isn't part of the export, but it's
+                // just a copy of what's in the file, so extract it again
+
+                let exif = if export_attachment.mime.starts_with("image/") { analyse::img::extract_exif(&bytes) } else { None };
+
+                let mime: mime::Mime = export_attachment.mime.parse().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid MIME for {}: {:?}", file_name, e)))?;
+
+                // Likewise, document text is extracted again
+
+                let text = analyse::document::extract_text(&bytes, file_name, &mime, warnings);
+
+                Some(picvudb::data::add::Attachment
+                {
+                    filename: export_attachment.filename,
+                    created: export_attachment.created,
+                    modified: export_attachment.modified,
+                    mime: mime,
+                    orientation: export_attachment.orientation,
+                    dimensions: export_attachment.dimensions,
+                    duration: export_attachment.duration,
+                    exif: exif,
+                    text: text,
+                    role: picvudb::data::AttachmentRole::Primary,
+                    bytes: bytes,
+                })
+            },
         };
 
         let data = picvudb::data::add::ObjectData
@@ -126,8 +410,11 @@ pub fn create_add_object_for_import(
             activity_time: Some(picvu_export.activity_time),
             location: picvu_export.location,
             attachment: attachment,
+            additional_attachments: Vec::new(),
             tags: tags,
             ext_ref: None,
+            user_orientation: picvu_export.user_orientation,
+            crop: picvu_export.crop,
         };
     
         let msg = picvudb::msgs::AddObjectRequest{ data };
@@ -155,10 +442,20 @@ pub fn create_add_object_for_import(
     let mut duration = None;
     let mut tags = Vec::new();
 
+    // The time recorded by the camera, which
+    // may need its clock to be corrected
+
+    let mut camera_taken = None;
+    let mut camera_make_model = None;
+
     // Try and guess the MIME type
 
-    let mut mime = guess_mime_type_from_filename(file_name)
-        .ok_or(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Cannot guess MIME type for file {}", file_name)))?;
+    let mut mime = match guess_mime_type_from_filename(file_name)
+    {
+        Some(mime) => mime,
+        None if import_options.unknown_files == UnknownFileHandling::StoreAsDocument => mime::APPLICATION_OCTET_STREAM,
+        None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Cannot guess MIME type for file {}", file_name))),
+    };
 
     // Often, the file created time is not useful - it will be the last time
     // the file was copied to a new folder. However, copy operations
@@ -286,7 +583,9 @@ pub fn create_add_object_for_import(
                     // image, then this is the best guess of when
                     // the photo was actually taken.
 
-                    obj_activity_time = analysis.orig_taken;
+                    obj_activity_time = analysis.orig_taken.clone();
+                    camera_taken = analysis.orig_taken.clone();
+                    camera_make_model = analysis.make_model.clone();
                 }
 
                 if analysis.location.is_some()
@@ -313,7 +612,10 @@ pub fn create_add_object_for_import(
 
     if mime.type_() == mime::IMAGE
     {
-        let image = image::load_from_memory(&bytes);
+        // Camera RAW and HEIF/HEIC files can't be decoded directly,
+        // so this will use their previews
+
+        let image = analyse::img::load_image(&bytes);
         if let Ok(image) = image
         {
             dimensions = Some(picvudb::data::Dimensions::new(image.width(), image.height()));
@@ -336,7 +638,7 @@ pub fn create_add_object_for_import(
                 // It's a JPEG with an MP4 attached - analyse the
                 // MP4 to collect a duration
 
-                match analyse::video::analyse_video(&bytes[mp4_offset..], file_name, 128, &import_options.assume_timezone, Some(google_cache), warnings)
+                match analyse::video::analyse_video(&bytes[mp4_offset..], file_name, Some(128), &import_options.assume_timezone, Some(google_cache), warnings)
                 {
                     Err(err) =>
                     {
@@ -367,7 +669,7 @@ pub fn create_add_object_for_import(
 
     if mime.type_() == mime::VIDEO
     {
-        match analyse::video::analyse_video(&bytes, file_name, 128, &import_options.assume_timezone, Some(google_cache), warnings)
+        match analyse::video::analyse_video(&bytes, file_name, Some(128), &import_options.assume_timezone, Some(google_cache), warnings)
         {
             Err(err) =>
             {
@@ -381,7 +683,9 @@ pub fn create_add_object_for_import(
                     // video has a date embedded in it, we will
                     // take this as a good guess.
 
-                    obj_activity_time = info.date;
+                    obj_activity_time = info.date.clone();
+                    camera_taken = info.date;
+                    camera_make_model = info.make_model;
                 }
 
                 if location.is_none()
@@ -407,6 +711,17 @@ pub fn create_add_object_for_import(
         }
     }
 
+    // Correct for cameras whose clocks were wrong, now
+    // that the time the camera recorded is known
+
+    if let (Some(taken), Some(make_model)) = (&camera_taken, &camera_make_model)
+    {
+        if let Some(corrected) = ClockCorrection::correct(&import_options.clock_corrections, make_model, taken)
+        {
+            obj_activity_time = Some(corrected);
+        }
+    }
+
     // Adjust the dimensions for any orienatation
     // that will be applied to the raw image
 
@@ -454,6 +769,14 @@ pub fn create_add_object_for_import(
         }
     }
 
+    if let (Some(track_log), Some(activity_time)) = (&import_options.track_log, &obj_activity_time)
+    {
+        if location.is_none()
+        {
+            location = track_log.locate(activity_time);
+        }
+    }
+
     if let Some(assume_location) = import_options.assume_location.clone()
     {
         if location.is_none()
@@ -471,13 +794,23 @@ pub fn create_add_object_for_import(
         {
             Ok(rev_geocode) =>
             {
+                // Names in the address hierarchy are nested
+                // under the broader name before them, e.g.
+                // Australia > Victoria > Melbourne
+
                 for name in rev_geocode.names
                 {
+                    let parent = rev_geocode.hierarchy.iter()
+                        .position(|h| *h == name)
+                        .filter(|pos| *pos > 0)
+                        .map(|pos| rev_geocode.hierarchy[pos - 1].clone());
+
                     tags.push(picvudb::data::add::Tag{
                         name: name,
                         kind: picvudb::data::TagKind::Location,
                         rating: picvudb::data::Rating::NotRated,
                         censor: picvudb::data::Censor::FamilyFriendly,
+                        parent,
                     });
                 }
             },
@@ -495,8 +828,17 @@ pub fn create_add_object_for_import(
         kind: picvudb::data::TagKind::Unsorted,
         rating: picvudb::data::Rating::NotRated,
         censor: picvudb::data::Censor::FamilyFriendly,
+        parent: None,
     });
     
+    // Keep a copy of all the EXIF metadata so it can be searched
+
+    let exif = if mime.type_() == mime::IMAGE { analyse::img::extract_exif(&bytes) } else { None };
+
+    // Similarly, documents have their text extracted for searching
+
+    let text = analyse::document::extract_text(&bytes, file_name, &mime, warnings);
+
     // Construct the Add Object request
 
     let attachment = picvudb::data::add::Attachment
@@ -508,6 +850,9 @@ pub fn create_add_object_for_import(
         orientation: orientation,
         dimensions: dimensions,
         duration: duration,
+        exif: exif,
+        text: text,
+        role: picvudb::data::AttachmentRole::Primary,
         bytes: bytes,
     };
 
@@ -521,12 +866,54 @@ pub fn create_add_object_for_import(
         modified_time: None,
         activity_time: obj_activity_time,
         location: location,
-        attachment: attachment,
+        attachment: Some(attachment),
+        additional_attachments: Vec::new(),
         tags: tags,
         ext_ref: opt_ext_ref,
+        user_orientation: None,
+        crop: None,
     };
 
     let msg = picvudb::msgs::AddObjectRequest{ data };
 
     Ok(msg)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_glob_matches()
+    {
+        assert!(glob_matches(b".DS_Store", b".DS_Store"));
+        assert!(glob_matches(b"thumbs.db", b"Thumbs.db"));
+        assert!(glob_matches(b"*.xmp", b"IMG_0001.CR2.xmp"));
+        assert!(glob_matches(b"IMG_????.txt", b"IMG_0001.txt"));
+        assert!(glob_matches(b"*", b""));
+        assert!(glob_matches(b"Takeout/*/*.html", b"Takeout/Google Photos/index.html"));
+
+        assert!(!glob_matches(b"*.xmp", b"IMG_0001.jpg"));
+        assert!(!glob_matches(b"IMG_????.txt", b"IMG_001.txt"));
+        assert!(!glob_matches(b"a*b", b"a"));
+    }
+
+    #[test]
+    fn test_pairing()
+    {
+        assert_eq!(pairing_stem("DCIM/IMG_0001.CR2"), "dcim/img_0001");
+        assert_eq!(pairing_stem("DCIM/IMG_0001.jpg"), "dcim/img_0001");
+        assert_eq!(pairing_stem("DCIM/IMG_0001.CR2.xmp"), "dcim/img_0001");
+        assert_eq!(pairing_stem("DCIM/IMG_0001.xmp"), "dcim/img_0001");
+
+        let file_names = vec!["IMG_0001.MOV".to_owned(), "IMG_0001.xmp".to_owned(), "IMG_0001.HEIC".to_owned(), "IMG_0001.CR2".to_owned()];
+
+        assert_eq!(order_paired_files(&file_names), vec![
+            (2, picvudb::data::AttachmentRole::Primary),
+            (3, picvudb::data::AttachmentRole::Raw),
+            (0, picvudb::data::AttachmentRole::Motion),
+            (1, picvudb::data::AttachmentRole::Sidecar),
+        ]);
+    }
+}
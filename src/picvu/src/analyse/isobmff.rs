@@ -0,0 +1,161 @@
+// Reading the boxes of the ISO Base Media File Format, which
+// is used by MP4/QuickTime movies and HEIF/HEIC images. Each
+// box is a big-endian 32-bit size (or 1, followed by a 64-bit
+// size, or 0 to extend to the end), a 4 byte type and then
+// the box's contents - which may be more boxes.
+
+pub struct BoxIter<'a>
+{
+    data: &'a [u8],
+}
+
+impl<'a> BoxIter<'a>
+{
+    pub fn new(data: &'a [u8]) -> Self
+    {
+        BoxIter { data }
+    }
+}
+
+impl<'a> Iterator for BoxIter<'a>
+{
+    type Item = ([u8; 4], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        if self.data.len() < 8
+        {
+            return None;
+        }
+
+        let size = be_u32(self.data, 0)? as u64;
+        let kind = [self.data[4], self.data[5], self.data[6], self.data[7]];
+
+        let (header_len, size) = match size
+        {
+            0 => (8, self.data.len() as u64),
+            1 => (16, be_u64(self.data, 8)?),
+            _ => (8, size),
+        };
+
+        if size < header_len || size > (self.data.len() as u64)
+        {
+            // Truncated or corrupt - stop here
+
+            self.data = &[];
+            return None;
+        }
+
+        let body = &self.data[(header_len as usize)..(size as usize)];
+        self.data = &self.data[(size as usize)..];
+
+        Some((kind, body))
+    }
+}
+
+/// Returns the contents of the first box of the given type.
+pub fn find_box<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]>
+{
+    BoxIter::new(data)
+        .find(|(k, _)| k == kind)
+        .map(|(_, body)| body)
+}
+
+/// Reads a big-endian unsigned integer of up to 8 bytes,
+/// advancing the position past it.
+pub fn read_uint(data: &[u8], pos: &mut usize, size: usize) -> Option<u64>
+{
+    if size > 8
+    {
+        return None;
+    }
+
+    let bytes = data.get(*pos..pos.checked_add(size)?)?;
+
+    let mut result: u64 = 0;
+
+    for b in bytes
+    {
+        result = (result << 8) | (*b as u64);
+    }
+
+    *pos += size;
+
+    Some(result)
+}
+
+pub fn be_u16(data: &[u8], offset: usize) -> Option<u16>
+{
+    let mut pos = offset;
+    read_uint(data, &mut pos, 2).map(|v| v as u16)
+}
+
+pub fn be_u32(data: &[u8], offset: usize) -> Option<u32>
+{
+    let mut pos = offset;
+    read_uint(data, &mut pos, 4).map(|v| v as u32)
+}
+
+pub fn be_u64(data: &[u8], offset: usize) -> Option<u64>
+{
+    let mut pos = offset;
+    read_uint(data, &mut pos, 8)
+}
+
+#[cfg(test)]
+pub fn make_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8>
+{
+    let mut result = Vec::new();
+    result.extend_from_slice(&((body.len() + 8) as u32).to_be_bytes());
+    result.extend_from_slice(kind);
+    result.extend_from_slice(body);
+    result
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_box_iter()
+    {
+        let mut data = make_box(b"ftyp", b"isom");
+
+        // A box with a 64-bit size
+
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&18u64.to_be_bytes());
+        data.extend_from_slice(b"ab");
+
+        // A truncated box, which ends the iteration
+
+        data.extend_from_slice(&100u32.to_be_bytes());
+        data.extend_from_slice(b"free");
+
+        let boxes = BoxIter::new(&data).collect::<Vec<_>>();
+
+        assert_eq!(boxes, vec![(*b"ftyp", &b"isom"[..]), (*b"mdat", &b"ab"[..])]);
+        assert_eq!(find_box(&data, b"mdat"), Some(&b"ab"[..]));
+        assert_eq!(find_box(&data, b"moov"), None);
+    }
+
+    #[test]
+    fn test_read_uint()
+    {
+        let data = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+
+        let mut pos = 1;
+        assert_eq!(read_uint(&data, &mut pos, 2), Some(0x0203));
+        assert_eq!(pos, 3);
+        assert_eq!(read_uint(&data, &mut pos, 0), Some(0));
+        assert_eq!(read_uint(&data, &mut pos, 8), None);
+        assert_eq!(pos, 3);
+
+        assert_eq!(be_u16(&data, 6), Some(0x0708));
+        assert_eq!(be_u32(&data, 0), Some(0x01020304));
+        assert_eq!(be_u64(&data, 0), Some(0x0102030405060708));
+        assert_eq!(be_u32(&data, 6), None);
+    }
+}
@@ -1,7 +1,13 @@
+pub mod clock;
+pub mod document;
 pub mod google;
+pub mod heif;
 pub mod img;
 pub mod import;
+pub mod isobmff;
+pub mod mp4;
 pub mod takeout;
+pub mod tracklog;
 pub mod tz;
 pub mod video;
 pub mod warning;
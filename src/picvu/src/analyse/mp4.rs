@@ -0,0 +1,418 @@
+use chrono::TimeZone;
+
+use crate::analyse::isobmff::{be_u16, be_u32, be_u64, BoxIter};
+
+// A minimal MP4/QuickTime metadata reader, used to collect
+// video metadata when ffprobe isn't available.
+
+/// Seconds between the MP4 epoch (1904-01-01) and the UNIX epoch.
+const MP4_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+#[derive(Debug, Default)]
+pub struct Mp4Metadata
+{
+    pub compatible_brands: String,
+    pub creation_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub duration_seconds: Option<f64>,
+    pub tracks: Vec<Mp4Track>,
+    pub tags: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Mp4Track
+{
+    pub handler: String,
+    pub codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub rotation: Option<f64>,
+    pub frame_rate: Option<f64>,
+}
+
+pub fn parse_mp4(bytes: &[u8]) -> Result<Mp4Metadata, std::io::Error>
+{
+    let mut result = Mp4Metadata::default();
+    let mut found_moov = false;
+
+    for (kind, body) in BoxIter::new(bytes)
+    {
+        match &kind
+        {
+            b"ftyp" =>
+            {
+                // Major brand, minor version, then the compatible
+                // brands, which ffprobe reports concatenated together
+
+                if let Some(brands) = body.get(8..)
+                {
+                    result.compatible_brands = brands.chunks_exact(4)
+                        .map(|b| String::from_utf8_lossy(b).to_string())
+                        .collect::<String>();
+                }
+            },
+            b"moov" =>
+            {
+                found_moov = true;
+                parse_moov(body, &mut result);
+            },
+            _ => {},
+        }
+    }
+
+    if !found_moov
+    {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "No MP4/QuickTime movie header found"));
+    }
+
+    Ok(result)
+}
+
+fn mp4_time_to_chrono(time: u64) -> Option<chrono::DateTime<chrono::Utc>>
+{
+    if time == 0
+    {
+        return None;
+    }
+
+    chrono::Utc.timestamp_opt((time as i64) - MP4_EPOCH_OFFSET, 0).single()
+}
+
+/// Reads the (creation, timescale, duration) from a `mvhd` or `mdhd`
+/// box, which share the same layout up to the duration.
+fn parse_header_timing(body: &[u8]) -> Option<(u64, u32, u64)>
+{
+    let (creation, timescale, duration) = if *body.first()? == 1
+    {
+        (be_u64(body, 4)?, be_u32(body, 20)?, be_u64(body, 24)?)
+    }
+    else
+    {
+        let duration = be_u32(body, 16)?;
+        let duration = if duration == u32::MAX { u64::MAX } else { duration as u64 };
+
+        (be_u32(body, 4)? as u64, be_u32(body, 12)?, duration)
+    };
+
+    Some((creation, timescale, duration))
+}
+
+fn parse_moov(moov: &[u8], result: &mut Mp4Metadata)
+{
+    for (kind, body) in BoxIter::new(moov)
+    {
+        match &kind
+        {
+            b"mvhd" =>
+            {
+                if let Some((creation, timescale, duration)) = parse_header_timing(body)
+                {
+                    result.creation_time = mp4_time_to_chrono(creation);
+
+                    if timescale != 0 && duration != u64::MAX
+                    {
+                        result.duration_seconds = Some((duration as f64) / (timescale as f64));
+                    }
+                }
+            },
+            b"trak" =>
+            {
+                result.tracks.push(parse_trak(body));
+            },
+            b"udta" =>
+            {
+                parse_udta(body, result);
+            },
+            b"meta" =>
+            {
+                parse_meta(body, result);
+            },
+            _ => {},
+        }
+    }
+}
+
+fn parse_trak(trak: &[u8]) -> Mp4Track
+{
+    let mut track = Mp4Track::default();
+
+    for (kind, body) in BoxIter::new(trak)
+    {
+        match &kind
+        {
+            b"tkhd" =>
+            {
+                parse_tkhd(body, &mut track);
+            },
+            b"mdia" =>
+            {
+                parse_mdia(body, &mut track);
+            },
+            _ => {},
+        }
+    }
+
+    track
+}
+
+fn parse_tkhd(body: &[u8], track: &mut Mp4Track) -> Option<()>
+{
+    let base = if *body.first()? == 1 { 36 } else { 24 };
+
+    // The transformation matrix is stored as 16.16 fixed point
+    // values - the rotation is given by the first two
+
+    let a = be_u32(body, base + 16)? as i32;
+    let b = be_u32(body, base + 20)? as i32;
+
+    if a != 0 || b != 0
+    {
+        track.rotation = Some((b as f64).atan2(a as f64).to_degrees());
+    }
+
+    let width = be_u32(body, base + 52)? >> 16;
+    let height = be_u32(body, base + 56)? >> 16;
+
+    if width != 0 && height != 0
+    {
+        track.width = Some(width);
+        track.height = Some(height);
+    }
+
+    Some(())
+}
+
+fn parse_mdia(mdia: &[u8], track: &mut Mp4Track)
+{
+    let mut timing = None;
+    let mut sample_count = None;
+
+    for (kind, body) in BoxIter::new(mdia)
+    {
+        match &kind
+        {
+            b"hdlr" =>
+            {
+                if let Some(handler) = body.get(8..12)
+                {
+                    track.handler = String::from_utf8_lossy(handler).to_string();
+                }
+            },
+            b"mdhd" =>
+            {
+                timing = parse_header_timing(body);
+            },
+            b"minf" =>
+            {
+                for (kind, body) in BoxIter::new(body)
+                {
+                    if &kind == b"stbl"
+                    {
+                        for (kind, body) in BoxIter::new(body)
+                        {
+                            match &kind
+                            {
+                                b"stsd" =>
+                                {
+                                    track.codec = body.get(12..16).map(codec_name);
+                                },
+                                b"stsz" =>
+                                {
+                                    sample_count = be_u32(body, 8);
+                                },
+                                _ => {},
+                            }
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    if track.handler == "vide"
+    {
+        if let (Some((_, timescale, duration)), Some(sample_count)) = (timing, sample_count)
+        {
+            if timescale != 0 && duration != 0 && duration != u64::MAX && sample_count != 0
+            {
+                track.frame_rate = Some((sample_count as f64) * (timescale as f64) / (duration as f64));
+            }
+        }
+    }
+}
+
+fn codec_name(fourcc: &[u8]) -> String
+{
+    match fourcc
+    {
+        b"avc1" | b"avc3" => "h264".to_owned(),
+        b"hvc1" | b"hev1" => "hevc".to_owned(),
+        b"mp4v" => "mpeg4".to_owned(),
+        b"mp4a" => "aac".to_owned(),
+        _ => String::from_utf8_lossy(fourcc).trim().to_owned(),
+    }
+}
+
+fn parse_udta(udta: &[u8], result: &mut Mp4Metadata)
+{
+    for (kind, body) in BoxIter::new(udta)
+    {
+        match &kind
+        {
+            [0xA9, b'x', b'y', b'z'] =>
+            {
+                // QuickTime user data text - a length,
+                // language code and then the ISO 6709 string
+
+                if let Some(len) = be_u16(body, 0)
+                {
+                    if let Some(text) = body.get(4..(4 + (len as usize)))
+                    {
+                        result.tags.push(("location".to_owned(), String::from_utf8_lossy(text).to_string()));
+                    }
+                }
+            },
+            b"meta" =>
+            {
+                parse_meta(body, result);
+            },
+            _ => {},
+        }
+    }
+}
+
+fn parse_meta(meta: &[u8], result: &mut Mp4Metadata)
+{
+    // In MP4 files "meta" is a full box with a version and flags,
+    // but in QuickTime files the children start immediately
+
+    let children = if meta.get(4..8) == Some(&b"hdlr"[..]) { meta } else { meta.get(4..).unwrap_or_default() };
+
+    let mut keys = Vec::new();
+
+    for (kind, body) in BoxIter::new(children)
+    {
+        match &kind
+        {
+            b"keys" =>
+            {
+                let mut offset = 8;
+
+                while let Some(size) = be_u32(body, offset)
+                {
+                    let size = size as usize;
+                    if size < 8
+                    {
+                        break;
+                    }
+
+                    match body.get((offset + 8)..(offset + size))
+                    {
+                        Some(key) => keys.push(String::from_utf8_lossy(key).to_string()),
+                        None => break,
+                    }
+
+                    offset += size;
+                }
+            },
+            b"ilst" =>
+            {
+                for (index, item) in BoxIter::new(body)
+                {
+                    // Items are identified by their 1-based index into the keys
+
+                    let index = u32::from_be_bytes(index) as usize;
+
+                    if let Some(key) = index.checked_sub(1).and_then(|i| keys.get(i))
+                    {
+                        for (kind, data) in BoxIter::new(item)
+                        {
+                            // Only UTF-8 values (type 1) are of interest
+
+                            if &kind == b"data" && be_u32(data, 0) == Some(1)
+                            {
+                                if let Some(value) = data.get(8..)
+                                {
+                                    result.tags.push((key.clone(), String::from_utf8_lossy(value).to_string()));
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::analyse::isobmff::make_box;
+
+    #[test]
+    fn test_parse_mp4()
+    {
+        // 2021-05-04T03:22:11Z in MP4 time
+
+        let creation = (1620098531i64 + MP4_EPOCH_OFFSET) as u32;
+
+        let mut mvhd = vec![0u8; 100];
+        mvhd[4..8].copy_from_slice(&creation.to_be_bytes());
+        mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&12500u32.to_be_bytes());
+
+        // A 1920x1080 track rotated by 90 degrees
+
+        let mut tkhd = vec![0u8; 84];
+        tkhd[40..44].copy_from_slice(&0u32.to_be_bytes());
+        tkhd[44..48].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        tkhd[76..80].copy_from_slice(&(1920u32 << 16).to_be_bytes());
+        tkhd[80..84].copy_from_slice(&(1080u32 << 16).to_be_bytes());
+
+        let mut hdlr = vec![0u8; 24];
+        hdlr[8..12].copy_from_slice(b"vide");
+
+        let mut mdhd = vec![0u8; 24];
+        mdhd[12..16].copy_from_slice(&30u32.to_be_bytes());
+        mdhd[16..20].copy_from_slice(&375u32.to_be_bytes());
+
+        let mut stsd = vec![0u8; 16];
+        stsd[12..16].copy_from_slice(b"avc1");
+
+        let mut stsz = vec![0u8; 12];
+        stsz[8..12].copy_from_slice(&375u32.to_be_bytes());
+
+        let stbl = make_box(b"stbl", &[make_box(b"stsd", &stsd), make_box(b"stsz", &stsz)].concat());
+        let minf = make_box(b"minf", &stbl);
+        let mdia = make_box(b"mdia", &[make_box(b"hdlr", &hdlr), make_box(b"mdhd", &mdhd), minf].concat());
+        let trak = make_box(b"trak", &[make_box(b"tkhd", &tkhd), mdia].concat());
+
+        let xyz = [&[0u8, 18, 0, 0][..], &b"+37.3318-122.0312/"[..]].concat();
+        let udta = make_box(b"udta", &make_box(&[0xA9, b'x', b'y', b'z'], &xyz));
+
+        let moov = make_box(b"moov", &[make_box(b"mvhd", &mvhd), trak, udta].concat());
+        let ftyp = make_box(b"ftyp", b"mp42\0\0\0\0mp42avc1niko");
+
+        let file = [ftyp, moov, make_box(b"mdat", &[0u8; 16])].concat();
+
+        let result = parse_mp4(&file).unwrap();
+
+        assert_eq!(result.compatible_brands, "mp42avc1niko");
+        assert_eq!(result.creation_time.unwrap().to_rfc3339(), "2021-05-04T03:22:11+00:00");
+        assert_eq!(result.duration_seconds, Some(12.5));
+        assert_eq!(result.tags, vec![("location".to_owned(), "+37.3318-122.0312/".to_owned())]);
+
+        assert_eq!(result.tracks.len(), 1);
+        let track = &result.tracks[0];
+        assert_eq!(track.handler, "vide");
+        assert_eq!(track.codec.as_deref(), Some("h264"));
+        assert_eq!(track.width, Some(1920));
+        assert_eq!(track.height, Some(1080));
+        assert!((track.rotation.unwrap() - 90.0).abs() < 0.001);
+        assert_eq!(track.frame_rate, Some(30.0));
+
+        assert!(parse_mp4(b"not a movie").is_err());
+    }
+}
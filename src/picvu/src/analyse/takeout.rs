@@ -46,4 +46,32 @@ pub fn parse_google_photos_takeout_metadata(json_bytes: Vec<u8>, err_path: &Stri
         .map_err(|e| { std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Google Photos takeout metadata {} could not be decoded: {:?}", err_path, e)) })?;
 
     Ok(metadata)
-}
\ No newline at end of file
+}
+
+/// Creates equivalent metadata for a media item retrieved directly
+/// from the Google Photos Library API, so that it can be imported
+/// in the same way as a Takeout file.
+pub fn metadata_from_library_media_item(media_item: &googlephotos::api::msgs::MediaItem) -> Result<Metadata, std::io::Error>
+{
+    let creation_time: chrono::DateTime<chrono::Utc> = media_item.media_metadata.creation_time.parse()
+        .map_err(|_| { std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Google Photos media item {} has an invalid creation time {:?}", media_item.filename, media_item.media_metadata.creation_time)) })?;
+
+    let timestamp = Timestamp
+    {
+        timestamp: creation_time.timestamp().to_string(),
+        formatted: media_item.media_metadata.creation_time.clone(),
+    };
+
+    Ok(Metadata
+    {
+        title: media_item.filename.clone(),
+        description: media_item.description.clone().unwrap_or_default(),
+        image_views: String::new(),
+        creation_time: timestamp.clone(),
+        modification_time: None,
+        geo_data: None,
+        geo_data_exif: None,
+        photo_taken_time: timestamp,
+        photo_last_modified_time: None,
+    })
+}
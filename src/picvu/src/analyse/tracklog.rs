@@ -0,0 +1,398 @@
+use chrono::{DateTime, Utc};
+
+// GPX and KML files are XML, but only a few elements are
+// needed, so they're found with a simple scan rather than
+// a full XML parser.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackPoint
+{
+    pub time: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+/// The recorded points from one or more GPS track logs,
+/// used to locate photos taken without GPS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackLog
+{
+    points: Vec<TrackPoint>,
+    /// Photos more than this far (in time) from the
+    /// track points either side of them aren't located
+    pub max_gap: chrono::Duration,
+    /// Added to an object's time to get the track log time
+    pub clock_offset: chrono::Duration,
+}
+
+impl TrackLog
+{
+    pub fn new(max_gap: chrono::Duration, clock_offset: chrono::Duration) -> Self
+    {
+        TrackLog
+        {
+            points: Vec::new(),
+            max_gap,
+            clock_offset,
+        }
+    }
+
+    /// Adds the points from a GPX or KML file, based on its file extension.
+    pub fn add_file(&mut self, file_name: &str, data: &[u8]) -> Result<(), String>
+    {
+        let xml = String::from_utf8_lossy(data);
+        let lower_name = file_name.to_lowercase();
+
+        let mut points = if lower_name.ends_with(".gpx")
+        {
+            parse_gpx(&xml)?
+        }
+        else if lower_name.ends_with(".kml")
+        {
+            parse_kml(&xml)?
+        }
+        else
+        {
+            return Err(format!("{}: Track logs must be GPX or KML files", file_name));
+        };
+
+        if points.is_empty()
+        {
+            return Err(format!("{}: No timestamped track points found", file_name));
+        }
+
+        self.points.append(&mut points);
+        self.points.sort_by(|a, b| a.time.cmp(&b.time));
+
+        Ok(())
+    }
+
+    /// Loads track logs from a list of file paths
+    pub fn load_files(paths: &Vec<String>, max_gap: chrono::Duration, clock_offset: chrono::Duration) -> Result<Self, String>
+    {
+        let mut result = TrackLog::new(max_gap, clock_offset);
+
+        for path in paths
+        {
+            let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+
+            result.add_file(path, &data)?;
+        }
+
+        Ok(result)
+    }
+
+    pub fn num_points(&self) -> usize
+    {
+        self.points.len()
+    }
+
+    /// Finds the location at a specified time by interpolating
+    /// between the track points either side of it.
+    pub fn locate(&self, date: &picvudb::data::Date) -> Option<picvudb::data::Location>
+    {
+        let time = date.to_chrono_utc() + self.clock_offset;
+
+        // Find the first point at or after the time
+
+        let after_index = self.points.partition_point(|p| p.time < time);
+
+        let before = if after_index > 0 { self.points.get(after_index - 1) } else { None };
+        let after = self.points.get(after_index);
+
+        let (latitude, longitude, altitude) = match (before, after)
+        {
+            (_, Some(after)) if after.time == time =>
+            {
+                (after.latitude, after.longitude, after.altitude)
+            },
+            (Some(before), Some(after)) =>
+            {
+                if (after.time - before.time) > self.max_gap
+                {
+                    return None;
+                }
+
+                let fraction = ((time - before.time).num_milliseconds() as f64) / ((after.time - before.time).num_milliseconds() as f64);
+                let interpolate = |a: f64, b: f64| a + ((b - a) * fraction);
+
+                (
+                    interpolate(before.latitude, after.latitude),
+                    interpolate(before.longitude, after.longitude),
+                    match (before.altitude, after.altitude)
+                    {
+                        (Some(a), Some(b)) => Some(interpolate(a, b)),
+                        _ => None,
+                    },
+                )
+            },
+            (Some(end), None)
+                | (None, Some(end)) =>
+            {
+                // Before the start or after the end of the
+                // track - only use it if it's close enough
+
+                let gap = if end.time > time { end.time - time } else { time - end.time };
+
+                if gap > self.max_gap
+                {
+                    return None;
+                }
+
+                (end.latitude, end.longitude, end.altitude)
+            },
+            (None, None) =>
+            {
+                return None;
+            },
+        };
+
+        Some(picvudb::data::Location::new(picvudb::data::LocationSource::TrackLog, latitude, longitude, altitude))
+    }
+}
+
+/// Finds each element with the specified name, returning
+/// its attributes and contents.
+fn find_elements<'a>(xml: &'a str, name: &str) -> Vec<(&'a str, &'a str)>
+{
+    let mut result = Vec::new();
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut pos = 0;
+
+    while let Some(found) = xml[pos..].find(&open)
+    {
+        let start = pos + found + open.len();
+
+        // Make sure this isn't just an element whose name starts with the same text
+
+        match xml[start..].chars().next()
+        {
+            Some(c) if c == '>' || c == '/' || c.is_whitespace() => {},
+            _ =>
+            {
+                pos = start;
+                continue;
+            },
+        }
+
+        let tag_end = match xml[start..].find('>')
+        {
+            Some(e) => start + e,
+            None => break,
+        };
+
+        let attrs = &xml[start..tag_end];
+
+        if attrs.ends_with('/')
+        {
+            result.push((&attrs[..(attrs.len() - 1)], ""));
+            pos = tag_end + 1;
+            continue;
+        }
+
+        let content_end = match xml[(tag_end + 1)..].find(&close)
+        {
+            Some(e) => tag_end + 1 + e,
+            None => break,
+        };
+
+        result.push((attrs, &xml[(tag_end + 1)..content_end]));
+        pos = content_end + close.len();
+    }
+
+    result
+}
+
+fn find_attribute<'a>(attrs: &'a str, name: &str) -> Option<&'a str>
+{
+    for quote in &['"', '\'']
+    {
+        let pattern = format!("{}={}", name, quote);
+        let mut pos = 0;
+
+        while let Some(found) = attrs[pos..].find(&pattern)
+        {
+            let start = pos + found;
+
+            if start == 0
+                || attrs[..start].ends_with(char::is_whitespace)
+            {
+                let value_start = start + pattern.len();
+                let value_end = attrs[value_start..].find(*quote)? + value_start;
+
+                return Some(&attrs[value_start..value_end]);
+            }
+
+            pos = start + pattern.len();
+        }
+    }
+
+    None
+}
+
+fn find_text<'a>(xml: &'a str, name: &str) -> Option<&'a str>
+{
+    find_elements(xml, name).into_iter().next().map(|(_, content)| content.trim())
+}
+
+fn parse_time(s: &str) -> Result<DateTime<Utc>, String>
+{
+    DateTime::parse_from_rfc3339(s.trim())
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| format!("Invalid track point time {:?}: {}", s, e))
+}
+
+fn parse_f64(s: &str) -> Result<f64, String>
+{
+    s.trim().parse().map_err(|_| format!("Invalid track point value {:?}", s))
+}
+
+fn parse_gpx(xml: &str) -> Result<Vec<TrackPoint>, String>
+{
+    let mut result = Vec::new();
+
+    for (attrs, content) in find_elements(xml, "trkpt")
+    {
+        // Points without a time can't be matched to a photo
+
+        if let Some(time) = find_text(content, "time")
+        {
+            result.push(TrackPoint
+            {
+                time: parse_time(time)?,
+                latitude: parse_f64(find_attribute(attrs, "lat").ok_or("Track point without a latitude")?)?,
+                longitude: parse_f64(find_attribute(attrs, "lon").ok_or("Track point without a longitude")?)?,
+                altitude: find_text(content, "ele").map(parse_f64).transpose()?,
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Parses KML coordinates - "longitude,latitude[,altitude]",
+/// or separated by spaces in "gx:coord" elements.
+fn parse_kml_coords(s: &str, separator: char) -> Result<(f64, f64, Option<f64>), String>
+{
+    let parts = s.trim().split(separator).filter(|p| !p.is_empty()).collect::<Vec<_>>();
+
+    match parts.as_slice()
+    {
+        [lon, lat] => Ok((parse_f64(lat)?, parse_f64(lon)?, None)),
+        [lon, lat, alt] => Ok((parse_f64(lat)?, parse_f64(lon)?, Some(parse_f64(alt)?))),
+        _ => Err(format!("Invalid KML coordinates {:?}", s)),
+    }
+}
+
+fn parse_kml(xml: &str) -> Result<Vec<TrackPoint>, String>
+{
+    let mut result = Vec::new();
+
+    // Tracks have a list of times followed
+    // by a list of matching coordinates
+
+    for (_, track) in find_elements(xml, "gx:Track")
+    {
+        let whens = find_elements(track, "when");
+        let coords = find_elements(track, "gx:coord");
+
+        if whens.len() != coords.len()
+        {
+            return Err("KML track has a different number of times and coordinates".to_owned());
+        }
+
+        for ((_, when), (_, coord)) in whens.into_iter().zip(coords.into_iter())
+        {
+            let (latitude, longitude, altitude) = parse_kml_coords(coord, ' ')?;
+
+            result.push(TrackPoint{ time: parse_time(when)?, latitude, longitude, altitude });
+        }
+    }
+
+    // Placemarks can also have a single time and point
+
+    for (_, placemark) in find_elements(xml, "Placemark")
+    {
+        if let (Some(when), Some(point)) = (find_text(placemark, "when"), find_text(placemark, "Point"))
+        {
+            if let Some(coords) = find_text(point, "coordinates")
+            {
+                let (latitude, longitude, altitude) = parse_kml_coords(coords, ',')?;
+
+                result.push(TrackPoint{ time: parse_time(when)?, latitude, longitude, altitude });
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn date(s: &str) -> picvudb::data::Date
+    {
+        picvudb::data::Date::from_rfc3339(s).unwrap()
+    }
+
+    #[test]
+    fn test_gpx_interpolation()
+    {
+        let gpx = r#"<?xml version="1.0"?>
+            <gpx version="1.1"><trk><trkseg>
+                <trkpt lat="-27.0" lon="153.0"><ele>10</ele><time>2019-06-10T00:00:00Z</time></trkpt>
+                <trkpt lat='-27.1' lon='153.2'><ele>20</ele><time>2019-06-10T00:10:00Z</time></trkpt>
+                <trkpt lat="-28.0" lon="154.0"><time>2019-06-10T05:00:00Z</time></trkpt>
+            </trkseg></trk></gpx>"#;
+
+        let mut log = TrackLog::new(chrono::Duration::minutes(30), chrono::Duration::zero());
+        log.add_file("walk.GPX", gpx.as_bytes()).unwrap();
+        assert_eq!(log.num_points(), 3);
+
+        let loc = log.locate(&date("2019-06-10T10:05:00+10:00")).unwrap();
+        assert_eq!(loc.source, picvudb::data::LocationSource::TrackLog);
+        assert!((loc.latitude - -27.05).abs() < 1e-9);
+        assert!((loc.longitude - 153.1).abs() < 1e-9);
+        assert_eq!(loc.altitude, Some(15.0));
+
+        // The gap between the 2nd and 3rd points is too large,
+        // but times near the end of the track are accepted
+
+        assert_eq!(log.locate(&date("2019-06-10T02:00:00Z")), None);
+        assert!(log.locate(&date("2019-06-10T05:20:00Z")).is_some());
+        assert_eq!(log.locate(&date("2019-06-10T06:00:00Z")), None);
+
+        // A camera clock 5 minutes slow
+
+        let log = TrackLog{ clock_offset: chrono::Duration::minutes(5), ..log };
+        let loc = log.locate(&date("2019-06-10T00:00:00Z")).unwrap();
+        assert!((loc.latitude - -27.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_kml()
+    {
+        let kml = r#"<kml><Document>
+            <Placemark><gx:Track>
+                <when>2019-06-10T00:00:00Z</when>
+                <when>2019-06-10T00:10:00Z</when>
+                <gx:coord>153.0 -27.0 10</gx:coord>
+                <gx:coord>153.2 -27.1 20</gx:coord>
+            </gx:Track></Placemark>
+            <Placemark><TimeStamp><when>2019-06-10T01:00:00Z</when></TimeStamp><Point><coordinates>154.0,-28.0</coordinates></Point></Placemark>
+            </Document></kml>"#;
+
+        let mut log = TrackLog::new(chrono::Duration::hours(1), chrono::Duration::zero());
+        log.add_file("trip.kml", kml.as_bytes()).unwrap();
+        assert_eq!(log.num_points(), 3);
+
+        let loc = log.locate(&date("2019-06-10T01:00:00Z")).unwrap();
+        assert_eq!((loc.latitude, loc.longitude, loc.altitude), (-28.0, 154.0, None));
+
+        assert!(log.add_file("trip.txt", kml.as_bytes()).is_err());
+    }
+}
@@ -1,7 +1,11 @@
+use std::collections::HashMap;
 use std::io::{Read, Write};
+use serde::Deserialize;
 
 use picvudb::data::{Date, Dimensions, Duration, Location, LocationSource, Orientation};
 use crate::analyse::google::GoogleCache;
+use crate::analyse::img::MakeModel;
+use crate::analyse::mp4;
 use crate::analyse::tz::ExplicitTimezone;
 use crate::analyse::warning::{Warning, WarningKind};
 
@@ -22,169 +26,392 @@ pub struct VideoAnalysisResults
     pub dimensions: Option<Dimensions>,
     pub duration: Option<Duration>,
     pub thumbnail: Option<Thumbnail>,
+    pub codec: Option<String>,
+    pub frame_rate: Option<f64>,
+    pub bit_rate: Option<u64>,
+    pub has_audio: bool,
+    pub make_model: Option<MakeModel>,
 }
 
-pub fn analyse_video(bytes: &[u8], filename: &str, thumbnail_size: u32, assume_timezone: &Option<ExplicitTimezone>, google_cache: Option<&GoogleCache>, warnings: &mut Vec<Warning>) -> Result<VideoAnalysisResults, std::io::Error>
+/// The subset of the output of
+/// `ffprobe -print_format json -show_format -show_streams`
+/// that we're interested in.
+#[derive(Debug, Default, Deserialize)]
+struct FfprobeOutput
+{
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+    #[serde(default)]
+    format: FfprobeFormat,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FfprobeStream
+{
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    disposition: FfprobeDisposition,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    side_data_list: Vec<FfprobeSideData>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FfprobeDisposition
+{
+    #[serde(default)]
+    attached_pic: u32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FfprobeSideData
+{
+    rotation: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FfprobeFormat
+{
+    duration: Option<String>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+impl FfprobeStream
+{
+    fn is_type(&self, codec_type: &str) -> bool
+    {
+        self.codec_type.as_deref() == Some(codec_type)
+    }
+
+    fn frame_rate(&self) -> Option<f64>
+    {
+        self.avg_frame_rate.as_deref().and_then(parse_rational)
+            .or_else(|| self.r_frame_rate.as_deref().and_then(parse_rational))
+    }
+
+    fn orientation(&self) -> Option<Orientation>
+    {
+        // Older versions of ffprobe report a "rotate" tag holding the
+        // clockwise rotation required to display the video. Newer versions
+        // report a display matrix rotation, which is counter-clockwise.
+
+        let degrees = match get_tag(&self.tags, "rotate").and_then(|r| r.trim().parse::<f64>().ok())
+        {
+            Some(rotate) => rotate,
+            None => -self.side_data_list.iter().filter_map(|sd| sd.rotation).next()?,
+        };
+
+        match (degrees.round() as i64).rem_euclid(360)
+        {
+            0 => Some(Orientation::Straight),
+            90 => Some(Orientation::RotatedLeft),
+            180 => Some(Orientation::UpsideDown),
+            270 => Some(Orientation::RotatedRight),
+            _ => None,
+        }
+    }
+}
+
+fn get_tag<'a>(tags: &'a HashMap<String, String>, name: &str) -> Option<&'a str>
+{
+    tags.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+fn parse_rational(value: &str) -> Option<f64>
+{
+    let (num, den) = match value.find('/')
+    {
+        Some(offset) => (value[0..offset].trim().parse::<f64>().ok()?, value[(offset + 1)..].trim().parse::<f64>().ok()?),
+        None => (value.trim().parse::<f64>().ok()?, 1.0),
+    };
+
+    if den == 0.0 || num == 0.0
+    {
+        return None;
+    }
+
+    Some(num / den)
+}
+
+/// Parses an ISO 6709 location string, such as
+/// "+37.3318-122.0312+012.000/", as written into
+/// the "location" and "com.apple.quicktime.location.ISO6709" tags.
+fn parse_iso6709(value: &str) -> Option<Location>
+{
+    let value = value.trim().trim_end_matches('/');
+
+    let starts = value.char_indices()
+        .filter(|(_, c)| *c == '+' || *c == '-')
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    if starts.len() < 2 || starts.len() > 3 || starts[0] != 0
+    {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    for (i, start) in starts.iter().enumerate()
+    {
+        let end = starts.get(i + 1).cloned().unwrap_or(value.len());
+        parts.push(value[*start..end].parse::<f64>().ok()?);
+    }
+
+    let latitude = parts[0];
+    let longitude = parts[1];
+    let altitude = parts.get(2).cloned();
+
+    if latitude.abs() > 90.0 || longitude.abs() > 180.0
+    {
+        return None;
+    }
+
+    Some(Location::new(LocationSource::CameraGps, latitude, longitude, altitude))
+}
+
+/// Returns the camera's make and model, which Apple devices
+/// (and some cameras) record in the QuickTime metadata.
+fn parse_make_model(tags: &HashMap<String, String>) -> Option<MakeModel>
+{
+    let make = get_tag(tags, "com.apple.quicktime.make").or_else(|| get_tag(tags, "make"))?;
+    let model = get_tag(tags, "com.apple.quicktime.model").or_else(|| get_tag(tags, "model"))?;
+
+    Some(MakeModel{ make: make.trim().to_owned(), model: model.trim().to_owned() })
+}
+
+/// Parses the "com.apple.quicktime.creationdate" tag, which,
+/// unlike "creation_time", is in local time with an offset.
+fn parse_quicktime_creation_date(value: &str) -> Option<Date>
+{
+    let value = value.trim();
+
+    chrono::DateTime::parse_from_rfc3339(value)
+        .or_else(|_| chrono::DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z"))
+        .or_else(|_| chrono::DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f%z"))
+        .ok()
+        .map(|d| Date::from_chrono_fixed(&d))
+}
+
+fn run_ffprobe(path: &std::path::Path) -> Result<FfprobeOutput, std::io::Error>
+{
+    let output = std::process::Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-print_format")
+        .arg("json")
+        .arg("-show_format")
+        .arg("-show_streams")
+        .arg(path)
+        .output()?;
+
+    if !output.status.success()
+    {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr).trim())));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("ffprobe output could not be decoded: {}", e)))
+}
+
+/// Converts the metadata from the built-in MP4 parser into the same
+/// form as the ffprobe output, so that it's interpreted identically.
+fn ffprobe_output_from_mp4(mp4: mp4::Mp4Metadata, file_size: usize) -> FfprobeOutput
+{
+    let mut format = FfprobeFormat::default();
+
+    if let Some(duration) = mp4.duration_seconds
+    {
+        format.duration = Some(duration.to_string());
+
+        if duration > 0.0
+        {
+            format.bit_rate = Some((((file_size as f64) * 8.0 / duration) as u64).to_string());
+        }
+    }
+
+    if !mp4.compatible_brands.is_empty()
+    {
+        format.tags.insert("compatible_brands".to_owned(), mp4.compatible_brands);
+    }
+
+    if let Some(creation_time) = mp4.creation_time
+    {
+        format.tags.insert("creation_time".to_owned(), creation_time.to_rfc3339());
+    }
+
+    for (key, value) in mp4.tags
+    {
+        format.tags.entry(key).or_insert(value);
+    }
+
+    let streams = mp4.tracks.into_iter()
+        .map(|track|
+        {
+            let mut stream = FfprobeStream::default();
+
+            stream.codec_type = Some(match track.handler.as_str()
+                {
+                    "vide" => "video".to_owned(),
+                    "soun" => "audio".to_owned(),
+                    other => other.to_owned(),
+                });
+            stream.codec_name = track.codec;
+            stream.width = track.width;
+            stream.height = track.height;
+            stream.avg_frame_rate = track.frame_rate.map(|f| f.to_string());
+
+            if let Some(rotation) = track.rotation
+            {
+                stream.tags.insert("rotate".to_owned(), rotation.round().to_string());
+            }
+
+            stream
+        })
+        .collect();
+
+    FfprobeOutput { streams, format }
+}
+
+fn is_not_found(err: &std::io::Error) -> bool
+{
+    err.kind() == std::io::ErrorKind::NotFound
+}
+
+pub fn analyse_video(bytes: &[u8], filename: &str, thumbnail_size: Option<u32>, assume_timezone: &Option<ExplicitTimezone>, google_cache: Option<&GoogleCache>, warnings: &mut Vec<Warning>) -> Result<VideoAnalysisResults, std::io::Error>
 {
     let mut date = None;
     let mut location = None;
-    let mut orientation = None;
-    let mut dimensions = None;
-    let mut duration = None;
     let mut thumbnail = None;
 
-    let mut times_are_local = false;
-
     let video_file = tempfile::NamedTempFile::new()?;
     video_file.as_file().write_all(bytes)?;
 
-    let output = std::process::Command::new("ffprobe").arg(video_file.path()).output()?;
-    let output = String::from_utf8(output.stderr).map_err(|e| { std::io::Error::new(std::io::ErrorKind::InvalidData, format!("ffprobe output is no UTF-8: {:?}", e)) })?;
+    // If ffprobe isn't installed, fall back to our own
+    // (more limited) parser rather than failing
 
-    for line in output.split('\n')
+    let mut tools_missing = false;
+
+    let probe = match run_ffprobe(video_file.path())
     {
-        if let Some(offset) = line.find(':')
+        Ok(probe) => probe,
+        Err(err) if is_not_found(&err) =>
         {
-            let param = line[0..offset].trim();
-            let value = line[(offset+1)..].trim();
-            
-            match param
+            warnings.push(Warning::new(filename, WarningKind::VideoToolsNotFound,
+                "ffprobe is not installed - using the built-in MP4/QuickTime parser, and no thumbnail can be generated"));
+
+            tools_missing = true;
+
+            ffprobe_output_from_mp4(mp4::parse_mp4(bytes)?, bytes.len())
+        },
+        Err(err) => return Err(err),
+    };
+
+    // Cover art is reported as a video stream, so skip it
+
+    let video_stream = probe.streams.iter()
+        .find(|s| s.is_type("video") && s.disposition.attached_pic == 0);
+
+    let has_audio = probe.streams.iter().any(|s| s.is_type("audio"));
+
+    let dimensions = video_stream
+        .and_then(|s| match (s.width, s.height)
             {
-                "compatible_brands" =>
-                {
-                    // For the movies my Nikon CoolPix W300 makes,
-                    // FFPROBE reports the creation time with a "Z" prefix,
-                    // but the times are actually in local time
+                (Some(w), Some(h)) if w != 0 && h != 0 => Some(Dimensions::new(w, h)),
+                _ => None,
+            });
 
-                    if value == "mp42avc1niko"
-                    {
-                        times_are_local = true;
-                    }
-                },
-                "creation_time" =>
-                {
-                    if date.is_none()
-                    {
-                        if times_are_local
-                        {
-                            if let Some(assume_timezone) = assume_timezone.clone()
-                            {
-                                if let Ok(decoded) = value.parse::<chrono::DateTime<chrono::Utc>>()
-                                {
-                                    let naive = decoded.naive_local();
-                                    let to_local = assume_timezone.from_local_assuming_tz(&naive);
-                                    date = Some(to_local);
-                                }
-                            }
-                            else
-                            {
-                                warnings.push(Warning::new(filename, WarningKind::VideoAnalysis,
-                                    format!("No assumed timezone has been provided to process video creation time in local time format: {}", value)));
-                            }
-                        }
-                        else if let Ok(decoded) = value.parse::<chrono::DateTime<chrono::Utc>>()
-                        {
-                            date = Some(Date::from_chrono_utc(&decoded));
-                        }
-                    }
-                },
-                "location" =>
-                {
-                    let value = value.trim_end_matches('/');
-                    if let Some(offset) = value.rfind(|c| c == '-' || c == '+')
-                    {
-                        if let Ok(lat) = value[0..offset].parse::<f64>()
-                        {
-                            if let Ok(long) = value[(offset + 1)..].parse::<f64>()
-                            {
-                                location = Some(Location::new(
-                                    LocationSource::CameraGps,
-                                    lat,
-                                    long,
-                                    None));
-                            }
-                        }
-                    }
-                },
-                "rotate" =>
-                {
-                    if value == "0"
-                    {
-                        orientation = Some(Orientation::Straight);
-                    }
-                    else if value == "90"
-                    {
-                        orientation = Some(Orientation::RotatedLeft);
-                    }
-                    else if value == "180"
-                    {
-                        orientation = Some(Orientation::UpsideDown);
-                    }
-                    else if value == "270"
-                    {
-                        orientation = Some(Orientation::RotatedRight);
-                    }
-                },
-                "Duration" =>
+    let orientation = video_stream.and_then(|s| s.orientation());
+    let codec = video_stream.and_then(|s| s.codec_name.clone());
+    let frame_rate = video_stream.and_then(|s| s.frame_rate());
+
+    let bit_rate = probe.format.bit_rate.as_deref()
+        .or_else(|| video_stream.and_then(|s| s.bit_rate.as_deref()))
+        .and_then(|b| b.trim().parse::<u64>().ok());
+
+    let duration = probe.format.duration.as_deref()
+        .and_then(|d| d.trim().parse::<f64>().ok())
+        .filter(|d| *d >= 0.0)
+        .map(|d| Duration::from_seconds(d as u32));
+
+    let tags = &probe.format.tags;
+
+    let make_model = parse_make_model(tags);
+
+    // Apple devices record the location and a creation date
+    // with the local offset in QuickTime metadata - prefer these
+
+    if let Some(value) = get_tag(tags, "com.apple.quicktime.location.ISO6709")
+    {
+        location = parse_iso6709(value);
+    }
+
+    if location.is_none()
+    {
+        if let Some(value) = get_tag(tags, "location")
+        {
+            location = parse_iso6709(value);
+        }
+    }
+
+    let mut date_has_offset = false;
+
+    if let Some(value) = get_tag(tags, "com.apple.quicktime.creationdate")
+    {
+        date = parse_quicktime_creation_date(value);
+        date_has_offset = date.is_some();
+    }
+
+    if date.is_none()
+    {
+        let creation_time = get_tag(tags, "creation_time")
+            .or_else(|| video_stream.and_then(|s| get_tag(&s.tags, "creation_time")));
+
+        if let Some(value) = creation_time
+        {
+            // For the movies my Nikon CoolPix W300 makes,
+            // FFPROBE reports the creation time with a "Z" prefix,
+            // but the times are actually in local time
+
+            let times_are_local = get_tag(tags, "compatible_brands") == Some("mp42avc1niko");
+
+            if times_are_local
+            {
+                if let Some(assume_timezone) = assume_timezone.clone()
                 {
-                    if let Some(hms) = value.split('.').nth(0)
+                    if let Ok(decoded) = value.parse::<chrono::DateTime<chrono::Utc>>()
                     {
-                        let parts = hms.split(':').collect::<Vec<_>>();
-
-                        if parts.len() == 3
-                        {
-                            if let Ok(h) = parts[0].parse::<u32>()
-                            {
-                                if let Ok(m) = parts[1].parse::<u32>()
-                                {
-                                    if let Ok(s) = parts[2].parse::<u32>()
-                                    {
-                                        let seconds = s + (60 * m) + (3600 * h);
-
-                                        duration = Some(Duration::from_seconds(seconds));
-                                    }
-                                }
-                            }
-                        }
+                        let naive = decoded.naive_local();
+                        let to_local = assume_timezone.from_local_assuming_tz(&naive);
+                        date = Some(to_local);
                     }
-                },
-                _ =>
+                }
+                else
                 {
-                    if param.starts_with("Stream #")
-                    {
-                        if let Some(_) = value.find("Video:")
-                        {
-                            for part in value.split(",")
-                            {
-                                let part = part.trim();
-                                if let Some(part) = part.split(' ').nth(0)
-                                {
-                                    let strs = part.split('x').collect::<Vec<_>>();
-                                    if strs.len() == 2
-                                    {
-                                        if let Ok(w) = strs[0].parse::<u32>()
-                                        {
-                                            if let Ok(h) = strs[1].parse::<u32>()
-                                            {
-                                                if dimensions.is_none()
-                                                {
-                                                    dimensions = Some(Dimensions::new(w, h));
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                },
+                    warnings.push(Warning::new(filename, WarningKind::VideoAnalysis,
+                        format!("No assumed timezone has been provided to process video creation time in local time format: {}", value)));
+                }
+            }
+            else if let Ok(decoded) = value.parse::<chrono::DateTime<chrono::Utc>>()
+            {
+                date = Some(Date::from_chrono_utc(&decoded));
             }
         }
     }
 
     // Update the time with timezone information if available
 
-    if let Some(google_cache) = google_cache
+    if let (Some(google_cache), false) = (google_cache, date_has_offset)
     {
         if let (Some(loc), Some(d)) = (&location, &date)
         {
@@ -203,9 +430,9 @@ pub fn analyse_video(bytes: &[u8], filename: &str, thumbnail_size: u32, assume_t
         }
     }
 
-    // Now, attempt to create a thumbnail
+    // Now, attempt to create a thumbnail, if one was requested
 
-    if let Some(dimensions) = dimensions.clone()
+    if let (Some(dimensions), Some(thumbnail_size), false) = (dimensions.clone(), thumbnail_size, tools_missing)
     {
         // FFMPEG automatically applies the rotation. But our dimensions
         // are of the raw data - not the rotated date.
@@ -229,9 +456,22 @@ pub fn analyse_video(bytes: &[u8], filename: &str, thumbnail_size: u32, assume_t
             .arg("0")
             .arg("-y")
             .arg(jpeg_file.path())
-            .output()?;
+            .output();
+
+        let output = match output
+        {
+            Ok(output) => Some(output),
+            Err(err) if is_not_found(&err) =>
+            {
+                warnings.push(Warning::new(filename, WarningKind::VideoToolsNotFound,
+                    "ffmpeg is not installed - no thumbnail can be generated"));
+
+                None
+            },
+            Err(err) => return Err(err),
+        };
 
-        if output.status.success()
+        if output.map(|o| o.status.success()).unwrap_or(false)
         {
             let mut bytes = Vec::new();
             jpeg_file.read_to_end(&mut bytes)?;
@@ -245,5 +485,73 @@ pub fn analyse_video(bytes: &[u8], filename: &str, thumbnail_size: u32, assume_t
         }
     }
 
-    Ok(VideoAnalysisResults { date, location, orientation, dimensions, duration, thumbnail })
-}
\ No newline at end of file
+    Ok(VideoAnalysisResults { date, location, orientation, dimensions, duration, thumbnail, codec, frame_rate, bit_rate, has_audio, make_model })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_parse_iso6709()
+    {
+        let loc = parse_iso6709("+37.3318-122.0312+012.000/").unwrap();
+        assert_eq!(loc.latitude, 37.3318);
+        assert_eq!(loc.longitude, -122.0312);
+        assert_eq!(loc.altitude, Some(12.0));
+
+        let loc = parse_iso6709("-33.8688+151.2093/").unwrap();
+        assert_eq!(loc.latitude, -33.8688);
+        assert_eq!(loc.longitude, 151.2093);
+        assert_eq!(loc.altitude, None);
+
+        assert!(parse_iso6709("").is_none());
+        assert!(parse_iso6709("37.3318").is_none());
+    }
+
+    #[test]
+    fn test_parse_ffprobe_json()
+    {
+        let json = r#"{
+            "streams": [
+                {
+                    "codec_name": "hevc",
+                    "codec_type": "video",
+                    "width": 1920,
+                    "height": 1080,
+                    "avg_frame_rate": "30000/1001",
+                    "side_data_list": [ { "side_data_type": "Display Matrix", "rotation": -90 } ]
+                },
+                {
+                    "codec_name": "aac",
+                    "codec_type": "audio"
+                }
+            ],
+            "format": {
+                "duration": "12.345000",
+                "bit_rate": "8123456",
+                "tags": {
+                    "com.apple.quicktime.location.ISO6709": "+37.3318-122.0312+012.000/",
+                    "com.apple.quicktime.creationdate": "2021-05-04T13:22:11+1000",
+                    "com.apple.quicktime.make": "Apple",
+                    "com.apple.quicktime.model": "iPhone 12 "
+                }
+            }
+        }"#;
+
+        let probe: FfprobeOutput = serde_json::from_str(json).unwrap();
+        let video = probe.streams.iter().find(|s| s.is_type("video")).unwrap();
+
+        assert_eq!(video.codec_name.as_deref(), Some("hevc"));
+        assert!(matches!(video.orientation(), Some(Orientation::RotatedLeft)));
+        assert!((video.frame_rate().unwrap() - 29.97).abs() < 0.01);
+        assert!(probe.streams.iter().any(|s| s.is_type("audio")));
+
+        let date = parse_quicktime_creation_date(get_tag(&probe.format.tags, "com.apple.quicktime.creationdate").unwrap()).unwrap();
+        assert_eq!(date.to_rfc3339(), "2021-05-04T13:22:11+10:00");
+
+        let make_model = parse_make_model(&probe.format.tags).unwrap();
+        assert_eq!((make_model.make.as_str(), make_model.model.as_str()), ("Apple", "iPhone 12"));
+    }
+}
@@ -27,6 +27,9 @@ pub enum WarningKind
     MvImgAnalysisError,
     VideoAnalysis,
     VideoAnalysisError,
+    VideoToolsNotFound,
+    DocumentAnalysisError,
+    DocumentToolsNotFound,
     SkippedDuplicateMvImgPart,
     NoGoogleTakeoutMetadataAvailable,
     MissingDimensions,
@@ -34,4 +37,7 @@ pub enum WarningKind
     ReverseGeocodeError,
     DuplicateGooglePhotosFilename,
     MissingGooglePhotosReference,
+    GooglePhotosLibraryItemSkipped,
+    UnknownFileSkipped,
+    RelatedObjectNotImported,
 }
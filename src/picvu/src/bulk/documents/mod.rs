@@ -0,0 +1,171 @@
+use std::future::Future;
+use std::pin::Pin;
+use actix_web::web;
+
+use picvudb::StoreAccess;
+use picvudb::ApiMessage;
+
+use crate::analyse;
+use crate::bulk::BulkOperation;
+use crate::bulk::progress::ProgressSender;
+
+/// Re-extracts the text of every document attachment, so that
+/// documents imported before their text was stored (or before
+/// pdftotext was installed) can be found by searching.
+pub struct DocumentTextBackfill
+{
+    db_uri: String,
+}
+
+impl DocumentTextBackfill
+{
+    pub fn new(db_uri: String) -> Self
+    {
+        DocumentTextBackfill
+        {
+            db_uri,
+        }
+    }
+}
+
+impl BulkOperation for DocumentTextBackfill
+{
+    type Error = actix_web::error::BlockingError<picvudb::Error>;
+    type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
+
+    fn name(&self) -> String
+    {
+        "Backfill document text".to_owned()
+    }
+
+    fn start(self, sender: ProgressSender) -> Self::Future
+    {
+        let db_uri = self.db_uri;
+
+        Box::pin(async move
+        {
+            web::block(move ||
+            {
+                sender.start_stage(
+                    "Loading objects".to_owned(),
+                    vec!["Extracting document text".to_owned(), "Summary".to_owned()]);
+
+                let store = picvudb::Store::new(&db_uri)?;
+
+                let objects =
+                {
+                    let msg = picvudb::msgs::GetObjectsRequest
+                    {
+                        query: picvudb::data::get::GetObjectsQuery::ByActivityDesc,
+                        pagination: None,
+                    };
+
+                    let results = store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+
+                    results.objects
+                        .into_iter()
+                        .filter(|o| o.attachment.iter()
+                            .chain(o.additional_attachments.iter())
+                            .any(|a| analyse::document::is_document_mime(&a.mime)))
+                        .collect::<Vec<_>>()
+                };
+
+                sender.start_stage(
+                    "Extracting document text".to_owned(),
+                    vec!["Summary".to_owned()]);
+
+                let num_objects = objects.len();
+                let mut done: usize = 0;
+                let mut summary_with_text: usize = 0;
+                let mut summary_without_text: usize = 0;
+
+                for object in objects
+                {
+                    done += 1;
+
+                    sender.set(
+                        (done as f64) / (num_objects as f64) * 100.0,
+                        vec![
+                            object.get_display_text(),
+                            format!("Processed {} of {} documents", done, num_objects),
+                            format!("{} with text", summary_with_text),
+                            format!("{} without text", summary_without_text),
+                        ]);
+
+                    // Like importing, the text of every document
+                    // attachment is stored, primary attachment first
+
+                    let mut texts = Vec::new();
+
+                    for attachment in object.attachment.iter().chain(object.additional_attachments.iter())
+                    {
+                        if !analyse::document::is_document_mime(&attachment.mime)
+                        {
+                            continue;
+                        }
+
+                        let data_msg = picvudb::msgs::GetAttachmentDataRequest
+                        {
+                            object_id: object.id.clone(),
+                            specific_hash: Some(attachment.hash.clone()),
+                        };
+
+                        let data_response = store.write_transaction(|ops|
+                        {
+                            data_msg.execute(ops)
+                        })?;
+
+                        if let picvudb::msgs::GetAttachmentDataResponse::Found{bytes, ..} = data_response
+                        {
+                            let mut warnings = Vec::new();
+
+                            if let Some(text) = analyse::document::extract_text(&bytes, &attachment.filename, &attachment.mime, &mut warnings)
+                            {
+                                texts.push(text);
+                            }
+                        }
+                    }
+
+                    // Text that can't be extracted now (such as when
+                    // pdftotext isn't installed) isn't removed
+
+                    if texts.is_empty()
+                    {
+                        summary_without_text += 1;
+                        continue;
+                    }
+
+                    summary_with_text += 1;
+
+                    let set_msg = picvudb::msgs::SetDocumentTextRequest
+                    {
+                        object_id: object.id,
+                        text: Some(texts.join("\n\n")),
+                    };
+
+                    store.write_transaction(|ops|
+                    {
+                        set_msg.execute(ops)
+                    })?;
+                }
+
+                sender.start_stage(
+                    "Summary".to_owned(),
+                    vec![]);
+
+                sender.set(100.0, vec![
+                    format!("Processed {} documents", num_objects),
+                    format!("{} with text", summary_with_text),
+                    format!("{} without text", summary_without_text),
+                ]);
+
+                Ok(())
+            }).await?;
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,150 @@
+use std::future::Future;
+use std::pin::Pin;
+use actix_web::web;
+
+use picvudb::StoreAccess;
+use picvudb::ApiMessage;
+
+use crate::analyse;
+use crate::bulk::BulkOperation;
+use crate::bulk::progress::ProgressSender;
+
+/// Re-reads the EXIF metadata from every image attachment
+/// and stores it, so that objects imported before the
+/// metadata was kept can also be searched.
+pub struct ExifBackfill
+{
+    db_uri: String,
+}
+
+impl ExifBackfill
+{
+    pub fn new(db_uri: String) -> Self
+    {
+        ExifBackfill
+        {
+            db_uri,
+        }
+    }
+}
+
+impl BulkOperation for ExifBackfill
+{
+    type Error = actix_web::error::BlockingError<picvudb::Error>;
+    type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
+
+    fn name(&self) -> String
+    {
+        "Backfill EXIF metadata".to_owned()
+    }
+
+    fn start(self, sender: ProgressSender) -> Self::Future
+    {
+        let db_uri = self.db_uri;
+
+        Box::pin(async move
+        {
+            web::block(move ||
+            {
+                sender.start_stage(
+                    "Loading objects".to_owned(),
+                    vec!["Extracting EXIF metadata".to_owned(), "Summary".to_owned()]);
+
+                let store = picvudb::Store::new(&db_uri)?;
+
+                let objects =
+                {
+                    let msg = picvudb::msgs::GetObjectsRequest
+                    {
+                        query: picvudb::data::get::GetObjectsQuery::ByActivityDesc,
+                        pagination: None,
+                    };
+
+                    let results = store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+
+                    results.objects
+                        .into_iter()
+                        .filter(|o| o.attachment.as_ref().map(|a| a.mime.type_() == mime::IMAGE).unwrap_or(false))
+                        .collect::<Vec<_>>()
+                };
+
+                sender.start_stage(
+                    "Extracting EXIF metadata".to_owned(),
+                    vec!["Summary".to_owned()]);
+
+                let num_objects = objects.len();
+                let mut done: usize = 0;
+                let mut summary_with_exif: usize = 0;
+                let mut summary_without_exif: usize = 0;
+
+                for object in objects
+                {
+                    done += 1;
+
+                    sender.set(
+                        (done as f64) / (num_objects as f64) * 100.0,
+                        vec![
+                            object.get_display_text(),
+                            format!("Processed {} of {} images", done, num_objects),
+                            format!("{} with EXIF metadata", summary_with_exif),
+                            format!("{} without EXIF metadata", summary_without_exif),
+                        ]);
+
+                    let data_msg = picvudb::msgs::GetAttachmentDataRequest
+                    {
+                        object_id: object.id.clone(),
+                        specific_hash: None,
+                    };
+
+                    let data_response = store.write_transaction(|ops|
+                    {
+                        data_msg.execute(ops)
+                    })?;
+
+                    let exif = match data_response
+                    {
+                        picvudb::msgs::GetAttachmentDataResponse::Found{bytes, ..} => analyse::img::extract_exif(&bytes),
+                        _ => continue,
+                    };
+
+                    if exif.is_some()
+                    {
+                        summary_with_exif += 1;
+                    }
+                    else
+                    {
+                        summary_without_exif += 1;
+                    }
+
+                    let set_msg = picvudb::msgs::SetAttachmentExifRequest
+                    {
+                        object_id: object.id,
+                        exif,
+                    };
+
+                    store.write_transaction(|ops|
+                    {
+                        set_msg.execute(ops)
+                    })?;
+                }
+
+                sender.start_stage(
+                    "Summary".to_owned(),
+                    vec![]);
+
+                sender.set(100.0, vec![
+                    format!("Processed {} images", num_objects),
+                    format!("{} with EXIF metadata", summary_with_exif),
+                    format!("{} without EXIF metadata", summary_without_exif),
+                ]);
+
+                Ok(())
+            }).await?;
+
+            Ok(())
+        })
+    }
+}
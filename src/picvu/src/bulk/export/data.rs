@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use picvudb::data::{Censor, Date, Dimensions, Duration, Location, Orientation, Rating, TagKind};
+use picvudb::data::{AttachmentRole, Censor, Crop, Date, Dimensions, Duration, Location, Orientation, Rating, RelationKind, TagKind};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttachmentMetadata
@@ -14,6 +14,8 @@ pub struct AttachmentMetadata
     pub dimensions: Option<Dimensions>,
     pub duration: Option<Duration>,
     pub hash: String,
+    #[serde(default)]
+    pub role: Option<AttachmentRole>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,11 +25,24 @@ pub struct TagMetadata
     pub kind: TagKind,
     pub rating: Rating,
     pub censor: Censor,
+    #[serde(default)]
+    pub parent: Option<String>,
+}
+
+/// A relation from the exported object to another,
+/// identified by the other object's `export_id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelationMetadata
+{
+    pub kind: RelationKind,
+    pub to: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObjectMetadata
 {
+    #[serde(default)]
+    pub export_id: Option<String>,
     pub created_time: Date,
     pub modified_time: Date,
     pub activity_time: Date,
@@ -36,8 +51,16 @@ pub struct ObjectMetadata
     pub rating: Rating,
     pub censor: Censor,
     pub location: Option<Location>,
-    pub attachment: AttachmentMetadata,
+    pub attachment: Option<AttachmentMetadata>,
+    #[serde(default)]
+    pub additional_attachments: Vec<AttachmentMetadata>,
     pub tags: Vec<TagMetadata>,
+    #[serde(default)]
+    pub user_orientation: Option<Orientation>,
+    #[serde(default)]
+    pub crop: Option<Crop>,
+    #[serde(default)]
+    pub relations: Vec<RelationMetadata>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,7 +84,20 @@ impl BulkOperation for Export
                         get_objects_req.execute(ops)
                     })?;
 
-                let total_bytes: u64 = get_objects_resp.objects.iter().map(|o| o.attachment.size).sum();
+                // Tags only know their parent's ID, so names
+                // are looked up to write the hierarchy
+
+                let tag_tree_resp = store.write_transaction(|ops|
+                    {
+                        picvudb::msgs::GetTagTreeRequest{}.execute(ops)
+                    })?;
+
+                let mut tag_names = std::collections::HashMap::new();
+                add_tag_names(&tag_tree_resp.roots, &mut tag_names);
+
+                let total_bytes: u64 = get_objects_resp.objects.iter()
+                    .map(|o| o.attachment.iter().chain(o.additional_attachments.iter()).map(|a| a.size).sum::<u64>())
+                    .sum();
 
                 sender.start_stage("Exporting".to_owned(), vec!["Cleaning Up".to_owned()]);
 
@@ -99,7 +112,7 @@ impl BulkOperation for Export
                             format!("{} of {} of media", format::bytes_to_string(bytes_done), format::bytes_to_string(total_bytes))]);
 
                     objs_done += 1;
-                    bytes_done += obj.attachment.size;
+                    bytes_done += obj.attachment.iter().chain(obj.additional_attachments.iter()).map(|a| a.size).sum::<u64>();
 
                     let activity_date = obj.activity_time.to_chrono_fixed_offset().date();
 
@@ -109,73 +122,142 @@ impl BulkOperation for Export
                         format!("{:02}", activity_date.day()),
                     ];
 
-                    let attachment_req = picvudb::msgs::GetAttachmentDataRequest
+                    let (json_filename, attachment_data, additional_attachments_data) = match &obj.attachment
                     {
-                        object_id: obj.id.clone(),
-                        specific_hash: None,
-                    };
+                        None =>
+                        {
+                            // Notes don't have any attachments, so just the
+                            // metadata is written, with a name that won't
+                            // clash with any media files
 
-                    let attachment_resp = store.write_transaction(|ops|
+                            (format!("note_{}.note.json", obj.id.to_string()), None, Vec::new())
+                        },
+                        Some(primary) =>
                         {
-                            attachment_req.execute(ops)
-                        })?;
+                            let attachment_req = picvudb::msgs::GetAttachmentDataRequest
+                            {
+                                object_id: obj.id.clone(),
+                                specific_hash: None,
+                            };
 
-                    if let picvudb::msgs::GetAttachmentDataResponse::Found{ metadata, bytes } = attachment_resp
-                    {
-                        writer.write_file(
-                            &path,
-                            &metadata.filename,
-                            &bytes)?;
+                            let attachment_resp = store.write_transaction(|ops|
+                                {
+                                    attachment_req.execute(ops)
+                                })?;
 
-                        let attachment_data = data::AttachmentMetadata
-                        {
-                            filename: obj.attachment.filename.clone(),
-                            created: obj.attachment.created.clone(),
-                            modified: obj.attachment.modified.clone(),
-                            mime: obj.attachment.mime.to_string(),
-                            size: obj.attachment.size.clone(),
-                            orientation: obj.attachment.orientation.clone(),
-                            dimensions: obj.attachment.dimensions.clone(),
-                            duration: obj.attachment.duration.clone(),
-                            hash: obj.attachment.hash.clone(),
-                        };
-
-                        let tags_data = obj.tags.iter().map(|t|
-                        {
-                            data::TagMetadata
+                            let metadata = match attachment_resp
                             {
-                                name: t.name.clone(),
-                                kind: t.kind.clone(),
-                                rating: t.rating.clone(),
-                                censor: t.censor.clone(),
+                                picvudb::msgs::GetAttachmentDataResponse::Found{ metadata, bytes } =>
+                                {
+                                    writer.write_file(
+                                        &path,
+                                        &metadata.filename,
+                                        &bytes)?;
+
+                                    metadata
+                                },
+                                _ =>
+                                {
+                                    return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "Could not load attachment data").into());
+                                },
+                            };
+
+                            // Additional attachments are written alongside, with the
+                            // same stem, so they're paired again when imported
+
+                            let mut additional_attachments_data = Vec::new();
+
+                            for additional in obj.additional_attachments.iter()
+                            {
+                                let additional_req = picvudb::msgs::GetAttachmentDataRequest
+                                {
+                                    object_id: obj.id.clone(),
+                                    specific_hash: Some(additional.hash.clone()),
+                                };
+
+                                let additional_resp = store.write_transaction(|ops|
+                                    {
+                                        additional_req.execute(ops)
+                                    })?;
+
+                                if let picvudb::msgs::GetAttachmentDataResponse::Found{ metadata, bytes } = additional_resp
+                                {
+                                    writer.write_file(
+                                        &path,
+                                        &metadata.filename,
+                                        &bytes)?;
+
+                                    additional_attachments_data.push(export_attachment_metadata(additional));
+                                }
+                                else
+                                {
+                                    return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "Could not load additional attachment data").into());
+                                }
                             }
-                        }).collect();
 
-                        let obj_data = data::ObjectMetadata
+                            (format!("{}.json", metadata.filename), Some(export_attachment_metadata(primary)), additional_attachments_data)
+                        },
+                    };
+
+                    let tags_data = obj.tags.iter().map(|t|
+                    {
+                        data::TagMetadata
+                        {
+                            name: t.name.clone(),
+                            kind: t.kind.clone(),
+                            rating: t.rating.clone(),
+                            censor: t.censor.clone(),
+                            parent: t.parent_id.as_ref().and_then(|p| tag_names.get(p).cloned()),
+                        }
+                    }).collect();
+
+                    // Only outgoing relations are written - the
+                    // incoming ones are written by the other object
+
+                    let relations_req = picvudb::msgs::GetObjectRelationsRequest
+                    {
+                        object_id: obj.id.clone(),
+                    };
+
+                    let relations_data = store.write_transaction(|ops|
                         {
-                            created_time: obj.created_time.clone(),
-                            modified_time: obj.modified_time.clone(),
-                            activity_time: obj.activity_time.clone(),
-                            title: obj.title.map(|m| m.get_markdown()),
-                            notes: obj.notes.map(|m| m.get_markdown()),
-                            rating: obj.rating.clone(),
-                            censor: obj.censor.clone(),
-                            location: obj.location.clone(),
-                            attachment: attachment_data,
-                            tags: tags_data,
-                        };
-
-                        let json_metadata = serde_json::to_string_pretty(&obj_data).unwrap();
-
-                        writer.write_file(
-                            &path,
-                            &format!("{}.json", metadata.filename),
-                            &json_metadata.as_bytes().to_vec())?;
-                    }
-                    else
+                            relations_req.execute(ops)
+                        })?
+                        .relations
+                        .into_iter()
+                        .filter(|r| r.outgoing)
+                        .map(|r| data::RelationMetadata
+                        {
+                            kind: r.kind,
+                            to: r.object.id.to_string(),
+                        })
+                        .collect();
+
+                    let obj_data = data::ObjectMetadata
                     {
-                        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "Could not load attachment data").into());
-                    }
+                        export_id: Some(obj.id.to_string()),
+                        created_time: obj.created_time.clone(),
+                        modified_time: obj.modified_time.clone(),
+                        activity_time: obj.activity_time.clone(),
+                        title: obj.title.map(|m| m.get_markdown()),
+                        notes: obj.notes.map(|m| m.get_markdown()),
+                        rating: obj.rating.clone(),
+                        censor: obj.censor.clone(),
+                        location: obj.location.clone(),
+                        attachment: attachment_data,
+                        additional_attachments: additional_attachments_data,
+                        tags: tags_data,
+                        user_orientation: obj.user_orientation.clone(),
+                        crop: obj.crop.clone(),
+                        relations: relations_data,
+                    };
+
+                    let json_metadata = serde_json::to_string_pretty(&obj_data).unwrap();
+
+                    writer.write_file(
+                        &path,
+                        &json_filename,
+                        &json_metadata.as_bytes().to_vec())?;
                 }
 
                 let export_metadata = data::ExportMetadata
@@ -207,3 +289,29 @@ impl BulkOperation for Export
         })
     }
 }
+
+fn export_attachment_metadata(attachment: &picvudb::data::get::AttachmentMetadata) -> data::AttachmentMetadata
+{
+    data::AttachmentMetadata
+    {
+        filename: attachment.filename.clone(),
+        created: attachment.created.clone(),
+        modified: attachment.modified.clone(),
+        mime: attachment.mime.to_string(),
+        size: attachment.size.clone(),
+        orientation: attachment.orientation.clone(),
+        dimensions: attachment.dimensions.clone(),
+        duration: attachment.duration.clone(),
+        hash: attachment.hash.clone(),
+        role: Some(attachment.role.clone()),
+    }
+}
+
+fn add_tag_names(nodes: &Vec<picvudb::data::get::TagTreeNode>, names: &mut std::collections::HashMap<picvudb::data::TagId, String>)
+{
+    for node in nodes.iter()
+    {
+        names.insert(node.tag.tag_id.clone(), node.tag.name.clone());
+        add_tag_names(&node.children, names);
+    }
+}
@@ -0,0 +1,133 @@
+use std::future::Future;
+use std::pin::Pin;
+use actix_web::web;
+
+use picvudb::StoreAccess;
+use picvudb::ApiMessage;
+
+use crate::analyse::tracklog::TrackLog;
+use crate::bulk::BulkOperation;
+use crate::bulk::progress::ProgressSender;
+
+/// Sets the location of existing objects that don't
+/// have one, using the GPS track logs.
+pub struct GeotagBulkOp
+{
+    db_uri: String,
+    track_log: TrackLog,
+}
+
+impl GeotagBulkOp
+{
+    pub fn new(db_uri: String, track_log: TrackLog) -> Self
+    {
+        GeotagBulkOp
+        {
+            db_uri,
+            track_log,
+        }
+    }
+}
+
+impl BulkOperation for GeotagBulkOp
+{
+    type Error = actix_web::error::BlockingError<picvudb::Error>;
+    type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
+
+    fn name(&self) -> String
+    {
+        "Geotag from track logs".to_owned()
+    }
+
+    fn start(self, sender: ProgressSender) -> Self::Future
+    {
+        let db_uri = self.db_uri;
+        let track_log = self.track_log;
+
+        Box::pin(async move
+        {
+            web::block(move ||
+            {
+                sender.start_stage(
+                    "Loading objects".to_owned(),
+                    vec!["Setting locations".to_owned(), "Summary".to_owned()]);
+
+                let store = picvudb::Store::new(&db_uri)?;
+
+                let objects =
+                {
+                    let msg = picvudb::msgs::GetObjectsRequest
+                    {
+                        query: picvudb::data::get::GetObjectsQuery::ByActivityDesc,
+                        pagination: None,
+                    };
+
+                    let results = store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+
+                    results.objects
+                        .into_iter()
+                        .filter(|o| o.location.is_none())
+                        .collect::<Vec<_>>()
+                };
+
+                sender.start_stage(
+                    "Setting locations".to_owned(),
+                    vec!["Summary".to_owned()]);
+
+                let num_objects = objects.len();
+                let mut done = 0;
+                let mut located = 0;
+
+                for object in objects
+                {
+                    done += 1;
+
+                    sender.set(
+                        (done as f64) / (num_objects as f64) * 100.0,
+                        vec![
+                            format!("Checked {} of {} objects without a location", done, num_objects),
+                            format!("Located {} objects", located),
+                        ]);
+
+                    if let Some(location) = track_log.locate(&object.activity_time)
+                    {
+                        located += 1;
+
+                        let msg = picvudb::msgs::UpdateObjectRequest
+                        {
+                            object_id: object.id,
+                            activity_time: object.activity_time,
+                            title: object.title,
+                            notes: object.notes,
+                            rating: object.rating,
+                            censor: object.censor,
+                            location: Some(location),
+                        };
+
+                        store.write_transaction(|ops|
+                        {
+                            msg.execute(ops)
+                        })?;
+                    }
+                }
+
+                sender.start_stage(
+                    "Summary".to_owned(),
+                    vec![]);
+
+                sender.set(100.0, vec![
+                    format!("Checked {} objects without a location", num_objects),
+                    format!("Located {} objects", located),
+                    format!("{} objects weren't covered by the track logs", num_objects - located),
+                ]);
+
+                Ok(())
+            }).await?;
+
+            Ok(())
+        })
+    }
+}
@@ -14,6 +14,8 @@ pub enum ImportError
     DbError { source: picvudb::Error },
     #[snafu(display("Sync Error: {:?}", source))]
     GoogleSyncError { source: SyncError },
+    #[snafu(display("Google Photos Auth Error: {}", msg))]
+    GoogleAuthError { msg: String },
 }
 
 impl From<std::io::Error> for ImportError
@@ -43,3 +45,10 @@ impl From<SyncError> for ImportError
         GoogleSyncSnafu{}.into_error(source)
     }
 }
+
+impl From<googlephotos::api::GoogleApiError> for ImportError
+{
+    fn from(source: googlephotos::api::GoogleApiError) -> Self {
+        GoogleSyncSnafu{}.into_error(source.into())
+    }
+}
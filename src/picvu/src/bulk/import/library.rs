@@ -0,0 +1,270 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::collections::HashSet;
+use std::sync::Arc;
+use actix_web::web;
+use actix_web::error::BlockingError;
+
+use picvudb::StoreAccess;
+use picvudb::ApiMessage;
+
+use googlephotos::auth::AccessToken;
+
+use crate::bulk::BulkOperation;
+use crate::bulk::progress::ProgressSender;
+use crate::format;
+use crate::analyse;
+use crate::analyse::warning::{Warning, WarningKind};
+
+use super::error::*;
+
+/// Returns a current access token - refreshing it if it has
+/// expired - or `None` if the user needs to login again.
+pub type AccessTokenSource = Box<dyn Fn() -> Pin<Box<dyn Future<Output=Result<Option<AccessToken>, String>>>>>;
+
+/// Imports media items directly from the Google Photos
+/// Library API. Items that are already linked to an
+/// object are skipped, so this can be run repeatedly
+/// to import new items as they are added.
+pub struct GooglePhotosLibraryImport
+{
+    db_uri: String,
+    google_api_key: String,
+    access_tokens: AccessTokenSource,
+    import_options: analyse::import::ImportOptions,
+}
+
+impl GooglePhotosLibraryImport
+{
+    pub fn new(db_uri: String, google_api_key: String, access_tokens: AccessTokenSource, import_options: analyse::import::ImportOptions) -> Self
+    {
+        GooglePhotosLibraryImport
+        {
+            db_uri,
+            google_api_key,
+            access_tokens,
+            import_options,
+        }
+    }
+}
+
+#[derive(Default)]
+struct LibraryImportSummary
+{
+    listed_media_items: usize,
+    already_linked: usize,
+    imported_media_files: usize,
+    imported_media_bytes: u64,
+    skipped_media_files: usize,
+    warnings: Vec<Warning>,
+}
+
+impl BulkOperation for GooglePhotosLibraryImport
+{
+    type Error = actix_web::error::BlockingError<ImportError>;
+    type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
+
+    fn name(&self) -> String
+    {
+        "Google Photos Library Import".to_owned()
+    }
+
+    fn start(self, sender: ProgressSender) -> Self::Future
+    {
+        let db_uri = self.db_uri;
+        let google_api_key = self.google_api_key;
+        let access_tokens = self.access_tokens;
+        let import_options = self.import_options;
+
+        Box::pin(async move
+        {
+            let linked_ids: Arc<HashSet<String>> =
+            {
+                let db_uri = db_uri.clone();
+                let sender = sender.clone();
+
+                web::block(move || -> Result<Arc<HashSet<String>>, ImportError>
+                {
+                    sender.start_stage(
+                        "Loading linked objects".to_owned(),
+                        vec!["Importing Media".to_owned(), "Summary".to_owned()]);
+
+                    let store = picvudb::Store::new(&db_uri)?;
+
+                    let msg = picvudb::msgs::GetObjectsRequest
+                    {
+                        query: picvudb::data::get::GetObjectsQuery::ByActivityDesc,
+                        pagination: None,
+                    };
+
+                    let results = store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+
+                    Ok(Arc::new(results.objects.into_iter()
+                        .filter_map(|o|
+                        {
+                            match o.ext_ref
+                            {
+                                Some(picvudb::data::ExternalReference::GooglePhotos{id}) => Some(id),
+                                None => None,
+                            }
+                        })
+                        .collect()))
+                }).await?
+            };
+
+            sender.start_stage(
+                "Importing Media".to_owned(),
+                vec!["Summary".to_owned()]);
+
+            let mut summary = LibraryImportSummary::default();
+            let mut next_page_token = None;
+
+            loop
+            {
+                // Importing a large library can take longer than
+                // an access token lasts, so a current token is
+                // fetched (and refreshed if needed) for each page
+
+                let access_token = match access_tokens().await
+                {
+                    Ok(Some(access_token)) => access_token,
+                    Ok(None) => return Err(BlockingError::Error(ImportError::GoogleAuthError{ msg: "Not logged in to Google Photos".to_owned() })),
+                    Err(msg) => return Err(BlockingError::Error(ImportError::GoogleAuthError{ msg })),
+                };
+
+                let db_uri = db_uri.clone();
+                let google_api_key = google_api_key.clone();
+                let import_options = import_options.clone();
+                let linked_ids = linked_ids.clone();
+                let sender = sender.clone();
+
+                let (page_summary, page_token) = web::block(move || -> Result<(LibraryImportSummary, Option<String>), ImportError>
+                {
+                    let mut summary = summary;
+
+                    let store = picvudb::Store::new(&db_uri)?;
+                    let google_cache = analyse::google::GoogleCache::new(google_api_key);
+
+                    let response = googlephotos::api::raw::media_items_list(&access_token, next_page_token)?;
+
+                    for media_item in response.media_items.unwrap_or_default()
+                    {
+                        summary.listed_media_items += 1;
+
+                        sender.set(0.0, vec![
+                            media_item.filename.clone(),
+                            format!("Listed {} media items", summary.listed_media_items),
+                            format!("{} already imported", summary.already_linked),
+                            format!("Imported {} media files", summary.imported_media_files),
+                            format!("Imported {} of media data", format::bytes_to_string(summary.imported_media_bytes)),
+                            format!("Skipped {} media files", summary.skipped_media_files),
+                        ]);
+
+                        if linked_ids.contains(&media_item.id)
+                        {
+                            summary.already_linked += 1;
+                            continue;
+                        }
+
+                        if analyse::import::guess_mime_type_from_filename(&media_item.filename).is_none()
+                        {
+                            summary.skipped_media_files += 1;
+
+                            summary.warnings.push(Warning::new(
+                                media_item.filename.clone(),
+                                WarningKind::GooglePhotosLibraryItemSkipped,
+                                format!("Unsupported media type {}", media_item.mime_type)));
+
+                            continue;
+                        }
+
+                        let metadata = analyse::takeout::metadata_from_library_media_item(&media_item)?;
+
+                        // A failed download shouldn't abort the whole
+                        // import - it's reported, and can be retried by
+                        // running the import again
+
+                        let bytes = match googlephotos::api::raw::media_item_download(&media_item)
+                        {
+                            Ok(bytes) => bytes,
+                            Err(err) =>
+                            {
+                                summary.skipped_media_files += 1;
+
+                                summary.warnings.push(Warning::new(
+                                    media_item.filename.clone(),
+                                    WarningKind::GooglePhotosLibraryItemSkipped,
+                                    format!("Download failed: {:?}", err)));
+
+                                continue;
+                            },
+                        };
+
+                        summary.imported_media_files += 1;
+                        summary.imported_media_bytes += bytes.len() as u64;
+
+                        let add_msg = analyse::import::create_add_object_for_import(
+                            bytes,
+                            &media_item.filename,
+                            &google_cache,
+                            &import_options,
+                            None,
+                            None,
+                            None,
+                            Some(metadata),
+                            Some(picvudb::data::ExternalReference::GooglePhotos{ id: media_item.id.clone() }),
+                            &mut summary.warnings)?;
+
+                        store.write_transaction(|ops|
+                        {
+                            add_msg.execute(ops)
+                        })?;
+                    }
+
+                    Ok((summary, response.next_page_token))
+                }).await?;
+
+                summary = page_summary;
+                next_page_token = page_token;
+
+                if next_page_token.is_none()
+                {
+                    break;
+                }
+            }
+
+            sender.start_stage(
+                "Summary".to_owned(),
+                vec![]);
+
+            let mut status = vec![
+                format!("Listed {} media items", summary.listed_media_items),
+                format!("{} were already imported", summary.already_linked),
+                format!("Imported {} media files", summary.imported_media_files),
+                format!("Imported {} of media data", format::bytes_to_string(summary.imported_media_bytes)),
+                format!("Skipped {} media files", summary.skipped_media_files),
+            ];
+
+            let mut warnings = summary.warnings;
+
+            if !warnings.is_empty()
+            {
+                status.push(format!("{} Warnings:", warnings.len()));
+
+                warnings.sort();
+
+                for w in warnings
+                {
+                    status.push(format!("{:?}", w));
+                }
+            }
+
+            sender.set(100.0, status);
+
+            Ok(())
+        })
+    }
+}
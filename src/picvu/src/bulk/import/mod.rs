@@ -1,6 +1,7 @@
 use std::future::Future;
 use std::pin::Pin;
 use std::collections::HashMap;
+use std::path::Path;
 use actix_web::web;
 
 use picvudb::StoreAccess;
@@ -17,14 +18,26 @@ use crate::analyse;
 use crate::analyse::warning::{Warning, WarningKind};
 
 mod error;
+mod library;
 mod scan;
 
 use error::*;
 
+pub use library::{AccessTokenSource, GooglePhotosLibraryImport};
+
 struct FoundMediaFileInfo
 {
     file_name: String,
     size: u64,
+    is_document: bool,
+}
+
+/// Files sharing a stem that are imported together as a single
+/// object. They're held until all of them have been read.
+struct PairedFiles
+{
+    members: Vec<(String, picvudb::data::AttachmentRole)>,
+    pending: HashMap<String, scan::FileEntry>,
 }
 
 pub struct FolderImport
@@ -78,9 +91,12 @@ impl BulkOperation for FolderImport
                     vec!["Loading Metadata".to_owned(), "Importing Media".to_owned(), "Summary".to_owned()]);
 
                 let mut path_to_info: HashMap<String, FoundMediaFileInfo> = HashMap::new();
+                let mut sidecar_path_to_info: HashMap<String, FoundMediaFileInfo> = HashMap::new();
                 let mut is_google_photos_takeout_archive = false;
                 let mut is_picvu_export_archive = false;
                 let mut warnings: Vec<Warning> = Vec::new();
+                let mut summary_ignored_files: usize = 0;
+                let mut summary_skipped_unknown_files: usize = 0;
 
                 {
                     for entry in scanner.clone_iter(|_| { false })
@@ -89,16 +105,35 @@ impl BulkOperation for FolderImport
 
                         sender.set(entry.percent, vec![entry.display_path.clone(), entry.progress_bytes]);
 
-                        if let Some(_mime_type) = analyse::import::guess_mime_type_from_filename(&entry.file_name)
+                        if self.import_options.is_ignored(&entry.archive_path, &entry.file_name)
+                        {
+                            summary_ignored_files += 1;
+                        }
+                        else if let Some(_mime_type) = analyse::import::guess_mime_type_from_filename(&entry.file_name)
                         {
                             let info = FoundMediaFileInfo
                             {
                                 file_name: entry.file_name,
                                 size: entry.size,
+                                is_document: false,
                             };
 
                             path_to_info.insert(entry.archive_path.clone(), info);
                         }
+                        else if analyse::import::is_sidecar_file_name(&entry.file_name)
+                        {
+                            // Sidecars are only imported if there's
+                            // a media file to pair them with (below)
+
+                            let info = FoundMediaFileInfo
+                            {
+                                file_name: entry.file_name,
+                                size: entry.size,
+                                is_document: true,
+                            };
+
+                            sidecar_path_to_info.insert(entry.archive_path.clone(), info);
+                        }
                         else if entry.file_name == "picvu.export.json"
                         {
                             // It is an export from this application
@@ -118,9 +153,35 @@ impl BulkOperation for FolderImport
                         }
                         else
                         {
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::InvalidData,
-                                format!("Unsupported file: {}", entry.display_path)).into());
+                            match self.import_options.unknown_files
+                            {
+                                analyse::import::UnknownFileHandling::Fail =>
+                                {
+                                    return Err(std::io::Error::new(
+                                        std::io::ErrorKind::InvalidData,
+                                        format!("Unsupported file: {}", entry.display_path)).into());
+                                },
+                                analyse::import::UnknownFileHandling::SkipWithWarning =>
+                                {
+                                    summary_skipped_unknown_files += 1;
+
+                                    warnings.push(Warning::new(
+                                        entry.display_path,
+                                        WarningKind::UnknownFileSkipped,
+                                        "Skipped unsupported file".to_owned()));
+                                },
+                                analyse::import::UnknownFileHandling::StoreAsDocument =>
+                                {
+                                    let info = FoundMediaFileInfo
+                                    {
+                                        file_name: entry.file_name,
+                                        size: entry.size,
+                                        is_document: true,
+                                    };
+
+                                    path_to_info.insert(entry.archive_path.clone(), info);
+                                },
+                            }
                         }
                     }
                 }
@@ -172,6 +233,7 @@ impl BulkOperation for FolderImport
 
                 let mut path_to_picvu_metadata: HashMap<String, export::data::ObjectMetadata> = HashMap::new();
                 let mut path_to_google_metadata: HashMap<String, analyse::takeout::Metadata> = HashMap::new();
+                let mut picvu_notes: Vec<(String, export::data::ObjectMetadata)> = Vec::new();
 
                 if is_picvu_export_archive || is_google_photos_takeout_archive
                 {
@@ -185,7 +247,19 @@ impl BulkOperation for FolderImport
 
                         sender.set(entry.percent, vec![entry.display_path.clone(), entry.progress_bytes, progress_files]);
 
-                        if entry.archive_path.ends_with(".json")
+                        if entry.archive_path.ends_with(".note.json")
+                        {
+                            // Notes have no media file - the
+                            // metadata is the whole object
+
+                            if is_picvu_export_archive
+                            {
+                                let metadata = export::data::parse_object_metadata(entry.bytes, &entry.display_path)?;
+
+                                picvu_notes.push((entry.display_path.clone(), metadata));
+                            }
+                        }
+                        else if entry.archive_path.ends_with(".json")
                         {
                             let media_name = entry.archive_path[0..(entry.archive_path.len()-5)].to_owned();
 
@@ -208,6 +282,107 @@ impl BulkOperation for FolderImport
                     }
                 }
 
+                // Picvu exports list each object's additional attachments,
+                // so these are grouped first, keeping the exported primary
+                // attachment and roles. Then files that share a stem, such
+                // as RAW+JPEG pairs, live photos and sidecar files, are
+                // paired so each group becomes a single object with
+                // multiple attachments
+
+                let mut paired_files: Vec<PairedFiles> = Vec::new();
+                let mut path_to_paired_files: HashMap<String, usize> = HashMap::new();
+
+                for (primary_path, metadata) in path_to_picvu_metadata.iter()
+                {
+                    let mut members = vec![(primary_path.clone(), picvudb::data::AttachmentRole::Primary)];
+
+                    for additional in metadata.additional_attachments.iter()
+                    {
+                        let path = Path::new(primary_path).with_file_name(&additional.filename).to_string_lossy().to_string();
+
+                        if let Some(info) = sidecar_path_to_info.remove(&path)
+                        {
+                            path_to_info.insert(path.clone(), info);
+                        }
+
+                        if path_to_info.contains_key(&path)
+                            && !path_to_paired_files.contains_key(&path)
+                            && !members.iter().any(|(p, _)| *p == path)
+                        {
+                            members.push((path, additional.role.clone().unwrap_or(picvudb::data::AttachmentRole::Original)));
+                        }
+                    }
+
+                    if members.len() > 1
+                    {
+                        for (path, _) in members.iter()
+                        {
+                            path_to_paired_files.insert(path.clone(), paired_files.len());
+                        }
+
+                        paired_files.push(PairedFiles{ members, pending: HashMap::new() });
+                    }
+                }
+
+                {
+                    let media_stems = path_to_info.iter()
+                        .filter(|(_, info)| !info.is_document)
+                        .map(|(path, _)| analyse::import::pairing_stem(path))
+                        .collect::<std::collections::HashSet<_>>();
+
+                    for (path, info) in sidecar_path_to_info.drain()
+                    {
+                        if media_stems.contains(&analyse::import::pairing_stem(&path))
+                            || self.import_options.unknown_files == analyse::import::UnknownFileHandling::StoreAsDocument
+                        {
+                            path_to_info.insert(path, info);
+                        }
+                        else
+                        {
+                            summary_skipped_unknown_files += 1;
+
+                            warnings.push(Warning::new(
+                                path,
+                                WarningKind::UnknownFileSkipped,
+                                "Skipped sidecar file with no matching media file".to_owned()));
+                        }
+                    }
+
+                    let mut stem_to_paths: HashMap<String, Vec<String>> = HashMap::new();
+
+                    for path in path_to_info.keys()
+                    {
+                        // Exported objects are never merged together
+
+                        if path_to_paired_files.contains_key(path)
+                            || path_to_picvu_metadata.contains_key(path)
+                        {
+                            continue;
+                        }
+
+                        stem_to_paths.entry(analyse::import::pairing_stem(path)).or_default().push(path.clone());
+                    }
+
+                    for (stem, paths) in stem_to_paths
+                    {
+                        if paths.len() > 1 && media_stems.contains(&stem)
+                        {
+                            let file_names = paths.iter().map(|p| path_to_info[p].file_name.clone()).collect();
+
+                            let members = analyse::import::order_paired_files(&file_names).into_iter()
+                                .map(|(i, role)| (paths[i].clone(), role))
+                                .collect::<Vec<_>>();
+
+                            for (path, _) in members.iter()
+                            {
+                                path_to_paired_files.insert(path.clone(), paired_files.len());
+                            }
+
+                            paired_files.push(PairedFiles{ members, pending: HashMap::new() });
+                        }
+                    }
+                }
+
                 sender.start_stage(
                     "Importing Media".to_owned(),
                     vec!["Summary".to_owned()]);
@@ -218,10 +393,20 @@ impl BulkOperation for FolderImport
 
                 let mut summary_imported_media_files: usize = 0;
                 let mut summary_imported_media_bytes: u64 = 0;
+                let mut summary_imported_documents: usize = 0;
                 let mut summary_with_picvu_metadata: usize = 0;
                 let mut summary_with_google_metadata: usize = 0;
                 let mut summary_with_location: usize = 0;
                 let mut summary_skipped_media_files: usize = 0;
+                let mut summary_paired_files: usize = 0;
+                let mut summary_imported_notes: usize = 0;
+                let mut summary_imported_relations: usize = 0;
+
+                // Relations refer to other objects by their exported ID, so
+                // they're added once all of the objects have been imported
+
+                let mut export_id_to_object_id: HashMap<String, picvudb::data::ObjectId> = HashMap::new();
+                let mut pending_relations: Vec<(picvudb::data::ObjectId, Vec<export::data::RelationMetadata>)> = Vec::new();
 
                 let store = picvudb::Store::new(&self.db_uri)?;
                 let import_options = self.import_options.clone();
@@ -231,7 +416,7 @@ impl BulkOperation for FolderImport
                     {
                         let entry = entry?;
 
-                        let progress_files = format!("Processed {} of {} media files", (summary_imported_media_files + summary_skipped_media_files), path_to_info.len());
+                        let progress_files = format!("Processed {} of {} media files", (summary_imported_media_files + summary_imported_documents + summary_skipped_media_files), path_to_info.len());
                         let progress_imported_files = format!("Imported {} media files", summary_imported_media_files);
                         let progress_imported_bytes = format!("Imported {} of media data", format::bytes_to_string(summary_imported_media_bytes));
                         let progress_picvu_metadata = format!("Processed {} of {} Picvu metadata files", summary_with_picvu_metadata, num_found_metadata_files);
@@ -239,10 +424,44 @@ impl BulkOperation for FolderImport
                         let progress_location = format!("Processed {} files with location data", summary_with_location);
                         let progress_skipped_files = format!("Skipped {} media files", summary_skipped_media_files);
 
-                        sender.set(entry.percent, vec![entry.display_path, entry.progress_bytes,
+                        sender.set(entry.percent, vec![entry.display_path.clone(), entry.progress_bytes.clone(),
                             progress_files, progress_imported_files, progress_imported_bytes,
                             progress_picvu_metadata, progress_google_metadata, progress_location, progress_skipped_files]);
 
+                        // Paired files are held until the whole group
+                        // has been read, and then imported together
+                        // with the primary attachment's metadata
+
+                        let (entry, paired_entries) = match path_to_paired_files.get(&entry.archive_path)
+                        {
+                            None => (entry, Vec::new()),
+                            Some(index) =>
+                            {
+                                let group = &mut paired_files[*index];
+
+                                group.pending.insert(entry.archive_path.clone(), entry);
+
+                                if group.pending.len() < group.members.len()
+                                {
+                                    continue;
+                                }
+
+                                let mut entries = Vec::new();
+
+                                for (path, role) in group.members.iter()
+                                {
+                                    if let Some(entry) = group.pending.remove(path)
+                                    {
+                                        entries.push((entry, role.clone()));
+                                    }
+                                }
+
+                                let (primary, _) = entries.remove(0);
+
+                                (primary, entries)
+                            },
+                        };
+
                         if let Some(found_info) = path_to_info.get(&entry.archive_path)
                         {
                             let picvu_metadata = path_to_picvu_metadata.get(&entry.archive_path).cloned();
@@ -262,6 +481,7 @@ impl BulkOperation for FolderImport
 
                             if is_google_photos_takeout_archive
                                 && google_metadata.is_none()
+                                && !found_info.is_document
                             {
                                 if found_info.file_name.starts_with("MVIMG")
                                     && found_info.file_name.ends_with("(1).jpg")
@@ -313,13 +533,22 @@ impl BulkOperation for FolderImport
                             }
                             else
                             {
-                                summary_imported_media_files += 1;
+                                if found_info.is_document
+                                {
+                                    summary_imported_documents += 1;
+                                }
+                                else
+                                {
+                                    summary_imported_media_files += 1;
+                                }
+
                                 summary_imported_media_bytes += entry.bytes.len() as u64;
 
                                 let ext_ref = google_photos_db.find_best_match(&entry.file_name, &entry.created);
 
                                 if is_google_photos_takeout_archive
                                     && ext_ref.is_none()
+                                    && !found_info.is_document
                                 {
                                     warnings.push(Warning::new(
                                         entry.file_name.clone(),
@@ -327,7 +556,17 @@ impl BulkOperation for FolderImport
                                         "No Google Photos link found".to_owned()));
                                 }
 
-                                let add_msg = analyse::import::create_add_object_for_import(
+                                // Exports record the roles of additional attachments
+
+                                let export_roles: HashMap<String, picvudb::data::AttachmentRole> = picvu_metadata.iter()
+                                    .flat_map(|m| m.additional_attachments.iter())
+                                    .filter_map(|a| a.role.clone().map(|r| (a.filename.clone(), r)))
+                                    .collect();
+
+                                let export_relations = picvu_metadata.as_ref()
+                                    .map(|m| (m.export_id.clone(), m.relations.clone()));
+
+                                let mut add_msg = analyse::import::create_add_object_for_import(
                                     entry.bytes,
                                     &entry.file_name,
                                     &google_cache,
@@ -339,20 +578,113 @@ impl BulkOperation for FolderImport
                                     ext_ref,
                                     &mut warnings)?;
 
+                                for (paired, role) in paired_entries
+                                {
+                                    summary_paired_files += 1;
+                                    summary_imported_media_bytes += paired.bytes.len() as u64;
+
+                                    let role = export_roles.get(&paired.file_name).cloned().unwrap_or(role);
+
+                                    add_msg.data.additional_attachments.push(
+                                        analyse::import::create_additional_attachment_for_import(
+                                            paired.bytes,
+                                            &paired.file_name,
+                                            role,
+                                            paired.created,
+                                            Some(paired.modified),
+                                            &import_options,
+                                            &mut warnings));
+                                }
+
                                 if add_msg.data.location.is_some()
                                 {
                                     summary_with_location += 1;
                                 }
 
-                                store.write_transaction(|ops|
+                                let object_id = store.write_transaction(|ops|
                                 {
                                     add_msg.execute(ops)
-                                })?;
+                                })?.object_id;
+
+                                if let Some((export_id, relations)) = export_relations
+                                {
+                                    if let Some(export_id) = export_id
+                                    {
+                                        export_id_to_object_id.insert(export_id, object_id.clone());
+                                    }
+
+                                    pending_relations.push((object_id, relations));
+                                }
                             }
                         }
                     }
                 }
 
+                for (display_path, metadata) in picvu_notes
+                {
+                    let export_id = metadata.export_id.clone();
+                    let relations = metadata.relations.clone();
+
+                    let add_msg = analyse::import::create_add_object_for_import(
+                        Vec::new(),
+                        &display_path,
+                        &google_cache,
+                        &import_options,
+                        None,
+                        None,
+                        Some(metadata),
+                        None,
+                        None,
+                        &mut warnings)?;
+
+                    let object_id = store.write_transaction(|ops|
+                    {
+                        add_msg.execute(ops)
+                    })?.object_id;
+
+                    if let Some(export_id) = export_id
+                    {
+                        export_id_to_object_id.insert(export_id, object_id.clone());
+                    }
+
+                    pending_relations.push((object_id, relations));
+
+                    summary_imported_notes += 1;
+                }
+
+                for (from, relations) in pending_relations
+                {
+                    for relation in relations
+                    {
+                        match export_id_to_object_id.get(&relation.to)
+                        {
+                            Some(to) =>
+                            {
+                                let relation_msg = picvudb::msgs::AddObjectRelationRequest
+                                {
+                                    from: from.clone(),
+                                    to: to.clone(),
+                                    kind: relation.kind,
+                                };
+
+                                store.write_transaction(|ops|
+                                {
+                                    relation_msg.execute(ops)
+                                })?;
+
+                                summary_imported_relations += 1;
+                            },
+                            None =>
+                            {
+                                warnings.push(Warning::new(
+                                    relation.to,
+                                    WarningKind::RelatedObjectNotImported,
+                                    "Skipped a relation to an object that wasn't imported".to_owned()));
+                            },
+                        }
+                    }
+                }
+
                 sender.start_stage(
                     "Summary".to_owned(),
                     vec![]);
@@ -360,10 +692,16 @@ impl BulkOperation for FolderImport
                 let mut status = vec![
                     format!("Imported {} media files", summary_imported_media_files),
                     format!("Imported {} of media data", format::bytes_to_string(summary_imported_media_bytes)),
+                    format!("Imported {} notes", summary_imported_notes),
+                    format!("Imported {} relations between objects", summary_imported_relations),
                     format!("{} files had Picvu metadata", summary_with_picvu_metadata),
                     format!("{} files had Google Photos Takeout metadata", summary_with_google_metadata),
                     format!("{} files had location data", summary_with_location),
                     format!("Skipped {} media files", summary_skipped_media_files),
+                    format!("Paired {} files with another file as additional attachments", summary_paired_files),
+                    format!("Imported {} unsupported files as documents", summary_imported_documents),
+                    format!("Skipped {} unsupported files", summary_skipped_unknown_files),
+                    format!("Ignored {} files matching the ignore patterns", summary_ignored_files),
                 ];
 
                 if !warnings.is_empty()
@@ -8,6 +8,7 @@ use std::cell::RefCell;
 use std::convert::TryInto;
 use flate2::read::GzDecoder;
 use tar::Archive;
+use zip::ZipArchive;
 
 use crate::bulk::progress::ProgressSender;
 use crate::format;
@@ -197,72 +198,27 @@ fn full_scan<F>(tx: SyncSender<Option<Result<FileEntry, std::io::Error>>>, total
     {
         let file_metadata = std::fs::metadata(file_name.clone())?;
         let file_size = file_metadata.len();
+        let lower_file_name = file_name.to_ascii_lowercase();
 
-        if file_name.ends_with(".tar.gz")
-            || file_name.ends_with(".tgz")
+        let keep_going = if lower_file_name.ends_with(".tar.gz")
+            || lower_file_name.ends_with(".tgz")
         {
             let tar_gz = File::open(file_name.clone())?;
             let (counted_reader, counted_get) = CountedRead::new(tar_gz);
             let tar = GzDecoder::new(counted_reader);
-            let mut archive = Archive::new(tar);
 
-            for entry in archive.entries()?
-            {
-                let mut entry = entry?;
-
-                let path = entry.path()?;
-                let path_str = path
-                    .to_str()
-                    .ok_or(std::io::Error::new(std::io::ErrorKind::InvalidData, "Contained file name contains non-UTF-8 byte sequences"))?
-                    .to_owned();
-
-                let entry_display_path = format!("{} => {}", file_name, path_str);
-                let entry_archive_path = path_str.clone();
-                let entry_file_name = Path::new(&path_str).file_name().unwrap_or_default().to_str().unwrap().to_owned();
-                let entry_ext = Path::new(&path_str).extension().unwrap_or_default().to_str().unwrap().to_owned().to_ascii_lowercase();
-
-                let entry_modified = unix_time_to_date(entry.header().mtime()?)?;
-
-                let tar_bytes_read = counted_get.get();
-
-                let entry_file_size: usize = entry.header().size()?
-                    .try_into()
-                    .map_err(|_| {std::io::Error::new(std::io::ErrorKind::InvalidData, format!("File {} is too large", path_str))})?;
-
-                let mut bytes = Vec::new();
-
-                if needs_file_bytes(&entry_file_name)
-                {
-                    bytes.reserve(entry_file_size);
-                    entry.read_to_end(&mut bytes)?;
-                }
-
-                let percent_bytes = bytes_processed + tar_bytes_read;
-                let percent = (percent_bytes as f64) / (total_bytes as f64) * 100.0;
-                let progress_bytes = format!("Processed {} of {}",
-                    format::bytes_to_string(percent_bytes),
-                    format::bytes_to_string(total_bytes));
+            scan_tar(&tx, &file_name, tar, counted_get, bytes_processed, total_bytes, &needs_file_bytes)?
+        }
+        else if lower_file_name.ends_with(".tar")
+        {
+            let tar = File::open(file_name.clone())?;
+            let (counted_reader, counted_get) = CountedRead::new(tar);
 
-                let result = FileEntry
-                {
-                    display_path: entry_display_path,
-                    archive_path: entry_archive_path,
-                    file_name: entry_file_name,
-                    ext: entry_ext,
-                    created: None,
-                    modified: entry_modified,
-                    size: entry_file_size as u64,
-                    bytes: bytes,
-                    percent: percent,
-                    progress_bytes: progress_bytes,
-                };
-
-                if tx.send(Some(Ok(result))).is_err()
-                {
-                    // The iterator has been dropped - we should abort
-                    return Ok(())
-                }
-            }
+            scan_tar(&tx, &file_name, counted_reader, counted_get, bytes_processed, total_bytes, &needs_file_bytes)?
+        }
+        else if lower_file_name.ends_with(".zip")
+        {
+            scan_zip(&tx, &file_name, bytes_processed, total_bytes, &needs_file_bytes)?
         }
         else // normal file
         {
@@ -306,11 +262,13 @@ fn full_scan<F>(tx: SyncSender<Option<Result<FileEntry, std::io::Error>>>, total
                 progress_bytes: progress_bytes,
             };
 
-            if tx.send(Some(Ok(result))).is_err()
-            {
-                // The iterator has been dropped - we should abort
-                return Ok(())
-            }
+            tx.send(Some(Ok(result))).is_ok()
+        };
+
+        if !keep_going
+        {
+            // The iterator has been dropped - we should abort
+            return Ok(());
         }
 
         bytes_processed += file_size;
@@ -323,6 +281,158 @@ fn full_scan<F>(tx: SyncSender<Option<Result<FileEntry, std::io::Error>>>, total
     Ok(())
 }
 
+/// Sends each file in a tar archive. Returns false if the
+/// iterator has been dropped and the scan should be aborted.
+fn scan_tar<R, F>(tx: &SyncSender<Option<Result<FileEntry, std::io::Error>>>, file_name: &String, reader: R, counted_get: CountedGet, bytes_processed: u64, total_bytes: u64, needs_file_bytes: &F) -> Result<bool, std::io::Error>
+    where R: Read,
+        F: Fn(&String) -> bool
+{
+    let mut archive = Archive::new(reader);
+
+    for entry in archive.entries()?
+    {
+        let mut entry = entry?;
+
+        let path = entry.path()?;
+        let path_str = path
+            .to_str()
+            .ok_or(std::io::Error::new(std::io::ErrorKind::InvalidData, "Contained file name contains non-UTF-8 byte sequences"))?
+            .to_owned();
+
+        let entry_display_path = format!("{} => {}", file_name, path_str);
+        let entry_archive_path = path_str.clone();
+        let entry_file_name = Path::new(&path_str).file_name().unwrap_or_default().to_str().unwrap().to_owned();
+        let entry_ext = Path::new(&path_str).extension().unwrap_or_default().to_str().unwrap().to_owned().to_ascii_lowercase();
+
+        let entry_modified = unix_time_to_date(entry.header().mtime()?)?;
+
+        let tar_bytes_read = counted_get.get();
+
+        let entry_file_size: usize = entry.header().size()?
+            .try_into()
+            .map_err(|_| {std::io::Error::new(std::io::ErrorKind::InvalidData, format!("File {} is too large", path_str))})?;
+
+        let mut bytes = Vec::new();
+
+        if needs_file_bytes(&entry_file_name)
+        {
+            bytes.reserve(entry_file_size);
+            entry.read_to_end(&mut bytes)?;
+        }
+
+        let percent_bytes = bytes_processed + tar_bytes_read;
+        let percent = (percent_bytes as f64) / (total_bytes as f64) * 100.0;
+        let progress_bytes = format!("Processed {} of {}",
+            format::bytes_to_string(percent_bytes),
+            format::bytes_to_string(total_bytes));
+
+        let result = FileEntry
+        {
+            display_path: entry_display_path,
+            archive_path: entry_archive_path,
+            file_name: entry_file_name,
+            ext: entry_ext,
+            created: None,
+            modified: entry_modified,
+            size: entry_file_size as u64,
+            bytes: bytes,
+            percent: percent,
+            progress_bytes: progress_bytes,
+        };
+
+        if tx.send(Some(Ok(result))).is_err()
+        {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Sends each file in a zip archive (including ZIP64 archives).
+/// Returns false if the iterator has been dropped and the
+/// scan should be aborted.
+fn scan_zip<F>(tx: &SyncSender<Option<Result<FileEntry, std::io::Error>>>, file_name: &String, bytes_processed: u64, total_bytes: u64, needs_file_bytes: &F) -> Result<bool, std::io::Error>
+    where F: Fn(&String) -> bool
+{
+    let zip_err = |e: zip::result::ZipError|
+    {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Zip archive {} could not be read: {:?}", file_name, e))
+    };
+
+    let mut archive = ZipArchive::new(File::open(file_name.clone())?).map_err(zip_err)?;
+
+    // Entries with a missing or invalid time use
+    // the archive file's own modified time
+
+    let archive_modified = system_time_to_date(std::fs::metadata(file_name.clone())?.modified()?)?;
+
+    // The zip entries are read directly from the
+    // file, so progress is based on the compressed size
+
+    let mut zip_bytes_read: u64 = 0;
+
+    for i in 0..archive.len()
+    {
+        let mut entry = archive.by_index(i).map_err(zip_err)?;
+
+        if entry.is_dir()
+        {
+            continue;
+        }
+
+        let path_str = entry.name().to_owned();
+
+        let entry_display_path = format!("{} => {}", file_name, path_str);
+        let entry_archive_path = path_str.clone();
+        let entry_file_name = Path::new(&path_str).file_name().unwrap_or_default().to_str().unwrap().to_owned();
+        let entry_ext = Path::new(&path_str).extension().unwrap_or_default().to_str().unwrap().to_owned().to_ascii_lowercase();
+
+        let entry_modified = zip_time_to_date(entry.last_modified()).unwrap_or_else(|| archive_modified.clone());
+
+        let entry_file_size: usize = entry.size()
+            .try_into()
+            .map_err(|_| {std::io::Error::new(std::io::ErrorKind::InvalidData, format!("File {} is too large", path_str))})?;
+
+        let mut bytes = Vec::new();
+
+        if needs_file_bytes(&entry_file_name)
+        {
+            bytes.reserve(entry_file_size);
+            entry.read_to_end(&mut bytes)?;
+        }
+
+        zip_bytes_read += entry.compressed_size();
+
+        let percent_bytes = bytes_processed + zip_bytes_read;
+        let percent = (percent_bytes as f64) / (total_bytes as f64) * 100.0;
+        let progress_bytes = format!("Processed {} of {}",
+            format::bytes_to_string(percent_bytes),
+            format::bytes_to_string(total_bytes));
+
+        let result = FileEntry
+        {
+            display_path: entry_display_path,
+            archive_path: entry_archive_path,
+            file_name: entry_file_name,
+            ext: entry_ext,
+            created: None,
+            modified: entry_modified,
+            size: entry_file_size as u64,
+            bytes: bytes,
+            percent: percent,
+            progress_bytes: progress_bytes,
+        };
+
+        if tx.send(Some(Ok(result))).is_err()
+        {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
 struct CountedGet
 {
     count: Rc<RefCell<u64>>,
@@ -386,3 +496,18 @@ fn unix_time_to_date(unix_time: u64) -> Result<picvudb::data::Date, std::io::Err
 
     Ok(picvudb::data::Date::from_chrono_utc(&utc))
 }
+
+fn zip_time_to_date(zip_time: zip::DateTime) -> Option<picvudb::data::Date>
+{
+    // Zip files store the local time with no timezone - the
+    // best we can do is assume that it's UTC, like tar files.
+    // A zero DOS time has no valid month or day, so it's
+    // treated as missing.
+
+    let naive = chrono::NaiveDate::from_ymd_opt(zip_time.year() as i32, zip_time.month() as u32, zip_time.day() as u32)
+        .and_then(|d| d.and_hms_opt(zip_time.hour() as u32, zip_time.minute() as u32, zip_time.second() as u32))?;
+
+    let utc = chrono::DateTime::<chrono::Utc>::from_utc(naive, chrono::Utc);
+
+    Some(picvudb::data::Date::from_chrono_utc(&utc))
+}
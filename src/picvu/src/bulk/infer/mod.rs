@@ -0,0 +1,340 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use actix_web::web;
+
+use picvudb::StoreAccess;
+use picvudb::ApiMessage;
+
+use crate::bulk::BulkOperation;
+use crate::bulk::progress::ProgressSender;
+
+/// A location for an object without one, copied
+/// from the object closest to it in time.
+#[derive(Debug, Clone)]
+pub struct LocationProposal
+{
+    pub object: picvudb::data::get::ObjectMetadata,
+    pub location: picvudb::data::Location,
+    pub from_object_id: picvudb::data::ObjectId,
+    pub time_difference: chrono::Duration,
+}
+
+pub type LocationProposalStore = Arc<Mutex<Option<Vec<LocationProposal>>>>;
+
+/// Proposes a location for each object without one, using the
+/// nearest object (in time) within the window that has a location.
+/// Locations that were themselves inferred aren't used, so
+/// errors don't accumulate.
+pub fn infer_locations(objects: &[picvudb::data::get::ObjectMetadata], window: chrono::Duration) -> Vec<LocationProposal>
+{
+    let mut located = objects.iter()
+        .filter(|o| match &o.location
+            {
+                Some(location) => location.source != picvudb::data::LocationSource::Inferred,
+                None => false,
+            })
+        .map(|o| (o.activity_time.to_chrono_utc(), o))
+        .collect::<Vec<_>>();
+
+    located.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut result = Vec::new();
+
+    for object in objects.iter().filter(|o| o.location.is_none())
+    {
+        let time = object.activity_time.to_chrono_utc();
+        let after_index = located.partition_point(|(t, _)| *t < time);
+
+        let mut candidates = Vec::new();
+
+        if after_index > 0
+        {
+            candidates.push(located[after_index - 1]);
+        }
+        if let Some(after) = located.get(after_index)
+        {
+            candidates.push(*after);
+        }
+
+        let nearest = candidates.into_iter()
+            .map(|(t, o)| (if t > time { t - time } else { time - t }, o))
+            .filter(|(difference, _)| *difference <= window)
+            .min_by_key(|(difference, _)| *difference);
+
+        if let Some((time_difference, nearest)) = nearest
+        {
+            let from = nearest.location.clone().unwrap();
+
+            result.push(LocationProposal
+            {
+                object: object.clone(),
+                location: picvudb::data::Location::new(picvudb::data::LocationSource::Inferred, from.latitude, from.longitude, from.altitude),
+                from_object_id: nearest.id.clone(),
+                time_difference,
+            });
+        }
+    }
+
+    result
+}
+
+pub struct InferLocationsPreview
+{
+    db_uri: String,
+    window: chrono::Duration,
+    proposal_store: LocationProposalStore,
+}
+
+impl InferLocationsPreview
+{
+    pub fn new(db_uri: String, window: chrono::Duration, proposal_store: LocationProposalStore) -> Self
+    {
+        InferLocationsPreview
+        {
+            db_uri,
+            window,
+            proposal_store,
+        }
+    }
+}
+
+impl BulkOperation for InferLocationsPreview
+{
+    type Error = actix_web::error::BlockingError<picvudb::Error>;
+    type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
+
+    fn name(&self) -> String
+    {
+        "Infer locations".to_owned()
+    }
+
+    fn start(self, sender: ProgressSender) -> Self::Future
+    {
+        let db_uri = self.db_uri;
+        let window = self.window;
+        let proposal_store = self.proposal_store;
+
+        Box::pin(async move
+        {
+            web::block(move ||
+            {
+                sender.start_stage(
+                    "Loading objects".to_owned(),
+                    vec!["Inferring locations".to_owned()]);
+
+                let store = picvudb::Store::new(&db_uri)?;
+
+                let objects =
+                {
+                    let msg = picvudb::msgs::GetObjectsRequest
+                    {
+                        query: picvudb::data::get::GetObjectsQuery::ByActivityDesc,
+                        pagination: None,
+                    };
+
+                    store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?.objects
+                };
+
+                sender.start_stage(
+                    "Inferring locations".to_owned(),
+                    vec![]);
+
+                let proposals = infer_locations(&objects, window);
+
+                sender.set(100.0, vec![
+                    format!("Proposed locations for {} objects", proposals.len()),
+                    "Review them on the Infer Locations page".to_owned(),
+                ]);
+
+                *proposal_store.lock().unwrap() = Some(proposals);
+
+                Ok(())
+            }).await?;
+
+            Ok(())
+        })
+    }
+}
+
+pub struct ApplyLocationProposals
+{
+    db_uri: String,
+    proposals: Vec<LocationProposal>,
+}
+
+impl ApplyLocationProposals
+{
+    pub fn new(db_uri: String, proposals: Vec<LocationProposal>) -> Self
+    {
+        ApplyLocationProposals
+        {
+            db_uri,
+            proposals,
+        }
+    }
+}
+
+impl BulkOperation for ApplyLocationProposals
+{
+    type Error = actix_web::error::BlockingError<picvudb::Error>;
+    type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
+
+    fn name(&self) -> String
+    {
+        "Apply inferred locations".to_owned()
+    }
+
+    fn start(self, sender: ProgressSender) -> Self::Future
+    {
+        let db_uri = self.db_uri;
+        let proposals = self.proposals;
+
+        Box::pin(async move
+        {
+            web::block(move ||
+            {
+                sender.start_stage("Setting locations".to_owned(), vec![]);
+
+                let store = picvudb::Store::new(&db_uri)?;
+
+                let num_proposals = proposals.len();
+                let mut done = 0;
+                let mut applied = 0;
+
+                for proposal in proposals
+                {
+                    done += 1;
+
+                    sender.set(
+                        (done as f64) / (num_proposals as f64) * 100.0,
+                        vec![format!("Processed {} of {} locations", done, num_proposals)]);
+
+                    // Re-load the object, in case it's been
+                    // changed since the proposal was made
+
+                    let get_msg = picvudb::msgs::GetObjectsRequest
+                    {
+                        query: picvudb::data::get::GetObjectsQuery::ByObjectId(proposal.object.id.clone()),
+                        pagination: None,
+                    };
+
+                    let object = match store.write_transaction(|ops| get_msg.execute(ops))?.objects.into_iter().next()
+                    {
+                        Some(object) if object.location.is_none() => object,
+                        _ => continue,
+                    };
+
+                    let msg = picvudb::msgs::UpdateObjectRequest
+                    {
+                        object_id: object.id,
+                        activity_time: object.activity_time,
+                        title: object.title,
+                        notes: object.notes,
+                        rating: object.rating,
+                        censor: object.censor,
+                        location: Some(proposal.location),
+                    };
+
+                    store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+
+                    applied += 1;
+                }
+
+                // Objects that have been given a location (or
+                // been deleted) since the proposals were made
+                // are skipped
+
+                sender.set(100.0, vec![
+                    format!("Set {} locations", applied),
+                    format!("Skipped {} objects that have changed", num_proposals - applied),
+                ]);
+
+                Ok(())
+            }).await?;
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use picvudb::data::{Location, LocationSource};
+
+    fn object(id: &str, time: &str, location: Option<Location>) -> picvudb::data::get::ObjectMetadata
+    {
+        let time = picvudb::data::Date::from_rfc3339(time).unwrap();
+
+        picvudb::data::get::ObjectMetadata
+        {
+            id: picvudb::data::ObjectId::try_new(id.to_owned()).unwrap(),
+            created_time: time.clone(),
+            modified_time: time.clone(),
+            activity_time: time,
+            title: None,
+            notes: None,
+            rating: picvudb::data::Rating::NotRated,
+            censor: picvudb::data::Censor::FamilyFriendly,
+            location,
+            attachment: None,
+            additional_attachments: Vec::new(),
+            previous_versions: Vec::new(),
+            tags: Vec::new(),
+            ext_ref: None,
+            user_orientation: None,
+            crop: None,
+        }
+    }
+
+    #[test]
+    fn test_infer_locations()
+    {
+        let before = Location::new(LocationSource::CameraGps, -27.0, 153.0, Some(10.0));
+        let after = Location::new(LocationSource::UserProvided, -28.0, 154.0, None);
+        let inferred = Location::new(LocationSource::Inferred, -29.0, 155.0, None);
+
+        // Object IDs 1 to 6
+
+        let objects = vec![
+            object("MW89", "2019-06-10T00:00:00Z", Some(before.clone())),
+            object("Mm89", "2019-06-10T00:20:00Z", Some(after.clone())),
+            object("M289", "2019-06-10T00:05:00Z", None),
+            object("NG89", "2019-06-10T00:14:00Z", None),
+            object("NW89", "2019-06-10T01:00:00Z", Some(inferred)),
+            object("Nm89", "2019-06-10T01:01:00Z", None),
+        ];
+
+        let proposals = infer_locations(&objects, chrono::Duration::minutes(15));
+
+        // The nearest object in time is used, and inferred
+        // locations aren't used to infer others
+
+        assert_eq!(proposals.len(), 2);
+
+        assert_eq!(proposals[0].object.id, objects[2].id);
+        assert_eq!(proposals[0].from_object_id, objects[0].id);
+        assert_eq!(proposals[0].time_difference, chrono::Duration::minutes(5));
+        assert_eq!(proposals[0].location.source, LocationSource::Inferred);
+        assert_eq!((proposals[0].location.latitude, proposals[0].location.longitude, proposals[0].location.altitude), (-27.0, 153.0, Some(10.0)));
+
+        assert_eq!(proposals[1].object.id, objects[3].id);
+        assert_eq!(proposals[1].from_object_id, objects[1].id);
+        assert_eq!(proposals[1].time_difference, chrono::Duration::minutes(6));
+
+        // Objects outside the window aren't given a location
+
+        let proposals = infer_locations(&objects, chrono::Duration::minutes(5));
+
+        assert_eq!(proposals.len(), 1);
+        assert_eq!(proposals[0].object.id, objects[2].id);
+    }
+}
@@ -1,11 +1,17 @@
 use std::future::Future;
 use std::sync::{Arc, Mutex};
 
+pub mod documents;
+pub mod exif;
 pub mod export;
+pub mod geotag;
 pub mod import;
+pub mod infer;
 pub mod progress;
+pub mod recipes;
 pub mod sync;
 pub mod tags;
+pub mod time_shift;
 
 pub trait BulkOperation
 {
@@ -0,0 +1,124 @@
+use std::future::Future;
+use std::pin::Pin;
+use actix_web::web;
+
+use picvudb::StoreAccess;
+use picvudb::ApiMessage;
+
+use crate::bulk::BulkOperation;
+use crate::bulk::progress::ProgressSender;
+
+/// Re-indexes the ingredients of every object with notes, so
+/// that recipes written before ingredients were indexed can
+/// be found by ingredient.
+pub struct RecipeBackfill
+{
+    db_uri: String,
+}
+
+impl RecipeBackfill
+{
+    pub fn new(db_uri: String) -> Self
+    {
+        RecipeBackfill
+        {
+            db_uri,
+        }
+    }
+}
+
+impl BulkOperation for RecipeBackfill
+{
+    type Error = actix_web::error::BlockingError<picvudb::Error>;
+    type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
+
+    fn name(&self) -> String
+    {
+        "Backfill recipe ingredients".to_owned()
+    }
+
+    fn start(self, sender: ProgressSender) -> Self::Future
+    {
+        let db_uri = self.db_uri;
+
+        Box::pin(async move
+        {
+            web::block(move ||
+            {
+                sender.start_stage(
+                    "Loading objects".to_owned(),
+                    vec!["Indexing recipes".to_owned(), "Summary".to_owned()]);
+
+                let store = picvudb::Store::new(&db_uri)?;
+
+                let objects =
+                {
+                    let msg = picvudb::msgs::GetObjectsRequest
+                    {
+                        query: picvudb::data::get::GetObjectsQuery::ByActivityDesc,
+                        pagination: None,
+                    };
+
+                    let results = store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+
+                    results.objects
+                        .into_iter()
+                        .filter(|o| o.notes.is_some())
+                        .collect::<Vec<_>>()
+                };
+
+                sender.start_stage(
+                    "Indexing recipes".to_owned(),
+                    vec!["Summary".to_owned()]);
+
+                let num_objects = objects.len();
+                let mut done: usize = 0;
+                let mut summary_recipes: usize = 0;
+
+                for object in objects
+                {
+                    done += 1;
+
+                    sender.set(
+                        (done as f64) / (num_objects as f64) * 100.0,
+                        vec![
+                            object.get_display_text(),
+                            format!("Processed {} of {} objects with notes", done, num_objects),
+                            format!("Found {} recipes", summary_recipes),
+                        ]);
+
+                    let msg = picvudb::msgs::ReindexRecipeRequest
+                    {
+                        object_id: object.id,
+                    };
+
+                    let response = store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+
+                    if response.is_recipe
+                    {
+                        summary_recipes += 1;
+                    }
+                }
+
+                sender.start_stage(
+                    "Summary".to_owned(),
+                    vec![]);
+
+                sender.set(100.0, vec![
+                    format!("Processed {} objects with notes", num_objects),
+                    format!("Found {} recipes", summary_recipes),
+                ]);
+
+                Ok(())
+            }).await?;
+
+            Ok(())
+        })
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use googlephotos::auth::AccessToken;
 
@@ -107,90 +107,166 @@ impl AlbumDatabase
         Ok(AlbumDatabase{ albums, name_to_album_id })
     }
 
-    pub fn create_albums(&mut self, album_names: HashSet<String>, access_token: &AccessToken, sender: &ProgressSender) -> Result<(), SyncError>
+    pub fn contents_by_title(&self) -> BTreeMap<String, HashSet<String>>
     {
-        // First, for out which albums we actually need to create
-        // by removing ones that we already know about
+        // Google Photos allows multiple albums with the same
+        // title - these all map onto the same tag
 
-        let mut album_names = album_names;
+        let mut result = BTreeMap::new();
 
-        for (name, _id) in self.name_to_album_id.iter()
+        for album_info in self.albums.values()
         {
-            album_names.remove(name);
+            result
+                .entry(album_info.title.clone())
+                .or_insert(HashSet::new())
+                .extend(album_info.contents.iter().cloned());
         }
 
-        // Now, actually create each item
+        result
+    }
 
-        let num_to_create = album_names.len();
-        let mut num_created: usize = 0;
+    /// Calculates the albums that need to be created, and the
+    /// items to add and remove from each album, so that the albums
+    /// match the wanted memberships. Existing albums for the synced
+    /// tags that no linked item wants are emptied. Items are only
+    /// ever removed if they're linked to a PicVu object.
+    pub fn plan_changes(&self, mut memberships: BTreeMap<String, HashSet<String>>, linked_media_ids: &HashSet<String>, synced_tags: &HashSet<String>) -> (Vec<String>, Vec<AlbumChange>)
+    {
+        let cur_contents = self.contents_by_title();
 
-        for name in album_names
+        for title in cur_contents.keys()
         {
-            num_created += 1;
+            if synced_tags.contains(title)
+            {
+                memberships
+                    .entry(title.clone())
+                    .or_insert(HashSet::new());
+            }
+        }
 
-            sender.set(
-                (num_created as f64) / (num_to_create as f64) * 100.0,
-                vec!
-                [
-                    format!("Creating {} of {} new albums", num_created, num_to_create),
-                    format!("Creating {:?}", name),
-                ]);
+        let mut albums_to_create = Vec::new();
+        let mut changes = Vec::new();
 
-            let id = googlephotos::api::raw::albums_create(access_token, name.clone())?;
+        for (title, wanted) in memberships
+        {
+            match self.name_to_album_id.get(&title)
+            {
+                Some(album_id) =>
+                {
+                    let empty = HashSet::new();
+                    let cur = cur_contents.get(&title).unwrap_or(&empty);
 
-            assert!(!self.albums.contains_key(&id));
+                    let mut add: Vec<String> = wanted.difference(cur).cloned().collect();
+                    let mut remove: Vec<String> = cur.iter()
+                        .filter(|id| linked_media_ids.contains(*id) && !wanted.contains(*id))
+                        .cloned()
+                        .collect();
 
-            let album_info = AlbumInfo
-            {
-                title: name.clone(),
-                contents: Vec::new(),
-            };
+                    add.sort();
+                    remove.sort();
+
+                    if !add.is_empty() || !remove.is_empty()
+                    {
+                        changes.push(AlbumChange
+                        {
+                            album_title: title,
+                            album_id: Some(album_id.clone()),
+                            add,
+                            remove,
+                        });
+                    }
+                },
+                None =>
+                {
+                    let mut add: Vec<String> = wanted.into_iter().collect();
+                    add.sort();
 
-            self.albums.insert(id.clone(), album_info);
-            self.name_to_album_id.insert(name, id);
+                    albums_to_create.push(title.clone());
+
+                    changes.push(AlbumChange
+                    {
+                        album_title: title,
+                        album_id: None,
+                        add,
+                        remove: Vec::new(),
+                    });
+                },
+            }
         }
 
-        Ok(())
+        (albums_to_create, changes)
     }
+}
+
+pub fn create_albums(album_names: &Vec<String>, access_token: &AccessToken, sender: &ProgressSender) -> Result<HashMap<String, String>, SyncError>
+{
+    let mut name_to_album_id = HashMap::new();
 
-    pub fn apply_changes(&mut self, memberships: HashMap<String, Vec<String>>, access_token: &AccessToken, sender: &ProgressSender) -> Result<(), SyncError>
+    let num_to_create = album_names.len();
+    let mut num_created: usize = 0;
+
+    for name in album_names
     {
-        let mut memberships = memberships;
+        num_created += 1;
 
-        let mut changes = Vec::new();
+        sender.set(
+            (num_created as f64) / (num_to_create as f64) * 100.0,
+            vec!
+            [
+                format!("Creating {} of {} new albums", num_created, num_to_create),
+                format!("Creating {:?}", name),
+            ]);
 
-        for (id, album_info) in self.albums.iter()
-        {
-            let cur_contents = &album_info.contents;
-            let wanted_contents = memberships.remove(&album_info.title).unwrap_or(Vec::new());
+        let id = googlephotos::api::raw::albums_create(access_token, name.clone())?;
 
-            calc_changes(&mut changes, id, &album_info.title, cur_contents, &wanted_contents);
-        }
+        name_to_album_id.insert(name.clone(), id);
+    }
 
-        println!("Changes:\n{:#?}", changes);
+    Ok(name_to_album_id)
+}
 
-        let num_changes = changes.len();
-        let mut changes_performed: usize = 0;
+pub fn apply_changes(changes: &Vec<AlbumChange>, created_albums: &HashMap<String, String>, access_token: &AccessToken, sender: &ProgressSender) -> Result<(), SyncError>
+{
+    let num_changes = changes.len();
+    let mut changes_performed: usize = 0;
 
-        for change in changes
-        {
-            changes_performed += 1;
+    for change in changes
+    {
+        changes_performed += 1;
 
-            sender.set(
-                (changes_performed as f64) / (num_changes as f64) * 100.0,
-                vec![
-                    format!("Performing {} or {} changes", changes_performed, num_changes),
-                    format!("Updating album {:?}", change.album_title)
-                ]);
+        sender.set(
+            (changes_performed as f64) / (num_changes as f64) * 100.0,
+            vec![
+                format!("Performing {} of {} changes", changes_performed, num_changes),
+                format!("Updating album {:?}", change.album_title)
+            ]);
 
-            if change.is_add
+        let album_id = match &change.album_id
+        {
+            Some(album_id) => album_id.clone(),
+            None =>
             {
-                googlephotos::api::raw::albums_bulk_add(access_token, change.album_id, change.media_item_ids)?;
-            }
+                created_albums.get(&change.album_title)
+                    .cloned()
+                    .ok_or_else(|| SyncError::new_parse_err(format!("Album {:?} was not created", change.album_title)))?
+            },
+        };
+
+        // The API limits the number of items that can
+        // be added or removed in a single request
+
+        for chunk in change.add.chunks(50)
+        {
+            googlephotos::api::raw::albums_bulk_add(access_token, album_id.clone(), chunk.to_vec())?;
         }
 
-        Ok(())
+        for chunk in change.remove.chunks(50)
+        {
+            googlephotos::api::raw::albums_bulk_remove(access_token, album_id.clone(), chunk.to_vec())?;
+        }
     }
+
+    Ok(())
 }
 
 #[derive(Debug)]
@@ -200,39 +276,11 @@ struct AlbumInfo
     contents: Vec<String>,
 }
 
-#[derive(Debug)]
-struct Change
+#[derive(Debug, Clone)]
+pub struct AlbumChange
 {
-    is_add: bool,
-    album_id: String,
-    album_title: String,
-    media_item_ids: Vec<String>,
+    pub album_title: String,
+    pub album_id: Option<String>,
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
 }
-
-fn calc_changes(changes: &mut Vec<Change>, album_id: &String, album_title: &String, cur_contents: &Vec<String>, wanted_contents: &Vec<String>)
-{
-    if cur_contents != wanted_contents
-    {
-        if !cur_contents.is_empty()
-        {
-            changes.push(Change
-                {
-                    is_add: false,
-                    album_id: album_id.clone(),
-                    album_title: album_title.clone(),
-                    media_item_ids: cur_contents.clone()
-                });
-        }
-
-        if !wanted_contents.is_empty()
-        {
-            changes.push(Change
-                {
-                    is_add: true,
-                    album_id: album_id.clone(),
-                    album_title: album_title.clone(),
-                    media_item_ids: wanted_contents.clone()
-                });
-        }
-    }
-}
\ No newline at end of file
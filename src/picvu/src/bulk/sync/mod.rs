@@ -1,6 +1,7 @@
 use std::future::Future;
 use std::pin::Pin;
-use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use actix_web::web;
 
 use googlephotos::auth::AccessToken;
@@ -13,55 +14,205 @@ use crate::bulk::progress::ProgressSender;
 pub mod albums;
 pub mod error;
 pub mod mediaitems;
+pub mod pull;
 
 pub use error::SyncError;
 
-pub struct GooglePhotosSync
+/// Selects which tags are synced with albums.
+#[derive(Debug, Clone)]
+pub struct SyncFilter
+{
+    pub kinds: Vec<picvudb::data::TagKind>,
+    pub excluded_tags: Vec<String>,
+}
+
+impl SyncFilter
+{
+    pub fn includes(&self, name: &String, kind: &picvudb::data::TagKind) -> bool
+    {
+        self.kinds.contains(kind)
+            && !self.excluded_tags.contains(name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncOptions
+{
+    pub push: bool,
+    pub pull: Option<pull::PullOptions>,
+    pub filter: SyncFilter,
+}
+
+/// The changes that a sync will make. This is calculated
+/// by `GooglePhotosSyncPreview` so that it can be confirmed
+/// by the user, and is then applied by `GooglePhotosSync`.
+#[derive(Debug, Clone)]
+pub struct SyncPlan
+{
+    pub tag_changes: Vec<pull::TagChange>,
+    pub albums_to_create: Vec<String>,
+    pub album_changes: Vec<albums::AlbumChange>,
+    pub media_item_names: HashMap<String, String>,
+}
+
+impl SyncPlan
+{
+    pub fn is_empty(&self) -> bool
+    {
+        self.tag_changes.is_empty()
+            && self.albums_to_create.is_empty()
+            && self.album_changes.is_empty()
+    }
+}
+
+pub type SyncPlanStore = Arc<Mutex<Option<SyncPlan>>>;
+
+pub struct GooglePhotosSyncPreview
 {
     access_token: AccessToken,
     db_uri: String,
+    options: SyncOptions,
+    plan_store: SyncPlanStore,
 }
 
-impl GooglePhotosSync
+impl GooglePhotosSyncPreview
 {
-    pub fn new(access_token: AccessToken, db_uri: String) -> Self
+    pub fn new(access_token: AccessToken, db_uri: String, options: SyncOptions, plan_store: SyncPlanStore) -> Self
     {
-        GooglePhotosSync
+        GooglePhotosSyncPreview
         {
             access_token,
             db_uri,
+            options,
+            plan_store,
         }
     }
 }
 
-impl BulkOperation for GooglePhotosSync
+fn load_objects(store: &picvudb::Store) -> Result<Vec<picvudb::data::get::ObjectMetadata>, SyncError>
+{
+    let msg = picvudb::msgs::GetObjectsRequest
+    {
+        query: picvudb::data::get::GetObjectsQuery::ByActivityDesc,
+        pagination: None,
+    };
+
+    let results = store.write_transaction(|ops|
+    {
+        msg.execute(ops)
+    })?;
+
+    assert_eq!(results.pagination_response.total, results.objects.len() as u64);
+
+    Ok(results.objects)
+}
+
+fn calc_plan(album_db: &albums::AlbumDatabase, objects: &Vec<picvudb::data::get::ObjectMetadata>, options: &SyncOptions) -> SyncPlan
+{
+    let tag_changes = match &options.pull
+    {
+        Some(pull_options) => pull::calc_tag_changes(album_db, objects, pull_options, &options.filter),
+        None => Vec::new(),
+    };
+
+    let mut albums_to_create = Vec::new();
+    let mut album_changes = Vec::new();
+    let mut media_item_names = HashMap::new();
+
+    if options.push
+    {
+        // Work out the tags each linked object will
+        // have once the pulled tag changes are applied
+
+        let changes_by_id: HashMap<String, &pull::TagChange> = tag_changes.iter()
+            .map(|c| (c.object_id.to_string(), c))
+            .collect();
+
+        let mut memberships = BTreeMap::new();
+        let mut linked_media_ids = HashSet::new();
+        let mut synced_tags = HashSet::new();
+
+        for object in objects.iter()
+        {
+            let mut tags: Vec<(String, picvudb::data::TagKind)> = object.tags.iter()
+                .map(|t| (t.name.clone(), t.kind.clone()))
+                .collect();
+
+            if let Some(change) = changes_by_id.get(&object.id.to_string())
+            {
+                tags.retain(|(name, _)| !change.remove.iter().any(|r| r.name == *name));
+                tags.extend(change.add.iter().map(|a| (a.name.clone(), a.kind.clone())));
+            }
+
+            // Every tag that passes the filter is synced, even if
+            // no linked object has it - its album is then emptied
+
+            tags.retain(|(name, kind)| options.filter.includes(name, kind));
+
+            synced_tags.extend(tags.iter().map(|(name, _)| name.clone()));
+
+            if let Some(picvudb::data::ExternalReference::GooglePhotos{id}) = &object.ext_ref
+            {
+                linked_media_ids.insert(id.clone());
+
+                media_item_names.insert(
+                    id.clone(),
+                    object.get_display_text());
+
+                for (name, _) in tags
+                {
+                    memberships
+                        .entry(name)
+                        .or_insert(HashSet::new())
+                        .insert(id.clone());
+                }
+            }
+        }
+
+        let (to_create, changes) = album_db.plan_changes(memberships, &linked_media_ids, &synced_tags);
+
+        albums_to_create = to_create;
+        album_changes = changes;
+    }
+
+    SyncPlan
+    {
+        tag_changes,
+        albums_to_create,
+        album_changes,
+        media_item_names,
+    }
+}
+
+impl BulkOperation for GooglePhotosSyncPreview
 {
     type Error = actix_web::error::BlockingError<error::SyncError>;
     type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
 
     fn name(&self) -> String
     {
-        "Google Photos Sync".to_owned()
+        "Google Photos Sync Preview".to_owned()
     }
 
     fn start(self, sender: ProgressSender) -> Self::Future
     {
         let access_token = self.access_token;
         let db_uri = self.db_uri;
+        let options = self.options;
+        let plan_store = self.plan_store;
 
         Box::pin(async move
         {
             let mut stages = vec![
                 "Loading Google Photos albums".to_owned(),
                 "Loading PicVu item data".to_owned(),
-                "Creating new albums".to_owned(),
-                "Updating albums".to_owned(),
+                "Calculating changes".to_owned(),
             ];
 
             web::block(move ||
             {
                 // Load albums
-                let mut album_db =
+                let album_db =
                 {
                     let stage = stages[0].clone();
                     stages.remove(0);
@@ -70,86 +221,123 @@ impl BulkOperation for GooglePhotosSync
                     albums::AlbumDatabase::load_all(&access_token, &sender)?
                 };
 
-                println!("Album DB:\n:{:#?}", album_db);
-                
                 // Load objects
+                let objects =
                 {
                     let stage = stages[0].clone();
                     stages.remove(0);
                     sender.start_stage(stage, stages.clone());
-                }
 
-                let store = picvudb::Store::new(&db_uri)?;
+                    let store = picvudb::Store::new(&db_uri)?;
 
-                let objects =
+                    load_objects(&store)?
+                };
+
+                // Calculate the plan
                 {
-                    let msg = picvudb::msgs::GetObjectsRequest
-                    {
-                        query: picvudb::data::get::GetObjectsQuery::ByActivityDesc,
-                        pagination: None,
-                    };
+                    let stage = stages[0].clone();
+                    stages.remove(0);
+                    sender.start_stage(stage, stages.clone());
 
-                    let results = store.write_transaction(|ops|
-                    {
-                        msg.execute(ops)
-                    })?;
+                    let plan = calc_plan(&album_db, &objects, &options);
 
-                    assert_eq!(results.pagination_response.total, results.objects.len() as u64);
+                    sender.set(100.0, vec![
+                        format!("{} items to re-tag", plan.tag_changes.len()),
+                        format!("{} albums to create", plan.albums_to_create.len()),
+                        format!("{} albums to update", plan.album_changes.len()),
+                        "Open the Sync page to review and confirm these changes".to_owned(),
+                    ]);
 
-                    results.objects
-                };
+                    *plan_store.lock().unwrap() = Some(plan);
+                }
 
-                // Creating albums
+                Ok(())
+
+            }).await?;
+
+            Ok(())
+        })
+    }
+}
+
+pub struct GooglePhotosSync
+{
+    access_token: AccessToken,
+    db_uri: String,
+    plan: SyncPlan,
+}
+
+impl GooglePhotosSync
+{
+    pub fn new(access_token: AccessToken, db_uri: String, plan: SyncPlan) -> Self
+    {
+        GooglePhotosSync
+        {
+            access_token,
+            db_uri,
+            plan,
+        }
+    }
+}
+
+impl BulkOperation for GooglePhotosSync
+{
+    type Error = actix_web::error::BlockingError<error::SyncError>;
+    type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
+
+    fn name(&self) -> String
+    {
+        "Google Photos Sync".to_owned()
+    }
+
+    fn start(self, sender: ProgressSender) -> Self::Future
+    {
+        let access_token = self.access_token;
+        let db_uri = self.db_uri;
+        let plan = self.plan;
+
+        Box::pin(async move
+        {
+            let mut stages = vec![
+                "Updating PicVu tags from albums".to_owned(),
+                "Creating new albums".to_owned(),
+                "Updating albums".to_owned(),
+            ];
+
+            web::block(move ||
+            {
+                // Pull album membership into tags
                 {
                     let stage = stages[0].clone();
                     stages.remove(0);
                     sender.start_stage(stage, stages.clone());
 
-                    let mut album_names = HashSet::new();
+                    let store = picvudb::Store::new(&db_uri)?;
 
-                    for object in objects.iter()
-                    {
-                        if let Some(picvudb::data::ExternalReference::GooglePhotos{..}) = &object.ext_ref
-                        {
-                            for tag in object.tags.iter()
-                            {
-                                album_names.insert(tag.name.clone());
-                            }
-                        }
-                    }
-
-                    album_db.create_albums(album_names, &access_token, &sender)?;
+                    pull::apply_tag_changes(&store, &plan.tag_changes, &sender)?;
                 }
 
-                // Now put items into the albums
+                // Creating albums
+                let created_albums =
+                {
+                    let stage = stages[0].clone();
+                    stages.remove(0);
+                    sender.start_stage(stage, stages.clone());
+
+                    albums::create_albums(&plan.albums_to_create, &access_token, &sender)?
+                };
+
+                // Now update the album contents
                 {
                     let stage = stages[0].clone();
                     stages.remove(0);
                     sender.start_stage(stage, stages.clone());
 
-                    sender.set(0.0, vec!["Analysing changes required...".to_owned()]);
-
-                    let mut memberships = HashMap::new();
-
-                    for object in objects.iter()
-                    {
-                        if let Some(picvudb::data::ExternalReference::GooglePhotos{id}) = &object.ext_ref
-                        {
-                            for tag in object.tags.iter()
-                            {
-                                memberships
-                                    .entry(tag.name.clone())
-                                    .or_insert(Vec::new())
-                                    .push(id.clone());
-                            }
-                        }
-                    }
-
-                    album_db.apply_changes(memberships, &access_token, &sender)?;
+                    albums::apply_changes(&plan.album_changes, &created_albums, &access_token, &sender)?;
                 }
 
                 Ok(())
-                
+
             }).await?;
 
             Ok(())
@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, HashMap};
+
+use picvudb::StoreAccess;
+use picvudb::ApiMessage;
+
+use crate::bulk::progress::ProgressSender;
+use crate::bulk::sync::{SyncError, SyncFilter};
+use crate::bulk::sync::albums::AlbumDatabase;
+
+/// Controls what happens when a Google Photos album
+/// and the PicVu tag with the same name disagree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PullConflictRule
+{
+    /// Tags are added to objects in the album, but never removed.
+    AddOnly,
+    /// The album is authoritative - the tag is also removed from
+    /// linked objects that are no longer in the album.
+    GooglePhotosWins,
+    /// Albums that already exist as a PicVu tag are not pulled.
+    PicvuWins,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlbumTagMapping
+{
+    Ignore,
+    Tag(picvudb::data::TagKind),
+}
+
+#[derive(Debug, Clone)]
+pub struct PullOptions
+{
+    pub conflict_rule: PullConflictRule,
+    pub default_kind: picvudb::data::TagKind,
+    pub album_mappings: HashMap<String, AlbumTagMapping>,
+}
+
+impl PullOptions
+{
+    fn mapping_for(&self, album_title: &String) -> AlbumTagMapping
+    {
+        self.album_mappings
+            .get(album_title)
+            .cloned()
+            .unwrap_or(AlbumTagMapping::Tag(self.default_kind.clone()))
+    }
+}
+
+/// Parses album mappings, one per line, in the
+/// form `Album Title = Kind` or `Album Title = Ignore`.
+pub fn parse_album_mappings(text: &str) -> Result<HashMap<String, AlbumTagMapping>, String>
+{
+    let mut result = HashMap::new();
+
+    for line in text.lines()
+    {
+        let line = line.trim();
+
+        if line.is_empty()
+        {
+            continue;
+        }
+
+        let (title, kind) = match line.rfind('=')
+        {
+            Some(pos) => (line[..pos].trim(), line[(pos + 1)..].trim()),
+            None => return Err(format!("Album mapping {:?} must be of the form \"Album Title = Kind\"", line)),
+        };
+
+        let mapping = if kind == "Ignore"
+        {
+            AlbumTagMapping::Ignore
+        }
+        else
+        {
+            AlbumTagMapping::Tag(kind.parse().map_err(|_| format!("Album mapping {:?} has an invalid tag kind {:?}", line, kind))?)
+        };
+
+        result.insert(title.to_owned(), mapping);
+    }
+
+    Ok(result)
+}
+
+#[derive(Debug, Clone)]
+pub struct TagChange
+{
+    pub object_id: picvudb::data::ObjectId,
+    pub object_name: String,
+    pub add: Vec<picvudb::data::add::Tag>,
+    pub remove: Vec<picvudb::data::get::TagMetadata>,
+}
+
+pub fn calc_tag_changes(album_db: &AlbumDatabase, objects: &Vec<picvudb::data::get::ObjectMetadata>, options: &PullOptions, filter: &SyncFilter) -> Vec<TagChange>
+{
+    // Index the Google-linked objects by media item ID,
+    // and all existing tags by name
+
+    let mut media_id_to_object = HashMap::new();
+    let mut existing_tags = HashMap::new();
+
+    for object in objects.iter()
+    {
+        if let Some(picvudb::data::ExternalReference::GooglePhotos{id}) = &object.ext_ref
+        {
+            media_id_to_object.insert(id.clone(), object);
+        }
+
+        for tag in object.tags.iter()
+        {
+            existing_tags.insert(tag.name.clone(), tag.clone());
+        }
+    }
+
+    // Work out the tags to add and remove for each object
+
+    let mut changes: BTreeMap<String, TagChange> = BTreeMap::new();
+
+    for (title, contents) in album_db.contents_by_title()
+    {
+        let kind = match options.mapping_for(&title)
+        {
+            AlbumTagMapping::Ignore => continue,
+            AlbumTagMapping::Tag(kind) => kind,
+        };
+
+        let existing_tag = existing_tags.get(&title);
+
+        if existing_tag.is_some()
+            && options.conflict_rule == PullConflictRule::PicvuWins
+        {
+            continue;
+        }
+
+        let filter_kind = existing_tag.map(|t| t.kind.clone()).unwrap_or(kind.clone());
+
+        if !filter.includes(&title, &filter_kind)
+        {
+            continue;
+        }
+
+        // If the tag already exists, it keeps its
+        // current kind, rating and censor
+
+        let new_tag = match existing_tag
+        {
+            Some(tag) => picvudb::data::add::Tag
+            {
+                name: tag.name.clone(),
+                kind: tag.kind.clone(),
+                rating: tag.rating.clone(),
+                censor: tag.censor.clone(),
+                parent: None,
+            },
+            None => picvudb::data::add::Tag
+            {
+                name: title.clone(),
+                kind,
+                rating: picvudb::data::Rating::NotRated,
+                censor: picvudb::data::Censor::FamilyFriendly,
+                parent: None,
+            },
+        };
+
+        for media_id in contents.iter()
+        {
+            if let Some(object) = media_id_to_object.get(media_id)
+            {
+                if !object.tags.iter().any(|t| t.name == title)
+                {
+                    change_for(&mut changes, object).add.push(new_tag.clone());
+                }
+            }
+        }
+
+        if options.conflict_rule == PullConflictRule::GooglePhotosWins
+        {
+            if let Some(existing_tag) = existing_tag
+            {
+                for (media_id, object) in media_id_to_object.iter()
+                {
+                    if !contents.contains(media_id)
+                        && object.tags.iter().any(|t| t.tag_id == existing_tag.tag_id)
+                    {
+                        change_for(&mut changes, object).remove.push(existing_tag.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    changes.into_iter().map(|(_id, change)| change).collect()
+}
+
+fn change_for<'a>(changes: &'a mut BTreeMap<String, TagChange>, object: &picvudb::data::get::ObjectMetadata) -> &'a mut TagChange
+{
+    changes.entry(object.id.to_string()).or_insert(TagChange
+    {
+        object_id: object.id.clone(),
+        object_name: object.get_display_text(),
+        add: Vec::new(),
+        remove: Vec::new(),
+    })
+}
+
+pub fn apply_tag_changes(store: &picvudb::Store, changes: &Vec<TagChange>, sender: &ProgressSender) -> Result<(), SyncError>
+{
+    let num_changes = changes.len();
+    let mut changes_performed: usize = 0;
+
+    for change in changes
+    {
+        changes_performed += 1;
+
+        sender.set(
+            (changes_performed as f64) / (num_changes as f64) * 100.0,
+            vec![format!("Updated tags on {} of {} items", changes_performed, num_changes)]);
+
+        let msg = picvudb::msgs::UpdateObjectTagsRequest
+        {
+            object_id: change.object_id.clone(),
+            remove: change.remove.iter().map(|t| t.tag_id.clone()).collect(),
+            add: change.add.clone(),
+        };
+
+        store.write_transaction(|ops|
+        {
+            msg.execute(ops)
+        })?;
+    }
+
+    Ok(())
+}
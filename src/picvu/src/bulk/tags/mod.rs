@@ -51,7 +51,7 @@ impl BulkOperation for DeleteTagBulkOp
 
                 let objects =
                 {
-                    let query = picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag_id.clone() };
+                    let query = picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag_id.clone(), include_descendants: false };
 
                     let msg = picvudb::msgs::GetObjectsRequest
                     {
@@ -69,6 +69,22 @@ impl BulkOperation for DeleteTagBulkOp
                     results.objects
                 };
 
+                // Tags with children aren't deleted when they're
+                // removed from the last object, so they're
+                // deleted explicitly once all objects are done
+
+                let has_children =
+                {
+                    let msg = picvudb::msgs::GetTagRequest{ tag_id: tag_id.clone() };
+
+                    let response = store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+
+                    !response.children.is_empty()
+                };
+
                 // Delete this tag from each object
 
                 let num_objects = objects.len();
@@ -95,6 +111,16 @@ impl BulkOperation for DeleteTagBulkOp
                         })?;
                 }
 
+                if has_children
+                {
+                    let msg = picvudb::msgs::DeleteTagRequest{ tag_id: tag_id.clone() };
+
+                    store.write_transaction(|ops|
+                        {
+                            msg.execute(ops)
+                        })?;
+                }
+
                 sender.set(100.0, vec!["Completed".to_owned()]);
 
                 Ok(())
@@ -0,0 +1,140 @@
+use std::future::Future;
+use std::pin::Pin;
+use actix_web::web;
+
+use picvudb::StoreAccess;
+use picvudb::ApiMessage;
+
+use crate::analyse::clock::TimeShift;
+use crate::bulk::BulkOperation;
+use crate::bulk::progress::ProgressSender;
+
+/// The objects that a bulk operation applies to - either
+/// all of the results of a query, or a hand-picked list.
+#[derive(Debug, Clone)]
+pub enum ObjectSelection
+{
+    Query(picvudb::data::get::GetObjectsQuery),
+    ObjectIds(Vec<picvudb::data::ObjectId>),
+}
+
+impl ObjectSelection
+{
+    pub fn queries(&self) -> Vec<picvudb::data::get::GetObjectsQuery>
+    {
+        match self
+        {
+            ObjectSelection::Query(query) => vec![query.clone()],
+            ObjectSelection::ObjectIds(ids) => ids.iter().map(|id| picvudb::data::get::GetObjectsQuery::ByObjectId(id.clone())).collect(),
+        }
+    }
+}
+
+pub struct TimeShiftBulkOp
+{
+    db_uri: String,
+    selection: ObjectSelection,
+    shift: TimeShift,
+}
+
+impl TimeShiftBulkOp
+{
+    pub fn new(db_uri: String, selection: ObjectSelection, shift: TimeShift) -> Self
+    {
+        TimeShiftBulkOp
+        {
+            db_uri,
+            selection,
+            shift,
+        }
+    }
+}
+
+impl BulkOperation for TimeShiftBulkOp
+{
+    type Error = actix_web::error::BlockingError<picvudb::Error>;
+    type Future = Pin<Box<dyn Future<Output=Result<(), Self::Error>>>>;
+
+    fn name(&self) -> String
+    {
+        "Shift activity times".to_owned()
+    }
+
+    fn start(self, sender: ProgressSender) -> Self::Future
+    {
+        let db_uri = self.db_uri;
+        let selection = self.selection;
+        let shift = self.shift;
+
+        Box::pin(async move
+        {
+            web::block(move ||
+            {
+                sender.start_stage(
+                    "Loading objects".to_owned(),
+                    vec!["Shifting activity times".to_owned()]);
+
+                let store = picvudb::Store::new(&db_uri)?;
+
+                // Load all of the objects before changing any, as
+                // changing the activity time can change the query results
+
+                let mut objects = Vec::new();
+
+                for query in selection.queries()
+                {
+                    let msg = picvudb::msgs::GetObjectsRequest
+                    {
+                        query,
+                        pagination: None,
+                    };
+
+                    let results = store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+
+                    objects.extend(results.objects);
+                }
+
+                sender.start_stage(
+                    "Shifting activity times".to_owned(),
+                    vec![]);
+
+                let num_objects = objects.len();
+                let mut done = 0;
+
+                for object in objects
+                {
+                    done += 1;
+
+                    sender.set(
+                        (done as f64) / (num_objects as f64) * 100.0,
+                        vec![format!("Shifted {} of {} objects", done, num_objects)]);
+
+                    let msg = picvudb::msgs::UpdateObjectRequest
+                    {
+                        object_id: object.id,
+                        activity_time: shift.apply(&object.activity_time),
+                        title: object.title,
+                        notes: object.notes,
+                        rating: object.rating,
+                        censor: object.censor,
+                        location: object.location,
+                    };
+
+                    store.write_transaction(|ops|
+                    {
+                        msg.execute(ops)
+                    })?;
+                }
+
+                sender.set(100.0, vec![format!("Shifted {} objects", num_objects)]);
+
+                Ok(())
+            }).await?;
+
+            Ok(())
+        })
+    }
+}
@@ -5,7 +5,9 @@ pub enum OutlineIcon
 {
     AlertTriangle,
     Calendar,
+    Camera,
     Cancel,
+    Clock,
     CloudUpload,
     DashCircle,
     Edit,
@@ -65,6 +67,8 @@ impl OutlineIcon
         {
             OutlineIcon::AlertTriangle => "exclamation-triangle",
             OutlineIcon::Calendar => "calendar4-week",
+            OutlineIcon::Camera => "camera",
+            OutlineIcon::Clock => "clock",
             OutlineIcon::Cancel => "x-circle",
             OutlineIcon::CloudUpload => "cloud-upload",
             OutlineIcon::DashCircle => "dash-circle",
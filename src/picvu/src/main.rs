@@ -24,6 +24,8 @@ pub struct State {
     db_uri: String,
     google_auth_client: Arc<Mutex<GoogleAuthClient>>,
     recent_tags: Arc<Mutex<cache::tags::RecentTagCache>>,
+    sync_plan: bulk::sync::SyncPlanStore,
+    location_proposals: bulk::infer::LocationProposalStore,
     header_links: pages::HeaderLinkCollection,
 }
 
@@ -74,6 +76,8 @@ async fn main() -> std::io::Result<()>
     let bulk_queue = Arc::new(Mutex::new(bulk::BulkQueue::new()));
     let google_auth_client = Arc::new(Mutex::new(GoogleAuthClient::new()));
     let recent_tags = Arc::new(Mutex::new(cache::tags::RecentTagCache::new()));
+    let sync_plan = Arc::new(Mutex::new(None));
+    let location_proposals = Arc::new(Mutex::new(None));
 
     HttpServer::new(move ||
     {
@@ -90,7 +94,16 @@ async fn main() -> std::io::Result<()>
         pages::auth::AuthPage::page_resources(&mut page_builder);
         pages::sync::SyncPage::page_resources(&mut page_builder);
         pages::add_object::AddObjectPage::page_resources(&mut page_builder);
+        pages::add_note::AddNotePage::page_resources(&mut page_builder);
+        pages::recipe::RecipePage::page_resources(&mut page_builder);
+        pages::relations::RelationsPage::page_resources(&mut page_builder);
+        pages::adjust_image::AdjustImagePage::page_resources(&mut page_builder);
         pages::bulk::BulkPage::page_resources(&mut page_builder);
+        pages::cameras::CamerasPage::page_resources(&mut page_builder);
+        pages::time_shift::TimeShiftPage::page_resources(&mut page_builder);
+        pages::geotag::GeotagPage::page_resources(&mut page_builder);
+        pages::infer_locations::InferLocationsPage::page_resources(&mut page_builder);
+        pages::replace_attachment::ReplaceAttachmentPage::page_resources(&mut page_builder);
 
         let state = State
         {
@@ -100,6 +113,8 @@ async fn main() -> std::io::Result<()>
             db_uri: db_uri2.clone(),
             google_auth_client: google_auth_client.clone(),
             recent_tags: recent_tags.clone(),
+            sync_plan: sync_plan.clone(),
+            location_proposals: location_proposals.clone(),
             header_links: page_builder.header_links,
         };
 
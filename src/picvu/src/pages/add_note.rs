@@ -0,0 +1,142 @@
+use serde::Deserialize;
+use actix_web::{web, HttpRequest, HttpResponse};
+use horrorshow::{owned_html, Template};
+
+use crate::icons::OutlineIcon;
+use crate::pages::{PageResources, PageResourcesBuilder};
+use crate::pages;
+use crate::view;
+use crate::State;
+
+#[allow(dead_code)]
+pub struct AddNotePage
+{
+}
+
+impl PageResources for AddNotePage
+{
+    fn page_resources(builder: &mut PageResourcesBuilder)
+    {
+        builder
+            .add_header_link("/view/add_note", "New Note", OutlineIcon::FilePlus, 600)
+            .route_view("/view/add_note", web::get().to(get_add_note))
+            .route_other("/form/add_note", web::post().to(post_add_note));
+    }
+}
+
+#[derive(Deserialize)]
+struct FormAddNote
+{
+    title: String,
+    notes: String,
+    location: String,
+    tags: String,
+}
+
+async fn get_add_note(state: web::Data<State>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let contents = owned_html!
+    {
+        h1: "New Note";
+
+        form(method="POST", action="/form/add_note", enctype="application/x-www-form-urlencoded")
+        {
+            table(class="details-table")
+            {
+                tr
+                {
+                    td: "Title";
+                    td
+                    {
+                        input(type="text", name="title", size=60);
+                    }
+                }
+                tr
+                {
+                    td: "Notes";
+                    td
+                    {
+                        textarea(name="notes", rows=20, cols=60);
+                    }
+                }
+                tr
+                {
+                    td: "Location";
+                    td
+                    {
+                        input(type="text", name="location", size=60, placeholder="Latitude, Longitude");
+                    }
+                }
+                tr
+                {
+                    td: "Tags";
+                    td
+                    {
+                        input(type="text", name="tags", size=60, placeholder="Comma separated labels");
+                    }
+                }
+            }
+
+            input(type="submit", value="Add Note");
+        }
+    }.into_string().unwrap();
+
+    Ok(view::html_page(&req, &state.header_links, "New Note", OutlineIcon::FileText, &contents))
+}
+
+async fn post_add_note(state: web::Data<State>, form: web::Form<FormAddNote>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let title = if form.title.is_empty() { None } else { Some(picvudb::data::TitleMarkdown::parse(form.title.clone())?) };
+    let notes = if form.notes.is_empty() { None } else { Some(picvudb::data::NotesMarkdown::parse(form.notes.clone())?) };
+
+    let location = if form.location.is_empty()
+    {
+        None
+    }
+    else
+    {
+        Some(form.location.parse::<picvudb::data::Location>()?)
+    };
+
+    let tags = form.tags
+        .split(',')
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .map(|t| picvudb::data::add::Tag
+        {
+            name: t.to_owned(),
+            kind: picvudb::data::TagKind::Label,
+            rating: picvudb::data::Rating::NotRated,
+            censor: picvudb::data::Censor::FamilyFriendly,
+            parent: None,
+        })
+        .collect();
+
+    // Notes are objects without any attachments - the
+    // times all default to now
+
+    let msg = picvudb::msgs::AddObjectRequest
+    {
+        data: picvudb::data::add::ObjectData
+        {
+            title,
+            notes,
+            rating: picvudb::data::Rating::NotRated,
+            censor: picvudb::data::Censor::FamilyFriendly,
+            created_time: None,
+            modified_time: None,
+            activity_time: None,
+            location,
+            attachment: None,
+            additional_attachments: Vec::new(),
+            tags,
+            ext_ref: None,
+            user_orientation: None,
+            crop: None,
+        },
+    };
+
+    let response = state.db.send(msg).await??;
+
+    Ok(view::redirect(pages::object_details::ObjectDetailsPage::path_for(&response.object_id)))
+}
@@ -0,0 +1,219 @@
+use serde::Deserialize;
+use actix_web::{web, HttpRequest, HttpResponse};
+use horrorshow::{owned_html, Raw, Template};
+
+use crate::icons::OutlineIcon;
+use crate::pages::{HeaderLinkCollection, PageResources, PageResourcesBuilder};
+use crate::pages;
+use crate::view;
+use crate::State;
+
+#[allow(dead_code)]
+pub struct AdjustImagePage
+{
+}
+
+impl AdjustImagePage
+{
+    pub fn path_for(obj_id: &picvudb::data::ObjectId) -> String
+    {
+        format!("/view/adjust_image/{}", obj_id.to_string())
+    }
+}
+
+impl PageResources for AdjustImagePage
+{
+    fn page_resources(builder: &mut PageResourcesBuilder)
+    {
+        builder
+            .route_view("/view/adjust_image/{obj_id}", web::get().to(get_adjust_image))
+            .route_other("/form/adjust_image/{obj_id}", web::post().to(post_adjust_image));
+    }
+}
+
+#[derive(Deserialize)]
+struct FormAdjustImage
+{
+    orientation: String,
+    crop_left: f64,
+    crop_top: f64,
+    crop_width: f64,
+    crop_height: f64,
+}
+
+const ORIENTATIONS: [(&str, &str); 5] =
+[
+    ("exif", "As Photographed (EXIF)"),
+    ("straight", "Straight"),
+    ("right", "Rotated Right"),
+    ("upside_down", "Upside Down"),
+    ("left", "Rotated Left"),
+];
+
+fn orientation_to_form(orientation: &Option<picvudb::data::Orientation>) -> &'static str
+{
+    match orientation
+    {
+        None => "exif",
+        Some(picvudb::data::Orientation::Straight) => "straight",
+        Some(picvudb::data::Orientation::RotatedRight) => "right",
+        Some(picvudb::data::Orientation::UpsideDown) => "upside_down",
+        Some(picvudb::data::Orientation::RotatedLeft) => "left",
+    }
+}
+
+fn orientation_from_form(value: &str) -> Option<picvudb::data::Orientation>
+{
+    match value
+    {
+        "straight" => Some(picvudb::data::Orientation::Straight),
+        "right" => Some(picvudb::data::Orientation::RotatedRight),
+        "upside_down" => Some(picvudb::data::Orientation::UpsideDown),
+        "left" => Some(picvudb::data::Orientation::RotatedLeft),
+        _ => None,
+    }
+}
+
+async fn get_object(state: &web::Data<State>, object_id: &picvudb::data::ObjectId) -> Result<Option<picvudb::data::get::ObjectMetadata>, view::ErrorResponder>
+{
+    let query = picvudb::data::get::GetObjectsQuery::ByObjectId(object_id.clone());
+
+    let msg = picvudb::msgs::GetObjectsRequest
+    {
+        query,
+        pagination: None,
+    };
+
+    let response = state.db.send(msg).await??;
+    let mut objects = response.objects;
+    let object = objects.drain(..).nth(0);
+
+    Ok(object)
+}
+
+async fn get_adjust_image(state: web::Data<State>, object_id: web::Path<String>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
+
+    match get_object(&state, &object_id).await?
+    {
+        None =>
+        {
+            Ok(view::err(HttpResponse::NotFound(), "Not Found"))
+        },
+        Some(object) =>
+        {
+            Ok(render_adjust_image(object, &req, &state.header_links))
+        },
+    }
+}
+
+async fn post_adjust_image(state: web::Data<State>, object_id: web::Path<String>, form: web::Form<FormAdjustImage>) -> Result<HttpResponse, HttpResponse>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())
+        .map_err(|e| view::err(HttpResponse::BadRequest(), e))?;
+
+    // The form uses percentages, but the crop
+    // is stored as fractions of the image size
+
+    let crop = picvudb::data::Crop::new(
+        form.crop_left / 100.0,
+        form.crop_top / 100.0,
+        form.crop_width / 100.0,
+        form.crop_height / 100.0)
+        .map_err(|e| view::err(HttpResponse::BadRequest(), e))?;
+
+    let msg = picvudb::msgs::UpdateObjectAdjustmentsRequest
+    {
+        object_id: object_id.clone(),
+        user_orientation: orientation_from_form(&form.orientation),
+        crop: Some(crop),
+    };
+
+    state.db.send(msg).await
+        .map_err(|e| view::err(HttpResponse::InternalServerError(), e))?
+        .map_err(|e| view::err(HttpResponse::InternalServerError(), e))?;
+
+    Ok(view::redirect(pages::object_details::ObjectDetailsPage::path_for(&object_id)))
+}
+
+fn render_adjust_image(object: picvudb::data::get::ObjectMetadata, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
+{
+    let filename = object.get_display_text();
+
+    let title = view::Title
+    {
+        text: object.title.clone().map(|m| m.get_display_text()).unwrap_or(filename.clone()),
+        html: Raw(object.title.clone().map(|m| m.get_html()).unwrap_or(owned_html!{ : filename.clone() }.into_string().unwrap())),
+    };
+
+    let current_orientation = orientation_to_form(&object.user_orientation);
+    let crop = object.crop.clone().unwrap_or(picvudb::data::Crop{ left: 0.0, top: 0.0, width: 1.0, height: 1.0 });
+
+    let contents = owned_html!
+    {
+        h1: "Rotate and Crop";
+
+        p
+        {
+            : pages::attachments::AttachmentsPage::raw_html_for_thumbnail(&object, 512, false);
+        }
+
+        form(method="POST", action=format!("/form/adjust_image/{}", object.id.to_string()), enctype="application/x-www-form-urlencoded")
+        {
+            p
+            {
+                : "These adjustments are applied when the image is shown or exported. The original file is never changed.";
+            }
+
+            h2: "Orientation";
+            p
+            {
+                select(name="orientation")
+                {
+                    @for (value, label) in ORIENTATIONS.iter()
+                    {
+                        @if *value == current_orientation
+                        {
+                            option(value=*value, selected="true"): *label;
+                        }
+                        else
+                        {
+                            option(value=*value): *label;
+                        }
+                    }
+                }
+            }
+
+            h2: "Crop";
+            em: "As percentages of the image, after it has been rotated.";
+            table
+            {
+                tr
+                {
+                    td: "Left";
+                    td { input(type="number", name="crop_left", min="0", max="100", step="any", value=format!("{}", crop.left * 100.0)); }
+                }
+                tr
+                {
+                    td: "Top";
+                    td { input(type="number", name="crop_top", min="0", max="100", step="any", value=format!("{}", crop.top * 100.0)); }
+                }
+                tr
+                {
+                    td: "Width";
+                    td { input(type="number", name="crop_width", min="0", max="100", step="any", value=format!("{}", crop.width * 100.0)); }
+                }
+                tr
+                {
+                    td: "Height";
+                    td { input(type="number", name="crop_height", min="0", max="100", step="any", value=format!("{}", crop.height * 100.0)); }
+                }
+            }
+
+            input(type="submit", value="Save");
+        }
+    }.into_string().unwrap();
+
+    view::html_page(req, header_links, title, OutlineIcon::Edit, &contents)
+}
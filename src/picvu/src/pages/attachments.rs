@@ -3,6 +3,7 @@ use actix_web::{web, HttpResponse};
 use horrorshow::{owned_html, Raw, Template};
 
 use crate::analyse;
+use crate::icons::{IconSize, OutlineIcon};
 use crate::pages::{PageResources, PageResourcesBuilder};
 use crate::State;
 use crate::view;
@@ -34,9 +35,104 @@ impl AttachmentsPage
         format!("/attachments/{}/video_thumb?hash={}&size={}", obj_id.to_string(), hash, size)
     }
 
+    pub fn path_document_thumbnail(obj_id: &picvudb::data::ObjectId, hash: &String, size: u32) -> String
+    {
+        format!("/attachments/{}/doc_thumb?hash={}&size={}", obj_id.to_string(), hash, size)
+    }
+
+    pub fn path_object_attachment(object: &picvudb::data::get::ObjectMetadata, attachment: &picvudb::data::get::AttachmentMetadata) -> String
+    {
+        with_adjustments_key(Self::path_attachment(&object.id, &attachment.hash), object, attachment)
+    }
+
     pub fn raw_html_for_thumbnail(object: &picvudb::data::get::ObjectMetadata, size: u32, play_video: bool) -> Raw<String>
     {
-        calc_raw_html_for_thumbnail(object, size, play_video)
+        match &object.attachment
+        {
+            Some(attachment) => calc_raw_html_for_thumbnail(object, attachment, size, play_video),
+            None => calc_raw_html_for_note_thumbnail(object, size),
+        }
+    }
+
+    pub fn thumbnail_dimensions(object: &picvudb::data::get::ObjectMetadata, size: u32) -> Option<picvudb::data::Dimensions>
+    {
+        match &object.attachment
+        {
+            Some(attachment) =>
+            {
+                let (user_orientation, crop) = adjustments_for(object, attachment);
+
+                analyse::img::adjusted_dimensions(&attachment.dimensions, &attachment.orientation, &user_orientation, &crop)
+                    .map(|d| d.resize_to_max_dimension(size))
+            },
+            None => Some(picvudb::data::Dimensions::new(size, size)),
+        }
+    }
+
+    pub fn raw_html_for_attachment_thumbnail(object: &picvudb::data::get::ObjectMetadata, attachment: &picvudb::data::get::AttachmentMetadata, size: u32, play_video: bool) -> Raw<String>
+    {
+        calc_raw_html_for_thumbnail(object, attachment, size, play_video)
+    }
+}
+
+// The user orientation and crop only apply to the current
+// primary attachment - other attachments and previous
+// versions are always shown as they were stored
+
+fn adjustments_for(object: &picvudb::data::get::ObjectMetadata, attachment: &picvudb::data::get::AttachmentMetadata) -> (Option<analyse::img::Orientation>, Option<picvudb::data::Crop>)
+{
+    if object.attachment.as_ref().map(|a| a.hash == attachment.hash).unwrap_or(false)
+        && attachment.mime.type_() == mime::IMAGE
+        && attachment.mime != mime::IMAGE_GIF
+    {
+        (object.user_orientation.clone(), object.crop.clone())
+    }
+    else
+    {
+        (None, None)
+    }
+}
+
+// Attachment responses are cached as immutable based on
+// their URL, so adjusted images include a key describing
+// the adjustments to make sure changes are shown
+
+fn with_adjustments_key(path: String, object: &picvudb::data::get::ObjectMetadata, attachment: &picvudb::data::get::AttachmentMetadata) -> String
+{
+    use std::hash::{Hash, Hasher};
+
+    match adjustments_for(object, attachment)
+    {
+        (None, None) => path,
+        adjustments =>
+        {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            format!("{:?}", adjustments).hash(&mut hasher);
+
+            format!("{}&adjust={:x}", path, hasher.finish())
+        },
+    }
+}
+
+async fn get_adjustments(state: &web::Data<State>, object_id: &picvudb::data::ObjectId, metadata: &picvudb::data::get::AttachmentMetadata) -> Result<(Option<analyse::img::Orientation>, Option<picvudb::data::Crop>), view::ErrorResponder>
+{
+    if metadata.index != 0 || metadata.is_previous_version
+    {
+        return Ok((None, None));
+    }
+
+    let msg = picvudb::msgs::GetObjectsRequest
+    {
+        query: picvudb::data::get::GetObjectsQuery::ByObjectId(object_id.clone()),
+        pagination: None,
+    };
+
+    let object = state.db.send(msg).await??.objects.into_iter().nth(0);
+
+    match object
+    {
+        None => Ok((None, None)),
+        Some(object) => Ok(adjustments_for(&object, metadata)),
     }
 }
 
@@ -48,6 +144,7 @@ impl PageResources for AttachmentsPage
             .route_other("/attachments/{object_id}/raw", web::get().to(get_attachment))
             .route_other("/attachments/{object_id}/img_thumb", web::get().to(get_img_thumbnail))
             .route_other("/attachments/{object_id}/video_thumb", web::get().to(get_video_thumbnail))
+            .route_other("/attachments/{object_id}/doc_thumb", web::get().to(get_document_thumbnail))
             .route_other("/attachments/{object_id}/mvimg", web::get().to(get_mvimg));
     }
 }
@@ -75,7 +172,7 @@ async fn get_attachment(state: web::Data<State>, object_id: web::Path<String>, f
 {
     let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
 
-    let msg = picvudb::msgs::GetAttachmentDataRequest{ object_id, specific_hash: Some(form.hash.clone()) };
+    let msg = picvudb::msgs::GetAttachmentDataRequest{ object_id: object_id.clone(), specific_hash: Some(form.hash.clone()) };
     let response = state.db.send(msg).await??;
 
     match response
@@ -90,7 +187,24 @@ async fn get_attachment(state: web::Data<State>, object_id: web::Path<String>, f
         }
         picvudb::msgs::GetAttachmentDataResponse::Found{bytes, metadata} =>
         {
-            Ok(view::binary(bytes, metadata.filename, metadata.mime, metadata.hash))
+            let (user_orientation, crop) = get_adjustments(&state, &object_id, &metadata).await?;
+
+            if user_orientation.is_none() && crop.is_none()
+            {
+                return Ok(view::binary(bytes, metadata.filename, metadata.mime, metadata.hash));
+            }
+
+            // Adjusted images are viewed as a rendered
+            // derivative - the stored bytes never change
+
+            let (bytes, metadata) = web::block(move || -> Result<(Vec<u8>, picvudb::data::get::AttachmentMetadata), image::ImageError>
+            {
+                let bytes = analyse::img::render_adjusted_jpeg(&bytes, &metadata.filename, &user_orientation, &crop, None)?;
+
+                Ok((bytes, metadata))
+            }).await?;
+
+            Ok(view::binary(bytes, metadata.filename, mime::IMAGE_JPEG, metadata.hash))
         }
     }
 }
@@ -99,7 +213,7 @@ async fn get_img_thumbnail(state: web::Data<State>, path: web::Path<String>, for
 {
     let object_id = picvudb::data::ObjectId::try_new(path.to_string())?;
 
-    let msg = picvudb::msgs::GetAttachmentDataRequest{ object_id, specific_hash: Some(form.hash.clone()) };
+    let msg = picvudb::msgs::GetAttachmentDataRequest{ object_id: object_id.clone(), specific_hash: Some(form.hash.clone()) };
     let response = state.db.send(msg).await??;
 
     match response
@@ -114,6 +228,8 @@ async fn get_img_thumbnail(state: web::Data<State>, path: web::Path<String>, for
         }
         picvudb::msgs::GetAttachmentDataResponse::Found{bytes, metadata} =>
         {
+            let (user_orientation, crop) = get_adjustments(&state, &object_id, &metadata).await?;
+
             let (bytes, metadata) = web::block(move || -> Result<(Vec<u8>, picvudb::data::get::AttachmentMetadata), image::ImageError>
             {
                 let orientation =
@@ -123,28 +239,18 @@ async fn get_img_thumbnail(state: web::Data<State>, path: web::Path<String>, for
                     .map(|(analysis, _warnings)|{ analysis.orientation })
                     .flatten();
 
-                let image = image::load_from_memory(&bytes)?;
-                let image = image.thumbnail(form.size, form.size);
+                let image = analyse::img::load_image(&bytes)?;
+
+                // Without adjustments, it's quicker to thumbnail
+                // first - but a crop needs the full resolution
 
-                let image = match orientation
+                let image = if user_orientation.is_none() && crop.is_none()
                 {
-                    None
-                        | Some(analyse::img::Orientation::Straight) =>
-                    {
-                        image
-                    },
-                    Some(analyse::img::Orientation::UpsideDown) =>
-                    {
-                        image.rotate180()
-                    }
-                    Some(analyse::img::Orientation::RotatedLeft) =>
-                    {
-                        image.rotate90()
-                    }
-                    Some(analyse::img::Orientation::RotatedRight) =>
-                    {
-                        image.rotate270()
-                    }
+                    analyse::img::apply_orientation(image.thumbnail(form.size, form.size), &orientation)
+                }
+                else
+                {
+                    analyse::img::apply_adjustments(image, &orientation, &user_orientation, &crop).thumbnail(form.size, form.size)
                 };
 
                 let mut cursor = std::io::Cursor::new(Vec::new());
@@ -184,7 +290,7 @@ async fn get_video_thumbnail(state: web::Data<State>, path: web::Path<String>, f
                 let assume_timezone = None;
                 let mut warnings = Vec::new();
 
-                analyse::video::analyse_video(&bytes, &filename, form.size, &assume_timezone, None, &mut warnings)
+                analyse::video::analyse_video(&bytes, &filename, Some(form.size), &assume_timezone, None, &mut warnings)
             }).await?;
 
             match info.thumbnail
@@ -206,6 +312,56 @@ async fn get_video_thumbnail(state: web::Data<State>, path: web::Path<String>, f
     }
 }
 
+async fn get_document_thumbnail(state: web::Data<State>, path: web::Path<String>, form: web::Query<FormThumbnail>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(path.to_string())?;
+
+    let msg = picvudb::msgs::GetAttachmentDataRequest{ object_id, specific_hash: Some(form.hash.clone()) };
+    let response = state.db.send(msg).await??;
+
+    match response
+    {
+        picvudb::msgs::GetAttachmentDataResponse::ObjectNotFound =>
+        {
+            Ok(view::err(HttpResponse::NotFound(), "Object not found"))
+        }
+        picvudb::msgs::GetAttachmentDataResponse::HashNotFound =>
+        {
+            Ok(view::err(HttpResponse::NotFound(), "Object's current attachment has a different hash"))
+        }
+        picvudb::msgs::GetAttachmentDataResponse::Found{bytes, metadata} =>
+        {
+            if metadata.mime != mime::APPLICATION_PDF
+            {
+                return Ok(view::err(HttpResponse::NotFound(), "Only PDF documents have thumbnails"));
+            }
+
+            let size = form.size;
+
+            let thumbnail = web::block(move || -> Result<Option<Vec<u8>>, std::io::Error>
+            {
+                analyse::document::render_pdf_thumbnail(&bytes, size)
+            }).await?;
+
+            match thumbnail
+            {
+                None =>
+                {
+                    Ok(view::err(HttpResponse::NotFound(), "Can't generate document thumbnail"))
+                },
+                Some(thumbnail) =>
+                {
+                    Ok(view::binary(
+                        thumbnail,
+                        format!("{}.jpg", metadata.filename),
+                        mime::IMAGE_JPEG,
+                        metadata.hash))
+                },
+            }
+        },
+    }
+}
+
 async fn get_mvimg(state: web::Data<State>, object_id: web::Path<String>, form: web::Query<FormMvImg>) -> Result<HttpResponse, view::ErrorResponder>
 {
     let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
@@ -252,41 +408,47 @@ async fn get_mvimg(state: web::Data<State>, object_id: web::Path<String>, form:
     }
 }
 
-fn calc_raw_html_for_thumbnail(object: &picvudb::data::get::ObjectMetadata, size: u32, play_video: bool) -> Raw<String>
+fn calc_raw_html_for_thumbnail(object: &picvudb::data::get::ObjectMetadata, attachment: &picvudb::data::get::AttachmentMetadata, size: u32, play_video: bool) -> Raw<String>
 {
-    let dimensions = object.attachment.dimensions.clone().map(|d| d.resize_to_max_dimension(size));
+    let obj_id = &object.id;
+    let (user_orientation, crop) = adjustments_for(object, attachment);
+
+    let dimensions = analyse::img::adjusted_dimensions(&attachment.dimensions, &attachment.orientation, &user_orientation, &crop)
+        .map(|d| d.resize_to_max_dimension(size));
+
+    let image_thumbnail_path = with_adjustments_key(AttachmentsPage::path_image_thumbnail(obj_id, &attachment.hash, size), object, attachment);
 
     Raw(owned_html!
     {
-        @if object.attachment.mime == mime::IMAGE_GIF
+        @if attachment.mime == mime::IMAGE_GIF
         {
             @if let Some(dimensions) = dimensions
             {
-                img(src=AttachmentsPage::path_attachment(&object.id, &object.attachment.hash),
+                img(src=AttachmentsPage::path_attachment(obj_id, &attachment.hash),
                     width=dimensions.width.to_string(),
                     height=dimensions.height.to_string())
             }
             else
             {
                 // No dimensions - just try as a re-sized thumbnail of the correct size
-                img(src=AttachmentsPage::path_image_thumbnail(&object.id, &object.attachment.hash, size))
+                img(src=AttachmentsPage::path_image_thumbnail(obj_id, &attachment.hash, size))
             }
         }
-        else if object.attachment.mime.type_() == mime::IMAGE
+        else if attachment.mime.type_() == mime::IMAGE
         {
             @if let Some(dimensions) = dimensions
             {
-                img(src=AttachmentsPage::path_image_thumbnail(&object.id, &object.attachment.hash, size),
+                img(src=image_thumbnail_path.clone(),
                     width=dimensions.width.to_string(),
                     height=dimensions.height.to_string())
             }
             else
             {
                 // No dimensions - just try as a re-sized thumbnail of the correct size
-                img(src=AttachmentsPage::path_image_thumbnail(&object.id, &object.attachment.hash, size))
+                img(src=image_thumbnail_path.clone())
             }
         }
-        else if object.attachment.mime.type_() == mime::VIDEO
+        else if attachment.mime.type_() == mime::VIDEO
         {
             @if let Some(dimensions) = dimensions
             {
@@ -300,13 +462,13 @@ fn calc_raw_html_for_thumbnail(object: &picvudb::data::get::ObjectMetadata, size
                         loop="true")
                     {
                         source(
-                            src=AttachmentsPage::path_attachment(&object.id, &object.attachment.hash),
-                            type=object.attachment.mime.to_string())
+                            src=AttachmentsPage::path_attachment(obj_id, &attachment.hash),
+                            type=attachment.mime.to_string())
                     }
                 }
                 else
                 {
-                    img(src=AttachmentsPage::path_video_thumbnail(&object.id, &object.attachment.hash, size),
+                    img(src=AttachmentsPage::path_video_thumbnail(obj_id, &attachment.hash, size),
                         width=dimensions.width.to_string(),
                         height=dimensions.height.to_string())
                 }
@@ -317,9 +479,49 @@ fn calc_raw_html_for_thumbnail(object: &picvudb::data::get::ObjectMetadata, size
             }
 
         }
+        else if attachment.mime == mime::APPLICATION_PDF
+        {
+            // The object element shows the generic icon
+            // if the first page can't be rendered
+
+            object(data=AttachmentsPage::path_document_thumbnail(obj_id, &attachment.hash, size),
+                type="image/jpeg",
+                style=format!("max-width: {}px;max-height: {}px;", size, size))
+            {
+                : document_icon_html(attachment);
+            }
+        }
         else
         {
-            div: "Other";
+            : document_icon_html(attachment);
+        }
+    }.into_string().unwrap())
+}
+
+fn document_icon_html(attachment: &picvudb::data::get::AttachmentMetadata) -> Raw<String>
+{
+    Raw(owned_html!
+    {
+        div(class="document-thumbnail")
+        {
+            : OutlineIcon::FileText.render(IconSize::Size32x32);
+            div: attachment.filename.clone();
+        }
+    }.into_string().unwrap())
+}
+
+fn calc_raw_html_for_note_thumbnail(object: &picvudb::data::get::ObjectMetadata, size: u32) -> Raw<String>
+{
+    // Notes have nothing to preview, so the
+    // rendered markdown is shown instead
+
+    let notes_html = object.notes.clone().map(|m| m.get_html()).unwrap_or_default();
+
+    Raw(owned_html!
+    {
+        div(class="note-thumbnail", style=format!("width: {}px;height: {}px;", size, size))
+        {
+            : Raw(notes_html);
         }
     }.into_string().unwrap())
 }
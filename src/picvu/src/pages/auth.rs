@@ -91,11 +91,95 @@ async fn get_auth_token(state: web::Data<State>) -> Result<HttpResponse, view::E
         operation.blocking_execute()
     }).await?;
 
+    let refresh_token =
     {
         let mut google_auth_client = state.google_auth_client.lock().unwrap();
 
         google_auth_client.save_token(token);
+        google_auth_client.refresh_token()
     };
 
+    if refresh_token.is_some()
+    {
+        pages::setup::set_refresh_token(&*state, refresh_token).await?;
+    }
+
     Ok(view::redirect("/".to_owned()))
 }
+
+/// Returns a valid access token - refreshing it from the
+/// current or stored refresh token if it has expired. Returns
+/// `None` if the user needs to login again.
+pub async fn get_access_token(state: &State) -> Result<Option<googlephotos::auth::AccessToken>, view::ErrorResponder>
+{
+    {
+        let google_auth_client = state.google_auth_client.lock().unwrap();
+
+        if let Some(access_token) = google_auth_client.access_token()
+        {
+            return Ok(Some(access_token));
+        }
+    }
+
+    let (client_id, client_secret) = pages::setup::get_client_id_and_secret(&*state).await?;
+    let stored_refresh_token = pages::setup::get_refresh_token(&*state).await?;
+
+    if client_id.is_empty() || client_secret.is_empty()
+    {
+        return Ok(None);
+    }
+
+    let operation =
+    {
+        let setup = googlephotos::auth::GoogleAuthSetup
+        {
+            client_id,
+            client_secret,
+            redirect_url: format!("{}/auth/google/callback", state.host_base),
+        };
+
+        let mut google_auth_client = state.google_auth_client.lock().unwrap();
+
+        google_auth_client.refresh(setup, stored_refresh_token)
+    };
+
+    let result = web::block(||
+    {
+        operation.blocking_execute()
+    }).await;
+
+    match result
+    {
+        Ok(token) =>
+        {
+            // A login may have been started while refreshing,
+            // in which case the refreshed token isn't kept
+
+            let mut google_auth_client = state.google_auth_client.lock().unwrap();
+
+            google_auth_client.save_refreshed_token(token);
+
+            Ok(google_auth_client.access_token())
+        },
+        Err(actix_web::error::BlockingError::Error(err)) =>
+        {
+            // The refresh token has probably been revoked or
+            // expired - the user will need to login again. Only
+            // the expired token is replaced with the error, so
+            // that a login started meanwhile can still complete
+
+            let mut google_auth_client = state.google_auth_client.lock().unwrap();
+
+            if let googlephotos::auth::TokenStatus::Expired{..} = google_auth_client.status()
+            {
+                google_auth_client.set_error(format!("{:?}", err));
+            }
+
+            Ok(None)
+        },
+        Err(actix_web::error::BlockingError::Canceled) =>
+        {
+            Err(view::ErrorResponder::BlockingOperationCanceled)
+        },
+    }
+}
@@ -1,6 +1,6 @@
 use serde::Deserialize;
 use actix_web::{web, HttpRequest, HttpResponse};
-use horrorshow::{owned_html, Template};
+use horrorshow::{owned_html, Raw, Template};
 
 use crate::analyse;
 use crate::bulk;
@@ -34,7 +34,9 @@ impl PageResources for BulkPage
             .route_view("/view/bulk_export", web::get().to(get_bulk_export))
             .route_other("/view/bulk-progress", web::get().to(get_bulk_progress))
             .route_other("/form/bulk_import", web::post().to(post_bulk_import))
+            .route_other("/form/bulk_import_google_photos", web::post().to(post_bulk_import_google_photos))
             .route_other("/form/bulk_export", web::post().to(post_bulk_export))
+            .route_other("/form/bulk_documents_backfill", web::post().to(post_bulk_documents_backfill))
             .route_other("/form/bulk_acknowledge", web::post().to(post_bulk_acknowledge));
     }
 }
@@ -47,6 +49,25 @@ pub struct BulkImportForm
     pub force_timezone: String,
     pub assume_notes: String,
     pub assume_location: String,
+    pub ignore_patterns: String,
+    pub unknown_files: String,
+    pub clock_corrections: String,
+    pub track_log_files: String,
+    pub track_log_max_gap: String,
+    pub track_log_clock_offset: String,
+}
+
+#[derive(Deserialize)]
+pub struct GooglePhotosImportForm
+{
+    pub assume_timezone: String,
+    pub force_timezone: String,
+    pub assume_notes: String,
+    pub assume_location: String,
+    pub clock_corrections: String,
+    pub track_log_files: String,
+    pub track_log_max_gap: String,
+    pub track_log_clock_offset: String,
 }
 
 #[derive(Deserialize)]
@@ -55,6 +76,38 @@ pub struct BulkExportForm
     pub folder: String,
 }
 
+fn parse_unknown_file_handling(s: &str) -> Result<analyse::import::UnknownFileHandling, HttpResponse>
+{
+    match s
+    {
+        "Fail" => Ok(analyse::import::UnknownFileHandling::Fail),
+        "SkipWithWarning" => Ok(analyse::import::UnknownFileHandling::SkipWithWarning),
+        "StoreAsDocument" => Ok(analyse::import::UnknownFileHandling::StoreAsDocument),
+        _ => Err(view::err(HttpResponse::BadRequest(), "Invalid parameter")),
+    }
+}
+
+async fn load_track_log(track_log_files: &String, track_log_max_gap: &String, track_log_clock_offset: &String) -> Result<Option<analyse::tracklog::TrackLog>, HttpResponse>
+{
+    let form = pages::geotag::TrackLogForm
+    {
+        track_log_files: Some(track_log_files.clone()),
+        track_log_max_gap: Some(track_log_max_gap.clone()),
+        track_log_clock_offset: Some(track_log_clock_offset.clone()),
+    };
+
+    pages::geotag::load_track_log(&form).await
+}
+
+fn parse_clock_corrections(s: &str) -> Result<Vec<analyse::clock::ClockCorrection>, HttpResponse>
+{
+    s.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.parse().map_err(|_| view::err(HttpResponse::BadRequest(), format!("Invalid clock correction {:?}", l))))
+        .collect()
+}
+
 fn parse_str_to_opt<T: std::str::FromStr>(s: &str) -> Result<Option<T>, HttpResponse>
 {
     if s.is_empty()
@@ -77,9 +130,10 @@ async fn post_bulk_import(state: web::Data<State>, form: web::Form<BulkImportFor
 
     let access_token =
     {
-        let auth = state.google_auth_client.lock().unwrap();
+        let access_token = pages::auth::get_access_token(&*state).await
+            .map_err(|e| view::err(HttpResponse::InternalServerError(), e))?;
 
-        match auth.access_token()
+        match access_token
         {
             Some(access_token) =>
             {
@@ -98,6 +152,14 @@ async fn post_bulk_import(state: web::Data<State>, form: web::Form<BulkImportFor
         force_timezone: parse_str_to_opt(&form.force_timezone)?,
         assume_notes: parse_str_to_opt(&form.assume_notes)?,
         assume_location: parse_str_to_opt(&form.assume_location)?,
+        ignore_patterns: form.ignore_patterns
+            .split(',')
+            .map(|s| s.trim().to_owned())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        unknown_files: parse_unknown_file_handling(&form.unknown_files)?,
+        clock_corrections: parse_clock_corrections(&form.clock_corrections)?,
+        track_log: load_track_log(&form.track_log_files, &form.track_log_max_gap, &form.track_log_clock_offset).await?,
     };
 
     {
@@ -109,6 +171,60 @@ async fn post_bulk_import(state: web::Data<State>, form: web::Form<BulkImportFor
     Ok(view::redirect(BulkPage::progress_path()))
 }
 
+async fn post_bulk_import_google_photos(state: web::Data<State>, form: web::Form<GooglePhotosImportForm>) -> Result<HttpResponse, HttpResponse>
+{
+    let api_key = pages::setup::get_api_key(&*state).await.unwrap_or_default();
+
+    {
+        let access_token = pages::auth::get_access_token(&*state).await
+            .map_err(|e| view::err(HttpResponse::InternalServerError(), e))?;
+
+        if access_token.is_none()
+        {
+            return Ok(view::redirect(pages::auth::AuthPage::path()));
+        }
+    }
+
+    let import_options = analyse::import::ImportOptions
+    {
+        assume_timezone: parse_str_to_opt(&form.assume_timezone)?,
+        force_timezone: parse_str_to_opt(&form.force_timezone)?,
+        assume_notes: parse_str_to_opt(&form.assume_notes)?,
+        assume_location: parse_str_to_opt(&form.assume_location)?,
+        ignore_patterns: Vec::new(),
+        unknown_files: analyse::import::UnknownFileHandling::Fail,
+        clock_corrections: parse_clock_corrections(&form.clock_corrections)?,
+        track_log: load_track_log(&form.track_log_files, &form.track_log_max_gap, &form.track_log_clock_offset).await?,
+    };
+
+    // The import asks for a current access token as it goes,
+    // since the token can expire part way through a long import
+
+    let access_tokens: bulk::import::AccessTokenSource =
+    {
+        let state = state.clone();
+
+        Box::new(move ||
+        {
+            let state = state.clone();
+
+            Box::pin(async move
+            {
+                pages::auth::get_access_token(&*state).await
+                    .map_err(|e| format!("{:?}", e))
+            })
+        })
+    };
+
+    {
+        let mut bulk_queue = state.bulk_queue.lock().unwrap();
+
+        bulk_queue.enqueue(bulk::import::GooglePhotosLibraryImport::new(state.db_uri.clone(), api_key, access_tokens, import_options));
+    }
+
+    Ok(view::redirect(BulkPage::progress_path()))
+}
+
 async fn post_bulk_export(state: web::Data<State>, form: web::Form<BulkExportForm>) -> Result<HttpResponse, HttpResponse>
 {
     {
@@ -120,6 +236,17 @@ async fn post_bulk_export(state: web::Data<State>, form: web::Form<BulkExportFor
     Ok(view::redirect(BulkPage::progress_path()))
 }
 
+async fn post_bulk_documents_backfill(state: web::Data<State>) -> HttpResponse
+{
+    {
+        let mut bulk_queue = state.bulk_queue.lock().unwrap();
+
+        bulk_queue.enqueue(bulk::documents::DocumentTextBackfill::new(state.db_uri.clone()));
+    }
+
+    view::redirect(BulkPage::progress_path())
+}
+
 async fn post_bulk_acknowledge(state: web::Data<State>) -> HttpResponse
 {
     {
@@ -131,6 +258,16 @@ async fn post_bulk_acknowledge(state: web::Data<State>) -> HttpResponse
     view::redirect("/".to_owned())
 }
 
+fn empty_track_log_form() -> pages::geotag::TrackLogForm
+{
+    pages::geotag::TrackLogForm
+    {
+        track_log_files: None,
+        track_log_max_gap: None,
+        track_log_clock_offset: None,
+    }
+}
+
 fn get_bulk_import(state: web::Data<State>, req: HttpRequest) -> HttpResponse
 {
     let contents = owned_html!
@@ -181,11 +318,93 @@ fn get_bulk_import(state: web::Data<State>, req: HttpRequest) -> HttpResponse
                 input(type="text", name="assume_location");
             }
 
+            h2: "Ignore Files";
+            em: "File name patterns to ignore, separated by commas. Use \"*\" and \"?\" as wildcards, and include a \"/\" to match the full path.";
+            p
+            {
+                input(type="text", name="ignore_patterns", value=".DS_Store, Thumbs.db, desktop.ini");
+            }
+
+            h2: "Unsupported Files";
+            em: "What to do with files that aren't supported media or metadata files.";
+            p
+            {
+                select(name="unknown_files")
+                {
+                    option(value="SkipWithWarning", selected?=true): "Skip them with a warning";
+                    option(value="StoreAsDocument"): "Import them as document objects";
+                    option(value="Fail"): "Stop the import";
+                }
+            }
+
+            h2: "Camera Clock Corrections";
+            p { em: "Corrects the times of photos from cameras whose clocks were wrong. Enter one correction per line, as \"camera; offset\" or \"camera; offset; date range\"."; }
+            p { em: "The camera is matched against part of the make and model, and the offset (e.g. \"+00:37\" or \"-01:00:30\") is added to the time the photo was taken. The date range is before the correction."; }
+            p
+            {
+                textarea(name="clock_corrections", rows="4", cols="60", placeholder="COOLPIX W300; +00:37; 2019-06-01 to 2019-06-20");
+            }
+
+            : Raw(pages::geotag::render_track_log_inputs(&empty_track_log_form()));
+
+            p
+            {
+                input(type="submit");
+            }
+        }
+        h1: "Import from Google Photos";
+        form(method="POST", action="/form/bulk_import_google_photos", enctype="application/x-www-form-urlencoded")
+        {
+            p { em: "Downloads any items from the Google Photos library that haven't already been imported."; }
+            p { em: "Note that Google Photos removes the GPS location from downloaded photos."; }
+
+            h2: "Assume Timezone";
+            p
+            {
+                input(type="text", name="assume_timezone")
+            }
+
+            h2: "Force Timezone";
+            p
+            {
+                input(type="text", name="force_timezone");
+            }
+
+            h2: "Assume Notes";
+            p
+            {
+                input(type="text", name="assume_notes");
+            }
+
+            h2: "Assume Location";
+            p
+            {
+                input(type="text", name="assume_location");
+            }
+
+            h2: "Camera Clock Corrections";
+            p
+            {
+                textarea(name="clock_corrections", rows="4", cols="60");
+            }
+
+            : Raw(pages::geotag::render_track_log_inputs(&empty_track_log_form()));
+
             p
             {
                 input(type="submit");
             }
         }
+        h1: "Backfill Document Text";
+        form(method="POST", action="/form/bulk_documents_backfill", enctype="application/x-www-form-urlencoded")
+        {
+            p { em: "Re-extracts the text from every PDF and text document, so documents imported before their text was stored can be searched."; }
+
+            p
+            {
+                input(type="submit", value="Backfill");
+            }
+        }
     }.into_string().unwrap();
 
     view::html_page(
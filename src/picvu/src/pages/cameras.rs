@@ -0,0 +1,204 @@
+use serde::Deserialize;
+use actix_web::{web, HttpRequest, HttpResponse};
+use horrorshow::{owned_html, Template};
+
+use crate::bulk;
+use crate::icons::OutlineIcon;
+use crate::pages::{PageResources, PageResourcesBuilder};
+use crate::view;
+use crate::pages;
+use crate::State;
+
+#[allow(dead_code)]
+pub struct CamerasPage
+{
+}
+
+impl CamerasPage
+{
+    pub fn path() -> String
+    {
+        "/view/cameras".to_owned()
+    }
+}
+
+impl PageResources for CamerasPage
+{
+    fn page_resources(builder: &mut PageResourcesBuilder)
+    {
+        builder
+            .add_header_link("/view/cameras", "Cameras", OutlineIcon::Camera, 3)
+            .route_view("/view/cameras", web::get().to(get_cameras))
+            .route_other("/form/cameras/search", web::post().to(post_cameras_search))
+            .route_other("/form/cameras/backfill", web::post().to(post_cameras_backfill));
+    }
+}
+
+#[derive(Deserialize)]
+struct FormCameraSearch
+{
+    make: String,
+    model: String,
+    min_iso: String,
+    max_iso: String,
+    min_focal_length: String,
+    max_focal_length: String,
+}
+
+fn parse_str_to_opt<T: std::str::FromStr>(s: &str, name: &str) -> Result<Option<T>, picvudb::ParseError>
+{
+    let s = s.trim();
+
+    if s.is_empty()
+    {
+        Ok(None)
+    }
+    else
+    {
+        s.parse()
+            .map(Some)
+            .map_err(|_| picvudb::ParseError::new(format!("Invalid {}", name)))
+    }
+}
+
+fn str_to_opt(s: &str) -> Option<String>
+{
+    let s = s.trim();
+
+    if s.is_empty() { None } else { Some(s.to_owned()) }
+}
+
+async fn post_cameras_search(form: web::Form<FormCameraSearch>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let filter = picvudb::data::ExifFilter
+    {
+        make: str_to_opt(&form.make),
+        model: str_to_opt(&form.model),
+        min_iso: parse_str_to_opt(&form.min_iso, "minimum ISO")?,
+        max_iso: parse_str_to_opt(&form.max_iso, "maximum ISO")?,
+        min_focal_length: parse_str_to_opt(&form.min_focal_length, "minimum focal length")?,
+        max_focal_length: parse_str_to_opt(&form.max_focal_length, "maximum focal length")?,
+    };
+
+    Ok(view::redirect(pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::ExifByActivityDesc{ filter })))
+}
+
+async fn post_cameras_backfill(state: web::Data<State>) -> HttpResponse
+{
+    {
+        let mut bulk_queue = state.bulk_queue.lock().unwrap();
+
+        bulk_queue.enqueue(bulk::exif::ExifBackfill::new(state.db_uri.clone()));
+    }
+
+    view::redirect(pages::bulk::BulkPage::progress_path())
+}
+
+async fn get_cameras(state: web::Data<State>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let cameras = state.db.send(picvudb::msgs::GetExifCamerasRequest{}).await??.cameras;
+
+    let contents = owned_html!
+    {
+        h1: "Cameras";
+
+        @if cameras.is_empty()
+        {
+            p: "No objects have any stored camera information.";
+        }
+        else
+        {
+            table(class="details-table")
+            {
+                tr
+                {
+                    th: "Make";
+                    th: "Model";
+                    th: "Objects";
+                }
+
+                @for camera in cameras.iter()
+                {
+                    tr
+                    {
+                        td: &camera.make;
+                        td
+                        {
+                            a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::ExifByActivityDesc
+                                {
+                                    filter: picvudb::data::ExifFilter
+                                    {
+                                        make: Some(camera.make.clone()),
+                                        model: Some(camera.model.clone()),
+                                        ..Default::default()
+                                    },
+                                }))
+                            {
+                                : &camera.model;
+                            }
+                        }
+                        td: camera.num_objects.to_string();
+                    }
+                }
+            }
+        }
+
+        h1: "Search by Camera Settings";
+        form(method="POST", action="/form/cameras/search", enctype="application/x-www-form-urlencoded")
+        {
+            p { em: "Leave any field empty to match all values. The ranges include the minimum and maximum values."; }
+
+            table(class="details-table")
+            {
+                tr
+                {
+                    th: "Make";
+                    td { input(type="text", name="make"); }
+                }
+                tr
+                {
+                    th: "Model";
+                    td { input(type="text", name="model"); }
+                }
+                tr
+                {
+                    th: "ISO";
+                    td
+                    {
+                        input(type="number", name="min_iso", min="0");
+                        : " to ";
+                        input(type="number", name="max_iso", min="0");
+                    }
+                }
+                tr
+                {
+                    th: "Focal Length (mm)";
+                    td
+                    {
+                        input(type="number", name="min_focal_length", min="0", step="any");
+                        : " to ";
+                        input(type="number", name="max_focal_length", min="0", step="any");
+                    }
+                }
+            }
+
+            p
+            {
+                input(type="submit", value="Search");
+            }
+        }
+
+        h1: "Backfill EXIF Metadata";
+        form(method="POST", action="/form/cameras/backfill", enctype="application/x-www-form-urlencoded")
+        {
+            p { em: "Re-reads the EXIF metadata from every image, so objects imported before it was stored can be searched."; }
+
+            p
+            {
+                input(type="submit", value="Backfill");
+            }
+        }
+    }.into_string().unwrap();
+
+    Ok(view::html_page(&req, &state.header_links, "Cameras", OutlineIcon::Camera, &contents))
+}
@@ -93,6 +93,7 @@ async fn post_delete_object(state: web::Data<State>, object_id: web::Path<String
                 kind: picvudb::data::TagKind::Trash,
                 rating: picvudb::data::Rating::NotRated,
                 censor: picvudb::data::Censor::FamilyFriendly,
+                parent: None,
             }];
 
             let msg = picvudb::msgs::UpdateObjectTagsRequest
@@ -111,7 +112,7 @@ async fn post_delete_object(state: web::Data<State>, object_id: web::Path<String
 
 fn render_delete_object(object: picvudb::data::get::ObjectMetadata, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
 {
-    let filename = object.attachment.filename.clone();
+    let filename = object.get_display_text();
 
     let title = view::Title
     {
@@ -183,6 +183,7 @@ async fn post_edit_object(state: web::Data<State>, object_id: web::Path<String>,
                 kind: form.add_tag_kind.parse()?,
                 rating: picvudb::data::Rating::from_num_stars(form.add_tag_rating.parse().unwrap_or(-1))?,
                 censor: form.add_tag_censor.parse()?,
+                parent: None,
             };
 
             {
@@ -348,7 +349,7 @@ async fn get_find_tags(state: web::Data<State>, form: web::Query<FormFindTags>)
 
 fn render_edit_object(state: &crate::State, object: picvudb::data::get::ObjectMetadata, all_objs_on_date: Vec<picvudb::data::get::ObjectMetadata>, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
 {
-    let filename = object.attachment.filename.clone();
+    let filename = object.get_display_text();
 
     let title = view::Title
     {
@@ -396,7 +397,7 @@ fn render_edit_object(state: &crate::State, object: picvudb::data::get::ObjectMe
             .values()   // Drop the tag IDs
             .filter(|tag| object.tags.iter().position(|otag| otag.tag_id == tag.tag_id).is_none())  // Only tags not already set for this object
             .filter(|tag| !tag.kind.is_system_kind())   // Filter out system tags
-            .map(|tag| picvudb::data::add::Tag { name: tag.name.clone(), kind: tag.kind.clone(), rating: tag.rating.clone(), censor: tag.censor.clone(), }) // Map to add structure
+            .map(|tag| picvudb::data::add::Tag { name: tag.name.clone(), kind: tag.kind.clone(), rating: tag.rating.clone(), censor: tag.censor.clone(), parent: None, }) // Map to add structure
             .collect();
 
         let recent =
@@ -474,7 +475,14 @@ fn render_edit_object(state: &crate::State, object: picvudb::data::get::ObjectMe
                         : "Preview";
                     }
 
-                    a(href=pages::attachments::AttachmentsPage::path_attachment(&object.id, &object.attachment.hash))
+                    @if let Some(attachment) = &object.attachment
+                    {
+                        a(href=pages::attachments::AttachmentsPage::path_object_attachment(&object, attachment))
+                        {
+                            : pages::attachments::AttachmentsPage::raw_html_for_thumbnail(&object, 512, true);
+                        }
+                    }
+                    else
                     {
                         : pages::attachments::AttachmentsPage::raw_html_for_thumbnail(&object, 512, true);
                     }
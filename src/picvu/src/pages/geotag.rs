@@ -0,0 +1,226 @@
+use serde::Deserialize;
+use actix_web::{web, HttpRequest, HttpResponse};
+use horrorshow::{owned_html, Template};
+
+use crate::analyse::tracklog::TrackLog;
+use crate::bulk;
+use crate::format;
+use crate::icons::OutlineIcon;
+use crate::pages::{PageResources, PageResourcesBuilder};
+use crate::view;
+use crate::pages;
+use crate::State;
+
+const NUM_PREVIEW_OBJECTS: usize = 20;
+const DEFAULT_MAX_GAP_MINUTES: &'static str = "10";
+
+#[allow(dead_code)]
+pub struct GeotagPage
+{
+}
+
+impl PageResources for GeotagPage
+{
+    fn page_resources(builder: &mut PageResourcesBuilder)
+    {
+        builder
+            .add_header_link("/view/geotag", "Geotag", OutlineIcon::Location, 600)
+            .route_view("/view/geotag", web::get().to(get_geotag))
+            .route_other("/form/geotag", web::post().to(post_geotag));
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TrackLogForm
+{
+    pub track_log_files: Option<String>,
+    pub track_log_max_gap: Option<String>,
+    pub track_log_clock_offset: Option<String>,
+}
+
+/// Loads the track logs specified in a form, or
+/// returns None if no track log files were specified.
+pub async fn load_track_log(form: &TrackLogForm) -> Result<Option<TrackLog>, HttpResponse>
+{
+    let files = form.track_log_files.clone().unwrap_or_default()
+        .split(|c| c == ',' || c == '\n')
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+
+    if files.is_empty()
+    {
+        return Ok(None);
+    }
+
+    let max_gap = form.track_log_max_gap.clone().unwrap_or_default();
+    let max_gap = if max_gap.trim().is_empty() { DEFAULT_MAX_GAP_MINUTES.to_owned() } else { max_gap };
+    let max_gap = max_gap.trim().parse::<i64>()
+        .map(chrono::Duration::minutes)
+        .map_err(|_| view::err(HttpResponse::BadRequest(), "Invalid maximum gap"))?;
+
+    let clock_offset = form.track_log_clock_offset.clone().unwrap_or_default();
+    let clock_offset = if clock_offset.trim().is_empty()
+    {
+        chrono::Duration::zero()
+    }
+    else
+    {
+        crate::analyse::clock::parse_offset(clock_offset.trim())
+            .ok_or_else(|| view::err(HttpResponse::BadRequest(), "Invalid clock offset"))?
+    };
+
+    let track_log = web::block(move || TrackLog::load_files(&files, max_gap, clock_offset)).await
+        .map_err(|e| view::err(HttpResponse::BadRequest(), e))?;
+
+    Ok(Some(track_log))
+}
+
+pub fn render_track_log_inputs(form: &TrackLogForm) -> String
+{
+    let files = form.track_log_files.clone().unwrap_or_default();
+    let max_gap = form.track_log_max_gap.clone().unwrap_or(DEFAULT_MAX_GAP_MINUTES.to_owned());
+    let clock_offset = form.track_log_clock_offset.clone().unwrap_or_default();
+
+    owned_html!
+    {
+        h2: "Track Log Files";
+        p { em: "The paths to GPX or KML files recorded by a GPS logger, one per line. Objects without a location are located by matching their time against the track."; }
+        p
+        {
+            textarea(name="track_log_files", rows="3", cols="60"): files;
+        }
+
+        h2: "Maximum Gap (minutes)";
+        p { em: "Objects more than this far from the nearest track points aren't located."; }
+        p
+        {
+            input(type="number", name="track_log_max_gap", min="0", value=max_gap);
+        }
+
+        h2: "Camera Clock Offset";
+        p { em: "Added to each object's time before matching it against the track, as \"+HH:MM\" or \"-HH:MM:SS\"."; }
+        p
+        {
+            input(type="text", name="track_log_clock_offset", value=clock_offset);
+        }
+    }.into_string().unwrap()
+}
+
+async fn get_geotag(state: web::Data<State>, form: web::Query<TrackLogForm>, req: HttpRequest) -> Result<HttpResponse, HttpResponse>
+{
+    let track_log = load_track_log(&form).await?;
+
+    let mut num_without_location = 0;
+    let mut matches = Vec::new();
+
+    if let Some(track_log) = &track_log
+    {
+        let msg = picvudb::msgs::GetObjectsRequest
+        {
+            query: picvudb::data::get::GetObjectsQuery::ByActivityDesc,
+            pagination: None,
+        };
+
+        let objects = state.db.send(msg).await
+            .map_err(|e| view::err(HttpResponse::InternalServerError(), e))?
+            .map_err(|e| view::err(HttpResponse::InternalServerError(), e))?
+            .objects;
+
+        for object in objects.into_iter().filter(|o| o.location.is_none())
+        {
+            num_without_location += 1;
+
+            if let Some(location) = track_log.locate(&object.activity_time)
+            {
+                matches.push((object, location));
+            }
+        }
+    }
+
+    let now = picvudb::data::Date::now();
+
+    let contents = owned_html!
+    {
+        h1: "Geotag from Track Logs";
+
+        form(method="GET", action="/view/geotag")
+        {
+            : horrorshow::Raw(render_track_log_inputs(&form));
+
+            p
+            {
+                input(type="submit", value="Preview");
+            }
+        }
+
+        @if let Some(track_log) = &track_log
+        {
+            h2: "Preview";
+            p: format!("{} track points. {} of {} objects without a location can be located.", track_log.num_points(), matches.len(), num_without_location);
+
+            table(class="details-table")
+            {
+                tr
+                {
+                    th: "Object";
+                    th: "Activity";
+                    th: "Location";
+                }
+
+                @for (object, location) in matches.iter().take(NUM_PREVIEW_OBJECTS)
+                {
+                    tr
+                    {
+                        td
+                        {
+                            a(href=pages::object_details::ObjectDetailsPage::path_for(&object.id))
+                            {
+                                : object.get_display_text();
+                            }
+                        }
+                        td: format::date_to_str(&object.activity_time, &now);
+                        td
+                        {
+                            a(href=format!("https://www.google.com/maps/search/?api=1&query={},{}", location.latitude, location.longitude), target="_blank")
+                            {
+                                : location.to_string();
+                            }
+                        }
+                    }
+                }
+            }
+
+            @if !matches.is_empty()
+            {
+                form(method="POST", action="/form/geotag", enctype="application/x-www-form-urlencoded")
+                {
+                    input(type="hidden", name="track_log_files", value=form.track_log_files.clone().unwrap_or_default());
+                    input(type="hidden", name="track_log_max_gap", value=form.track_log_max_gap.clone().unwrap_or_default());
+                    input(type="hidden", name="track_log_clock_offset", value=form.track_log_clock_offset.clone().unwrap_or_default());
+
+                    p
+                    {
+                        input(type="submit", value=format!("Geotag {} objects", matches.len()));
+                    }
+                }
+            }
+        }
+    }.into_string().unwrap();
+
+    Ok(view::html_page(&req, &state.header_links, "Geotag", OutlineIcon::Location, &contents))
+}
+
+async fn post_geotag(state: web::Data<State>, form: web::Form<TrackLogForm>) -> Result<HttpResponse, HttpResponse>
+{
+    let track_log = load_track_log(&form).await?
+        .ok_or_else(|| view::err(HttpResponse::BadRequest(), "No track log files specified"))?;
+
+    {
+        let mut bulk_queue = state.bulk_queue.lock().unwrap();
+
+        bulk_queue.enqueue(bulk::geotag::GeotagBulkOp::new(state.db_uri.clone(), track_log));
+    }
+
+    Ok(view::redirect(pages::bulk::BulkPage::progress_path()))
+}
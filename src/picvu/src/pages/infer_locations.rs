@@ -0,0 +1,218 @@
+use serde::Deserialize;
+use actix_web::{web, HttpRequest, HttpResponse};
+use horrorshow::{owned_html, Template};
+
+use crate::bulk;
+use crate::format;
+use crate::icons::OutlineIcon;
+use crate::pages::{PageResources, PageResourcesBuilder};
+use crate::view;
+use crate::pages;
+use crate::State;
+
+#[allow(dead_code)]
+pub struct InferLocationsPage
+{
+}
+
+impl PageResources for InferLocationsPage
+{
+    fn page_resources(builder: &mut PageResourcesBuilder)
+    {
+        builder
+            .add_header_link("/view/infer_locations", "Infer Locations", OutlineIcon::Location, 600)
+            .route_view("/view/infer_locations", web::get().to(get_infer_locations))
+            .route_other("/form/infer_locations", web::post().to(post_infer_locations))
+            .route_other("/form/infer_locations/accept", web::post().to(post_infer_locations_accept))
+            .route_other("/form/infer_locations/discard", web::post().to(post_infer_locations_discard));
+    }
+}
+
+#[derive(Deserialize)]
+struct InferLocationsForm
+{
+    window_minutes: String,
+}
+
+async fn get_infer_locations(state: web::Data<State>, req: HttpRequest) -> HttpResponse
+{
+    let proposals =
+    {
+        let location_proposals = state.location_proposals.lock().unwrap();
+
+        location_proposals.clone()
+    };
+
+    let contents = match proposals
+    {
+        Some(proposals) => render_proposals(&proposals),
+        None => render_options(),
+    };
+
+    view::html_page(&req, &state.header_links, "Infer Locations", OutlineIcon::Location, &contents)
+}
+
+fn render_options() -> String
+{
+    owned_html!
+    {
+        h1: "Infer Locations";
+        form(method="POST", action="/form/infer_locations", enctype="application/x-www-form-urlencoded")
+        {
+            p { em: "Proposes a location for each object without one, copied from the object with a location that's closest to it in time. The proposals can be reviewed before they're applied."; }
+
+            h2: "Time Window (minutes)";
+            p { em: "Only objects with a location within this time are used."; }
+            p
+            {
+                input(type="number", name="window_minutes", min="1", value="15");
+            }
+
+            p
+            {
+                input(type="submit", value="Propose Locations");
+            }
+        }
+    }.into_string().unwrap()
+}
+
+fn render_proposals(proposals: &Vec<bulk::infer::LocationProposal>) -> String
+{
+    let now = picvudb::data::Date::now();
+
+    owned_html!
+    {
+        h1: "Review Inferred Locations";
+
+        @if proposals.is_empty()
+        {
+            p: "No locations could be inferred.";
+        }
+        else
+        {
+            form(method="POST", action="/form/infer_locations/accept", enctype="application/x-www-form-urlencoded")
+            {
+                table(class="details-table")
+                {
+                    tr
+                    {
+                        th: "Accept";
+                        th: "Object";
+                        th: "Activity";
+                        th: "Location";
+                        th: "From";
+                    }
+
+                    @for proposal in proposals.iter()
+                    {
+                        tr
+                        {
+                            td
+                            {
+                                input(type="checkbox", name="accept", value=proposal.object.id.to_string(), checked?=true);
+                            }
+                            td
+                            {
+                                a(href=pages::object_details::ObjectDetailsPage::path_for(&proposal.object.id))
+                                {
+                                    : proposal.object.get_display_text();
+                                }
+                            }
+                            td: format::date_to_str(&proposal.object.activity_time, &now);
+                            td
+                            {
+                                a(href=format!("https://www.google.com/maps/search/?api=1&query={},{}", proposal.location.latitude, proposal.location.longitude), target="_blank")
+                                {
+                                    : proposal.location.to_string();
+                                }
+                            }
+                            td
+                            {
+                                a(href=pages::object_details::ObjectDetailsPage::path_for(&proposal.from_object_id))
+                                {
+                                    : format!("{} minutes away", proposal.time_difference.num_minutes());
+                                }
+                            }
+                        }
+                    }
+                }
+
+                p
+                {
+                    input(type="submit", value="Accept Selected");
+                }
+            }
+        }
+
+        form(method="POST", action="/form/infer_locations/discard", enctype="application/x-www-form-urlencoded")
+        {
+            input(type="submit", value="Discard");
+        }
+    }.into_string().unwrap()
+}
+
+async fn post_infer_locations(state: web::Data<State>, form: web::Form<InferLocationsForm>) -> Result<HttpResponse, HttpResponse>
+{
+    let window_minutes = form.window_minutes.trim().parse::<i64>()
+        .map_err(|_| view::err(HttpResponse::BadRequest(), "Invalid time window"))?;
+
+    if window_minutes <= 0
+    {
+        return Err(view::err(HttpResponse::BadRequest(), "The time window must be at least one minute"));
+    }
+
+    {
+        let mut bulk_queue = state.bulk_queue.lock().unwrap();
+
+        bulk_queue.enqueue(bulk::infer::InferLocationsPreview::new(
+            state.db_uri.clone(),
+            chrono::Duration::minutes(window_minutes),
+            state.location_proposals.clone()));
+    }
+
+    Ok(view::redirect(pages::bulk::BulkPage::progress_path()))
+}
+
+async fn post_infer_locations_accept(state: web::Data<State>, form: web::Form<Vec<(String, String)>>) -> HttpResponse
+{
+    // The checkboxes share a name, so the form
+    // is read as a list of name/value pairs
+
+    let accepted = form.iter()
+        .filter(|(n, _)| n == "accept")
+        .map(|(_, v)| v.clone())
+        .collect::<Vec<_>>();
+
+    let proposals =
+    {
+        let mut location_proposals = state.location_proposals.lock().unwrap();
+
+        location_proposals.take()
+    };
+
+    if let Some(proposals) = proposals
+    {
+        let proposals = proposals.into_iter()
+            .filter(|p| accepted.contains(&p.object.id.to_string()))
+            .collect::<Vec<_>>();
+
+        let mut bulk_queue = state.bulk_queue.lock().unwrap();
+
+        bulk_queue.enqueue(bulk::infer::ApplyLocationProposals::new(state.db_uri.clone(), proposals));
+
+        return view::redirect(pages::bulk::BulkPage::progress_path());
+    }
+
+    view::redirect("/view/infer_locations".to_owned())
+}
+
+async fn post_infer_locations_discard(state: web::Data<State>) -> HttpResponse
+{
+    {
+        let mut location_proposals = state.location_proposals.lock().unwrap();
+
+        *location_proposals = None;
+    }
+
+    view::redirect("/view/infer_locations".to_owned())
+}
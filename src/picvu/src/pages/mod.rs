@@ -3,19 +3,28 @@ use std::collections::BTreeMap;
 
 use crate::icons::Icon;
 
+pub mod add_note;
 pub mod add_object;
+pub mod adjust_image;
 pub mod attachments;
 pub mod auth;
 pub mod bulk;
+pub mod cameras;
 pub mod delete_object;
 pub mod edit_object;
+pub mod geotag;
+pub mod infer_locations;
 pub mod object_details;
 pub mod object_listing;
+pub mod recipe;
+pub mod relations;
+pub mod replace_attachment;
 pub mod search;
 pub mod setup;
 pub mod sync;
 pub mod tags;
 pub mod templates;
+pub mod time_shift;
 
 pub struct HeaderLink
 {
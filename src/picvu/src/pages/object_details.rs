@@ -1,3 +1,4 @@
+use serde::Deserialize;
 use actix_web::{web, HttpRequest, HttpResponse};
 use horrorshow::{owned_html, Raw, Template};
 
@@ -20,6 +21,18 @@ impl ObjectDetailsPage
     {
         format!("/view/object/{}", obj_id.to_string())
     }
+
+    pub fn path_for_attachment(obj_id: &picvudb::data::ObjectId, hash: &String) -> String
+    {
+        format!("/view/object/{}?attachment={}", obj_id.to_string(), hash)
+    }
+}
+
+#[derive(Deserialize)]
+struct ObjectDetailsQuery
+{
+    attachment: Option<String>,
+    servings: Option<u32>,
 }
 
 impl PageResources for ObjectDetailsPage
@@ -31,7 +44,7 @@ impl PageResources for ObjectDetailsPage
     }
 }
 
-async fn get_object_details(state: web::Data<State>, object_id: web::Path<String>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+async fn get_object_details(state: web::Data<State>, object_id: web::Path<String>, query: web::Query<ObjectDetailsQuery>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
 {
     let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
 
@@ -55,9 +68,54 @@ async fn get_object_details(state: web::Data<State>, object_id: web::Path<String
         },
         Some(object) =>
         {
+            let api_key = pages::setup::get_api_key(&state).await?;
+            let google_cache = analyse::google::GoogleCache::new(api_key);
+            let google_cache1 = google_cache.clone();
+            let google_cache2 = google_cache.clone();
+
+            let mut timezone_info: Option<analyse::google::TimezoneInfo> = None;
+            let mut geocode_info: Option<analyse::google::ReverseGeocode> = None;
+
+            if let Some(location) = &object.location
+            {
+                let location1 = location.clone();
+                let timestamp1 = object.activity_time.clone();
+                let location2 = location.clone();
+
+                if let Ok(tz_result) = web::block(move ||
+                    {
+                        google_cache1.get_timezone_for(&location1, &timestamp1)
+                    }).await
+                {
+                    timezone_info = Some(tz_result);
+                }
+
+                if let Ok(rg_result) = web::block(move ||
+                    {
+                        google_cache2.reverse_geocode(&location2)
+                    }).await
+                {
+                    geocode_info = Some(rg_result);
+                }
+            }
+
+            let links = state.db.send(picvudb::msgs::GetObjectLinksRequest{ object_id: object.id.clone() }).await??;
+            let relations = state.db.send(picvudb::msgs::GetObjectRelationsRequest{ object_id: object.id.clone() }).await??.relations;
+
+            if object.attachment.is_none()
+                && query.attachment.is_none()
+            {
+                // Notes don't have any attachment to show
+
+                return Ok(render_object_details(object, None, Ok(None), None, None, analyse::img::MvImgSplit::Neither, timezone_info, geocode_info, links, relations, query.servings, &req, &state.header_links));
+            }
+
+            // Objects with multiple attachments show the primary
+            // attachment unless another one has been chosen
+
             let get_attachment_data_msg = picvudb::msgs::GetAttachmentDataRequest{
                 object_id: object.id.clone(),
-                specific_hash: None,
+                specific_hash: query.attachment.clone(),
             };
 
             let attachment_response = state.db.send(get_attachment_data_msg).await??;
@@ -71,52 +129,42 @@ async fn get_object_details(state: web::Data<State>, object_id: web::Path<String
                 },
                 picvudb::msgs::GetAttachmentDataResponse::Found{metadata, bytes} =>
                 {
-                    let api_key = pages::setup::get_api_key(&state).await?;
-                    let google_cache = analyse::google::GoogleCache::new(api_key);
-                    let google_cache1 = google_cache.clone();
-                    let google_cache2 = google_cache.clone();
+                    let stored_exif = state.db.send(picvudb::msgs::GetAttachmentExifRequest{ object_id: object.id.clone() }).await??.exif;
 
-                    let mut timezone_info: Option<analyse::google::TimezoneInfo> = None;
-                    let mut geocode_info: Option<analyse::google::ReverseGeocode> = None;
+                    let image_analysis = analyse::img::ImgAnalysis::decode(&bytes, &metadata.filename, Some(&google_cache));
+                    let mvimg_split = analyse::img::parse_mvimg_split(&bytes, &metadata.filename);
 
-                    if let Some(location) = &object.location
+                    let video_analysis = if metadata.mime.type_() == mime::VIDEO
                     {
-                        let location1 = location.clone();
-                        let timestamp1 = object.activity_time.clone();
-                        let location2 = location.clone();
+                        let filename = metadata.filename.clone();
 
-                        if let Ok(tz_result) = web::block(move ||
-                            {
-                                google_cache1.get_timezone_for(&location1, &timestamp1)
-                            }).await
+                        let result = web::block(move || -> Result<(analyse::video::VideoAnalysisResults, Vec<analyse::warning::Warning>), std::io::Error>
                         {
-                            timezone_info = Some(tz_result);
-                        }
+                            let mut warnings = Vec::new();
+                            let video_analysis = analyse::video::analyse_video(&bytes, &filename, None, &None, None, &mut warnings)?;
 
-                        if let Ok(rg_result) = web::block(move ||
-                            {
-                                google_cache2.reverse_geocode(&location2)
-                            }).await
-                        {
-                            geocode_info = Some(rg_result);
-                        }
-                    }
+                            Ok((video_analysis, warnings))
+                        }).await;
 
-                    let image_analysis = analyse::img::ImgAnalysis::decode(&bytes, &metadata.filename, Some(&google_cache));
-                    let mvimg_split = analyse::img::parse_mvimg_split(&bytes, &metadata.filename);
+                        Some(result.map_err(|e| e.to_string()))
+                    }
+                    else
+                    {
+                        None
+                    };
 
-                    Ok(render_object_details(object, image_analysis, mvimg_split, timezone_info, geocode_info, &req, &state.header_links))
+                    Ok(render_object_details(object, Some(metadata), image_analysis, stored_exif, video_analysis, mvimg_split, timezone_info, geocode_info, links, relations, query.servings, &req, &state.header_links))
                 },
             }
         },
     }
 }
 
-fn render_object_details(object: picvudb::data::get::ObjectMetadata, image_analysis: Result<Option<(analyse::img::ImgAnalysis, Vec<analyse::warning::Warning>)>, analyse::img::ImgAnalysisError>, mvimg_split: analyse::img::MvImgSplit, timezone_info: Option<analyse::google::TimezoneInfo>, geocode_info: Option<analyse::google::ReverseGeocode>, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
+fn render_object_details(object: picvudb::data::get::ObjectMetadata, selected: Option<picvudb::data::get::AttachmentMetadata>, image_analysis: Result<Option<(analyse::img::ImgAnalysis, Vec<analyse::warning::Warning>)>, analyse::img::ImgAnalysisError>, stored_exif: Option<picvudb::data::AttachmentExif>, video_analysis: Option<Result<(analyse::video::VideoAnalysisResults, Vec<analyse::warning::Warning>), String>>, mvimg_split: analyse::img::MvImgSplit, timezone_info: Option<analyse::google::TimezoneInfo>, geocode_info: Option<analyse::google::ReverseGeocode>, links: picvudb::msgs::GetObjectLinksResponse, relations: Vec<picvudb::data::get::RelatedObject>, servings: Option<u32>, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
 {
     let now = picvudb::data::Date::now();
 
-    let filename = object.attachment.filename.clone();
+    let filename = object.get_display_text();
 
     let title = view::Title
     {
@@ -124,11 +172,13 @@ fn render_object_details(object: picvudb::data::get::ObjectMetadata, image_analy
         html: Raw(object.title.clone().map(|m| m.get_html()).unwrap_or(owned_html!{ : filename.clone() }.into_string().unwrap())),
     };
 
-    let icon = if object.attachment.mime.type_() == mime::IMAGE
+    let primary_mime_type = object.attachment.as_ref().map(|a| a.mime.type_());
+
+    let icon = if primary_mime_type == Some(mime::IMAGE)
     {
         OutlineIcon::Image
     }
-    else if object.attachment.mime.type_() == mime::VIDEO
+    else if primary_mime_type == Some(mime::VIDEO)
     {
         OutlineIcon::Video
     }
@@ -137,6 +187,17 @@ fn render_object_details(object: picvudb::data::get::ObjectMetadata, image_analy
         OutlineIcon::FileText
     };
 
+    // Previous versions can't be replaced themselves,
+    // so the primary attachment is offered instead
+
+    let replace_hash = match &selected
+    {
+        Some(selected) if !selected.is_previous_version => Some(selected.hash.clone()),
+        _ => object.attachment.as_ref().map(|a| a.hash.clone()),
+    };
+
+    let recipe = object.notes.as_ref().and_then(|notes| picvudb::data::Recipe::from_notes(notes));
+
     let contents = owned_html!
     {
         div(class="cmdbar cmdbar-top")
@@ -151,6 +212,35 @@ fn render_object_details(object: picvudb::data::get::ObjectMetadata, image_analy
                 : OutlineIcon::Trash2.render(IconSize::Size16x16);
                 : " Delete"
             }
+            a(href=pages::time_shift::TimeShiftPage::path_for_object(&object.id), class="cmdbar-link")
+            {
+                : OutlineIcon::Clock.render(IconSize::Size16x16);
+                : " Shift Time"
+            }
+            @if let Some(replace_hash) = &replace_hash
+            {
+                a(href=pages::replace_attachment::ReplaceAttachmentPage::path_for(&object.id, replace_hash), class="cmdbar-link")
+                {
+                    : OutlineIcon::CloudUpload.render(IconSize::Size16x16);
+                    : " Replace"
+                }
+            }
+            @if primary_mime_type == Some(mime::IMAGE)
+            {
+                a(href=pages::adjust_image::AdjustImagePage::path_for(&object.id), class="cmdbar-link")
+                {
+                    : OutlineIcon::Edit.render(IconSize::Size16x16);
+                    : " Rotate and Crop"
+                }
+            }
+            @if recipe.is_some() || object.attachment.is_none()
+            {
+                a(href=pages::recipe::RecipePage::edit_path_for(&object.id), class="cmdbar-link")
+                {
+                    : OutlineIcon::FileText.render(IconSize::Size16x16);
+                    : if recipe.is_some() { " Edit Recipe" } else { " Make Recipe" }
+                }
+            }
             div(class="cmdbar-summary")
             {
             }
@@ -158,17 +248,20 @@ fn render_object_details(object: picvudb::data::get::ObjectMetadata, image_analy
         
         table(class="details-table")
         {
-            tr
+            @if let Some(selected) = &selected
             {
-                th(colspan="2"): "Preview";
-            }
-            tr
-            {
-                td(colspan="2")
+                tr
+                {
+                    th(colspan="2"): "Preview";
+                }
+                tr
                 {
-                    a(href=pages::attachments::AttachmentsPage::path_attachment(&object.id, &object.attachment.hash))
+                    td(colspan="2")
                     {
-                        : pages::attachments::AttachmentsPage::raw_html_for_thumbnail(&object, 512, true);
+                        a(href=pages::attachments::AttachmentsPage::path_object_attachment(&object, selected))
+                        {
+                            : pages::attachments::AttachmentsPage::raw_html_for_attachment_thumbnail(&object, selected, 512, true);
+                        }
                     }
                 }
             }
@@ -247,7 +340,7 @@ fn render_object_details(object: picvudb::data::get::ObjectMetadata, image_analy
                     {
                         @for tag in object.tags.iter()
                         {
-                            a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone() }),
+                            a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone(), include_descendants: false }),
                                 class="tag")
                             {
                                 : pages::templates::tags::render_existing(tag);
@@ -257,16 +350,45 @@ fn render_object_details(object: picvudb::data::get::ObjectMetadata, image_analy
                 }
             }
 
-            @if object.notes.is_some()
+            @if let Some(recipe) = &recipe
+            {
+                tr
+                {
+                    td: "Recipe";
+                    td: pages::recipe::render_recipe(&object, recipe, servings);
+                }
+            }
+            else if object.notes.is_some()
             {
                 tr
                 {
                     td: "Notes";
-                    td: Raw(object.notes.clone().map(|m| m.get_html()).unwrap_or(String::new()));
+                    td: Raw(object.notes.clone().map(|m| m.get_html_with_links(|link| resolve_wiki_link(link, &links))).unwrap_or(String::new()));
+                }
+            }
+
+            tr
+            {
+                td: "Related";
+                td: pages::relations::render_relations_strip(&object, &relations);
+            }
+
+            @if !links.referenced_by.is_empty()
+            {
+                tr
+                {
+                    td: "Referenced by";
+                    td
+                    {
+                        @for referencing in links.referenced_by.iter()
+                        {
+                            : Raw(render_wiki_link(referencing));
+                        }
+                    }
                 }
             }
 
-            @if let Some(ext_ref) = object.ext_ref
+            @if let Some(ext_ref) = object.ext_ref.clone()
             {
                 tr
                 {
@@ -283,15 +405,96 @@ fn render_object_details(object: picvudb::data::get::ObjectMetadata, image_analy
 
             : location_details(&object.location, &timezone_info, &geocode_info);
 
-            : attachment_details(&object.id, &object.attachment, &mvimg_split, &now);
+            : attachments_list(&object, &selected);
+
+            @if let Some(selected) = &selected
+            {
+                : attachment_details(&object.id, selected, &mvimg_split, &now);
+            }
+
+            : video_details(&video_analysis);
 
             : exif_details(&image_analysis);
+
+            : stored_exif_details(&stored_exif);
         }
     }.into_string().unwrap();
 
     view::html_page(req, header_links, title, icon, &contents)
 }
 
+fn video_details(video: &Option<Result<(analyse::video::VideoAnalysisResults, Vec<analyse::warning::Warning>), String>>) -> Raw<String>
+{
+    Raw(owned_html!
+    {
+        @if let Some(Ok((video, video_warnings))) = video
+        {
+            tr
+            {
+                th(colspan="2"): "Video Data";
+            }
+
+            @if let Some(codec) = &video.codec
+            {
+                tr
+                {
+                    td: "Codec";
+                    td: codec;
+                }
+            }
+
+            @if let Some(frame_rate) = video.frame_rate
+            {
+                tr
+                {
+                    td: "Frame Rate";
+                    td: format!("{:.2} fps", frame_rate);
+                }
+            }
+
+            @if let Some(bit_rate) = video.bit_rate
+            {
+                tr
+                {
+                    td: "Bit Rate";
+                    td: format!("{} kbps", bit_rate / 1000);
+                }
+            }
+
+            tr
+            {
+                td: "Audio";
+                td: if video.has_audio { "Yes" } else { "No" };
+            }
+
+            @for w in video_warnings
+            {
+                tr
+                {
+                    td: "Warning";
+                    td
+                    {
+                        : format!("{:?}", w);
+                    }
+                }
+            }
+        }
+        else if let Some(Err(video_err)) = video
+        {
+            tr
+            {
+                th(colspan="2"): "Video Data";
+            }
+
+            tr
+            {
+                td: "Error";
+                td: video_err;
+            }
+        }
+    }.into_string().unwrap())
+}
+
 fn exif_details(exif: &Result<Option<(analyse::img::ImgAnalysis, Vec<analyse::warning::Warning>)>, analyse::img::ImgAnalysisError>) -> Raw<String>
 {
     let exif = exif.clone();
@@ -348,6 +551,10 @@ fn exif_details(exif: &Result<Option<(analyse::img::ImgAnalysis, Vec<analyse::wa
                             {
                                 p: format!("Orig Taken: {:?}", taken);
                             }
+                            @if let Some(offset) = image_analysis.orig_taken_offset
+                            {
+                                p: format!("Orig Taken Offset: {}", offset);
+                            }
                             @if let Some(digitized) = image_analysis.orig_digitized_naive
                             {
                                 p: format!("Digitized: {:?}", digitized);
@@ -413,6 +620,87 @@ fn exif_details(exif: &Result<Option<(analyse::img::ImgAnalysis, Vec<analyse::wa
     }.into_string().unwrap())
 }
 
+fn stored_exif_link(filter: picvudb::data::ExifFilter, text: String) -> Raw<String>
+{
+    Raw(owned_html!
+    {
+        a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::ExifByActivityDesc{ filter }))
+        {
+            : text;
+        }
+    }.into_string().unwrap())
+}
+
+fn stored_exif_details(exif: &Option<picvudb::data::AttachmentExif>) -> Raw<String>
+{
+    let exif = exif.clone();
+
+    Raw(owned_html!
+    {
+        @if let Some(exif) = exif
+        {
+            tr
+            {
+                th(colspan="2"): "Stored EXIF Data";
+            }
+
+            @if exif.make.is_some() || exif.model.is_some()
+            {
+                tr
+                {
+                    td: "Camera";
+                    td: stored_exif_link(
+                        picvudb::data::ExifFilter{ make: exif.make.clone(), model: exif.model.clone(), ..Default::default() },
+                        format!("{} {}", exif.make.clone().unwrap_or_default(), exif.model.clone().unwrap_or_default()).trim().to_owned());
+                }
+            }
+
+            @if let Some(iso) = exif.iso
+            {
+                tr
+                {
+                    td: "ISO";
+                    td: stored_exif_link(
+                        picvudb::data::ExifFilter{ min_iso: Some(iso), max_iso: Some(iso), ..Default::default() },
+                        iso.to_string());
+                }
+            }
+
+            @if let Some(focal_length) = exif.focal_length
+            {
+                tr
+                {
+                    td: "Focal Length";
+                    td: stored_exif_link(
+                        picvudb::data::ExifFilter{ min_focal_length: Some(focal_length), max_focal_length: Some(focal_length), ..Default::default() },
+                        format!("{}mm", focal_length));
+                }
+            }
+
+            @for entry in exif.entries.iter()
+            {
+                tr
+                {
+                    td: &entry.tag;
+                    td: &entry.value;
+                }
+            }
+
+            @if let Some(xmp) = &exif.xmp
+            {
+                tr
+                {
+                    td: "XMP";
+                    td
+                    {
+                        pre: xmp;
+                    }
+                }
+            }
+        }
+    }.into_string().unwrap())
+}
+
 fn location_details(location: &Option<picvudb::data::Location>, timezone_info: &Option<analyse::google::TimezoneInfo>, geocode_info: &Option<analyse::google::ReverseGeocode>) -> Raw<String>
 {
     let location = location.clone();
@@ -475,6 +763,87 @@ fn location_details(location: &Option<picvudb::data::Location>, timezone_info: &
     }.into_string().unwrap())
 }
 
+fn attachments_list(object: &picvudb::data::get::ObjectMetadata, selected: &Option<picvudb::data::get::AttachmentMetadata>) -> Raw<String>
+{
+    let obj_id = object.id.clone();
+    let selected_hash = selected.as_ref().map(|s| s.hash.clone()).unwrap_or_default();
+
+    let attachments = object.attachment.iter()
+        .chain(object.additional_attachments.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let previous_versions = object.previous_versions.iter()
+        .rev()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Raw(owned_html!
+    {
+        @if attachments.len() > 1
+        {
+            tr
+            {
+                th(colspan="2"): "Attachments";
+            }
+
+            @for attachment in attachments.iter()
+            {
+                tr
+                {
+                    td: attachment.role.to_string();
+                    td
+                    {
+                        @if attachment.hash == selected_hash
+                        {
+                            strong: attachment.filename.clone();
+                        }
+                        else
+                        {
+                            a(href=ObjectDetailsPage::path_for_attachment(&obj_id, &attachment.hash))
+                            {
+                                : attachment.filename.clone();
+                            }
+                        }
+                        : format!(" ({}, {})", attachment.mime, format::bytes_to_string(attachment.size));
+                    }
+                }
+            }
+        }
+
+        @if !previous_versions.is_empty()
+        {
+            tr
+            {
+                th(colspan="2"): "Previous Versions";
+            }
+
+            @for version in previous_versions.iter()
+            {
+                tr
+                {
+                    td: version.role.to_string();
+                    td
+                    {
+                        @if version.hash == selected_hash
+                        {
+                            strong: version.filename.clone();
+                        }
+                        else
+                        {
+                            a(href=ObjectDetailsPage::path_for_attachment(&obj_id, &version.hash))
+                            {
+                                : version.filename.clone();
+                            }
+                        }
+                        : format!(" ({}, {})", version.mime, format::bytes_to_string(version.size));
+                    }
+                }
+            }
+        }
+    }.into_string().unwrap())
+}
+
 fn attachment_details(obj_id: &picvudb::data::ObjectId, attachment: &picvudb::data::get::AttachmentMetadata, mvimg_split: &analyse::img::MvImgSplit, now: &picvudb::data::Date) -> Raw<String>
 {
     let now = now.clone();
@@ -586,3 +955,46 @@ fn attachment_details(obj_id: &picvudb::data::ObjectId, attachment: &picvudb::da
         }
     }.into_string().unwrap())
 }
+
+fn render_wiki_link(object: &picvudb::data::get::ObjectMetadata) -> String
+{
+    let title = object.title.clone().map(|m| m.get_display_text()).unwrap_or(object.get_display_text());
+
+    owned_html!
+    {
+        a(href=ObjectDetailsPage::path_for(&object.id), class="wiki-link")
+        {
+            @if object.attachment.is_some()
+            {
+                : pages::attachments::AttachmentsPage::raw_html_for_thumbnail(object, 64, false);
+            }
+            : title;
+        }
+    }.into_string().unwrap()
+}
+
+fn resolve_wiki_link(link: &picvudb::data::WikiLink, links: &picvudb::msgs::GetObjectLinksResponse) -> Option<String>
+{
+    match link
+    {
+        picvudb::data::WikiLink::Object(obj_id) =>
+        {
+            links.linked_objects.iter()
+                .find(|o| o.id == *obj_id)
+                .map(|o| render_wiki_link(o))
+        },
+        picvudb::data::WikiLink::Tag(name) =>
+        {
+            links.linked_tags.iter()
+                .find(|(link_name, _)| link_name == name)
+                .map(|(_, tag)| owned_html!
+                {
+                    a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone(), include_descendants: false }),
+                        class="tag")
+                    {
+                        : pages::templates::tags::render_existing(tag);
+                    }
+                }.into_string().unwrap())
+        },
+    }
+}
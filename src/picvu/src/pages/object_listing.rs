@@ -52,6 +52,8 @@ pub struct TagListViewOptionsForm
 {
     #[serde(with = "serde_with::rust::display_fromstr")]
     pub tag_id: picvudb::data::TagId,
+    #[serde(default)]
+    pub include_descendants: bool,
     pub list_type: Option<ViewObjectsListType>,
     pub offset: Option<u64>,
     pub page_size: Option<u64>,
@@ -67,6 +69,105 @@ pub struct ActivityRangeListViewOptionsForm
     pub page_size: Option<u64>,
 }
 
+#[derive(Deserialize)]
+pub struct ExifListViewOptionsForm
+{
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub min_iso: Option<u32>,
+    pub max_iso: Option<u32>,
+    pub min_focal_length: Option<f64>,
+    pub max_focal_length: Option<f64>,
+    pub list_type: Option<ViewObjectsListType>,
+    pub offset: Option<u64>,
+    pub page_size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct IngredientListViewOptionsForm
+{
+    pub ingredient: String,
+    pub list_type: Option<ViewObjectsListType>,
+    pub offset: Option<u64>,
+    pub page_size: Option<u64>,
+}
+
+impl LocationListViewOptionsForm
+{
+    fn query(&self) -> picvudb::data::get::GetObjectsQuery
+    {
+        picvudb::data::get::GetObjectsQuery::NearLocationByActivityDesc
+        {
+            location: self.location.clone(),
+            radius_meters: self.radius_meters,
+        }
+    }
+}
+
+impl SearchListViewOptionsForm
+{
+    fn query(&self) -> picvudb::data::get::GetObjectsQuery
+    {
+        picvudb::data::get::GetObjectsQuery::TitleNotesSearchByActivityDesc
+        {
+            search: picvudb::data::get::SearchString::FullSearch(self.q.clone()),
+        }
+    }
+}
+
+impl TagListViewOptionsForm
+{
+    fn query(&self) -> picvudb::data::get::GetObjectsQuery
+    {
+        picvudb::data::get::GetObjectsQuery::TagByActivityDesc
+        {
+            tag_id: self.tag_id.clone(),
+            include_descendants: self.include_descendants,
+        }
+    }
+}
+
+impl ActivityRangeListViewOptionsForm
+{
+    fn query(&self) -> picvudb::data::get::GetObjectsQuery
+    {
+        picvudb::data::get::GetObjectsQuery::ActivityDateRangeByActivityDesc
+        {
+            date_range: self.date_range.clone(),
+        }
+    }
+}
+
+impl ExifListViewOptionsForm
+{
+    fn query(&self) -> picvudb::data::get::GetObjectsQuery
+    {
+        picvudb::data::get::GetObjectsQuery::ExifByActivityDesc
+        {
+            filter: picvudb::data::ExifFilter
+            {
+                make: self.make.clone(),
+                model: self.model.clone(),
+                min_iso: self.min_iso,
+                max_iso: self.max_iso,
+                min_focal_length: self.min_focal_length,
+                max_focal_length: self.max_focal_length,
+            },
+        }
+    }
+}
+
+impl IngredientListViewOptionsForm
+{
+    fn query(&self) -> picvudb::data::get::GetObjectsQuery
+    {
+        picvudb::data::get::GetObjectsQuery::IngredientByActivityDesc
+        {
+            ingredient: self.ingredient.clone(),
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub struct ObjectListingPage
 {
@@ -88,6 +189,8 @@ impl ObjectListingPage
             picvudb::data::get::GetObjectsQuery::TitleNotesSearchByActivityDesc{ .. } => "/view/objects/search".to_owned(),
             picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ .. } => "/view/objects/by_tag".to_owned(),
             picvudb::data::get::GetObjectsQuery::ActivityDateRangeByActivityDesc{ .. } => "/view/objects/by_activity_range_desc".to_owned(),
+            picvudb::data::get::GetObjectsQuery::ExifByActivityDesc{ .. } => "/view/objects/by_exif".to_owned(),
+            picvudb::data::get::GetObjectsQuery::IngredientByActivityDesc{ .. } => "/view/objects/by_ingredient".to_owned(),
         };
 
         if let picvudb::data::get::GetObjectsQuery::NearLocationByActivityDesc{ location, radius_meters} = query
@@ -99,14 +202,50 @@ impl ObjectListingPage
         {
             params.push(("q", search.to_literal_string()));
         }
-        else if let picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id } = query
+        else if let picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id, include_descendants } = query
         {
             params.push(("tag_id", tag_id.to_string()));
+
+            if include_descendants
+            {
+                params.push(("include_descendants", "true".to_owned()));
+            }
         }
         else if let picvudb::data::get::GetObjectsQuery::ActivityDateRangeByActivityDesc{ date_range } = query
         {
             params.push(("date_range", date_range.to_string()));
         }
+        else if let picvudb::data::get::GetObjectsQuery::ExifByActivityDesc{ filter } = query
+        {
+            if let Some(make) = filter.make
+            {
+                params.push(("make", urlencoding::encode(&make).into_owned()));
+            }
+            if let Some(model) = filter.model
+            {
+                params.push(("model", urlencoding::encode(&model).into_owned()));
+            }
+            if let Some(min_iso) = filter.min_iso
+            {
+                params.push(("min_iso", min_iso.to_string()));
+            }
+            if let Some(max_iso) = filter.max_iso
+            {
+                params.push(("max_iso", max_iso.to_string()));
+            }
+            if let Some(min_focal_length) = filter.min_focal_length
+            {
+                params.push(("min_focal_length", min_focal_length.to_string()));
+            }
+            if let Some(max_focal_length) = filter.max_focal_length
+            {
+                params.push(("max_focal_length", max_focal_length.to_string()));
+            }
+        }
+        else if let picvudb::data::get::GetObjectsQuery::IngredientByActivityDesc{ ingredient } = query
+        {
+            params.push(("ingredient", urlencoding::encode(&ingredient).into_owned()));
+        }
 
         (base_url, params)
     }
@@ -134,6 +273,29 @@ impl ObjectListingPage
         result
     }
 
+    /// Converts a listing path (as returned by `path`) back
+    /// into its query, or None if it isn't a listing path.
+    pub fn query_from_path(path: &str) -> Option<picvudb::data::get::GetObjectsQuery>
+    {
+        let mut parts = path.splitn(2, '?');
+        let base_url = parts.next().unwrap_or_default();
+        let params = parts.next().unwrap_or_default();
+
+        match base_url
+        {
+            "/view/objects/by_activity_desc" => Some(picvudb::data::get::GetObjectsQuery::ByActivityDesc),
+            "/view/objects/by_modified_desc" => Some(picvudb::data::get::GetObjectsQuery::ByModifiedDesc),
+            "/view/objects/by_size_desc" => Some(picvudb::data::get::GetObjectsQuery::ByAttachmentSizeDesc),
+            "/view/objects/near_location_by_activity_desc" => web::Query::<LocationListViewOptionsForm>::from_query(params).ok().map(|q| q.query()),
+            "/view/objects/search" => web::Query::<SearchListViewOptionsForm>::from_query(params).ok().map(|q| q.query()),
+            "/view/objects/by_tag" => web::Query::<TagListViewOptionsForm>::from_query(params).ok().map(|q| q.query()),
+            "/view/objects/by_activity_range_desc" => web::Query::<ActivityRangeListViewOptionsForm>::from_query(params).ok().map(|q| q.query()),
+            "/view/objects/by_exif" => web::Query::<ExifListViewOptionsForm>::from_query(params).ok().map(|q| q.query()),
+            "/view/objects/by_ingredient" => web::Query::<IngredientListViewOptionsForm>::from_query(params).ok().map(|q| q.query()),
+            _ => None,
+        }
+    }
+
     pub fn path(query: picvudb::data::get::GetObjectsQuery) -> String
     {
         let (base_url, params) = Self::base_url(query);
@@ -163,6 +325,8 @@ impl ObjectListingPage
             picvudb::data::get::GetObjectsQuery::TitleNotesSearchByActivityDesc{ .. } => OutlineIcon::Search,
             picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ .. } => OutlineIcon::Label,
             picvudb::data::get::GetObjectsQuery::ActivityDateRangeByActivityDesc{ .. } => OutlineIcon::Calendar,
+            picvudb::data::get::GetObjectsQuery::ExifByActivityDesc{ .. } => OutlineIcon::Camera,
+            picvudb::data::get::GetObjectsQuery::IngredientByActivityDesc{ .. } => OutlineIcon::FileText,
         }.into()
     }
 }
@@ -181,7 +345,9 @@ impl PageResources for ObjectListingPage
             .route_view("/view/objects/near_location_by_activity_desc", web::get().to(objects_near_location_by_activity_desc))
             .route_view("/view/objects/search", web::get().to(objects_search))
             .route_view("/view/objects/by_tag", web::get().to(objects_by_tag))
-            .route_view("/view/objects/by_activity_range_desc", web::get().to(objects_by_activity_range_desc));
+            .route_view("/view/objects/by_activity_range_desc", web::get().to(objects_by_activity_range_desc))
+            .route_view("/view/objects/by_exif", web::get().to(objects_by_exif))
+            .route_view("/view/objects/by_ingredient", web::get().to(objects_by_ingredient));
     }
 }
 
@@ -233,11 +399,11 @@ pub async fn object_query(state: web::Data<State>, options: &ListViewOptionsForm
         Vec::new()
     };
 
-    let search_tag = if let picvudb::data::get::GetObjectsQuery::TagByActivityDesc{tag_id} = &response.query
+    let search_tag = if let picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id, .. } = &response.query
     {
         let get_tag_msg = picvudb::msgs::GetTagRequest{ tag_id: tag_id.clone() };
 
-        Some(state.db.send(get_tag_msg).await??.tag)
+        Some(state.db.send(get_tag_msg).await??)
     }
     else
     {
@@ -277,11 +443,7 @@ async fn objects_near_location_by_activity_desc(state: web::Data<State>, query:
         page_size: query.page_size,
     };
 
-    let query = picvudb::data::get::GetObjectsQuery::NearLocationByActivityDesc
-    {
-        location: query.location.clone(),
-        radius_meters: query.radius_meters,
-    };
+    let query = query.query();
 
     object_query(state, &options, query, req).await
 }
@@ -295,10 +457,7 @@ async fn objects_search(state: web::Data<State>, query: web::Query<SearchListVie
         page_size: query.page_size,
     };
 
-    let query = picvudb::data::get::GetObjectsQuery::TitleNotesSearchByActivityDesc
-    {
-        search: picvudb::data::get::SearchString::FullSearch(query.q.clone()),
-    };
+    let query = query.query();
 
     object_query(state, &options, query, req).await
 }
@@ -312,15 +471,26 @@ async fn objects_by_tag(state: web::Data<State>, query: web::Query<TagListViewOp
         page_size: query.page_size,
     };
 
-    let query = picvudb::data::get::GetObjectsQuery::TagByActivityDesc
+    let query = query.query();
+
+    object_query(state, &options, query, req).await
+}
+
+async fn objects_by_activity_range_desc(state: web::Data<State>, query: web::Query<ActivityRangeListViewOptionsForm>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let options = ListViewOptionsForm
     {
-        tag_id: query.tag_id.clone(),
+        list_type: query.list_type,
+        offset: query.offset,
+        page_size: query.page_size,
     };
 
+    let query = query.query();
+
     object_query(state, &options, query, req).await
 }
 
-async fn objects_by_activity_range_desc(state: web::Data<State>, query: web::Query<ActivityRangeListViewOptionsForm>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+async fn objects_by_exif(state: web::Data<State>, query: web::Query<ExifListViewOptionsForm>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
 {
     let options = ListViewOptionsForm
     {
@@ -329,15 +499,31 @@ async fn objects_by_activity_range_desc(state: web::Data<State>, query: web::Que
         page_size: query.page_size,
     };
 
-    let query = picvudb::data::get::GetObjectsQuery::ActivityDateRangeByActivityDesc
+    let query = query.query();
+
+    object_query(state, &options, query, req).await
+}
+
+async fn objects_by_ingredient(state: web::Data<State>, query: web::Query<IngredientListViewOptionsForm>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    if query.ingredient.trim().is_empty()
     {
-        date_range: query.date_range.clone(),
+        return Ok(view::err(HttpResponse::BadRequest(), "No ingredient to search for"));
+    }
+
+    let options = ListViewOptionsForm
+    {
+        list_type: query.list_type,
+        offset: query.offset,
+        page_size: query.page_size,
     };
 
+    let query = query.query();
+
     object_query(state, &options, query, req).await
 }
 
-pub fn render_object_listing(resp: GetObjectsResponse, tags: Vec<picvudb::data::get::TagMetadata>, search_tag: Option<picvudb::data::get::TagMetadata>, list_type: ViewObjectsListType, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
+pub fn render_object_listing(resp: GetObjectsResponse, tags: Vec<picvudb::data::get::TagMetadata>, search_tag: Option<picvudb::msgs::GetTagResponse>, list_type: ViewObjectsListType, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
 {
     match list_type
     {
@@ -346,7 +532,7 @@ pub fn render_object_listing(resp: GetObjectsResponse, tags: Vec<picvudb::data::
     }
 }
 
-pub fn render_objects_thumbnails(resp: GetObjectsResponse, tags: Vec<picvudb::data::get::TagMetadata>, search_tag: Option<picvudb::data::get::TagMetadata>, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
+pub fn render_objects_thumbnails(resp: GetObjectsResponse, tags: Vec<picvudb::data::get::TagMetadata>, search_tag: Option<picvudb::msgs::GetTagResponse>, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
 {
     let (title, icon) = get_title_and_icon(&resp.query, &search_tag);
 
@@ -354,7 +540,7 @@ pub fn render_objects_thumbnails(resp: GetObjectsResponse, tags: Vec<picvudb::da
 
     let contents = owned_html!{
 
-        : get_query_commands(&resp.query);
+        : get_query_commands(&resp.query, &search_tag);
 
         : (pagination(resp.query.clone(), tags.len(), ViewObjectsListType::ThumbnailsGrid, resp.pagination_response.clone(), true));
 
@@ -373,7 +559,7 @@ pub fn render_objects_thumbnails(resp: GetObjectsResponse, tags: Vec<picvudb::da
                     {
                         @for tag in tags.iter()
                         {
-                            a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone() }),
+                            a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone(), include_descendants: false }),
                                 class="tag")
                             {
                                 : pages::templates::tags::render_existing(tag);
@@ -398,7 +584,7 @@ pub fn render_objects_thumbnails(resp: GetObjectsResponse, tags: Vec<picvudb::da
                         {
                             @for tag in get_tags_for_objects_with_heading(&cur_heading, &resp.objects, &resp.query)
                             {
-                                a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone() }),
+                                a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone(), include_descendants: false }),
                                     class="tag")
                                 {
                                     : pages::templates::tags::render_existing(&tag);
@@ -422,7 +608,7 @@ pub fn render_objects_thumbnails(resp: GetObjectsResponse, tags: Vec<picvudb::da
     view::html_page(req, header_links, &title, icon, &contents)
 }
 
-pub fn render_objects_details(resp: GetObjectsResponse, tags: Vec<picvudb::data::get::TagMetadata>, search_tag: Option<picvudb::data::get::TagMetadata>, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
+pub fn render_objects_details(resp: GetObjectsResponse, tags: Vec<picvudb::data::get::TagMetadata>, search_tag: Option<picvudb::msgs::GetTagResponse>, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
 {
     let now = picvudb::data::Date::now();
 
@@ -430,7 +616,7 @@ pub fn render_objects_details(resp: GetObjectsResponse, tags: Vec<picvudb::data:
 
     let contents = owned_html!{
 
-        : get_query_commands(&resp.query);
+        : get_query_commands(&resp.query, &search_tag);
 
         : (pagination(resp.query.clone(), tags.len(), ViewObjectsListType::DetailsTable, resp.pagination_response.clone(), true));
 
@@ -457,7 +643,7 @@ pub fn render_objects_details(resp: GetObjectsResponse, tags: Vec<picvudb::data:
                         td
                         {
                             : "Tag ";
-                            a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone() }))
+                            a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone(), include_descendants: false }))
                             {
                                 : (match tag.kind
                                     {
@@ -512,7 +698,7 @@ pub fn render_objects_details(resp: GetObjectsResponse, tags: Vec<picvudb::data:
                             }
                             else
                             {
-                                : &object.attachment.filename
+                                : object.get_display_text()
                             }
                         }
                     }
@@ -539,10 +725,10 @@ pub fn render_objects_details(resp: GetObjectsResponse, tags: Vec<picvudb::data:
                             });
                     }
 
-                    td: format::bytes_to_string(object.attachment.size);
-                    td: object.attachment.mime.to_string();
-                    td: object.attachment.dimensions.clone().map(|d| d.to_string()).unwrap_or_default();
-                    td: object.attachment.duration.clone().map(|d| d.to_string()).unwrap_or_default();
+                    td: object.attachment.as_ref().map(|a| format::bytes_to_string(a.size)).unwrap_or_default();
+                    td: object.attachment.as_ref().map(|a| a.mime.to_string()).unwrap_or_default();
+                    td: object.attachment.as_ref().and_then(|a| a.dimensions.clone()).map(|d| d.to_string()).unwrap_or_default();
+                    td: object.attachment.as_ref().and_then(|a| a.duration.clone()).map(|d| d.to_string()).unwrap_or_default();
                     td: object.location.clone().map(|l| l.to_string()).unwrap_or_default();
                 }
             }
@@ -555,14 +741,20 @@ pub fn render_objects_details(resp: GetObjectsResponse, tags: Vec<picvudb::data:
     view::html_page(req, header_links, &title, icon, &contents)
 }
 
-fn get_title_and_icon(query: &picvudb::data::get::GetObjectsQuery, search_tag: &Option<picvudb::data::get::TagMetadata>) -> (String, Icon)
+fn get_title_and_icon(query: &picvudb::data::get::GetObjectsQuery, search_tag: &Option<picvudb::msgs::GetTagResponse>) -> (String, Icon)
 {
     let mut title = format::query_to_string(query);
     let mut icon = ObjectListingPage::icon(query);
 
-    if let Some(tag) = search_tag
+    if let Some(tag) = search_tag.as_ref().map(|t| &t.tag)
     {
         title = format!("Tagged: {}", tag.name);
+
+        if let picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ include_descendants: true, .. } = query
+        {
+            title += " and sub-tags";
+        }
+
         icon = match tag.kind
         {
             picvudb::data::TagKind::Activity => OutlineIcon::Sun,
@@ -579,10 +771,13 @@ fn get_title_and_icon(query: &picvudb::data::get::GetObjectsQuery, search_tag: &
     (title, icon)
 }
 
-fn get_query_commands(query: &picvudb::data::get::GetObjectsQuery) -> Raw<String>
+fn get_query_commands(query: &picvudb::data::get::GetObjectsQuery, search_tag: &Option<picvudb::msgs::GetTagResponse>) -> Raw<String>
 {
-    if let picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id } = query
+    if let picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id, include_descendants } = query
     {
+        let ancestors = search_tag.as_ref().map(|t| t.ancestors.clone()).unwrap_or_default();
+        let children = search_tag.as_ref().map(|t| t.children.clone()).unwrap_or_default();
+
         return Raw(owned_html!
         {
             div(class="cmdbar cmdbar-top")
@@ -597,10 +792,52 @@ fn get_query_commands(query: &picvudb::data::get::GetObjectsQuery) -> Raw<String
                     : OutlineIcon::Trash2.render(IconSize::Size16x16);
                     : " Delete Tag"
                 }
+                @if !children.is_empty() || *include_descendants
+                {
+                    a(href=ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag_id.clone(), include_descendants: !include_descendants }), class="cmdbar-link")
+                    {
+                        : OutlineIcon::Label.render(IconSize::Size16x16);
+                        : (if *include_descendants { " Only This Tag" } else { " Include Sub-tags" });
+                    }
+                }
                 div(class="cmdbar-summary")
                 {
                 }
             }
+
+            @if !ancestors.is_empty() || !children.is_empty()
+            {
+                div(class="tag-hierarchy")
+                {
+                    @for ancestor in ancestors.iter()
+                    {
+                        a(href=ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: ancestor.tag_id.clone(), include_descendants: false }))
+                        {
+                            : &ancestor.name;
+                        }
+                        : " > ";
+                    }
+
+                    : search_tag.as_ref().map(|t| t.tag.name.clone()).unwrap_or_default();
+
+                    @if !children.is_empty()
+                    {
+                        : " > ";
+
+                        @for (i, child) in children.iter().enumerate()
+                        {
+                            @if i != 0
+                            {
+                                : ", ";
+                            }
+                            a(href=ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: child.tag_id.clone(), include_descendants: false }))
+                            {
+                                : &child.name;
+                            }
+                        }
+                    }
+                }
+            }
         }.into_string().unwrap());
     }
 
@@ -623,13 +860,15 @@ fn get_heading(object: &picvudb::data::get::ObjectMetadata, query: &picvudb::dat
             | picvudb::data::get::GetObjectsQuery::NearLocationByActivityDesc{ .. }
             | picvudb::data::get::GetObjectsQuery::TitleNotesSearchByActivityDesc{ .. }
             | picvudb::data::get::GetObjectsQuery::TagByActivityDesc { .. }
-            | picvudb::data::get::GetObjectsQuery::ActivityDateRangeByActivityDesc{ .. } =>
+            | picvudb::data::get::GetObjectsQuery::ActivityDateRangeByActivityDesc{ .. }
+            | picvudb::data::get::GetObjectsQuery::ExifByActivityDesc{ .. }
+            | picvudb::data::get::GetObjectsQuery::IngredientByActivityDesc{ .. } =>
         {
             format::date_to_date_only_string(&object.activity_time)
         },
         picvudb::data::get::GetObjectsQuery::ByAttachmentSizeDesc =>
         {
-            format::bytes_to_group_header(object.attachment.size)
+            format::bytes_to_group_header(object.attachment.as_ref().map(|a| a.size).unwrap_or(0))
         },
 
     }
@@ -758,6 +997,12 @@ fn pagination(query: picvudb::data::get::GetObjectsQuery, num_tags: usize, list_
                 : OutlineIcon::List.render(IconSize::Size16x16);
                 : " Details ";
             }
+
+            a(href=pages::time_shift::TimeShiftPage::path_for_query(query.clone()), class="cmdbar-link")
+            {
+                : OutlineIcon::Clock.render(IconSize::Size16x16);
+                : " Shift Times ";
+            }
         }
     }.into_string().unwrap();
 
@@ -0,0 +1,567 @@
+use serde::Deserialize;
+use actix_web::{web, HttpRequest, HttpResponse};
+use horrorshow::{owned_html, Raw, Template};
+
+use crate::bulk;
+use crate::icons::{IconSize, OutlineIcon};
+use crate::pages::{PageResources, PageResourcesBuilder};
+use crate::pages;
+use crate::view;
+use crate::State;
+
+#[allow(dead_code)]
+pub struct RecipePage
+{
+}
+
+impl RecipePage
+{
+    pub fn edit_path_for(obj_id: &picvudb::data::ObjectId) -> String
+    {
+        format!("/view/edit_recipe/{}", obj_id.to_string())
+    }
+
+    pub fn json_ld_path_for(obj_id: &picvudb::data::ObjectId) -> String
+    {
+        format!("/recipes/{}/recipe.jsonld", obj_id.to_string())
+    }
+}
+
+impl PageResources for RecipePage
+{
+    fn page_resources(builder: &mut PageResourcesBuilder)
+    {
+        builder
+            .add_header_link("/view/add_recipe", "New Recipe", OutlineIcon::FilePlus, 610)
+            .route_view("/view/add_recipe", web::get().to(get_add_recipe))
+            .route_other("/form/add_recipe", web::post().to(post_add_recipe))
+            .route_other("/form/recipes/backfill", web::post().to(post_recipes_backfill))
+            .route_view("/view/edit_recipe/{obj_id}", web::get().to(get_edit_recipe))
+            .route_other("/form/edit_recipe/{obj_id}", web::post().to(post_edit_recipe))
+            .route_other("/recipes/{obj_id}/recipe.jsonld", web::get().to(get_recipe_json_ld));
+    }
+}
+
+#[derive(Deserialize)]
+struct FormRecipe
+{
+    title: String,
+    description: String,
+    servings: String,
+    prep_minutes: String,
+    cook_minutes: String,
+    source_url: String,
+    ingredients: String,
+    steps: String,
+}
+
+impl FormRecipe
+{
+    fn title(&self) -> Result<Option<picvudb::data::TitleMarkdown>, picvudb::ParseError>
+    {
+        if self.title.trim().is_empty()
+        {
+            Ok(None)
+        }
+        else
+        {
+            Ok(Some(picvudb::data::TitleMarkdown::parse(self.title.trim().to_owned())?))
+        }
+    }
+
+    fn recipe(&self) -> Result<picvudb::data::Recipe, picvudb::ParseError>
+    {
+        let ingredients = self.ingredients
+            .lines()
+            .filter_map(|line| picvudb::data::Ingredient::parse(line))
+            .collect::<Vec<_>>();
+
+        if ingredients.is_empty()
+        {
+            return Err(picvudb::ParseError::new("A recipe needs at least one ingredient"));
+        }
+
+        let steps = self.steps
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_owned())
+            .collect();
+
+        let source_url = self.source_url.trim();
+
+        Ok(picvudb::data::Recipe
+        {
+            description: self.description.trim().to_owned(),
+            servings: parse_optional_num(&self.servings, "Invalid servings")?,
+            prep_minutes: parse_optional_num(&self.prep_minutes, "Invalid prep time")?,
+            cook_minutes: parse_optional_num(&self.cook_minutes, "Invalid cook time")?,
+            source_url: if source_url.is_empty() { None } else { Some(source_url.to_owned()) },
+            ingredients,
+            steps,
+        })
+    }
+}
+
+fn parse_optional_num(value: &str, msg: &str) -> Result<Option<u32>, picvudb::ParseError>
+{
+    let value = value.trim();
+
+    if value.is_empty()
+    {
+        Ok(None)
+    }
+    else
+    {
+        Ok(Some(value.parse().map_err(|_| picvudb::ParseError::new(msg))?))
+    }
+}
+
+async fn get_object(state: &web::Data<State>, object_id: &picvudb::data::ObjectId) -> Result<Option<picvudb::data::get::ObjectMetadata>, view::ErrorResponder>
+{
+    let query = picvudb::data::get::GetObjectsQuery::ByObjectId(object_id.clone());
+
+    let msg = picvudb::msgs::GetObjectsRequest
+    {
+        query,
+        pagination: None,
+    };
+
+    let response = state.db.send(msg).await??;
+    let mut objects = response.objects;
+    let object = objects.drain(..).nth(0);
+
+    Ok(object)
+}
+
+fn render_recipe_form(action: String, submit: &str, title: String, recipe: picvudb::data::Recipe) -> String
+{
+    let num_to_string = |num: Option<u32>| num.map(|n| n.to_string()).unwrap_or_default();
+
+    let ingredients = recipe.ingredients.iter().map(|i| i.to_string()).collect::<Vec<_>>().join("\n");
+    let steps = recipe.steps.join("\n");
+
+    owned_html!
+    {
+        form(method="POST", action=action, enctype="application/x-www-form-urlencoded")
+        {
+            table(class="details-table")
+            {
+                tr
+                {
+                    td: "Title";
+                    td
+                    {
+                        input(type="text", name="title", size=60, value=title);
+                    }
+                }
+                tr
+                {
+                    td: "Description";
+                    td
+                    {
+                        textarea(name="description", rows=5, cols=60): recipe.description;
+                    }
+                }
+                tr
+                {
+                    td: "Servings";
+                    td
+                    {
+                        input(type="number", name="servings", min=1, value=num_to_string(recipe.servings));
+                    }
+                }
+                tr
+                {
+                    td: "Prep time";
+                    td
+                    {
+                        input(type="number", name="prep_minutes", min=0, value=num_to_string(recipe.prep_minutes));
+                        : " minutes";
+                    }
+                }
+                tr
+                {
+                    td: "Cook time";
+                    td
+                    {
+                        input(type="number", name="cook_minutes", min=0, value=num_to_string(recipe.cook_minutes));
+                        : " minutes";
+                    }
+                }
+                tr
+                {
+                    td: "Source";
+                    td
+                    {
+                        input(type="text", name="source_url", size=60, value=recipe.source_url.unwrap_or_default(), placeholder="https://");
+                    }
+                }
+                tr
+                {
+                    td: "Ingredients";
+                    td
+                    {
+                        textarea(name="ingredients", rows=10, cols=60, placeholder="One per line, e.g. 200 g flour"): ingredients;
+                    }
+                }
+                tr
+                {
+                    td: "Steps";
+                    td
+                    {
+                        textarea(name="steps", rows=10, cols=60, placeholder="One per line"): steps;
+                    }
+                }
+            }
+
+            input(type="submit", value=submit);
+        }
+    }.into_string().unwrap()
+}
+
+async fn get_add_recipe(state: web::Data<State>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let recipe = picvudb::data::Recipe
+    {
+        description: String::new(),
+        servings: None,
+        prep_minutes: None,
+        cook_minutes: None,
+        source_url: None,
+        ingredients: Vec::new(),
+        steps: Vec::new(),
+    };
+
+    let contents = owned_html!
+    {
+        h1: "New Recipe";
+
+        : Raw(render_recipe_form("/form/add_recipe".to_owned(), "Add Recipe", String::new(), recipe));
+
+        h1: "Backfill Recipe Ingredients";
+        form(method="POST", action="/form/recipes/backfill", enctype="application/x-www-form-urlencoded")
+        {
+            p { em: "Re-indexes the ingredients of every recipe, so recipes written in notes before ingredients were indexed can be found by ingredient."; }
+
+            p
+            {
+                input(type="submit", value="Backfill");
+            }
+        }
+    }.into_string().unwrap();
+
+    Ok(view::html_page(&req, &state.header_links, "New Recipe", OutlineIcon::FileText, &contents))
+}
+
+async fn post_recipes_backfill(state: web::Data<State>) -> HttpResponse
+{
+    {
+        let mut bulk_queue = state.bulk_queue.lock().unwrap();
+
+        bulk_queue.enqueue(bulk::recipes::RecipeBackfill::new(state.db_uri.clone()));
+    }
+
+    view::redirect(pages::bulk::BulkPage::progress_path())
+}
+
+async fn post_add_recipe(state: web::Data<State>, form: web::Form<FormRecipe>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let title = form.title()?;
+    let recipe = form.recipe()?;
+
+    // Like notes, recipes are objects without
+    // any attachments
+
+    let msg = picvudb::msgs::AddObjectRequest
+    {
+        data: picvudb::data::add::ObjectData
+        {
+            title,
+            notes: Some(recipe.to_notes()?),
+            rating: picvudb::data::Rating::NotRated,
+            censor: picvudb::data::Censor::FamilyFriendly,
+            created_time: None,
+            modified_time: None,
+            activity_time: None,
+            location: None,
+            attachment: None,
+            additional_attachments: Vec::new(),
+            tags: Vec::new(),
+            ext_ref: None,
+            user_orientation: None,
+            crop: None,
+        },
+    };
+
+    let response = state.db.send(msg).await??;
+
+    Ok(view::redirect(pages::object_details::ObjectDetailsPage::path_for(&response.object_id)))
+}
+
+fn can_edit_recipe(object: &picvudb::data::get::ObjectMetadata) -> bool
+{
+    object.notes.as_ref()
+        .map(|notes| picvudb::data::Recipe::can_edit_notes(notes))
+        .unwrap_or(true)
+}
+
+async fn get_edit_recipe(state: web::Data<State>, object_id: web::Path<String>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
+
+    let object = match get_object(&state, &object_id).await?
+    {
+        Some(object) => object,
+        None => return Ok(view::err(HttpResponse::NotFound(), "Not Found")),
+    };
+
+    if !can_edit_recipe(&object)
+    {
+        let contents = owned_html!
+        {
+            h1: "Edit Recipe";
+
+            p: "These notes have text that the recipe editor would lose or move, such as lines between the ingredients.";
+
+            a(href=pages::edit_object::EditObjectPage::path_for(&object_id)): "Edit the notes instead";
+        }.into_string().unwrap();
+
+        return Ok(view::html_page(&req, &state.header_links, "Edit Recipe", OutlineIcon::Edit, &contents));
+    }
+
+    // Existing notes that aren't yet a recipe are kept
+    // as the description, so they can be converted
+
+    let recipe = object.notes.as_ref()
+        .and_then(|notes| picvudb::data::Recipe::from_notes(notes))
+        .unwrap_or_else(|| picvudb::data::Recipe
+        {
+            description: object.notes.as_ref().map(|n| n.get_markdown()).unwrap_or_default(),
+            servings: None,
+            prep_minutes: None,
+            cook_minutes: None,
+            source_url: None,
+            ingredients: Vec::new(),
+            steps: Vec::new(),
+        });
+
+    let title = object.title.as_ref().map(|t| t.get_markdown()).unwrap_or_default();
+
+    let contents = owned_html!
+    {
+        h1: "Edit Recipe";
+
+        : Raw(render_recipe_form(format!("/form/edit_recipe/{}", object_id.to_string()), "Save Recipe", title, recipe));
+    }.into_string().unwrap();
+
+    Ok(view::html_page(&req, &state.header_links, "Edit Recipe", OutlineIcon::Edit, &contents))
+}
+
+async fn post_edit_recipe(state: web::Data<State>, object_id: web::Path<String>, form: web::Form<FormRecipe>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
+
+    let object = match get_object(&state, &object_id).await?
+    {
+        Some(object) => object,
+        None => return Ok(view::err(HttpResponse::NotFound(), "Not Found")),
+    };
+
+    if !can_edit_recipe(&object)
+    {
+        return Ok(view::err(HttpResponse::BadRequest(), "Saving these notes as a recipe would lose some of their text"));
+    }
+
+    let title = form.title()?;
+    let recipe = form.recipe()?;
+
+    let msg = picvudb::msgs::EditObjectRequest
+    {
+        details: Some(picvudb::msgs::UpdateObjectRequest
+        {
+            object_id: object_id.clone(),
+            activity_time: object.activity_time,
+            title,
+            notes: Some(recipe.to_notes()?),
+            rating: object.rating,
+            censor: object.censor,
+            location: object.location,
+        }),
+        tags: None,
+    };
+
+    let _ = state.db.send(msg).await??;
+
+    Ok(view::redirect(pages::object_details::ObjectDetailsPage::path_for(&object_id)))
+}
+
+async fn get_recipe_json_ld(state: web::Data<State>, object_id: web::Path<String>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
+
+    let object = match get_object(&state, &object_id).await?
+    {
+        Some(object) => object,
+        None => return Ok(view::err(HttpResponse::NotFound(), "Not Found")),
+    };
+
+    match object.notes.as_ref().and_then(|notes| picvudb::data::Recipe::from_notes(notes))
+    {
+        Some(recipe) =>
+        {
+            let json = serde_json::to_string_pretty(&recipe_json_ld(&object, &recipe)).unwrap();
+
+            Ok(HttpResponse::Ok()
+                .content_type("application/ld+json")
+                .body(json))
+        },
+        None => Ok(view::err(HttpResponse::NotFound(), "This object isn't a recipe")),
+    }
+}
+
+fn iso_duration(minutes: u32) -> String
+{
+    format!("PT{}M", minutes)
+}
+
+/// Converts a recipe into a schema.org Recipe, as
+/// understood by other recipe managers and search engines.
+pub fn recipe_json_ld(object: &picvudb::data::get::ObjectMetadata, recipe: &picvudb::data::Recipe) -> serde_json::Value
+{
+    let mut json = serde_json::json!({
+        "@context": "https://schema.org",
+        "@type": "Recipe",
+        "name": object.title.as_ref().map(|t| t.get_display_text()).unwrap_or_else(|| object.get_display_text()),
+        "recipeIngredient": recipe.ingredients.iter().map(|i| i.to_string()).collect::<Vec<_>>(),
+        "recipeInstructions": recipe.steps.iter().map(|s| serde_json::json!({ "@type": "HowToStep", "text": s })).collect::<Vec<_>>(),
+    });
+
+    if !recipe.description.is_empty()
+    {
+        json["description"] = recipe.description.clone().into();
+    }
+
+    if let Some(servings) = recipe.servings
+    {
+        json["recipeYield"] = format!("{} servings", servings).into();
+    }
+
+    if let Some(prep_minutes) = recipe.prep_minutes
+    {
+        json["prepTime"] = iso_duration(prep_minutes).into();
+    }
+
+    if let Some(cook_minutes) = recipe.cook_minutes
+    {
+        json["cookTime"] = iso_duration(cook_minutes).into();
+    }
+
+    if let (Some(prep_minutes), Some(cook_minutes)) = (recipe.prep_minutes, recipe.cook_minutes)
+    {
+        if let Some(total_minutes) = prep_minutes.checked_add(cook_minutes)
+        {
+            json["totalTime"] = iso_duration(total_minutes).into();
+        }
+    }
+
+    if let Some(source_url) = &recipe.source_url
+    {
+        json["url"] = source_url.clone().into();
+    }
+
+    json
+}
+
+/// Renders a recipe on the object details page, with the
+/// ingredients scaled to the requested number of servings.
+pub fn render_recipe(object: &picvudb::data::get::ObjectMetadata, recipe: &picvudb::data::Recipe, servings: Option<u32>) -> Raw<String>
+{
+    let scaled = match servings
+    {
+        Some(servings) if servings != 0 => recipe.scale_to_servings(servings),
+        _ => recipe.clone(),
+    };
+
+    // The JSON-LD is embedded in a script element, so
+    // "</script>" inside a string mustn't end it early
+
+    let json_ld = serde_json::to_string(&recipe_json_ld(object, recipe)).unwrap().replace("</", "<\\/");
+
+    Raw(owned_html!
+    {
+        script(type="application/ld+json"): Raw(json_ld);
+
+        @if !scaled.description.is_empty()
+        {
+            p: scaled.description.clone();
+        }
+
+        @if let Some(cur_servings) = scaled.servings
+        {
+            form(method="GET", action=pages::object_details::ObjectDetailsPage::path_for(&object.id))
+            {
+                : "Servings ";
+                input(type="number", name="servings", min=1, value=cur_servings.to_string());
+                : " ";
+                input(type="submit", value="Scale");
+            }
+        }
+
+        @if scaled.prep_minutes.is_some() || scaled.cook_minutes.is_some()
+        {
+            p
+            {
+                @if let Some(prep_minutes) = scaled.prep_minutes
+                {
+                    : format!("Prep time: {} ", picvudb::data::format_minutes(prep_minutes));
+                }
+                @if let Some(cook_minutes) = scaled.cook_minutes
+                {
+                    : format!("Cook time: {}", picvudb::data::format_minutes(cook_minutes));
+                }
+            }
+        }
+
+        h3: "Ingredients";
+        ul
+        {
+            @for ingredient in scaled.ingredients.iter()
+            {
+                li
+                {
+                    a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::IngredientByActivityDesc{ ingredient: ingredient.get_search_name() }))
+                    {
+                        : ingredient.to_string();
+                    }
+                }
+            }
+        }
+
+        @if !scaled.steps.is_empty()
+        {
+            h3: "Steps";
+            ol
+            {
+                @for step in scaled.steps.iter()
+                {
+                    li: step;
+                }
+            }
+        }
+
+        p
+        {
+            @if let Some(source_url) = scaled.source_url.clone()
+            {
+                a(href=source_url, target="_blank"): "Source";
+                : " ";
+            }
+            a(href=RecipePage::json_ld_path_for(&object.id))
+            {
+                : OutlineIcon::Export.render(IconSize::Size16x16);
+                : " Export JSON-LD";
+            }
+        }
+    }.into_string().unwrap())
+}
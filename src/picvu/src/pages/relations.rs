@@ -0,0 +1,153 @@
+use serde::Deserialize;
+use actix_web::{web, HttpResponse};
+use horrorshow::{owned_html, Raw, Template};
+
+use crate::pages::{PageResources, PageResourcesBuilder};
+use crate::pages;
+use crate::view;
+use crate::State;
+
+#[allow(dead_code)]
+pub struct RelationsPage
+{
+}
+
+impl PageResources for RelationsPage
+{
+    fn page_resources(builder: &mut PageResourcesBuilder)
+    {
+        builder
+            .route_other("/form/add_relation/{obj_id}", web::post().to(post_add_relation))
+            .route_other("/form/remove_relation/{obj_id}", web::post().to(post_remove_relation));
+    }
+}
+
+#[derive(Deserialize)]
+struct FormAddRelation
+{
+    relation: String,
+    other_id: String,
+}
+
+#[derive(Deserialize)]
+struct FormRemoveRelation
+{
+    from: String,
+    to: String,
+    kind: String,
+}
+
+// Relations are chosen by how the other object is
+// described, so each kind appears once for each
+// direction - e.g. "Before" and "After"
+
+fn relation_choices() -> Vec<(String, String)>
+{
+    let mut result = Vec::new();
+
+    for kind in picvudb::data::RelationKind::values()
+    {
+        result.push((format!("{}:out", kind.to_string()), kind.outgoing_label()));
+
+        if kind.incoming_label() != kind.outgoing_label()
+        {
+            result.push((format!("{}:in", kind.to_string()), kind.incoming_label()));
+        }
+    }
+
+    result
+}
+
+async fn post_add_relation(state: web::Data<State>, object_id: web::Path<String>, form: web::Form<FormAddRelation>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
+    let other_id = picvudb::data::ObjectId::try_new(form.other_id.trim().to_owned())?;
+
+    let mut parts = form.relation.splitn(2, ':');
+    let kind: picvudb::data::RelationKind = parts.next().unwrap_or_default().parse()?;
+    let outgoing = parts.next() != Some("in");
+
+    let (from, to) = if outgoing { (object_id.clone(), other_id) } else { (other_id, object_id.clone()) };
+
+    let msg = picvudb::msgs::AddObjectRelationRequest{ from, to, kind };
+
+    match state.db.send(msg).await??
+    {
+        picvudb::msgs::AddObjectRelationResponse::ObjectNotFound =>
+        {
+            Ok(view::err(HttpResponse::NotFound(), "Object not found"))
+        },
+        picvudb::msgs::AddObjectRelationResponse::SameObject =>
+        {
+            Ok(view::err(HttpResponse::BadRequest(), "An object can't be related to itself"))
+        },
+        picvudb::msgs::AddObjectRelationResponse::Added =>
+        {
+            Ok(view::redirect(pages::object_details::ObjectDetailsPage::path_for(&object_id)))
+        },
+    }
+}
+
+async fn post_remove_relation(state: web::Data<State>, object_id: web::Path<String>, form: web::Form<FormRemoveRelation>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
+
+    let msg = picvudb::msgs::RemoveObjectRelationRequest
+    {
+        from: picvudb::data::ObjectId::try_new(form.from.clone())?,
+        to: picvudb::data::ObjectId::try_new(form.to.clone())?,
+        kind: form.kind.parse()?,
+    };
+
+    let _ = state.db.send(msg).await??;
+
+    Ok(view::redirect(pages::object_details::ObjectDetailsPage::path_for(&object_id)))
+}
+
+/// Renders the strip of related objects shown on the object
+/// details page, with forms to add and remove relations.
+pub fn render_relations_strip(object: &picvudb::data::get::ObjectMetadata, relations: &Vec<picvudb::data::get::RelatedObject>) -> Raw<String>
+{
+    Raw(owned_html!
+    {
+        div(class="related-objects")
+        {
+            @for related in relations.iter()
+            {
+                div(class="related-object")
+                {
+                    a(href=pages::object_details::ObjectDetailsPage::path_for(&related.object.id))
+                    {
+                        : pages::attachments::AttachmentsPage::raw_html_for_thumbnail(&related.object, 128, false);
+                    }
+                    div
+                    {
+                        : related.label();
+                    }
+                    form(method="POST", action=format!("/form/remove_relation/{}", object.id.to_string()), enctype="application/x-www-form-urlencoded")
+                    {
+                        input(type="hidden", name="from", value=(if related.outgoing { object.id.to_string() } else { related.object.id.to_string() }));
+                        input(type="hidden", name="to", value=(if related.outgoing { related.object.id.to_string() } else { object.id.to_string() }));
+                        input(type="hidden", name="kind", value=related.kind.to_string());
+                        input(type="submit", value="Remove");
+                    }
+                }
+            }
+        }
+
+        form(method="POST", action=format!("/form/add_relation/{}", object.id.to_string()), enctype="application/x-www-form-urlencoded")
+        {
+            select(name="relation")
+            {
+                @for (value, label) in relation_choices()
+                {
+                    option(value=value): label;
+                }
+            }
+            : " ";
+            input(type="text", name="other_id", size=20, placeholder="Object ID");
+            : " ";
+            input(type="submit", value="Add Relation");
+        }
+    }.into_string().unwrap())
+}
@@ -0,0 +1,362 @@
+use serde::Deserialize;
+use futures::{StreamExt, TryStreamExt};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_multipart::Multipart;
+use horrorshow::{owned_html, Raw, Template};
+
+use crate::analyse;
+use crate::format;
+use crate::icons::OutlineIcon;
+use crate::pages::{HeaderLinkCollection, PageResources, PageResourcesBuilder};
+use crate::pages;
+use crate::view;
+use crate::State;
+
+#[allow(dead_code)]
+pub struct ReplaceAttachmentPage
+{
+}
+
+impl ReplaceAttachmentPage
+{
+    pub fn path_for(obj_id: &picvudb::data::ObjectId, hash: &String) -> String
+    {
+        format!("/view/replace_attachment/{}?attachment={}", obj_id.to_string(), hash)
+    }
+}
+
+#[derive(Deserialize)]
+struct ReplaceAttachmentQuery
+{
+    attachment: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RevertAttachmentForm
+{
+    hash: String,
+}
+
+impl PageResources for ReplaceAttachmentPage
+{
+    fn page_resources(builder: &mut PageResourcesBuilder)
+    {
+        builder
+            .route_view("/view/replace_attachment/{obj_id}", web::get().to(get_replace_attachment))
+            .route_other("/form/replace_attachment/{obj_id}", web::post().to(post_replace_attachment))
+            .route_other("/form/revert_attachment/{obj_id}", web::post().to(post_revert_attachment));
+    }
+}
+
+async fn get_object(state: &web::Data<State>, object_id: &picvudb::data::ObjectId) -> Result<Option<picvudb::data::get::ObjectMetadata>, view::ErrorResponder>
+{
+    let query = picvudb::data::get::GetObjectsQuery::ByObjectId(object_id.clone());
+
+    let msg = picvudb::msgs::GetObjectsRequest
+    {
+        query,
+        pagination: None,
+    };
+
+    let response = state.db.send(msg).await??;
+    let mut objects = response.objects;
+    let object = objects.drain(..).nth(0);
+
+    Ok(object)
+}
+
+async fn get_replace_attachment(state: web::Data<State>, object_id: web::Path<String>, query: web::Query<ReplaceAttachmentQuery>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
+
+    match get_object(&state, &object_id).await?
+    {
+        None =>
+        {
+            Ok(view::err(HttpResponse::NotFound(), "Not Found"))
+        },
+        Some(object) =>
+        {
+            // Only current attachments can be replaced - older
+            // versions are restored by reverting to them
+
+            let current = object.attachment.iter()
+                .chain(object.additional_attachments.iter())
+                .find(|a| query.attachment.is_none() || (*query.attachment.as_ref().unwrap() == a.hash))
+                .cloned();
+
+            match current
+            {
+                None =>
+                {
+                    Ok(view::err(HttpResponse::NotFound(), "Not Found"))
+                },
+                Some(current) =>
+                {
+                    Ok(render_replace_attachment(object, current, &req, &state.header_links))
+                },
+            }
+        },
+    }
+}
+
+async fn post_replace_attachment(state: web::Data<State>, object_id: web::Path<String>, query: web::Query<ReplaceAttachmentQuery>, mut payload: Multipart) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
+
+    let mut file: Option<(String, Vec<u8>)> = None;
+
+    loop
+    {
+        let section = payload.try_next().await?;
+
+        match section
+        {
+            None => break,
+            Some(mut field) =>
+            {
+                if let Some(content_type) = field.content_disposition()
+                {
+                    if let Some(filename) = content_type.get_filename()
+                    {
+                        let mut bytes: Vec<u8> = Vec::new();
+
+                        while let Some(chunk) = field.next().await
+                        {
+                            let chunk = chunk?;
+
+                            bytes.extend_from_slice(&chunk);
+                        }
+
+                        file = Some((filename.to_owned(), bytes));
+                    }
+                }
+            },
+        }
+    }
+
+    let multipart_err = actix_multipart::MultipartError::Payload(actix_http::error::PayloadError::Incomplete(Some(
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "Request is missing a file")
+    )));
+
+    let (file_name, bytes) = file.ok_or(multipart_err)?;
+
+    let object = match get_object(&state, &object_id).await?
+    {
+        Some(object) => object,
+        None => return Ok(view::err(HttpResponse::NotFound(), "Not Found")),
+    };
+
+    let hash = match query.attachment.clone().or(object.attachment.map(|a| a.hash))
+    {
+        Some(hash) => hash,
+        None => return Ok(view::err(HttpResponse::NotFound(), "Object has no attachment to replace")),
+    };
+
+    // We ignore warnings here
+
+    let mut warnings = Vec::new();
+
+    let exif = analyse::img::extract_exif(&bytes);
+
+    let mut attachment = analyse::import::create_additional_attachment_for_import(
+        bytes,
+        &file_name,
+        picvudb::data::AttachmentRole::Edited,
+        None,
+        None,
+        &analyse::import::ImportOptions::default(),
+        &mut warnings);
+
+    if attachment.mime.type_() == mime::IMAGE
+    {
+        attachment.exif = exif;
+    }
+
+    let msg = picvudb::msgs::ReplaceAttachmentRequest
+    {
+        object_id: object_id.clone(),
+        hash,
+        attachment,
+    };
+
+    match state.db.send(msg).await??
+    {
+        picvudb::msgs::ReplaceAttachmentResponse::ObjectNotFound
+            | picvudb::msgs::ReplaceAttachmentResponse::HashNotFound =>
+        {
+            Ok(view::err(HttpResponse::NotFound(), "Not Found"))
+        },
+        picvudb::msgs::ReplaceAttachmentResponse::Replaced =>
+        {
+            Ok(view::redirect(pages::object_details::ObjectDetailsPage::path_for(&object_id)))
+        },
+    }
+}
+
+async fn post_revert_attachment(state: web::Data<State>, object_id: web::Path<String>, form: web::Form<RevertAttachmentForm>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let object_id = picvudb::data::ObjectId::try_new(object_id.to_string())?;
+
+    // The stored EXIF data and document text always
+    // describe the primary attachment, so they're
+    // re-extracted from the version being restored
+
+    let get_attachment_data_msg = picvudb::msgs::GetAttachmentDataRequest
+    {
+        object_id: object_id.clone(),
+        specific_hash: Some(form.hash.clone()),
+    };
+
+    let (exif, text) = match state.db.send(get_attachment_data_msg).await??
+    {
+        picvudb::msgs::GetAttachmentDataResponse::ObjectNotFound
+            | picvudb::msgs::GetAttachmentDataResponse::HashNotFound =>
+        {
+            return Ok(view::err(HttpResponse::NotFound(), "Not Found"));
+        },
+        picvudb::msgs::GetAttachmentDataResponse::Found{metadata, bytes} =>
+        {
+            let exif = if metadata.mime.type_() == mime::IMAGE { analyse::img::extract_exif(&bytes) } else { None };
+            let text = analyse::document::extract_text(&bytes, &metadata.filename, &metadata.mime, &mut Vec::new());
+
+            (exif, text)
+        },
+    };
+
+    let msg = picvudb::msgs::RevertAttachmentRequest
+    {
+        object_id: object_id.clone(),
+        hash: form.hash.clone(),
+        exif,
+        text,
+    };
+
+    match state.db.send(msg).await??
+    {
+        picvudb::msgs::RevertAttachmentResponse::ObjectNotFound
+            | picvudb::msgs::RevertAttachmentResponse::HashNotFound =>
+        {
+            Ok(view::err(HttpResponse::NotFound(), "Not Found"))
+        },
+        picvudb::msgs::RevertAttachmentResponse::Reverted =>
+        {
+            Ok(view::redirect(pages::object_details::ObjectDetailsPage::path_for(&object_id)))
+        },
+    }
+}
+
+fn render_replace_attachment(object: picvudb::data::get::ObjectMetadata, current: picvudb::data::get::AttachmentMetadata, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
+{
+    let filename = object.get_display_text();
+
+    let title = view::Title
+    {
+        text: object.title.clone().map(|m| m.get_display_text()).unwrap_or(filename.clone()),
+        html: Raw(object.title.clone().map(|m| m.get_html()).unwrap_or(owned_html!{ : filename.clone() }.into_string().unwrap())),
+    };
+
+    // Previous versions are listed newest first. The
+    // original is the oldest one that wasn't itself
+    // an edited upload
+
+    let mut versions = object.previous_versions.iter()
+        .filter(|v| v.index == current.index)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    versions.reverse();
+
+    let original_hash = versions.iter()
+        .rev()
+        .find(|v| v.role != picvudb::data::AttachmentRole::Edited)
+        .map(|v| v.hash.clone());
+
+    let obj_id = object.id.clone();
+    let revert_path = format!("/form/revert_attachment/{}", object.id.to_string());
+
+    let contents = owned_html!
+    {
+        h1: "Replace Attachment";
+
+        table(class="details-table")
+        {
+            tr
+            {
+                th(colspan="2"): "Current Version";
+            }
+            tr
+            {
+                td
+                {
+                    : pages::attachments::AttachmentsPage::raw_html_for_attachment_thumbnail(&object, &current, 128, false);
+                }
+                td
+                {
+                    p: current.filename.clone();
+                    p: format!("{}, {}", current.mime, format::bytes_to_string(current.size));
+                }
+            }
+        }
+
+        form(method="POST", action=format!("/form/replace_attachment/{}?attachment={}", object.id.to_string(), current.hash), enctype="multipart/form-data")
+        {
+            p
+            {
+                : "The uploaded file becomes the current version. The existing file is kept as a previous version, and can be restored later.";
+            }
+
+            input(type="file", name="file");
+            input(type="submit", value="Replace");
+        }
+
+        @if let Some(original_hash) = &original_hash
+        {
+            form(method="POST", action=&revert_path, enctype="application/x-www-form-urlencoded")
+            {
+                input(type="hidden", name="hash", value=original_hash);
+                input(type="submit", value="Revert to Original");
+            }
+        }
+
+        @if !versions.is_empty()
+        {
+            table(class="details-table")
+            {
+                tr
+                {
+                    th(colspan="3"): "Previous Versions";
+                }
+
+                @for version in versions.iter()
+                {
+                    tr
+                    {
+                        td
+                        {
+                            a(href=pages::object_details::ObjectDetailsPage::path_for_attachment(&obj_id, &version.hash))
+                            {
+                                : pages::attachments::AttachmentsPage::raw_html_for_attachment_thumbnail(&object, &version, 128, false);
+                            }
+                        }
+                        td
+                        {
+                            p: version.filename.clone();
+                            p: format!("{}, {}, {}", version.role.to_string(), version.mime, format::bytes_to_string(version.size));
+                        }
+                        td
+                        {
+                            form(method="POST", action=&revert_path, enctype="application/x-www-form-urlencoded")
+                            {
+                                input(type="hidden", name="hash", value=&version.hash);
+                                input(type="submit", value="Revert to this Version");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }.into_string().unwrap();
+
+    view::html_page(req, header_links, title, OutlineIcon::CloudUpload, &contents)
+}
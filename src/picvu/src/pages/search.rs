@@ -35,7 +35,21 @@ struct SearchForm
 
 async fn get_search(form: web::Query<SearchForm>) -> HttpResponse
 {
-    if let Ok(location) = form.q.parse()
+    if let Some(ingredient) = form.q.trim().strip_prefix("ingredient:")
+    {
+        // Searching for "ingredient:flour" lists the
+        // recipes that use that ingredient.
+
+        if ingredient.trim().is_empty()
+        {
+            return view::err(HttpResponse::BadRequest(), "No ingredient to search for");
+        }
+
+        let query = picvudb::data::get::GetObjectsQuery::IngredientByActivityDesc{ ingredient: ingredient.trim().to_owned() };
+
+        view::redirect(pages::object_listing::ObjectListingPage::path(query))
+    }
+    else if let Ok(location) = form.q.parse()
     {
         // If we can decode the query as a location, then
         // redirect to the location page.
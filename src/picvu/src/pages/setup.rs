@@ -3,9 +3,11 @@ use serde::Deserialize;
 use actix_web::{web, HttpRequest, HttpResponse};
 use horrorshow::{owned_html, Template};
 
+use crate::format;
 use crate::icons::OutlineIcon;
 use crate::pages::{PageResources, PageResourcesBuilder};
 use crate::view;
+use crate::pages;
 use crate::State;
 
 #[allow(dead_code)]
@@ -28,7 +30,8 @@ impl PageResources for SetupPage
         builder
             .add_header_link("/view/setup", "Setup", OutlineIcon::Settings, 400)
             .route_view("/view/setup", web::get().to(get_setup_form))
-            .route_other("/forms/setup", web::post().to(post_setup_form));
+            .route_other("/forms/setup", web::post().to(post_setup_form))
+            .route_other("/forms/setup/revoke", web::post().to(post_revoke_form));
     }
 }
 
@@ -57,12 +60,100 @@ pub async fn get_client_id_and_secret(state: &State) -> Result<(String, String),
     Ok((client_id, client_secret))
 }
 
+pub async fn get_refresh_token(state: &State) -> Result<Option<String>, view::ErrorResponder>
+{
+    let mut properties = state.db.send(picvudb::msgs::GetPropertiesRequest{}).await??;
+
+    let refresh_token = properties.properties.remove(PROP_NAME_REFRESH_TOKEN).unwrap_or_default();
+
+    if refresh_token.is_empty()
+    {
+        Ok(None)
+    }
+    else
+    {
+        Ok(Some(refresh_token))
+    }
+}
+
+pub async fn set_refresh_token(state: &State, refresh_token: Option<String>) -> Result<(), view::ErrorResponder>
+{
+    let mut properties = HashMap::new();
+
+    properties.insert(PROP_NAME_REFRESH_TOKEN.to_owned(), refresh_token.unwrap_or_default());
+
+    let _ = state.db.send(picvudb::msgs::SetPropertiesRequest{ properties }).await??;
+
+    Ok(())
+}
+
 async fn get_setup_form(state: web::Data<State>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
 {
     let (api_key, client_id, client_secret) = get_api_key_client_id_and_secret(&*state).await?;
+    let stored_refresh_token = get_refresh_token(&*state).await?;
+
+    let status =
+    {
+        let google_auth_client = state.google_auth_client.lock().unwrap();
+
+        google_auth_client.status()
+    };
+
+    let status_text = match status
+    {
+        googlephotos::auth::TokenStatus::None =>
+        {
+            if stored_refresh_token.is_some()
+            {
+                "Not logged in this session - a stored refresh token will be used when required".to_owned()
+            }
+            else
+            {
+                "Not logged in".to_owned()
+            }
+        },
+        googlephotos::auth::TokenStatus::InProgress => "Login in progress".to_owned(),
+        googlephotos::auth::TokenStatus::Error{err} => format!("Error: {}", err),
+        googlephotos::auth::TokenStatus::Valid{expires_at, refreshable} =>
+        {
+            let expires = match expires_at
+            {
+                Some(expires_at) => format!("expires {}", format::date_to_str(&picvudb::data::Date::Utc(expires_at), &picvudb::data::Date::now())),
+                None => "no expiry".to_owned(),
+            };
+
+            format!("Logged in ({}){}", expires, if refreshable { ", will be refreshed automatically" } else { "" })
+        },
+        googlephotos::auth::TokenStatus::Expired{refreshable} =>
+        {
+            if refreshable
+            {
+                "Expired - will be refreshed automatically".to_owned()
+            }
+            else
+            {
+                "Expired - please login again".to_owned()
+            }
+        },
+    };
 
     let contents = owned_html!
     {
+        h1: "Google Photos Login";
+        p: status_text;
+        p
+        {
+            a(href=pages::auth::AuthPage::path()): "Login";
+        }
+        @if stored_refresh_token.is_some()
+        {
+            form(method="POST", action="/forms/setup/revoke", enctype="application/x-www-form-urlencoded")
+            {
+                input(type="submit", value="Revoke");
+            }
+        }
+
+        h1: "API Settings";
         form(method="POST", action="/forms/setup", enctype="application/x-www-form-urlencoded")
         {
             p { : "Google Maps (Geocoding/TimeZone) API Key" }
@@ -94,6 +185,7 @@ async fn get_setup_form(state: web::Data<State>, req: HttpRequest) -> Result<Htt
 const PROP_NAME_API_KEY: &'static str = "api_key.auth.google.com";
 const PROP_NAME_CLIENT_ID: &'static str = "client_id.auth.google.com";
 const PROP_NAME_CLIENT_SECRET: &'static str = "client_secret.auth.google.com";
+const PROP_NAME_REFRESH_TOKEN: &'static str = "refresh_token.auth.google.com";
 
 #[derive(Deserialize)]
 pub struct SetupForm
@@ -115,3 +207,27 @@ async fn post_setup_form(state: web::Data<State>, form: web::Form<SetupForm>) ->
 
     Ok(view::redirect("/view/setup".to_owned()))
 }
+
+async fn post_revoke_form(state: web::Data<State>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let stored_refresh_token = get_refresh_token(&*state).await?;
+
+    let operation =
+    {
+        let mut google_auth_client = state.google_auth_client.lock().unwrap();
+
+        google_auth_client.revoke(stored_refresh_token)
+    };
+
+    // Forget the stored token even if the server
+    // can't be contacted to revoke it
+
+    set_refresh_token(&*state, None).await?;
+
+    web::block(||
+    {
+        operation.blocking_execute()
+    }).await?;
+
+    Ok(view::redirect(SetupPage::path()))
+}
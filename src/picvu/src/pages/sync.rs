@@ -1,4 +1,6 @@
-use actix_web::{web, HttpResponse};
+use std::collections::HashMap;
+use actix_web::{web, HttpRequest, HttpResponse};
+use horrorshow::{owned_html, Template};
 
 use crate::bulk;
 use crate::icons::OutlineIcon;
@@ -17,29 +19,311 @@ impl PageResources for SyncPage
     fn page_resources(builder: &mut PageResourcesBuilder)
     {
         builder
-            .add_header_link("/sync/start", "Sync", OutlineIcon::CloudUpload, 600)
-            .route_view("/sync/start", web::get().to(get_sync_start));
+            .add_header_link("/view/sync", "Sync", OutlineIcon::CloudUpload, 600)
+            .route_view("/view/sync", web::get().to(get_sync))
+            .route_other("/form/sync", web::post().to(post_sync))
+            .route_other("/form/sync/confirm", web::post().to(post_sync_confirm))
+            .route_other("/form/sync/discard", web::post().to(post_sync_discard));
     }
 }
 
-async fn get_sync_start(state: web::Data<State>) -> Result<HttpResponse, view::ErrorResponder>
+const PROP_NAME_ALBUM_MAPPINGS: &'static str = "album_mappings.sync.google.com";
+const PROP_NAME_KINDS: &'static str = "kinds.sync.google.com";
+const PROP_NAME_EXCLUDED_TAGS: &'static str = "excluded_tags.sync.google.com";
+
+struct SavedSyncSettings
+{
+    album_mappings: String,
+    kinds: Vec<picvudb::data::TagKind>,
+    excluded_tags: String,
+}
+
+async fn get_saved_settings(state: &State) -> Result<SavedSyncSettings, view::ErrorResponder>
+{
+    let mut properties = state.db.send(picvudb::msgs::GetPropertiesRequest{}).await??;
+
+    let album_mappings = properties.properties.remove(PROP_NAME_ALBUM_MAPPINGS).unwrap_or_default();
+    let excluded_tags = properties.properties.remove(PROP_NAME_EXCLUDED_TAGS).unwrap_or_default();
+
+    // By default, don't sync location or system tags
+
+    let kinds = match properties.properties.remove(PROP_NAME_KINDS)
+    {
+        Some(kinds) => kinds.split(',').filter_map(|k| k.parse().ok()).collect(),
+        None => picvudb::data::TagKind::values().into_iter()
+            .filter(|k| !k.is_system_kind() && *k != picvudb::data::TagKind::Location)
+            .collect(),
+    };
+
+    Ok(SavedSyncSettings{ album_mappings, kinds, excluded_tags })
+}
+
+async fn get_sync(state: web::Data<State>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
 {
-    let access_token =
+    let plan =
     {
-        let auth = state.google_auth_client.lock().unwrap();
+        let sync_plan = state.sync_plan.lock().unwrap();
 
-        auth.access_token()
+        sync_plan.clone()
     };
 
+    let contents = match plan
+    {
+        Some(plan) => render_plan(&plan),
+        None => render_options(get_saved_settings(&*state).await?),
+    };
+
+    Ok(view::html_page(&req, &state.header_links, "Sync", OutlineIcon::CloudUpload, &contents))
+}
+
+fn render_options(settings: SavedSyncSettings) -> String
+{
+    owned_html!
+    {
+        h1: "Google Photos Sync";
+        p { em: "The changes will be previewed, and must be confirmed before they are made."; }
+        form(method="POST", action="/form/sync", enctype="application/x-www-form-urlencoded")
+        {
+            h2: "Push";
+            p
+            {
+                input(type="checkbox", id="push", name="push", value="true", checked?=true);
+                label(for="push"): "Create and add items to albums for the tags of linked objects";
+            }
+
+            h2: "Pull";
+            p
+            {
+                input(type="checkbox", id="pull", name="pull", value="true");
+                label(for="pull"): "Add tags to linked objects from the albums they are in";
+            }
+
+            h3: "Conflicts";
+            p { em: "What to do when an album and the tag with the same name have different contents."; }
+            p
+            {
+                select(name="conflict_rule")
+                {
+                    option(value="AddOnly", selected?=true): "Only add tags - never remove them";
+                    option(value="GooglePhotosWins"): "Google Photos wins - remove tags from items no longer in the album";
+                    option(value="PicvuWins"): "PicVu wins - don't pull albums that already exist as tags";
+                }
+            }
+
+            h3: "Default Tag Kind";
+            p { em: "The kind of tag created for new albums that aren't listed below."; }
+            p
+            {
+                select(name="default_kind")
+                {
+                    @for kind in picvudb::data::TagKind::values().into_iter().filter(|k| !k.is_system_kind())
+                    {
+                        option(value=kind.to_string(), selected?=(kind == picvudb::data::TagKind::Label)): kind.to_string();
+                    }
+                }
+            }
+
+            h3: "Album Mappings";
+            p { em: "One album per line, as \"Album Title = Kind\". Use \"Album Title = Ignore\" to not pull an album."; }
+            p
+            {
+                textarea(name="album_mappings", rows="10", cols="60"): &settings.album_mappings;
+            }
+
+            h2: "Filter";
+            p { em: "Only tags of these kinds are synced."; }
+            p
+            {
+                @for kind in picvudb::data::TagKind::values()
+                {
+                    input(type="checkbox", id=format!("kind-{}", kind.to_string()), name="kind", value=kind.to_string(), checked?=settings.kinds.contains(&kind));
+                    label(for=format!("kind-{}", kind.to_string())): kind.to_string();
+                    br;
+                }
+            }
+            p { em: "Tags that are never synced, separated by commas."; }
+            p
+            {
+                input(type="text", name="excluded_tags", value=&settings.excluded_tags);
+            }
+
+            p
+            {
+                input(type="submit", value="Preview Sync");
+            }
+        }
+    }.into_string().unwrap()
+}
+
+fn render_plan(plan: &bulk::sync::SyncPlan) -> String
+{
+    let media_name = |id: &String| -> String
+    {
+        plan.media_item_names.get(id).cloned().unwrap_or(id.clone())
+    };
+
+    owned_html!
+    {
+        h1: "Google Photos Sync Preview";
+
+        @if plan.is_empty()
+        {
+            p: "Everything is already in sync - there are no changes to make.";
+        }
+
+        @if !plan.tag_changes.is_empty()
+        {
+            h2: format!("Tag Changes ({} items)", plan.tag_changes.len());
+            ul
+            {
+                @for change in plan.tag_changes.iter()
+                {
+                    li
+                    {
+                        a(href=pages::object_details::ObjectDetailsPage::path_for(&change.object_id)): &change.object_name;
+                        @for tag in change.add.iter()
+                        {
+                            : format!(" +{}", tag.name);
+                        }
+                        @for tag in change.remove.iter()
+                        {
+                            : format!(" -{}", tag.name);
+                        }
+                    }
+                }
+            }
+        }
+
+        @if !plan.albums_to_create.is_empty()
+        {
+            h2: format!("Albums to Create ({})", plan.albums_to_create.len());
+            ul
+            {
+                @for title in plan.albums_to_create.iter()
+                {
+                    li: title;
+                }
+            }
+        }
+
+        @if !plan.album_changes.is_empty()
+        {
+            h2: format!("Album Changes ({} albums)", plan.album_changes.len());
+            @for change in plan.album_changes.iter()
+            {
+                h3: &change.album_title;
+                ul
+                {
+                    @for id in change.add.iter()
+                    {
+                        li: format!("Add {}", media_name(id));
+                    }
+                    @for id in change.remove.iter()
+                    {
+                        li: format!("Remove {}", media_name(id));
+                    }
+                }
+            }
+        }
+
+        @if !plan.is_empty()
+        {
+            form(method="POST", action="/form/sync/confirm", enctype="application/x-www-form-urlencoded")
+            {
+                input(type="submit", value="Confirm and Sync");
+            }
+        }
+        form(method="POST", action="/form/sync/discard", enctype="application/x-www-form-urlencoded")
+        {
+            input(type="submit", value="Discard");
+        }
+    }.into_string().unwrap()
+}
+
+fn get_form_value(form: &Vec<(String, String)>, name: &str) -> String
+{
+    form.iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.clone())
+        .unwrap_or_default()
+}
+
+async fn post_sync(state: web::Data<State>, form: web::Form<Vec<(String, String)>>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    // The kind checkboxes share a name, so the form
+    // is read as a list of name/value pairs
+
+    let album_mappings_text = get_form_value(&form, "album_mappings");
+    let excluded_tags_text = get_form_value(&form, "excluded_tags");
+
+    let kinds: Vec<picvudb::data::TagKind> = form.iter()
+        .filter(|(n, _)| n == "kind")
+        .map(|(_, v)| v.parse())
+        .collect::<Result<_, _>>()?;
+
+    let filter = bulk::sync::SyncFilter
+    {
+        kinds: kinds.clone(),
+        excluded_tags: excluded_tags_text
+            .split(',')
+            .map(|s| s.trim().to_owned())
+            .filter(|s| !s.is_empty())
+            .collect(),
+    };
+
+    let pull = if !get_form_value(&form, "pull").is_empty()
+    {
+        let conflict_rule = match get_form_value(&form, "conflict_rule").as_str()
+        {
+            "AddOnly" => bulk::sync::pull::PullConflictRule::AddOnly,
+            "GooglePhotosWins" => bulk::sync::pull::PullConflictRule::GooglePhotosWins,
+            "PicvuWins" => bulk::sync::pull::PullConflictRule::PicvuWins,
+            other => return Ok(view::err(HttpResponse::BadRequest(), format!("Unknown conflict rule: {}", other))),
+        };
+
+        Some(bulk::sync::pull::PullOptions
+        {
+            conflict_rule,
+            default_kind: get_form_value(&form, "default_kind").parse()?,
+            album_mappings: bulk::sync::pull::parse_album_mappings(&album_mappings_text)
+                .map_err(picvudb::ParseError::new)?,
+        })
+    }
+    else
+    {
+        None
+    };
+
+    let options = bulk::sync::SyncOptions
+    {
+        push: !get_form_value(&form, "push").is_empty(),
+        pull,
+        filter,
+    };
+
+    // Remember the settings for next time
+
+    {
+        let mut properties = HashMap::new();
+
+        properties.insert(PROP_NAME_ALBUM_MAPPINGS.to_owned(), album_mappings_text);
+        properties.insert(PROP_NAME_EXCLUDED_TAGS.to_owned(), excluded_tags_text);
+        properties.insert(PROP_NAME_KINDS.to_owned(), kinds.iter().map(|k| k.to_string()).collect::<Vec<_>>().join(","));
+
+        let _ = state.db.send(picvudb::msgs::SetPropertiesRequest{ properties }).await??;
+    }
+
+    let access_token = pages::auth::get_access_token(&*state).await?;
+
     match access_token
     {
         Some(access_token) =>
         {
             let mut bulk_queue = state.bulk_queue.lock().unwrap();
 
-            bulk_queue.enqueue(bulk::sync::GooglePhotosSync::new(access_token, state.db_uri.clone()));
+            bulk_queue.enqueue(bulk::sync::GooglePhotosSyncPreview::new(access_token, state.db_uri.clone(), options, state.sync_plan.clone()));
 
-            Ok(view::redirect("/".to_owned()))
+            Ok(view::redirect(pages::bulk::BulkPage::progress_path()))
         },
         None =>
         {
@@ -47,3 +331,41 @@ async fn get_sync_start(state: web::Data<State>) -> Result<HttpResponse, view::E
         },
     }
 }
+
+async fn post_sync_confirm(state: web::Data<State>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let access_token = match pages::auth::get_access_token(&*state).await?
+    {
+        Some(access_token) => access_token,
+        None => return Ok(view::redirect(pages::auth::AuthPage::path())),
+    };
+
+    let plan =
+    {
+        let mut sync_plan = state.sync_plan.lock().unwrap();
+
+        sync_plan.take()
+    };
+
+    if let Some(plan) = plan
+    {
+        let mut bulk_queue = state.bulk_queue.lock().unwrap();
+
+        bulk_queue.enqueue(bulk::sync::GooglePhotosSync::new(access_token, state.db_uri.clone(), plan));
+
+        return Ok(view::redirect(pages::bulk::BulkPage::progress_path()));
+    }
+
+    Ok(view::redirect("/view/sync".to_owned()))
+}
+
+async fn post_sync_discard(state: web::Data<State>) -> HttpResponse
+{
+    {
+        let mut sync_plan = state.sync_plan.lock().unwrap();
+
+        *sync_plan = None;
+    }
+
+    view::redirect("/view/sync".to_owned())
+}
@@ -1,6 +1,6 @@
 use serde::Deserialize;
 use actix_web::{web, HttpRequest, HttpResponse};
-use horrorshow::{owned_html, Template};
+use horrorshow::{owned_html, Raw, Template};
 
 use crate::icons::{IconSize, OutlineIcon};
 use crate::pages::{HeaderLinkCollection, PageResources, PageResourcesBuilder};
@@ -16,6 +16,11 @@ pub struct TagPages
 
 impl TagPages
 {
+    pub fn tree_path() -> String
+    {
+        "/view/tags".to_owned()
+    }
+
     pub fn edit_path(tag_id: &picvudb::data::TagId) -> String
     {
         format!("/edit/tag/{}", tag_id.to_string())
@@ -32,10 +37,15 @@ impl PageResources for TagPages
     fn page_resources(builder: &mut PageResourcesBuilder)
     {
         builder
+            .add_header_link("/view/tags", "Tags", OutlineIcon::Label, 4)
+            .route_view("/view/tags", web::get().to(get_tag_tree))
             .route_view("/edit/tag/{tag_id}", web::get().to(get_edit_tag))
             .route_view("/delete/tag/{tag_id}", web::get().to(get_delete_tag))
             .route_other("/form/edit_tag/{tag_id}", web::post().to(post_edit_tag))
-            .route_other("/form/delete_tag/{tag_id}", web::post().to(post_delete_tag));
+            .route_other("/form/delete_tag/{tag_id}", web::post().to(post_delete_tag))
+            .route_other("/form/merge_tag/{tag_id}", web::post().to(post_merge_tag))
+            .route_other("/form/add_tag_alias/{tag_id}", web::post().to(post_add_tag_alias))
+            .route_other("/form/remove_tag_alias/{tag_id}", web::post().to(post_remove_tag_alias));
     }
 }
 
@@ -43,9 +53,34 @@ async fn get_edit_tag(state: web::Data<State>, tag_id: web::Path<String>, req: H
 {
     let tag_id = picvudb::data::TagId::try_new(tag_id.to_string())?;
 
-    let tag = state.db.send(picvudb::msgs::GetTagRequest{ tag_id }).await??.tag;
+    let tag = state.db.send(picvudb::msgs::GetTagRequest{ tag_id }).await??;
+    let tree = state.db.send(picvudb::msgs::GetTagTreeRequest{}).await??.roots;
+
+    Ok(render_edit_tag(tag, tree, &req, &state.header_links))
+}
+
+async fn get_tag_tree(state: web::Data<State>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let tree = state.db.send(picvudb::msgs::GetTagTreeRequest{}).await??.roots;
+
+    let contents = owned_html!
+    {
+        h1: "Tags";
+
+        @if tree.is_empty()
+        {
+            p: "There are no tags.";
+        }
+        else
+        {
+            div(class="tag-tree")
+            {
+                : render_tag_tree(&tree);
+            }
+        }
+    }.into_string().unwrap();
 
-    Ok(render_edit_tag(tag, &req, &state.header_links))
+    Ok(view::html_page(&req, &state.header_links, "Tags", OutlineIcon::Label, &contents))
 }
 
 async fn get_delete_tag(state: web::Data<State>, tag_id: web::Path<String>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
@@ -53,7 +88,7 @@ async fn get_delete_tag(state: web::Data<State>, tag_id: web::Path<String>, req:
     let tag_id = picvudb::data::TagId::try_new(tag_id.to_string())?;
 
     let tag = state.db.send(picvudb::msgs::GetTagRequest{ tag_id: tag_id.clone() }).await??.tag;
-    let num_objects = state.db.send(picvudb::msgs::GetNumObjectsRequest{ query: picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag_id.clone()} }).await??.num_objects;
+    let num_objects = state.db.send(picvudb::msgs::GetNumObjectsRequest{ query: picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag_id.clone(), include_descendants: false } }).await??.num_objects;
 
     Ok(render_delete_tag(tag, num_objects, &req, &state.header_links))
 }
@@ -65,9 +100,22 @@ struct FormEditTag
     rating: String,
     censor: String,
     kind: String,
+    parent: String,
 }
 
-async fn post_edit_tag(state: web::Data<State>, tag_id: web::Path<String>, form: web::Form<FormEditTag>) -> Result<HttpResponse, view::ErrorResponder>
+#[derive(Deserialize)]
+struct FormMergeTag
+{
+    into: String,
+}
+
+#[derive(Deserialize)]
+struct FormTagAlias
+{
+    alias: String,
+}
+
+async fn post_edit_tag(state: web::Data<State>, tag_id: web::Path<String>, form: web::Form<FormEditTag>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
 {
     let tag_id = picvudb::data::TagId::try_new(tag_id.to_string())?;
 
@@ -97,10 +145,82 @@ async fn post_edit_tag(state: web::Data<State>, tag_id: web::Path<String>, form:
             kind,
         };
 
-        let _response = state.db.send(msg).await??;
+        // Renaming a tag to the name of another one
+        // offers to merge the two tags instead
+
+        if let picvudb::msgs::UpdateTagResponse::NameConflict{ existing } = state.db.send(msg).await??
+        {
+            return Ok(render_merge_tag(tag, existing, &req, &state.header_links));
+        }
+    }
+
+    let parent_id = if form.parent.is_empty() { None } else { Some(picvudb::data::TagId::try_new(form.parent.clone())?) };
+
+    if parent_id != tag.parent_id
+    {
+        let msg = picvudb::msgs::SetTagParentRequest
+        {
+            tag_id: tag_id.clone(),
+            parent_id,
+        };
+
+        if let picvudb::msgs::SetTagParentResponse::WouldCreateCycle = state.db.send(msg).await??
+        {
+            return Ok(view::err(HttpResponse::BadRequest(), "A tag can't be placed under itself or one of its sub-tags"));
+        }
+    }
+
+    Ok(view::redirect(pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id, include_descendants: false })))
+}
+
+async fn post_merge_tag(state: web::Data<State>, tag_id: web::Path<String>, form: web::Form<FormMergeTag>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let from = picvudb::data::TagId::try_new(tag_id.to_string())?;
+    let into = picvudb::data::TagId::try_new(form.into.clone())?;
+
+    let msg = picvudb::msgs::MergeTagsRequest{ from, into: into.clone() };
+
+    match state.db.send(msg).await??
+    {
+        picvudb::msgs::MergeTagsResponse::SameTag =>
+        {
+            Ok(view::err(HttpResponse::BadRequest(), "A tag can't be merged into itself"))
+        },
+        picvudb::msgs::MergeTagsResponse::Merged =>
+        {
+            Ok(view::redirect(pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: into, include_descendants: false })))
+        },
+    }
+}
+
+async fn post_add_tag_alias(state: web::Data<State>, tag_id: web::Path<String>, form: web::Form<FormTagAlias>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let tag_id = picvudb::data::TagId::try_new(tag_id.to_string())?;
+
+    if !form.alias.trim().is_empty()
+    {
+        let msg = picvudb::msgs::AddTagAliasRequest
+        {
+            tag_id: tag_id.clone(),
+            alias: form.alias.clone(),
+        };
+
+        if let picvudb::msgs::AddTagAliasResponse::NameConflict{ existing } = state.db.send(msg).await??
+        {
+            return Ok(view::err(HttpResponse::BadRequest(), format!("The alias {} already belongs to the tag {} - merge the tags instead", form.alias.trim(), existing.name)));
+        }
     }
 
-    Ok(view::redirect(pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id })))
+    Ok(view::redirect(TagPages::edit_path(&tag_id)))
+}
+
+async fn post_remove_tag_alias(state: web::Data<State>, tag_id: web::Path<String>, form: web::Form<FormTagAlias>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let tag_id = picvudb::data::TagId::try_new(tag_id.to_string())?;
+
+    let _ = state.db.send(picvudb::msgs::RemoveTagAliasRequest{ alias: form.alias.clone() }).await??;
+
+    Ok(view::redirect(TagPages::edit_path(&tag_id)))
 }
 
 async fn post_delete_tag(state: web::Data<State>, tag_id: web::Path<String>) -> Result<HttpResponse, view::ErrorResponder>
@@ -114,10 +234,19 @@ async fn post_delete_tag(state: web::Data<State>, tag_id: web::Path<String>) ->
     Ok(view::redirect("/".to_owned()))
 }
 
-fn render_edit_tag(tag: picvudb::data::get::TagMetadata, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
+fn render_edit_tag(tag_resp: picvudb::msgs::GetTagResponse, tree: Vec<picvudb::data::get::TagTreeNode>, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
 {
+    let tag = tag_resp.tag;
+    let aliases = tag_resp.aliases;
+
     let title = format!("Edit Tag {}", tag.name);
 
+    let mut parent_choices = Vec::new();
+    add_tag_choices(&tree, &tag.tag_id, false, 0, &mut parent_choices);
+
+    let mut merge_choices = Vec::new();
+    add_tag_choices(&tree, &tag.tag_id, true, 0, &mut merge_choices);
+
     let contents = owned_html!
     {
         script(src="/assets/picvu.js");
@@ -132,7 +261,7 @@ fn render_edit_tag(tag: picvudb::data::get::TagMetadata, req: &HttpRequest, head
                     : OutlineIcon::Save.render(IconSize::Size16x16);
                     : " Save"
                 }
-                a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone(), }), class="cmdbar-link")
+                a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: tag.tag_id.clone(), include_descendants: false }), class="cmdbar-link")
                 {
                     : OutlineIcon::Cancel.render(IconSize::Size16x16);
                     : " Cancel"
@@ -151,6 +280,112 @@ fn render_edit_tag(tag: picvudb::data::get::TagMetadata, req: &HttpRequest, head
             : pages::templates::tag_kind::render("kind", &tag.kind);
             : pages::templates::rating::render("rating", &tag.rating);
             : pages::templates::censor::render("censor", &tag.censor);
+
+            label(for="parent")
+            {
+                : "Parent";
+            }
+            select(id="edit-parent", name="parent")
+            {
+                option(value="", selected?=(tag.parent_id.is_none()))
+                {
+                    : "(None)";
+                }
+                @for (tag_id, label) in parent_choices.iter()
+                {
+                    option(value=tag_id.to_string(), selected?=(tag.parent_id.as_ref() == Some(tag_id)))
+                    {
+                        : label;
+                    }
+                }
+            }
+        }
+
+        h2: "Aliases";
+
+        p
+        {
+            em: "Searching for or adding an alias finds this tag.";
+        }
+
+        @if !aliases.is_empty()
+        {
+            table(class="details-table")
+            {
+                @for alias in aliases.iter()
+                {
+                    tr
+                    {
+                        td: alias;
+                        td
+                        {
+                            form(method="POST", action=format!("/form/remove_tag_alias/{}", tag.tag_id.to_string()), enctype="application/x-www-form-urlencoded")
+                            {
+                                input(type="hidden", name="alias", value=alias);
+                                input(type="submit", value="Remove");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        form(method="POST", action=format!("/form/add_tag_alias/{}", tag.tag_id.to_string()), enctype="application/x-www-form-urlencoded")
+        {
+            input(type="text", name="alias");
+            : " ";
+            input(type="submit", value="Add Alias");
+        }
+
+        @if !merge_choices.is_empty()
+        {
+            h2: "Merge";
+
+            form(method="POST", action=format!("/form/merge_tag/{}", tag.tag_id.to_string()), enctype="application/x-www-form-urlencoded")
+            {
+                p
+                {
+                    em: "Moves every object with this tag to another tag, and then deletes this tag. Its name is kept as an alias.";
+                }
+
+                select(name="into")
+                {
+                    @for (tag_id, label) in merge_choices.iter()
+                    {
+                        option(value=tag_id.to_string()): label;
+                    }
+                }
+                : " ";
+                input(type="submit", value="Merge");
+            }
+        }
+    }.into_string().unwrap();
+
+    view::html_page(req, header_links, title, OutlineIcon::Edit, &contents)
+}
+
+fn render_merge_tag(tag: picvudb::data::get::TagMetadata, existing: picvudb::data::get::TagMetadata, req: &HttpRequest, header_links: &HeaderLinkCollection) -> HttpResponse
+{
+    let title = format!("Merge Tag {}", tag.name);
+
+    let contents = owned_html!
+    {
+        form(method="POST", action=format!("/form/merge_tag/{}", tag.tag_id.to_string()), enctype="application/x-www-form-urlencoded")
+        {
+            p
+            {
+                : format!("There is already a tag named \"{}\". Do you want to merge \"{}\" into it?", existing.name, tag.name);
+            }
+
+            p
+            {
+                : format!("Every object tagged \"{}\" will be tagged \"{}\" instead, and \"{}\" will be kept as an alias.", tag.name, existing.name, tag.name);
+            }
+
+            input(type="hidden", name="into", value=existing.tag_id.to_string());
+            input(type="submit", value="Merge");
+            : " ";
+            a(href=TagPages::edit_path(&tag.tag_id)): "Cancel";
         }
     }.into_string().unwrap();
 
@@ -188,3 +423,55 @@ fn render_delete_tag(tag: picvudb::data::get::TagMetadata, num_objects: u64, req
 
     view::html_page(req, header_links, title, OutlineIcon::Trash2, &contents)
 }
+
+fn render_tag_tree(nodes: &Vec<picvudb::data::get::TagTreeNode>) -> Raw<String>
+{
+    Raw(owned_html!
+    {
+        ul
+        {
+            @for node in nodes.iter()
+            {
+                li
+                {
+                    a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: node.tag.tag_id.clone(), include_descendants: false }))
+                    {
+                        : &node.tag.name;
+                    }
+
+                    @if !node.children.is_empty()
+                    {
+                        : " (";
+                        a(href=pages::object_listing::ObjectListingPage::path(picvudb::data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: node.tag.tag_id.clone(), include_descendants: true }))
+                        {
+                            : "with sub-tags";
+                        }
+                        : ")";
+
+                        : render_tag_tree(&node.children);
+                    }
+                }
+            }
+        }
+    }.into_string().unwrap())
+}
+
+/// Lists the other tags, indented to show the tree. The
+/// tag's own sub-tags can be left out - they can't be its
+/// parent, as that would form a cycle.
+fn add_tag_choices(nodes: &Vec<picvudb::data::get::TagTreeNode>, tag_id: &picvudb::data::TagId, include_sub_tags: bool, depth: usize, result: &mut Vec<(picvudb::data::TagId, String)>)
+{
+    for node in nodes.iter()
+    {
+        if node.tag.tag_id != *tag_id
+        {
+            result.push((node.tag.tag_id.clone(), format!("{}{}", "\u{a0}\u{a0}".repeat(depth), node.tag.name)));
+
+            add_tag_choices(&node.children, tag_id, include_sub_tags, depth + 1, result);
+        }
+        else if include_sub_tags
+        {
+            add_tag_choices(&node.children, tag_id, include_sub_tags, depth + 1, result);
+        }
+    }
+}
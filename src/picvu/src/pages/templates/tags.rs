@@ -9,6 +9,7 @@ pub fn render_existing(tag: &picvudb::data::get::TagMetadata) -> Raw<String>
         kind: tag.kind.clone(),
         rating: tag.rating.clone(),
         censor: tag.censor.clone(),
+        parent: None,
     })
 }
 
@@ -6,7 +6,7 @@ pub fn render(object: &picvudb::data::get::ObjectMetadata, href: String, selecte
 {
     let icons_style = |o: &picvudb::data::get::ObjectMetadata|
     {
-        let dimensions = o.attachment.dimensions.clone().map(|d| d.resize_to_max_dimension(128));
+        let dimensions = pages::attachments::AttachmentsPage::thumbnail_dimensions(o, 128);
         let width = dimensions.clone().map_or("100%".to_owned(), |d| format!("{}px", d.width));
         let height = dimensions.map_or("100%".to_owned(), |d| format!("{}px", d.height));
 
@@ -51,7 +51,7 @@ pub fn render(object: &picvudb::data::get::ObjectMetadata, href: String, selecte
                                 picvudb::data::Censor::Explicit => ColoredIcon::EvilGrin.render(IconSize::Size16x16),
                             });
 
-                        @if let Some(duration) = &object.attachment.duration
+                        @if let Some(duration) = object.attachment.as_ref().and_then(|a| a.duration.clone())
                         {
                             : ColoredIcon::Play.render(IconSize::Size16x16);
                             : " ";
@@ -71,7 +71,7 @@ pub fn render(object: &picvudb::data::get::ObjectMetadata, href: String, selecte
                 }
                 else
                 {
-                    : render_with_zero_width_spaces(vec![ pulldown_cmark::Event::Text(pulldown_cmark::CowStr::Borrowed(&object.attachment.filename))].drain(..));
+                    : render_with_zero_width_spaces(vec![ pulldown_cmark::Event::Text(pulldown_cmark::CowStr::Owned(object.get_display_text()))].drain(..));
                 }
             }
         }
@@ -0,0 +1,256 @@
+use serde::Deserialize;
+use actix_web::{web, HttpRequest, HttpResponse};
+use horrorshow::{owned_html, Template};
+
+use crate::analyse::clock::TimeShift;
+use crate::analyse::tz::ExplicitTimezone;
+use crate::bulk;
+use crate::bulk::time_shift::ObjectSelection;
+use crate::format;
+use crate::icons::OutlineIcon;
+use crate::pages::{PageResources, PageResourcesBuilder};
+use crate::view;
+use crate::pages;
+use crate::State;
+
+const NUM_PREVIEW_OBJECTS: u64 = 20;
+
+#[allow(dead_code)]
+pub struct TimeShiftPage
+{
+}
+
+impl TimeShiftPage
+{
+    pub fn path_for_query(query: picvudb::data::get::GetObjectsQuery) -> String
+    {
+        format!("/view/time_shift?objects={}", urlencoding::encode(&pages::object_listing::ObjectListingPage::path(query)))
+    }
+
+    pub fn path_for_object(object_id: &picvudb::data::ObjectId) -> String
+    {
+        format!("/view/time_shift?object_ids={}", object_id.to_string())
+    }
+}
+
+impl PageResources for TimeShiftPage
+{
+    fn page_resources(builder: &mut PageResourcesBuilder)
+    {
+        builder
+            .route_view("/view/time_shift", web::get().to(get_time_shift))
+            .route_other("/form/time_shift", web::post().to(post_time_shift));
+    }
+}
+
+#[derive(Deserialize)]
+struct TimeShiftForm
+{
+    objects: Option<String>,
+    object_ids: Option<String>,
+    offset: Option<String>,
+    timezone: Option<String>,
+}
+
+impl TimeShiftForm
+{
+    fn text(val: &Option<String>) -> String
+    {
+        val.clone().unwrap_or_default().trim().to_owned()
+    }
+
+    fn selection(&self) -> Result<ObjectSelection, picvudb::ParseError>
+    {
+        let object_ids = Self::text(&self.object_ids);
+
+        if !object_ids.is_empty()
+        {
+            let object_ids = object_ids
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|s| !s.is_empty())
+                .map(|s| picvudb::data::ObjectId::try_new(s.to_owned()))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            return Ok(ObjectSelection::ObjectIds(object_ids));
+        }
+
+        let objects = Self::text(&self.objects);
+
+        if !objects.is_empty()
+        {
+            let query = pages::object_listing::ObjectListingPage::query_from_path(&objects)
+                .ok_or_else(|| picvudb::ParseError::new(format!("Invalid object listing {:?}", objects)))?;
+
+            return Ok(ObjectSelection::Query(query));
+        }
+
+        Err(picvudb::ParseError::new("No objects have been selected"))
+    }
+
+    fn shift(&self) -> Result<TimeShift, picvudb::ParseError>
+    {
+        let offset = Self::text(&self.offset);
+        let timezone = Self::text(&self.timezone);
+
+        let offset = if offset.is_empty()
+        {
+            chrono::Duration::zero()
+        }
+        else
+        {
+            crate::analyse::clock::parse_offset(&offset)
+                .ok_or_else(|| picvudb::ParseError::new(format!("Invalid offset {:?}", offset)))?
+        };
+
+        let timezone = if timezone.is_empty()
+        {
+            None
+        }
+        else
+        {
+            Some(timezone.parse::<ExplicitTimezone>()
+                .map_err(|_| picvudb::ParseError::new(format!("Invalid timezone {:?}", timezone)))?)
+        };
+
+        Ok(TimeShift{ offset, timezone })
+    }
+}
+
+async fn get_time_shift(state: web::Data<State>, form: web::Query<TimeShiftForm>, req: HttpRequest) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let selection = form.selection()?;
+    let shift = form.shift()?;
+
+    // Load the first few objects to preview the change
+
+    let mut preview = Vec::new();
+    let total;
+
+    match &selection
+    {
+        ObjectSelection::Query(query) =>
+        {
+            let msg = picvudb::msgs::GetObjectsRequest
+            {
+                query: query.clone(),
+                pagination: Some(picvudb::data::get::PaginationRequest{ offset: 0, page_size: NUM_PREVIEW_OBJECTS }),
+            };
+
+            let response = state.db.send(msg).await??;
+
+            total = response.pagination_response.total;
+            preview = response.objects;
+        },
+        ObjectSelection::ObjectIds(object_ids) =>
+        {
+            total = object_ids.len() as u64;
+
+            for query in selection.queries().into_iter().take(NUM_PREVIEW_OBJECTS as usize)
+            {
+                let msg = picvudb::msgs::GetObjectsRequest
+                {
+                    query,
+                    pagination: None,
+                };
+
+                preview.extend(state.db.send(msg).await??.objects);
+            }
+        },
+    }
+
+    let now = picvudb::data::Date::now();
+    let objects = TimeShiftForm::text(&form.objects);
+    let object_ids = TimeShiftForm::text(&form.object_ids);
+    let offset = TimeShiftForm::text(&form.offset);
+    let timezone = TimeShiftForm::text(&form.timezone);
+
+    let contents = owned_html!
+    {
+        h1: "Shift Activity Times";
+
+        form(method="GET", action="/view/time_shift")
+        {
+            input(type="hidden", name="objects", value=&objects);
+            input(type="hidden", name="object_ids", value=&object_ids);
+
+            h2: "Offset";
+            p { em: "The amount to add to each activity time, as \"+HH:MM\" or \"-HH:MM:SS\". Leave empty to not shift the times."; }
+            p
+            {
+                input(type="text", name="offset", value=&offset);
+            }
+
+            h2: "Timezone";
+            p { em: "The timezone (e.g. \"+10:00\") to display the shifted times in. Leave empty to keep each object's timezone."; }
+            p
+            {
+                input(type="text", name="timezone", value=&timezone);
+            }
+
+            p
+            {
+                input(type="submit", value="Preview");
+            }
+        }
+
+        h2: format!("Preview ({} of {} objects)", preview.len(), total);
+
+        table(class="details-table")
+        {
+            tr
+            {
+                th: "Object";
+                th: "Before";
+                th: "After";
+            }
+
+            @for object in preview.iter()
+            {
+                tr
+                {
+                    td
+                    {
+                        a(href=pages::object_details::ObjectDetailsPage::path_for(&object.id))
+                        {
+                            : object.get_display_text();
+                        }
+                    }
+                    td: format::date_to_str(&object.activity_time, &now);
+                    td: format::date_to_str(&shift.apply(&object.activity_time), &now);
+                }
+            }
+        }
+
+        @if !shift.is_noop() && total != 0
+        {
+            form(method="POST", action="/form/time_shift", enctype="application/x-www-form-urlencoded")
+            {
+                input(type="hidden", name="objects", value=&objects);
+                input(type="hidden", name="object_ids", value=&object_ids);
+                input(type="hidden", name="offset", value=&offset);
+                input(type="hidden", name="timezone", value=&timezone);
+
+                p
+                {
+                    input(type="submit", value=format!("Shift {} objects", total));
+                }
+            }
+        }
+    }.into_string().unwrap();
+
+    Ok(view::html_page(&req, &state.header_links, "Shift Activity Times", OutlineIcon::Clock, &contents))
+}
+
+async fn post_time_shift(state: web::Data<State>, form: web::Form<TimeShiftForm>) -> Result<HttpResponse, view::ErrorResponder>
+{
+    let selection = form.selection()?;
+    let shift = form.shift()?;
+
+    {
+        let mut bulk_queue = state.bulk_queue.lock().unwrap();
+
+        bulk_queue.enqueue(bulk::time_shift::TimeShiftBulkOp::new(state.db_uri.clone(), selection, shift));
+    }
+
+    Ok(view::redirect(pages::bulk::BulkPage::progress_path()))
+}
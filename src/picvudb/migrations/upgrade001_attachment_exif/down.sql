@@ -0,0 +1 @@
+DROP TABLE attachment_exif
\ No newline at end of file
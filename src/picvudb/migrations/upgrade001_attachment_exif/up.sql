@@ -0,0 +1,21 @@
+CREATE TABLE attachment_exif (
+  obj_id INTEGER PRIMARY KEY,
+  make TEXT,
+  model TEXT,
+  exposure_time TEXT,
+  aperture REAL,
+  focal_length REAL,
+  iso INTEGER,
+  gps_dop REAL,
+  entries TEXT NOT NULL,
+  xmp TEXT
+);
+
+CREATE INDEX attachment_exif_by_camera
+  ON attachment_exif(make, model, obj_id);
+
+CREATE INDEX attachment_exif_by_iso
+  ON attachment_exif(iso, obj_id);
+
+CREATE INDEX attachment_exif_by_focal_length
+  ON attachment_exif(focal_length, obj_id);
@@ -0,0 +1,4 @@
+ALTER TABLE objects ADD COLUMN activity_nanos INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX objects_by_activity_timestamp_nanos
+  ON objects(activity_timestamp, activity_nanos, id);
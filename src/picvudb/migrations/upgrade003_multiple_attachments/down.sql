@@ -0,0 +1,59 @@
+-- Only the primary attachments can be kept
+
+CREATE TABLE attachments_metadata_old (
+  obj_id INTEGER PRIMARY KEY,
+  filename TEXT NOT NULL,
+  created_timestamp INTEGER NOT NULL,
+  created_offset INTEGER,
+  modified_timestamp INTEGER NOT NULL,
+  modified_offset INTEGER,
+  mime TEXT NOT NULL,
+  size INTEGER NOT NULL,
+  orientation INTEGER,
+  width INTEGER,
+  height INTEGER,
+  duration INTEGER,
+  hash TEXT NOT NULL
+);
+
+INSERT INTO attachments_metadata_old
+  (obj_id, filename, created_timestamp, created_offset, modified_timestamp, modified_offset, mime, size, orientation, width, height, duration, hash)
+  SELECT obj_id, filename, created_timestamp, created_offset, modified_timestamp, modified_offset, mime, size, orientation, width, height, duration, hash
+  FROM attachments_metadata
+  WHERE att_index = 0;
+
+DROP INDEX attachments_metadata_by_filename;
+DROP INDEX attachments_metadata_by_size;
+DROP INDEX attachments_metadata_by_hash;
+DROP TABLE attachments_metadata;
+
+ALTER TABLE attachments_metadata_old RENAME TO attachments_metadata;
+
+CREATE INDEX attachments_metadata_by_filename
+  ON attachments_metadata(filename, obj_id);
+
+CREATE INDEX attachments_metadata_by_size
+  ON attachments_metadata(size, obj_id);
+
+CREATE INDEX attachments_metadata_by_hash
+  ON attachments_metadata(hash, obj_id);
+
+CREATE TABLE attachments_data_old (
+  obj_id INTEGER NOT NULL,
+  offset BIGINT NOT NULL,
+  bytes BLOB NOT NULL,
+  UNIQUE(obj_id, offset)
+);
+
+INSERT INTO attachments_data_old
+  (obj_id, offset, bytes)
+  SELECT obj_id, offset, bytes
+  FROM attachments_data
+  WHERE att_index = 0;
+
+DROP TABLE attachments_data;
+
+ALTER TABLE attachments_data_old RENAME TO attachments_data;
+
+CREATE INDEX attachments_data_by_obj_id_offset
+  ON attachments_data(obj_id, offset);
@@ -0,0 +1,61 @@
+-- Objects can now have an ordered list of attachments,
+-- so they're keyed by (obj_id, att_index). The existing
+-- attachments become the primary attachment at index 0.
+
+CREATE TABLE attachments_metadata_new (
+  obj_id INTEGER NOT NULL,
+  att_index INTEGER NOT NULL,
+  role INTEGER NOT NULL,
+  filename TEXT NOT NULL,
+  created_timestamp INTEGER NOT NULL,
+  created_offset INTEGER,
+  modified_timestamp INTEGER NOT NULL,
+  modified_offset INTEGER,
+  mime TEXT NOT NULL,
+  size INTEGER NOT NULL,
+  orientation INTEGER,
+  width INTEGER,
+  height INTEGER,
+  duration INTEGER,
+  hash TEXT NOT NULL,
+  PRIMARY KEY(obj_id, att_index)
+);
+
+INSERT INTO attachments_metadata_new
+  (obj_id, att_index, role, filename, created_timestamp, created_offset, modified_timestamp, modified_offset, mime, size, orientation, width, height, duration, hash)
+  SELECT obj_id, 0, 0, filename, created_timestamp, created_offset, modified_timestamp, modified_offset, mime, size, orientation, width, height, duration, hash
+  FROM attachments_metadata;
+
+DROP INDEX attachments_metadata_by_filename;
+DROP INDEX attachments_metadata_by_size;
+DROP INDEX attachments_metadata_by_hash;
+DROP TABLE attachments_metadata;
+
+ALTER TABLE attachments_metadata_new RENAME TO attachments_metadata;
+
+CREATE INDEX attachments_metadata_by_filename
+  ON attachments_metadata(filename, obj_id);
+
+CREATE INDEX attachments_metadata_by_size
+  ON attachments_metadata(att_index, size, obj_id);
+
+CREATE INDEX attachments_metadata_by_hash
+  ON attachments_metadata(hash, obj_id);
+
+CREATE TABLE attachments_data_new (
+  obj_id INTEGER NOT NULL,
+  att_index INTEGER NOT NULL,
+  offset BIGINT NOT NULL,
+  bytes BLOB NOT NULL,
+  UNIQUE(obj_id, att_index, offset)
+);
+
+INSERT INTO attachments_data_new
+  (obj_id, att_index, offset, bytes)
+  SELECT obj_id, 0, offset, bytes
+  FROM attachments_data;
+
+DROP INDEX attachments_data_by_obj_id_offset;
+DROP TABLE attachments_data;
+
+ALTER TABLE attachments_data_new RENAME TO attachments_data;
@@ -0,0 +1,50 @@
+DELETE FROM attachments_data
+  WHERE EXISTS (
+    SELECT 1 FROM attachments_metadata
+    WHERE attachments_metadata.obj_id = attachments_data.obj_id
+      AND attachments_metadata.att_index = attachments_data.att_index
+      AND attachments_metadata.version_of IS NOT NULL);
+
+DELETE FROM attachments_metadata WHERE version_of IS NOT NULL;
+
+-- Then re-create the table without version_of
+
+CREATE TABLE attachments_metadata_old (
+  obj_id INTEGER NOT NULL,
+  att_index INTEGER NOT NULL,
+  role INTEGER NOT NULL,
+  filename TEXT NOT NULL,
+  created_timestamp INTEGER NOT NULL,
+  created_offset INTEGER,
+  modified_timestamp INTEGER NOT NULL,
+  modified_offset INTEGER,
+  mime TEXT NOT NULL,
+  size INTEGER NOT NULL,
+  orientation INTEGER,
+  width INTEGER,
+  height INTEGER,
+  duration INTEGER,
+  hash TEXT NOT NULL,
+  PRIMARY KEY(obj_id, att_index)
+);
+
+INSERT INTO attachments_metadata_old
+  (obj_id, att_index, role, filename, created_timestamp, created_offset, modified_timestamp, modified_offset, mime, size, orientation, width, height, duration, hash)
+  SELECT obj_id, att_index, role, filename, created_timestamp, created_offset, modified_timestamp, modified_offset, mime, size, orientation, width, height, duration, hash
+  FROM attachments_metadata;
+
+DROP INDEX attachments_metadata_by_filename;
+DROP INDEX attachments_metadata_by_size;
+DROP INDEX attachments_metadata_by_hash;
+DROP TABLE attachments_metadata;
+
+ALTER TABLE attachments_metadata_old RENAME TO attachments_metadata;
+
+CREATE INDEX attachments_metadata_by_filename
+  ON attachments_metadata(filename, obj_id);
+
+CREATE INDEX attachments_metadata_by_size
+  ON attachments_metadata(att_index, size, obj_id);
+
+CREATE INDEX attachments_metadata_by_hash
+  ON attachments_metadata(hash, obj_id);
\ No newline at end of file
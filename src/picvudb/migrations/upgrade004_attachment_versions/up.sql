@@ -0,0 +1,5 @@
+-- Previous versions of replaced attachments are kept, moved
+-- to a new index, with the index of the attachment they
+-- were a version of
+
+ALTER TABLE attachments_metadata ADD COLUMN version_of INTEGER;
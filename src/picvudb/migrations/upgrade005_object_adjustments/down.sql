@@ -0,0 +1,46 @@
+-- Rebuilds objects without the adjustment columns,
+-- copying the ids so objects_fts and objects_location
+-- still line up
+
+CREATE TABLE objects_old (
+  id INTEGER PRIMARY KEY,
+  created_timestamp INTEGER NOT NULL,
+  created_offset INTEGER,
+  modified_timestamp INTEGER NOT NULL,
+  modified_offset INTEGER,
+  activity_timestamp INTEGER NOT NULL,
+  activity_offset INTEGER,
+  title TEXT,
+  notes TEXT,
+  rating INTEGER,
+  censor INTEGER NOT NULL,
+  location_source INTEGER,
+  latitude REAL,
+  longitude REAL,
+  altitude REAL,
+  tag_set TEXT,
+  ext_ref_type TEXT,
+  ext_ref_id TEXT,
+  activity_nanos INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO objects_old
+  (id, created_timestamp, created_offset, modified_timestamp, modified_offset, activity_timestamp, activity_offset, title, notes, rating, censor, location_source, latitude, longitude, altitude, tag_set, ext_ref_type, ext_ref_id, activity_nanos)
+  SELECT id, created_timestamp, created_offset, modified_timestamp, modified_offset, activity_timestamp, activity_offset, title, notes, rating, censor, location_source, latitude, longitude, altitude, tag_set, ext_ref_type, ext_ref_id, activity_nanos
+  FROM objects;
+
+DROP INDEX objects_by_activity_timestamp_nanos;
+DROP INDEX objects_by_modified_timestamp;
+DROP INDEX objects_by_activity_timestamp;
+DROP TABLE objects;
+
+ALTER TABLE objects_old RENAME TO objects;
+
+CREATE INDEX objects_by_modified_timestamp
+  ON objects(modified_timestamp, id);
+
+CREATE INDEX objects_by_activity_timestamp
+  ON objects(activity_timestamp, id);
+
+CREATE INDEX objects_by_activity_timestamp_nanos
+  ON objects(activity_timestamp, activity_nanos, id);
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- A user orientation overrides the EXIF orientation,
+-- and the crop is a rectangle expressed as fractions
+-- of the image after orientation has been applied
+
+ALTER TABLE objects ADD COLUMN user_orientation INTEGER;
+ALTER TABLE objects ADD COLUMN crop_left REAL;
+ALTER TABLE objects ADD COLUMN crop_top REAL;
+ALTER TABLE objects ADD COLUMN crop_width REAL;
+ALTER TABLE objects ADD COLUMN crop_height REAL;
@@ -0,0 +1,16 @@
+CREATE VIRTUAL TABLE objects_fts_old USING fts5(
+  id,
+  title,
+  notes,
+  tokenize = 'porter unicode61',
+  prefix = 3);
+
+INSERT INTO objects_fts_old(rowid, title, notes)
+  SELECT rowid, title, notes FROM objects_fts
+  WHERE title IS NOT NULL OR notes IS NOT NULL;
+
+ALTER TABLE objects_fts RENAME TO objects_fts_prev;
+
+ALTER TABLE objects_fts_old RENAME TO objects_fts;
+
+DROP TABLE objects_fts_prev;
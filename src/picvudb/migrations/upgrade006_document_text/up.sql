@@ -0,0 +1,24 @@
+-- Text extracted from documents (such as PDFs) is
+-- searchable alongside the title and notes. FTS5 tables
+-- can't have columns added, so the table is re-created
+
+CREATE VIRTUAL TABLE objects_fts_new USING fts5(
+  id,
+  title,
+  notes,
+  content,
+  tokenize = 'porter unicode61',
+  prefix = 3);
+
+INSERT INTO objects_fts_new(rowid, title, notes)
+  SELECT rowid, title, notes FROM objects_fts;
+
+-- The old table is renamed out of the way before it's
+-- dropped, as SQLite 3.17 reports "database table is locked"
+-- when dropping an FTS5 table read earlier in the transaction
+
+ALTER TABLE objects_fts RENAME TO objects_fts_prev;
+
+ALTER TABLE objects_fts_new RENAME TO objects_fts;
+
+DROP TABLE objects_fts_prev;
@@ -0,0 +1 @@
+DROP TABLE recipe_ingredients
\ No newline at end of file
@@ -0,0 +1,12 @@
+-- The ingredients of objects whose notes are
+-- a recipe, so they can be found by ingredient.
+-- Existing recipes are indexed by the recipe backfill
+
+CREATE TABLE recipe_ingredients (
+  obj_id INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  PRIMARY KEY(obj_id, name)
+);
+
+CREATE INDEX recipe_ingredients_by_name
+  ON recipe_ingredients(name, obj_id);
@@ -0,0 +1 @@
+DROP TABLE object_links
\ No newline at end of file
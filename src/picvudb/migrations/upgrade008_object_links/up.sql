@@ -0,0 +1,12 @@
+-- Wiki links from an object's notes to other
+-- objects, so each object can list what links to it.
+-- Existing notes are indexed when next edited
+
+CREATE TABLE object_links (
+  obj_id INTEGER NOT NULL,
+  linked_obj_id INTEGER NOT NULL,
+  PRIMARY KEY(obj_id, linked_obj_id)
+);
+
+CREATE INDEX object_links_by_linked
+  ON object_links(linked_obj_id, obj_id);
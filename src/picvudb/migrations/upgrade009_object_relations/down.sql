@@ -0,0 +1 @@
+DROP TABLE object_relations
\ No newline at end of file
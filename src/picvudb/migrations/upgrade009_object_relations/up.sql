@@ -0,0 +1,12 @@
+-- Typed, directional relations between objects, such
+-- as a before and after pair, or a scan of a print
+
+CREATE TABLE object_relations (
+  from_obj_id INTEGER NOT NULL,
+  to_obj_id INTEGER NOT NULL,
+  relation_kind INTEGER NOT NULL,
+  PRIMARY KEY(from_obj_id, to_obj_id, relation_kind)
+);
+
+CREATE INDEX object_relations_by_to
+  ON object_relations(to_obj_id, from_obj_id);
@@ -0,0 +1,25 @@
+-- Re-creates tags without tag_parent_id, as the
+-- bundled SQLite is too old for DROP COLUMN
+
+CREATE TABLE tags_old
+(
+  tag_id INTEGER PRIMARY KEY,
+  tag_name TEXT NOT NULL UNIQUE,
+  tag_kind INTEGER NOT NULL,
+  tag_rating INTEGER,
+  tag_censor INTEGER NOT NULL
+);
+
+INSERT INTO tags_old
+  (tag_id, tag_name, tag_kind, tag_rating, tag_censor)
+  SELECT tag_id, tag_name, tag_kind, tag_rating, tag_censor
+  FROM tags;
+
+DROP INDEX tags_by_parent;
+DROP INDEX tags_by_name;
+DROP TABLE tags;
+
+ALTER TABLE tags_old RENAME TO tags;
+
+CREATE INDEX tags_by_name
+  ON tags(tag_name);
\ No newline at end of file
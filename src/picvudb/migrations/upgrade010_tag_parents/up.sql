@@ -0,0 +1,7 @@
+-- Tags can optionally have a parent tag, so they form
+-- a hierarchy such as Australia > Victoria > Melbourne
+
+ALTER TABLE tags ADD COLUMN tag_parent_id INTEGER NULL;
+
+CREATE INDEX tags_by_parent
+  ON tags(tag_parent_id);
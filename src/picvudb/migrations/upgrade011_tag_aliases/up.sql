@@ -0,0 +1,11 @@
+-- Other names for a tag. Searching for or adding
+-- an alias finds the tag it belongs to. The aliases
+-- are also added to the tag's full-text search entry.
+
+CREATE TABLE tag_aliases (
+  alias_name TEXT NOT NULL PRIMARY KEY,
+  tag_id INTEGER NOT NULL
+);
+
+CREATE INDEX tag_aliases_by_tag
+  ON tag_aliases(tag_id, alias_name);
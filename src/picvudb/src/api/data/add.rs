@@ -1,4 +1,4 @@
-use crate::api::data::{Censor, Date, Dimensions, Duration, ExternalReference, Location, NotesMarkdown, Orientation, Rating, TagKind, TitleMarkdown};
+use crate::api::data::{AttachmentExif, AttachmentRole, Censor, Crop, Date, Dimensions, Duration, ExternalReference, Location, NotesMarkdown, Orientation, Rating, TagKind, TitleMarkdown};
 
 #[derive(Debug)]
 pub struct Attachment
@@ -10,6 +10,9 @@ pub struct Attachment
     pub orientation: Option<Orientation>,
     pub dimensions: Option<Dimensions>,
     pub duration: Option<Duration>,
+    pub exif: Option<AttachmentExif>,
+    pub text: Option<String>,
+    pub role: AttachmentRole,
     pub bytes: Vec<u8>,
 }
 
@@ -20,6 +23,9 @@ pub struct Tag
     pub kind: TagKind,
     pub rating: Rating,
     pub censor: Censor,
+    /// The name of the parent tag. It's only used if
+    /// the tag doesn't already have a parent.
+    pub parent: Option<String>,
 }
 
 #[derive(Debug)]
@@ -33,7 +39,10 @@ pub struct ObjectData
     pub modified_time: Option<Date>,
     pub activity_time: Option<Date>,
     pub location: Option<Location>,
-    pub attachment: Attachment,
+    pub attachment: Option<Attachment>,
+    pub additional_attachments: Vec<Attachment>,
     pub tags: Vec<Tag>,
     pub ext_ref: Option<ExternalReference>,
+    pub user_orientation: Option<Orientation>,
+    pub crop: Option<Crop>,
 }
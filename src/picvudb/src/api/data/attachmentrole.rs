@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use crate::Error;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum AttachmentRole
+{
+    Primary,
+    Original,
+    Raw,
+    Motion,
+    Sidecar,
+    Edited,
+}
+
+impl AttachmentRole
+{
+    pub(crate) fn to_db_field(&self) -> i32
+    {
+        match self
+        {
+            Self::Primary => 0,
+            Self::Original => 1,
+            Self::Raw => 2,
+            Self::Motion => 3,
+            Self::Sidecar => 4,
+            Self::Edited => 5,
+        }
+    }
+
+    pub(crate) fn from_db_field(value: i32) -> Result<Self, Error>
+    {
+        match value
+        {
+            0 => Ok(Self::Primary),
+            1 => Ok(Self::Original),
+            2 => Ok(Self::Raw),
+            3 => Ok(Self::Motion),
+            4 => Ok(Self::Sidecar),
+            5 => Ok(Self::Edited),
+            _ => Err(Error::DatabaseConsistencyError{ msg: format!("Invalid AttachmentRole value {}", value) }),
+        }
+    }
+}
+
+impl ToString for AttachmentRole
+{
+    fn to_string(&self) -> String
+    {
+        match self
+        {
+            Self::Primary => "Primary",
+            Self::Original => "Original",
+            Self::Raw => "Raw",
+            Self::Motion => "Motion",
+            Self::Sidecar => "Sidecar",
+            Self::Edited => "Edited",
+        }.to_owned()
+    }
+}
+
+impl std::str::FromStr for AttachmentRole
+{
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "Primary" => Ok(Self::Primary),
+            "Original" => Ok(Self::Original),
+            "Raw" => Ok(Self::Raw),
+            "Motion" => Ok(Self::Motion),
+            "Sidecar" => Ok(Self::Sidecar),
+            "Edited" => Ok(Self::Edited),
+            _ => Err(Error::DatabaseConsistencyError{msg : "Invalid AttachmentRole".to_owned() }),
+        }
+    }
+}
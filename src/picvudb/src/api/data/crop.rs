@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use crate::data::Dimensions;
+use crate::ParseError;
+
+// A crop rectangle, with each value a fraction of the
+// width or height of the image after it has been oriented,
+// so the same crop applies to thumbnails of any size
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Crop
+{
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Crop
+{
+    pub fn new(left: f64, top: f64, width: f64, height: f64) -> Result<Self, ParseError>
+    {
+        // Crops that reach the right or bottom edge can add up
+        // to just over 1.0 after converting from percentages,
+        // so allow for rounding and then clamp to the edge
+
+        const EPSILON: f64 = 1e-9;
+
+        let valid = left >= 0.0
+            && top >= 0.0
+            && width > 0.0
+            && height > 0.0
+            && (left + width) <= (1.0 + EPSILON)
+            && (top + height) <= (1.0 + EPSILON);
+
+        if !valid
+        {
+            return Err(ParseError::new(format!("Invalid crop rectangle [{}, {}, {}, {}]", left, top, width, height)));
+        }
+
+        let width = width.min(1.0 - left);
+        let height = height.min(1.0 - top);
+
+        Ok(Crop{ left, top, width, height })
+    }
+
+    pub fn is_full_image(&self) -> bool
+    {
+        self.left == 0.0
+            && self.top == 0.0
+            && self.width == 1.0
+            && self.height == 1.0
+    }
+
+    pub fn to_pixels(&self, dimensions: &Dimensions) -> (u32, u32, Dimensions)
+    {
+        let x = ((self.left * (dimensions.width as f64)).round() as u32).min(dimensions.width.saturating_sub(1));
+        let y = ((self.top * (dimensions.height as f64)).round() as u32).min(dimensions.height.saturating_sub(1));
+        let width = ((self.width * (dimensions.width as f64)).round() as u32).max(1).min(dimensions.width - x);
+        let height = ((self.height * (dimensions.height as f64)).round() as u32).max(1).min(dimensions.height - y);
+
+        (x, y, Dimensions::new(width, height))
+    }
+
+    pub fn adjust_dimensions(&self, dimensions: &Dimensions) -> Dimensions
+    {
+        self.to_pixels(dimensions).2
+    }
+
+    pub(crate) fn from_db_fields(left: Option<f64>, top: Option<f64>, width: Option<f64>, height: Option<f64>) -> Result<Option<Self>, ParseError>
+    {
+        match (left, top, width, height)
+        {
+            (None, None, None, None) => Ok(None),
+            (Some(left), Some(top), Some(width), Some(height)) => Ok(Some(Crop::new(left, top, width, height)?)),
+            _ => Err(ParseError::new(format!("Invalid crop [{:?}, {:?}, {:?}, {:?}]", left, top, width, height))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::Crop;
+    use crate::data::Dimensions;
+
+    #[test]
+    fn test_crop_pixels()
+    {
+        assert!(Crop::new(0.5, 0.0, 0.6, 1.0).is_err());
+        assert!(Crop::new(0.0, 0.0, 0.0, 1.0).is_err());
+        assert!(Crop::new(0.0, 0.0, 1.0, 1.0).unwrap().is_full_image());
+
+        let crop = Crop::new(0.25, 0.5, 0.5, 0.25).unwrap();
+
+        assert_eq!(crop.to_pixels(&Dimensions::new(400, 200)), (100, 100, Dimensions::new(200, 50)));
+        assert_eq!(crop.adjust_dimensions(&Dimensions::new(4, 4)), Dimensions::new(2, 1));
+    }
+
+    #[test]
+    fn test_crop_percentages()
+    {
+        // The adjust image form uses percentages, and shows
+        // the stored crop as percentages to be submitted again
+
+        for hundredths in 0..10000
+        {
+            let left_percent = (hundredths as f64) / 100.0;
+            let width_percent = 100.0 - left_percent;
+
+            let crop = Crop::new(left_percent / 100.0, left_percent / 100.0, width_percent / 100.0, width_percent / 100.0)
+                .expect("Edge crop rejected");
+
+            assert!((crop.left + crop.width) <= 1.0);
+            assert!((crop.top + crop.height) <= 1.0);
+
+            let resubmitted = Crop::new(
+                (crop.left * 100.0) / 100.0,
+                (crop.top * 100.0) / 100.0,
+                (crop.width * 100.0) / 100.0,
+                (crop.height * 100.0) / 100.0)
+                .expect("Saved crop rejected");
+
+            assert!((resubmitted.left + resubmitted.width) <= 1.0);
+        }
+    }
+}
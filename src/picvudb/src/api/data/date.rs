@@ -45,7 +45,12 @@ impl Date
 
     pub(crate) fn from_db_fields(timestamp: i64, offset: Option<i32>) -> Result<Self, Error>
     {
-        let naive = NaiveDateTime::from_timestamp_opt(timestamp, 0).ok_or(Error::DatabaseConsistencyError{msg: format!("Invalid Date/Time fields {} offset {:?}", timestamp, offset)})?;
+        Self::from_db_fields_with_nanos(timestamp, 0, offset)
+    }
+
+    pub(crate) fn from_db_fields_with_nanos(timestamp: i64, nanos: i32, offset: Option<i32>) -> Result<Self, Error>
+    {
+        let naive = NaiveDateTime::from_timestamp_opt(timestamp, nanos as u32).ok_or(Error::DatabaseConsistencyError{msg: format!("Invalid Date/Time fields {}.{:09} offset {:?}", timestamp, nanos, offset)})?;
         let utc = DateTime::<Utc>::from_utc(naive, Utc);
 
         match offset
@@ -80,6 +85,15 @@ impl Date
         }
     }
 
+    pub(crate) fn to_db_nanos(&self) -> i32
+    {
+        match self
+        {
+            Date::Utc(utc) => utc.timestamp_subsec_nanos() as i32,
+            Date::FixedOffset(fixed) => fixed.timestamp_subsec_nanos() as i32,
+        }
+    }
+
     pub(crate) fn to_db_offset(&self) -> Option<i32>
     {
         match self
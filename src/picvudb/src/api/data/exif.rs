@@ -0,0 +1,143 @@
+use crate::models;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExifEntry
+{
+    pub tag: String,
+    pub value: String,
+}
+
+/// The EXIF (and XMP) metadata extracted from an attachment.
+/// The commonly searched fields are stored separately so
+/// they can be queried, and all entries are kept for display.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentExif
+{
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub exposure_time: Option<String>,
+    pub aperture: Option<f64>,
+    pub focal_length: Option<f64>,
+    pub iso: Option<u32>,
+    pub gps_dop: Option<f64>,
+    pub entries: Vec<ExifEntry>,
+    pub xmp: Option<String>,
+}
+
+impl AttachmentExif
+{
+    pub(crate) fn to_db_model(&self, obj_id: i64) -> models::AttachmentExif
+    {
+        // Entries are stored one per line, as "tag<TAB>value"
+
+        let entries = self.entries.iter()
+            .map(|e| format!("{}\t{}", clean_db_entry_text(&e.tag), clean_db_entry_text(&e.value)))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        models::AttachmentExif
+        {
+            obj_id,
+            make: self.make.clone(),
+            model: self.model.clone(),
+            exposure_time: self.exposure_time.clone(),
+            aperture: self.aperture,
+            focal_length: self.focal_length,
+            iso: self.iso.map(|i| i as i32),
+            gps_dop: self.gps_dop,
+            entries,
+            xmp: self.xmp.clone(),
+        }
+    }
+
+    pub(crate) fn from_db_model(model: models::AttachmentExif) -> Self
+    {
+        let entries = model.entries
+            .lines()
+            .filter_map(|line|
+            {
+                let mut parts = line.splitn(2, '\t');
+
+                match (parts.next(), parts.next())
+                {
+                    (Some(tag), Some(value)) => Some(ExifEntry{ tag: tag.to_owned(), value: value.to_owned() }),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        AttachmentExif
+        {
+            make: model.make,
+            model: model.model,
+            exposure_time: model.exposure_time,
+            aperture: model.aperture,
+            focal_length: model.focal_length,
+            iso: model.iso.map(|i| i as u32),
+            gps_dop: model.gps_dop,
+            entries,
+            xmp: model.xmp,
+        }
+    }
+}
+
+fn clean_db_entry_text(text: &str) -> String
+{
+    text.replace(|c| c == '\t' || c == '\n' || c == '\r', " ")
+}
+
+/// Selects objects by their stored EXIF metadata. All of
+/// the specified fields must match, and ranges are inclusive.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExifFilter
+{
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub min_iso: Option<u32>,
+    pub max_iso: Option<u32>,
+    pub min_focal_length: Option<f64>,
+    pub max_focal_length: Option<f64>,
+}
+
+impl ToString for ExifFilter
+{
+    fn to_string(&self) -> String
+    {
+        let mut parts = Vec::new();
+
+        match (&self.make, &self.model)
+        {
+            (Some(make), Some(model)) => parts.push(format!("{} {}", make, model)),
+            (Some(make), None) => parts.push(make.clone()),
+            (None, Some(model)) => parts.push(model.clone()),
+            (None, None) => {},
+        }
+
+        match (self.min_iso, self.max_iso)
+        {
+            (Some(min), Some(max)) if min == max => parts.push(format!("ISO {}", min)),
+            (Some(min), Some(max)) => parts.push(format!("ISO {} to {}", min, max)),
+            (Some(min), None) => parts.push(format!("ISO {} and above", min)),
+            (None, Some(max)) => parts.push(format!("ISO {} and below", max)),
+            (None, None) => {},
+        }
+
+        match (self.min_focal_length, self.max_focal_length)
+        {
+            (Some(min), Some(max)) if min == max => parts.push(format!("{}mm", min)),
+            (Some(min), Some(max)) => parts.push(format!("{}mm to {}mm", min, max)),
+            (Some(min), None) => parts.push(format!("{}mm and above", min)),
+            (None, Some(max)) => parts.push(format!("{}mm and below", max)),
+            (None, None) => {},
+        }
+
+        if parts.is_empty()
+        {
+            "Any EXIF".to_owned()
+        }
+        else
+        {
+            parts.join(", ")
+        }
+    }
+}
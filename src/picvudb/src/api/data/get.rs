@@ -1,4 +1,5 @@
-use crate::api::data::{Censor, Date, DateRange, Dimensions, Duration, ExternalReference, Location, NotesMarkdown, ObjectId, Orientation, Rating, TagId, TagKind, TitleMarkdown};
+use crate::{models, Error};
+use crate::api::data::{AttachmentRole, Censor, Crop, Date, DateRange, Dimensions, Duration, ExifFilter, ExternalReference, Location, NotesMarkdown, ObjectId, Orientation, Rating, RelationKind, TagId, TagKind, TitleMarkdown};
 
 #[derive(Debug, Clone)]
 pub struct AttachmentMetadata
@@ -12,6 +13,31 @@ pub struct AttachmentMetadata
     pub dimensions: Option<Dimensions>,
     pub duration: Option<Duration>,
     pub hash: String,
+    pub role: AttachmentRole,
+    pub index: u32,
+    pub is_previous_version: bool,
+}
+
+impl AttachmentMetadata
+{
+    pub(crate) fn from_db_model(attachment: models::AttachmentMetadata) -> Result<Self, Error>
+    {
+        Ok(AttachmentMetadata
+        {
+            filename: attachment.filename,
+            created: Date::from_db_fields(attachment.created_timestamp, attachment.created_offset)?,
+            modified: Date::from_db_fields(attachment.modified_timestamp, attachment.modified_offset)?,
+            mime: attachment.mime.parse::<mime::Mime>()?,
+            size: attachment.size as u64,
+            orientation: Orientation::from_db_field(attachment.orientation)?,
+            dimensions: Dimensions::from_db_fields(attachment.width, attachment.height),
+            duration: Duration::from_db_field(attachment.duration)?,
+            hash: attachment.hash,
+            role: AttachmentRole::from_db_field(attachment.role)?,
+            index: attachment.version_of.unwrap_or(attachment.att_index) as u32,
+            is_previous_version: attachment.version_of.is_some(),
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -22,6 +48,31 @@ pub struct TagMetadata
     pub kind: TagKind,
     pub rating: Rating,
     pub censor: Censor,
+    pub parent_id: Option<TagId>,
+}
+
+impl TagMetadata
+{
+    pub(crate) fn from_db_model(tag: models::Tag) -> Result<Self, Error>
+    {
+        Ok(TagMetadata
+        {
+            tag_id: TagId::from_db_field(tag.tag_id),
+            name: tag.tag_name,
+            kind: TagKind::from_db_field(tag.tag_kind)?,
+            rating: Rating::from_db_field(tag.tag_rating)?,
+            censor: Censor::from_db_field(tag.tag_censor)?,
+            parent_id: tag.tag_parent_id.map(TagId::from_db_field),
+        })
+    }
+}
+
+/// A tag and all the tags below it in the hierarchy
+#[derive(Debug, Clone)]
+pub struct TagTreeNode
+{
+    pub tag: TagMetadata,
+    pub children: Vec<TagTreeNode>,
 }
 
 #[derive(Debug, Clone)]
@@ -36,9 +87,46 @@ pub struct ObjectMetadata
     pub rating: Rating,
     pub censor: Censor,
     pub location: Option<Location>,
-    pub attachment: AttachmentMetadata,
+    pub attachment: Option<AttachmentMetadata>,
+    pub additional_attachments: Vec<AttachmentMetadata>,
+    pub previous_versions: Vec<AttachmentMetadata>,
     pub tags: Vec<TagMetadata>,
     pub ext_ref: Option<ExternalReference>,
+    pub user_orientation: Option<Orientation>,
+    pub crop: Option<Crop>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RelatedObject
+{
+    pub kind: RelationKind,
+    /// True if the relation is from the object the
+    /// relations were requested for, to this object
+    pub outgoing: bool,
+    pub object: ObjectMetadata,
+}
+
+impl RelatedObject
+{
+    pub fn label(&self) -> String
+    {
+        if self.outgoing
+        {
+            self.kind.outgoing_label()
+        }
+        else
+        {
+            self.kind.incoming_label()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CameraSummary
+{
+    pub make: String,
+    pub model: String,
+    pub num_objects: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -65,8 +153,10 @@ pub enum GetObjectsQuery
     ByObjectId(ObjectId),
     NearLocationByActivityDesc{ location: Location, radius_meters: f64 },
     TitleNotesSearchByActivityDesc{ search: SearchString },
-    TagByActivityDesc{ tag_id: TagId },
+    TagByActivityDesc{ tag_id: TagId, include_descendants: bool },
     ActivityDateRangeByActivityDesc{ date_range: DateRange },
+    ExifByActivityDesc{ filter: ExifFilter },
+    IngredientByActivityDesc{ ingredient: String },
 }
 
 #[derive(Debug, Clone)]
@@ -76,6 +166,19 @@ pub enum SearchString
     Suggestion(String),
 }
 
+impl ObjectMetadata
+{
+    pub fn get_display_text(&self) -> String
+    {
+        match (&self.title, &self.attachment)
+        {
+            (Some(title), _) => title.get_display_text(),
+            (None, Some(attachment)) => attachment.filename.clone(),
+            (None, None) => "Untitled Note".to_owned(),
+        }
+    }
+}
+
 impl SearchString
 {
     pub fn to_fts5_query(&self) -> String
@@ -8,6 +8,8 @@ pub enum LocationSource
     UserProvided,
     CameraGps,
     ThirdPartyMetadata,
+    TrackLog,
+    Inferred,
 }
 
 impl LocationSource
@@ -19,6 +21,8 @@ impl LocationSource
             Self::UserProvided => 0x01,
             Self::CameraGps => 0x02,
             Self::ThirdPartyMetadata => 0x04,
+            Self::TrackLog => 0x08,
+            Self::Inferred => 0x10,
         }
     }
 
@@ -29,6 +33,8 @@ impl LocationSource
             0x01 => Ok(Self::UserProvided),
             0x02 => Ok(Self::CameraGps),
             0x04 => Ok(Self::ThirdPartyMetadata),
+            0x08 => Ok(Self::TrackLog),
+            0x10 => Ok(Self::Inferred),
             _ => Err(ParseError::new(format!("Invalid LocationSource field 0x{:0x}", val))),
         }
     }
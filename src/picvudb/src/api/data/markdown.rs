@@ -2,6 +2,7 @@ use std::str::FromStr;
 use pulldown_cmark::{html, Event, Options, Parser, Tag};
 
 use crate::err::ParseError;
+use crate::data::wikilink::{split_wiki_links, WikiFragment, WikiLink};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TitleMarkdown(String);
@@ -221,8 +222,92 @@ impl NotesMarkdown
 
     pub fn get_html(&self) -> String
     {
+        self.get_html_with_links(|_| None)
+    }
+
+    /// Renders the notes, replacing each wiki link with the
+    /// HTML returned by `resolve`. Links that can't be resolved
+    /// are left as their original text.
+    pub fn get_html_with_links<F>(&self, resolve: F) -> String
+        where F: Fn(&WikiLink) -> Option<String>
+    {
+        let mut events = Vec::new();
+        let mut in_code_block = false;
+
+        for event in merge_text_events(Parser::new_ext(&self.0, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH))
+        {
+            match event
+            {
+                Event::Start(Tag::CodeBlock(_)) =>
+                {
+                    in_code_block = true;
+                    events.push(event);
+                },
+                Event::End(Tag::CodeBlock(_)) =>
+                {
+                    in_code_block = false;
+                    events.push(event);
+                },
+                Event::Text(text) if !in_code_block =>
+                {
+                    for fragment in split_wiki_links(&text)
+                    {
+                        match fragment
+                        {
+                            WikiFragment::Text(t) => events.push(Event::Text(t.to_owned().into())),
+                            WikiFragment::Link(link, original) =>
+                            {
+                                match resolve(&link)
+                                {
+                                    Some(html) => events.push(Event::Html(html.into())),
+                                    None => events.push(Event::Text(original.to_owned().into())),
+                                }
+                            },
+                        }
+                    }
+                },
+                _ =>
+                {
+                    events.push(event);
+                },
+            }
+        }
+
         let mut result = String::new();
-        html::push_html(&mut result, Parser::new_ext(&self.0, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH));
+        html::push_html(&mut result, events.into_iter());
+        result
+    }
+
+    /// Returns the wiki links in the notes, in order, without
+    /// duplicates. Links inside code aren't included.
+    pub fn get_wiki_links(&self) -> Vec<WikiLink>
+    {
+        let mut result = Vec::new();
+        let mut in_code_block = false;
+
+        for event in merge_text_events(Parser::new_ext(&self.0, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH))
+        {
+            match event
+            {
+                Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+                Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+                Event::Text(text) if !in_code_block =>
+                {
+                    for fragment in split_wiki_links(&text)
+                    {
+                        if let WikiFragment::Link(link, _) = fragment
+                        {
+                            if !result.contains(&link)
+                            {
+                                result.push(link);
+                            }
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+
         result
     }
 
@@ -242,6 +327,30 @@ impl FromStr for NotesMarkdown
     }
 }
 
+fn merge_text_events<'a>(parser: Parser<'a, 'a>) -> Vec<Event<'a>>
+{
+    // The parser splits text at brackets, as they might
+    // start a link - wiki links need the text together
+
+    let mut result: Vec<Event<'a>> = Vec::new();
+
+    for event in parser
+    {
+        if let Event::Text(text) = &event
+        {
+            if let Some(Event::Text(prev)) = result.last_mut()
+            {
+                *prev = format!("{}{}", prev, text).into();
+                continue;
+            }
+        }
+
+        result.push(event);
+    }
+
+    result
+}
+
 fn get_display_text(s: &str) -> String
 {
     // To get a "display" string, we just want to
@@ -353,4 +462,39 @@ fn get_search_text(s: &str) -> String
     }
 
     result
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const NOTES: &str = "See [[tag:Grandma]] and [[obj:5]], then [[tag:Grandma]] again.\n\n```\n[[obj:6]]\n```\n\n    [[tag:Indented]]\n\nInline `[[tag:Code]]` too.\n";
+
+    #[test]
+    fn test_get_wiki_links()
+    {
+        let notes = NotesMarkdown::parse(NOTES.to_owned()).unwrap();
+
+        let links: Vec<String> = notes.get_wiki_links().iter().map(|l| l.to_markdown()).collect();
+
+        assert_eq!(links, vec!["[[tag:Grandma]]", "[[obj:5]]"]);
+    }
+
+    #[test]
+    fn test_get_html_with_links()
+    {
+        let notes = NotesMarkdown::parse(NOTES.to_owned()).unwrap();
+
+        let html = notes.get_html_with_links(|link|
+        {
+            match link
+            {
+                WikiLink::Tag(name) => Some(format!("<a href=\"/tag\">{}</a>", name)),
+                WikiLink::Object(_) => None,
+            }
+        });
+
+        assert_eq!(html, "<p>See <a href=\"/tag\">Grandma</a> and [[obj:5]], then <a href=\"/tag\">Grandma</a> again.</p>\n<pre><code>[[obj:6]]\n</code></pre>\n<pre><code>[[tag:Indented]]\n</code></pre>\n<p>Inline <code>[[tag:Code]]</code> too.</p>\n");
+    }
+}
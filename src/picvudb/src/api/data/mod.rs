@@ -1,8 +1,11 @@
+mod attachmentrole;
 mod censor;
+mod crop;
 mod date;
 mod daterange;
 mod dimensions;
 mod duration;
+mod exif;
 mod extref;
 mod id;
 mod location;
@@ -10,15 +13,23 @@ mod markdown;
 mod objectid;
 mod orientation;
 mod rating;
+mod recipe;
+mod relationkind;
 mod tagid;
 mod tagkind;
 mod tagset;
+mod wikilink;
 
+pub use attachmentrole::AttachmentRole;
 pub use censor::Censor;
+pub use crop::Crop;
 pub use date::Date;
 pub use daterange::DateRange;
 pub use dimensions::Dimensions;
 pub use duration::Duration;
+pub use exif::AttachmentExif;
+pub use exif::ExifEntry;
+pub use exif::ExifFilter;
 pub use extref::ExternalReference;
 pub use markdown::NotesMarkdown;
 pub use markdown::TitleMarkdown;
@@ -27,9 +38,15 @@ pub use location::Location;
 pub use objectid::ObjectId;
 pub use orientation::Orientation;
 pub use rating::Rating;
+pub use recipe::Ingredient;
+pub use recipe::Recipe;
+pub use recipe::format_minutes;
+pub use recipe::format_quantity;
+pub use relationkind::RelationKind;
 pub use tagid::TagId;
 pub use tagkind::TagKind;
 pub use tagset::TagSet;
+pub use wikilink::WikiLink;
 
 pub mod add;
 pub mod get;
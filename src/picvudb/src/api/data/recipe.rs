@@ -0,0 +1,786 @@
+use std::fmt;
+
+use crate::err::ParseError;
+use crate::data::NotesMarkdown;
+
+/// A recipe is stored as a convention in an object's notes:
+///
+/// ```text
+/// Fluffy pancakes for a weekend breakfast.
+///
+/// Servings: 4
+/// Prep time: 10 min
+/// Cook time: 20 min
+/// Source: https://example.com/pancakes
+///
+/// ## Ingredients
+///
+/// - 1 1/2 cups flour
+/// - 2 eggs
+/// - salt
+///
+/// ## Steps
+///
+/// 1. Mix everything together
+/// 2. Cook in a hot pan
+/// ```
+///
+/// Notes are only treated as a recipe if they
+/// list at least one ingredient.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe
+{
+    pub description: String,
+    pub servings: Option<u32>,
+    pub prep_minutes: Option<u32>,
+    pub cook_minutes: Option<u32>,
+    pub source_url: Option<String>,
+    pub ingredients: Vec<Ingredient>,
+    pub steps: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ingredient
+{
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub name: String,
+}
+
+enum Section
+{
+    Preamble,
+    Ingredients,
+    Steps,
+    Other,
+}
+
+const UNITS: [&str; 38] =
+[
+    "g", "gram", "grams", "kg", "mg",
+    "ml", "l", "litre", "litres", "liter", "liters",
+    "tsp", "teaspoon", "teaspoons",
+    "tbsp", "tablespoon", "tablespoons",
+    "cup", "cups", "oz", "ounce", "ounces", "lb", "lbs", "pound", "pounds",
+    "pinch", "pinches", "dash", "can", "cans", "clove", "cloves",
+    "slice", "slices", "handful", "handfuls", "bunch",
+];
+
+const UNICODE_FRACTIONS: [(char, f64); 7] =
+[
+    ('½', 1.0 / 2.0),
+    ('⅓', 1.0 / 3.0),
+    ('⅔', 2.0 / 3.0),
+    ('¼', 1.0 / 4.0),
+    ('¾', 3.0 / 4.0),
+    ('⅛', 1.0 / 8.0),
+    ('⅜', 3.0 / 8.0),
+];
+
+impl Recipe
+{
+    pub fn from_notes(notes: &NotesMarkdown) -> Option<Recipe>
+    {
+        Recipe::parse_notes(notes).map(|(recipe, _)| recipe)
+    }
+
+    /// Returns false if saving the notes as a recipe would lose
+    /// or move some of their text - such as lines in the
+    /// ingredients that aren't list items, or sub-headings after
+    /// the ingredients. Notes that aren't a recipe can always be
+    /// edited, as they become the recipe's description.
+    pub fn can_edit_notes(notes: &NotesMarkdown) -> bool
+    {
+        Recipe::parse_notes(notes).map(|(_, lossless)| lossless).unwrap_or(true)
+    }
+
+    fn parse_notes(notes: &NotesMarkdown) -> Option<(Recipe, bool)>
+    {
+        let markdown = notes.get_markdown();
+
+        let mut section = Section::Preamble;
+        let mut description = Vec::new();
+        let mut servings = None;
+        let mut prep_minutes = None;
+        let mut cook_minutes = None;
+        let mut source_url = None;
+        let mut ingredients = Vec::new();
+        let mut steps: Vec<String> = Vec::new();
+        let mut structured = false;
+        let mut lossless = true;
+
+        for line in markdown.lines()
+        {
+            let trimmed = line.trim();
+
+            if let Some(heading) = heading_text(trimmed)
+            {
+                section = match heading.to_lowercase().as_str()
+                {
+                    "ingredients" => Section::Ingredients,
+                    "steps" | "method" | "directions" | "instructions" => Section::Steps,
+                    _ => Section::Other,
+                };
+
+                match section
+                {
+                    Section::Other =>
+                    {
+                        // Other sections are kept in the description,
+                        // so they'd move above the ingredients
+
+                        if structured
+                        {
+                            lossless = false;
+                        }
+
+                        description.push(line);
+                    },
+                    _ => structured = true,
+                }
+
+                continue;
+            }
+
+            match section
+            {
+                Section::Preamble =>
+                {
+                    if let Some((key, value)) = split_field(trimmed)
+                    {
+                        match key.as_str()
+                        {
+                            "servings" | "serves" | "yield" =>
+                            {
+                                if let Some(num) = leading_integer(value)
+                                {
+                                    servings = Some(num);
+                                    continue;
+                                }
+                            },
+                            "prep" | "prep time" =>
+                            {
+                                if let Some(minutes) = parse_minutes(value)
+                                {
+                                    prep_minutes = Some(minutes);
+                                    continue;
+                                }
+                            },
+                            "cook" | "cook time" =>
+                            {
+                                if let Some(minutes) = parse_minutes(value)
+                                {
+                                    cook_minutes = Some(minutes);
+                                    continue;
+                                }
+                            },
+                            "source" | "url" =>
+                            {
+                                source_url = Some(value.to_owned());
+                                continue;
+                            },
+                            _ => {},
+                        }
+                    }
+
+                    description.push(line);
+                },
+                Section::Ingredients =>
+                {
+                    if let Some(ingredient) = list_item_text(trimmed).and_then(Ingredient::parse)
+                    {
+                        ingredients.push(ingredient);
+                    }
+                    else if !trimmed.is_empty()
+                    {
+                        lossless = false;
+                    }
+                },
+                Section::Steps =>
+                {
+                    if let Some(step) = list_item_text(trimmed)
+                    {
+                        steps.push(step.to_owned());
+                    }
+                    else if !trimmed.is_empty()
+                    {
+                        // Continues the previous step
+
+                        match steps.last_mut()
+                        {
+                            Some(step) =>
+                            {
+                                step.push(' ');
+                                step.push_str(trimmed);
+                            },
+                            None => steps.push(trimmed.to_owned()),
+                        }
+                    }
+                },
+                Section::Other =>
+                {
+                    description.push(line);
+                },
+            }
+        }
+
+        if ingredients.is_empty()
+        {
+            return None;
+        }
+
+        let recipe = Recipe
+        {
+            description: description.join("\n").trim().to_owned(),
+            servings,
+            prep_minutes,
+            cook_minutes,
+            source_url,
+            ingredients,
+            steps,
+        };
+
+        Some((recipe, lossless))
+    }
+
+    pub fn to_markdown(&self) -> String
+    {
+        let mut result = String::new();
+
+        if !self.description.trim().is_empty()
+        {
+            result.push_str(self.description.trim());
+            result.push_str("\n\n");
+        }
+
+        let mut fields = Vec::new();
+
+        if let Some(servings) = self.servings
+        {
+            fields.push(format!("Servings: {}", servings));
+        }
+        if let Some(minutes) = self.prep_minutes
+        {
+            fields.push(format!("Prep time: {}", format_minutes(minutes)));
+        }
+        if let Some(minutes) = self.cook_minutes
+        {
+            fields.push(format!("Cook time: {}", format_minutes(minutes)));
+        }
+        if let Some(source_url) = &self.source_url
+        {
+            fields.push(format!("Source: {}", source_url));
+        }
+
+        if !fields.is_empty()
+        {
+            // Each field is kept on its own line when rendered
+
+            result.push_str(&fields.join("  \n"));
+            result.push_str("\n\n");
+        }
+
+        result.push_str("## Ingredients\n\n");
+
+        for ingredient in self.ingredients.iter()
+        {
+            result.push_str(&format!("- {}\n", ingredient));
+        }
+
+        if !self.steps.is_empty()
+        {
+            result.push_str("\n## Steps\n\n");
+
+            for (i, step) in self.steps.iter().enumerate()
+            {
+                result.push_str(&format!("{}. {}\n", i + 1, step));
+            }
+        }
+
+        result
+    }
+
+    pub fn to_notes(&self) -> Result<NotesMarkdown, ParseError>
+    {
+        NotesMarkdown::parse(self.to_markdown())
+    }
+
+    /// Returns the recipe with the ingredient quantities
+    /// scaled to make a different number of servings.
+    pub fn scale_to_servings(&self, servings: u32) -> Recipe
+    {
+        let mut result = self.clone();
+
+        if let Some(current) = self.servings
+        {
+            if current != 0
+            {
+                let factor = (servings as f64) / (current as f64);
+
+                result.servings = Some(servings);
+                result.ingredients = self.ingredients.iter().map(|i| i.scaled(factor)).collect();
+            }
+        }
+
+        result
+    }
+
+    /// The names of the ingredients, as used for searching
+    pub fn get_search_names(&self) -> Vec<String>
+    {
+        let mut names = self.ingredients.iter()
+            .map(|i| i.get_search_name())
+            .filter(|n| !n.is_empty())
+            .collect::<Vec<_>>();
+
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+impl Ingredient
+{
+    /// Parses an ingredient such as "200 g flour", "1 1/2 cups milk",
+    /// "2 eggs" or "salt". The quantity and unit are optional.
+    pub fn parse(text: &str) -> Option<Ingredient>
+    {
+        let text = text.trim();
+
+        let (quantity, unit, name) = match take_token(text)
+        {
+            (first, rest) if parse_number(first).is_some() =>
+            {
+                let mut quantity = parse_number(first).unwrap();
+                let mut rest = rest;
+
+                // A whole number can be followed
+                // by a fraction, as in "1 1/2"
+
+                let (second, after) = take_token(rest);
+
+                if quantity.fract() == 0.0
+                    && is_fraction(second)
+                {
+                    quantity += parse_number(second).unwrap_or(0.0);
+                    rest = after;
+                }
+
+                let (unit, name) = split_unit(rest);
+
+                (Some(quantity), unit, name)
+            },
+            (first, rest) =>
+            {
+                // The unit may be attached, as in "200g"
+
+                match split_attached_unit(first)
+                {
+                    Some((quantity, unit)) => (Some(quantity), Some(unit), strip_of(rest)),
+                    None => (None, None, text),
+                }
+            },
+        };
+
+        let name = name.trim();
+
+        if name.is_empty()
+        {
+            return None;
+        }
+
+        Some(Ingredient
+        {
+            quantity,
+            unit,
+            name: name.to_owned(),
+        })
+    }
+
+    pub fn scaled(&self, factor: f64) -> Ingredient
+    {
+        Ingredient
+        {
+            quantity: self.quantity.map(|q| q * factor),
+            unit: self.unit.clone(),
+            name: self.name.clone(),
+        }
+    }
+
+    /// The name without any preparation notes, so
+    /// "Potatoes, peeled" is found as "potatoes"
+    pub fn get_search_name(&self) -> String
+    {
+        self.name
+            .split(|c: char| c == ',' || c == '(')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+    }
+}
+
+impl fmt::Display for Ingredient
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        if let Some(quantity) = self.quantity
+        {
+            write!(f, "{} ", format_quantity(quantity))?;
+        }
+
+        if let Some(unit) = &self.unit
+        {
+            write!(f, "{} ", unit)?;
+        }
+
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Formats a quantity as a whole number or simple
+/// fraction where possible, such as "1 1/2"
+pub fn format_quantity(quantity: f64) -> String
+{
+    let whole = quantity.trunc();
+    let fract = quantity - whole;
+
+    for den in [2u32, 3, 4, 8].iter()
+    {
+        let den = *den as f64;
+        let num = (fract * den).round();
+
+        if (fract - (num / den)).abs() < 0.01
+        {
+            if num == 0.0
+            {
+                return format!("{}", whole as u64);
+            }
+            else if num == den
+            {
+                return format!("{}", (whole as u64) + 1);
+            }
+            else if whole == 0.0
+            {
+                return format!("{}/{}", num as u64, den as u64);
+            }
+            else
+            {
+                return format!("{} {}/{}", whole as u64, num as u64, den as u64);
+            }
+        }
+    }
+
+    let result = format!("{:.2}", quantity);
+
+    result.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+pub fn format_minutes(minutes: u32) -> String
+{
+    match (minutes / 60, minutes % 60)
+    {
+        (0, m) => format!("{} min", m),
+        (h, 0) => format!("{} h", h),
+        (h, m) => format!("{} h {} min", h, m),
+    }
+}
+
+/// Parses a time such as "15", "15 min", "1 h 30 min",
+/// "1 hour 30 minutes" or "1:30" into minutes
+fn parse_minutes(value: &str) -> Option<u32>
+{
+    if let Some(pos) = value.find(':')
+    {
+        let hours = value[..pos].trim().parse::<u32>().ok()?;
+        let minutes = value[pos + 1..].trim().parse::<u32>().ok()?;
+
+        return hours.checked_mul(60)?.checked_add(minutes);
+    }
+
+    let mut total: u32 = 0;
+    let mut number: Option<u32> = None;
+    let mut found = false;
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next()
+    {
+        if c.is_ascii_digit()
+        {
+            let mut digits = c.to_string();
+
+            while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit())
+            {
+                digits.push(*d);
+                chars.next();
+            }
+
+            total = total.checked_add(number.take().unwrap_or(0))?;
+            number = Some(digits.parse().ok()?);
+            found = true;
+        }
+        else if c.is_alphabetic()
+        {
+            let mut word = c.to_string();
+
+            while let Some(d) = chars.peek().filter(|d| d.is_alphabetic())
+            {
+                word.push(*d);
+                chars.next();
+            }
+
+            let word = word.to_lowercase();
+
+            if word.starts_with('h')
+            {
+                total = total.checked_add(number.take()?.checked_mul(60)?)?;
+            }
+            else if word.starts_with('m')
+            {
+                total = total.checked_add(number.take()?)?;
+            }
+            else
+            {
+                return None;
+            }
+        }
+    }
+
+    if !found
+    {
+        return None;
+    }
+
+    total.checked_add(number.unwrap_or(0))
+}
+
+fn heading_text(line: &str) -> Option<&str>
+{
+    if !line.starts_with('#')
+    {
+        return None;
+    }
+
+    let text = line.trim_start_matches('#');
+
+    if !text.starts_with(' ')
+    {
+        return None;
+    }
+
+    Some(text.trim().trim_end_matches('#').trim().trim_end_matches(':'))
+}
+
+fn split_field(line: &str) -> Option<(String, &str)>
+{
+    let pos = line.find(':')?;
+    let key = line[..pos].trim_matches(|c: char| c == '*' || c == '_' || c == ' ').to_lowercase();
+    let value = line[pos + 1..].trim_matches(|c: char| c == '*' || c == '_' || c == ' ');
+
+    Some((key, value))
+}
+
+fn list_item_text(line: &str) -> Option<&str>
+{
+    for bullet in ["- ", "* ", "+ "].iter()
+    {
+        if let Some(rest) = line.strip_prefix(*bullet)
+        {
+            return Some(rest.trim());
+        }
+    }
+
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+
+    if digits > 0
+    {
+        let rest = &line[digits..];
+
+        if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") "))
+        {
+            return Some(rest.trim());
+        }
+    }
+
+    None
+}
+
+fn leading_integer(value: &str) -> Option<u32>
+{
+    let digits = value.chars().take_while(|c| c.is_ascii_digit()).collect::<String>();
+
+    digits.parse().ok()
+}
+
+fn take_token(text: &str) -> (&str, &str)
+{
+    let text = text.trim_start();
+
+    match text.find(char::is_whitespace)
+    {
+        Some(pos) => (&text[..pos], &text[pos..]),
+        None => (text, ""),
+    }
+}
+
+fn is_fraction(token: &str) -> bool
+{
+    (token.contains('/') && parse_number(token).is_some())
+        || UNICODE_FRACTIONS.iter().any(|(c, _)| token == c.to_string())
+}
+
+fn parse_number(token: &str) -> Option<f64>
+{
+    // Avoid names such as "nan" or "infinity"
+    // being parsed as floating point numbers
+
+    if !token.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && !UNICODE_FRACTIONS.iter().any(|(c, _)| token.starts_with(*c))
+    {
+        return None;
+    }
+
+    if let Some(pos) = token.find('/')
+    {
+        let num = token[..pos].parse::<f64>().ok()?;
+        let den = token[pos + 1..].parse::<f64>().ok()?;
+
+        if den == 0.0
+        {
+            return None;
+        }
+
+        return Some(num / den);
+    }
+
+    for (c, value) in UNICODE_FRACTIONS.iter()
+    {
+        if let Some(whole) = token.strip_suffix(*c)
+        {
+            let whole = if whole.is_empty() { 0.0 } else { whole.parse::<f64>().ok()? };
+
+            return Some(whole + value);
+        }
+    }
+
+    token.parse::<f64>().ok()
+}
+
+fn split_unit(text: &str) -> (Option<String>, &str)
+{
+    let (token, rest) = take_token(text);
+    let unit = token.trim_end_matches('.');
+
+    if UNITS.contains(&unit.to_lowercase().as_str())
+    {
+        (Some(unit.to_owned()), strip_of(rest))
+    }
+    else
+    {
+        (None, text)
+    }
+}
+
+fn split_attached_unit(token: &str) -> Option<(f64, String)>
+{
+    let pos = token.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+    let quantity = parse_number(&token[..pos])?;
+    let unit = token[pos..].trim_end_matches('.');
+
+    if UNITS.contains(&unit.to_lowercase().as_str())
+    {
+        Some((quantity, unit.to_owned()))
+    }
+    else
+    {
+        None
+    }
+}
+
+fn strip_of(text: &str) -> &str
+{
+    let text = text.trim_start();
+
+    text.strip_prefix("of ").unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_parse_ingredients()
+    {
+        let parse = |s: &str| Ingredient::parse(s).unwrap();
+
+        assert_eq!(parse("200 g flour"), Ingredient{ quantity: Some(200.0), unit: Some("g".to_owned()), name: "flour".to_owned() });
+        assert_eq!(parse("1 1/2 cups of milk"), Ingredient{ quantity: Some(1.5), unit: Some("cups".to_owned()), name: "milk".to_owned() });
+        assert_eq!(parse("½ tsp salt"), Ingredient{ quantity: Some(0.5), unit: Some("tsp".to_owned()), name: "salt".to_owned() });
+        assert_eq!(parse("500g potatoes, peeled"), Ingredient{ quantity: Some(500.0), unit: Some("g".to_owned()), name: "potatoes, peeled".to_owned() });
+        assert_eq!(parse("2 eggs"), Ingredient{ quantity: Some(2.0), unit: None, name: "eggs".to_owned() });
+        assert_eq!(parse("Salt and pepper"), Ingredient{ quantity: None, unit: None, name: "Salt and pepper".to_owned() });
+        assert_eq!(Ingredient::parse("  "), None);
+
+        assert_eq!(parse("500g potatoes, peeled").get_search_name(), "potatoes");
+    }
+
+    #[test]
+    fn test_quantities_and_times()
+    {
+        assert_eq!(format_quantity(1.5), "1 1/2");
+        assert_eq!(format_quantity(0.75), "3/4");
+        assert_eq!(format_quantity(2.0 / 3.0), "2/3");
+        assert_eq!(format_quantity(3.0), "3");
+        assert_eq!(format_quantity(0.999), "1");
+        assert_eq!(format_quantity(0.3), "0.3");
+
+        assert_eq!(parse_minutes("15"), Some(15));
+        assert_eq!(parse_minutes("15 min"), Some(15));
+        assert_eq!(parse_minutes("1 h 30 min"), Some(90));
+        assert_eq!(parse_minutes("2 hours"), Some(120));
+        assert_eq!(parse_minutes("1:30"), Some(90));
+        assert_eq!(parse_minutes("overnight"), None);
+        assert_eq!(parse_minutes("99999999 hours"), None);
+        assert_eq!(parse_minutes("99999999:30"), None);
+        assert_eq!(parse_minutes("4294967295 min 1 min"), None);
+    }
+
+    #[test]
+    fn test_recipe_notes()
+    {
+        let notes = NotesMarkdown::parse("Weekend pancakes.\n\nServes: 4\nPrep time: 10 min\nCook: 1 hour\nSource: https://example.com/pancakes\n\n## Ingredients\n\n- 1 1/2 cups flour\n- 2 eggs\n- salt\n\n## Method\n\n1. Mix everything\n   together\n2. Cook\n".to_owned()).unwrap();
+
+        let recipe = Recipe::from_notes(&notes).unwrap();
+
+        assert_eq!(recipe.description, "Weekend pancakes.");
+        assert_eq!(recipe.servings, Some(4));
+        assert_eq!(recipe.prep_minutes, Some(10));
+        assert_eq!(recipe.cook_minutes, Some(60));
+        assert_eq!(recipe.source_url, Some("https://example.com/pancakes".to_owned()));
+        assert_eq!(recipe.ingredients.len(), 3);
+        assert_eq!(recipe.steps, vec!["Mix everything together".to_owned(), "Cook".to_owned()]);
+        assert_eq!(recipe.get_search_names(), vec!["eggs".to_owned(), "flour".to_owned(), "salt".to_owned()]);
+
+        assert_eq!(Recipe::from_notes(&recipe.to_notes().unwrap()), Some(recipe.clone()));
+
+        let doubled = recipe.scale_to_servings(8);
+
+        assert_eq!(doubled.servings, Some(8));
+        assert_eq!(doubled.ingredients[0].to_string(), "3 cups flour");
+        assert_eq!(doubled.ingredients[2].to_string(), "salt");
+
+        assert_eq!(Recipe::from_notes(&NotesMarkdown::parse("Just some notes".to_owned()).unwrap()), None);
+
+        assert!(Recipe::can_edit_notes(&notes));
+        assert!(Recipe::can_edit_notes(&NotesMarkdown::parse("Just some notes\n\n## Ingredients\n\nTo taste".to_owned()).unwrap()));
+        assert!(!Recipe::can_edit_notes(&NotesMarkdown::parse("## Ingredients\n\n- flour\n\nFor the sauce:\n\n- butter\n".to_owned()).unwrap()));
+        assert!(!Recipe::can_edit_notes(&NotesMarkdown::parse("## Ingredients\n\n- flour\n\n### For the sauce\n\n- butter\n".to_owned()).unwrap()));
+        assert!(!Recipe::can_edit_notes(&NotesMarkdown::parse("## Ingredients\n\n- flour\n\n## Notes\n\nBest served warm\n".to_owned()).unwrap()));
+
+        let huge = Recipe::from_notes(&NotesMarkdown::parse("Prep: 99999999 hours\nCook: 4294967295 min\n\n## Ingredients\n\n- salt\n".to_owned()).unwrap()).unwrap();
+
+        assert_eq!(huge.prep_minutes, None);
+        assert_eq!(huge.cook_minutes, Some(u32::MAX));
+    }
+}
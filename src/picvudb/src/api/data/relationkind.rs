@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use crate::ParseError;
+
+/// The kind of a directional relation from
+/// one object to another.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RelationKind
+{
+    /// The object is the "before" of the other
+    BeforeAfter,
+    /// Both objects are part of the same panorama
+    Panorama,
+    /// The object is a scan of the print in the other
+    ScanOf,
+    Related,
+}
+
+impl RelationKind
+{
+    pub(crate) fn to_db_field(&self) -> i32
+    {
+        match self
+        {
+            Self::BeforeAfter => 1,
+            Self::Panorama => 2,
+            Self::ScanOf => 3,
+            Self::Related => 4,
+        }
+    }
+
+    pub(crate) fn from_db_field(val: i32) -> Result<Self, ParseError>
+    {
+        match val
+        {
+            1 => Ok(Self::BeforeAfter),
+            2 => Ok(Self::Panorama),
+            3 => Ok(Self::ScanOf),
+            4 => Ok(Self::Related),
+            _ => Err(ParseError::new(format!("Invalid RelationKind field {}", val))),
+        }
+    }
+
+    pub fn values() -> Vec<Self>
+    {
+        vec![
+            Self::BeforeAfter,
+            Self::Panorama,
+            Self::ScanOf,
+            Self::Related,
+        ]
+    }
+
+    /// Describes the other object, as seen from the
+    /// object the relation starts from
+    pub fn outgoing_label(&self) -> String
+    {
+        match self
+        {
+            Self::BeforeAfter => "After",
+            Self::Panorama => "Panorama",
+            Self::ScanOf => "Original",
+            Self::Related => "Related",
+        }.to_owned()
+    }
+
+    /// Describes the object the relation starts from,
+    /// as seen from the other object
+    pub fn incoming_label(&self) -> String
+    {
+        match self
+        {
+            Self::BeforeAfter => "Before",
+            Self::Panorama => "Panorama",
+            Self::ScanOf => "Scan",
+            Self::Related => "Related",
+        }.to_owned()
+    }
+}
+
+impl ToString for RelationKind
+{
+    fn to_string(&self) -> String
+    {
+        match self
+        {
+            Self::BeforeAfter => "BeforeAfter",
+            Self::Panorama => "Panorama",
+            Self::ScanOf => "ScanOf",
+            Self::Related => "Related",
+        }.to_owned()
+    }
+}
+
+impl std::str::FromStr for RelationKind
+{
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "BeforeAfter" => Ok(Self::BeforeAfter),
+            "Panorama" => Ok(Self::Panorama),
+            "ScanOf" => Ok(Self::ScanOf),
+            "Related" => Ok(Self::Related),
+            _ => Err(ParseError::new(format!("Invalid RelationKind {:?}", s))),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::data::ObjectId;
+
+/// A link to another object or tag, written in notes
+/// as `[[obj:1234]]` or `[[tag:Grandma]]`. Objects can
+/// be given either by their number or their ID string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WikiLink
+{
+    Object(ObjectId),
+    Tag(String),
+}
+
+pub(crate) enum WikiFragment<'a>
+{
+    Text(&'a str),
+    Link(WikiLink, &'a str),
+}
+
+impl WikiLink
+{
+    pub fn parse(text: &str) -> Option<WikiLink>
+    {
+        let text = text.trim();
+
+        if let Some(obj) = text.strip_prefix("obj:")
+        {
+            let obj = obj.trim();
+
+            if let Ok(num) = obj.parse::<i64>()
+            {
+                return Some(WikiLink::Object(ObjectId::from_db_field(num)));
+            }
+
+            ObjectId::try_new(obj.to_owned()).ok().map(WikiLink::Object)
+        }
+        else if let Some(tag) = text.strip_prefix("tag:")
+        {
+            let tag = tag.trim();
+
+            if tag.is_empty()
+            {
+                None
+            }
+            else
+            {
+                Some(WikiLink::Tag(tag.to_owned()))
+            }
+        }
+        else
+        {
+            None
+        }
+    }
+
+    pub fn to_markdown(&self) -> String
+    {
+        match self
+        {
+            WikiLink::Object(obj_id) => format!("[[obj:{}]]", obj_id.to_db_field()),
+            WikiLink::Tag(name) => format!("[[tag:{}]]", name),
+        }
+    }
+}
+
+/// Splits text into plain text and wiki links. Anything
+/// that looks like a link but isn't valid stays as text.
+pub(crate) fn split_wiki_links(text: &str) -> Vec<WikiFragment<'_>>
+{
+    let mut result = Vec::new();
+    let mut rest = text;
+    let mut text_start = 0;
+    let mut pos = 0;
+
+    while let Some(open) = rest.find("[[")
+    {
+        let after_open = &rest[open + 2..];
+
+        match after_open.find("]]")
+        {
+            Some(close) =>
+            {
+                let link_start = pos + open;
+                let link_end = link_start + 2 + close + 2;
+
+                if let Some(link) = WikiLink::parse(&after_open[..close])
+                {
+                    if text_start != link_start
+                    {
+                        result.push(WikiFragment::Text(&text[text_start..link_start]));
+                    }
+
+                    result.push(WikiFragment::Link(link, &text[link_start..link_end]));
+                    text_start = link_end;
+                    pos = link_end;
+                }
+                else
+                {
+                    pos = link_start + 2;
+                }
+
+                rest = &text[pos..];
+            },
+            None =>
+            {
+                break;
+            },
+        }
+    }
+
+    if text_start != text.len()
+    {
+        result.push(WikiFragment::Text(&text[text_start..]));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn links(text: &str) -> Vec<String>
+    {
+        split_wiki_links(text)
+            .iter()
+            .map(|f| match f
+            {
+                WikiFragment::Text(t) => format!("Text({})", t),
+                WikiFragment::Link(l, _) => l.to_markdown(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_wiki_links()
+    {
+        assert_eq!(links("no links"), vec!["Text(no links)"]);
+        assert_eq!(links("see [[obj:12]] and [[tag: Grandma ]]."), vec!["Text(see )", "[[obj:12]]", "Text( and )", "[[tag:Grandma]]", "Text(.)"]);
+        assert_eq!(links("[[obj:MW89]]"), vec!["[[obj:1]]"]);
+        assert_eq!(links("[[foo]] [[obj:x]] [[tag:]] [[tag:A]"), vec!["Text([[foo]] [[obj:x]] [[tag:]] [[tag:A])"]);
+        assert_eq!(links("[[[[tag:A]]]]"), vec!["Text([[)", "[[tag:A]]", "Text(]])"]);
+    }
+}
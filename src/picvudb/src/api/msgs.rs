@@ -98,8 +98,10 @@ impl ApiMessage for GetNumObjectsRequest
             data::get::GetObjectsQuery::ByObjectId(_) => 1,
             data::get::GetObjectsQuery::NearLocationByActivityDesc{ location, radius_meters } => ops.get_num_objects_near_location(location.latitude, location.longitude, *radius_meters)?,
             data::get::GetObjectsQuery::TitleNotesSearchByActivityDesc{ search } => ops.get_num_objects_for_text_search(search)?,
-            data::get::GetObjectsQuery::TagByActivityDesc{ tag_id } => ops.get_num_objects_with_tag(tag_id.to_db_field())?,
+            data::get::GetObjectsQuery::TagByActivityDesc{ tag_id, include_descendants } => ops.get_num_objects_with_tags(&query_tag_ids(ops, tag_id, *include_descendants)?)?,
             data::get::GetObjectsQuery::ActivityDateRangeByActivityDesc{ date_range } => ops.get_num_objects_in_activity_date_range(&date_range)?,
+            data::get::GetObjectsQuery::ExifByActivityDesc{ filter } => ops.get_num_objects_with_exif(filter)?,
+            data::get::GetObjectsQuery::IngredientByActivityDesc{ ingredient } => ops.get_num_objects_with_ingredient(ingredient)?,
         };
 
         let response = GetNumObjectsResponse
@@ -169,39 +171,27 @@ impl ApiMessage for GetObjectsRequest
             data::get::GetObjectsQuery::ByObjectId(obj_id) => ops.get_object_by_id(obj_id.to_db_field())?.iter().map(|o| { o.clone() }).collect(),
             data::get::GetObjectsQuery::NearLocationByActivityDesc{ location, radius_meters } => ops.get_objects_near_location_by_activity_desc(location.latitude, location.longitude, *radius_meters, pagination.offset, pagination.page_size)?,
             data::get::GetObjectsQuery::TitleNotesSearchByActivityDesc{ search } => ops.get_objects_for_text_search(search, pagination.offset, pagination.page_size)?,
-            data::get::GetObjectsQuery::TagByActivityDesc{ tag_id } => ops.get_objects_with_tag_by_activity_desc(tag_id.to_db_field(), pagination.offset, pagination.page_size)?,
+            data::get::GetObjectsQuery::TagByActivityDesc{ tag_id, include_descendants } => ops.get_objects_with_tags_by_activity_desc(&query_tag_ids(ops, tag_id, *include_descendants)?, pagination.offset, pagination.page_size)?,
             data::get::GetObjectsQuery::ActivityDateRangeByActivityDesc{ date_range } => ops.get_objects_in_activity_date_range(&date_range, pagination.offset, pagination.page_size)?,
+            data::get::GetObjectsQuery::ExifByActivityDesc{ filter } => ops.get_objects_with_exif_by_activity_desc(filter, pagination.offset, pagination.page_size)?,
+            data::get::GetObjectsQuery::IngredientByActivityDesc{ ingredient } => ops.get_objects_with_ingredient_by_activity_desc(ingredient, pagination.offset, pagination.page_size)?,
         };
 
         results.reserve(from_db.len());
 
         for object in from_db.drain(..)
         {
-            let attachment = 
-            {
-                if let Some(attachment) = ops.get_attachment_metadata(object.id)?
-                {
-                    data::get::AttachmentMetadata
-                    {
-                        filename: attachment.filename,
-                        created: data::Date::from_db_fields(attachment.created_timestamp, attachment.created_offset)?,
-                        modified: data::Date::from_db_fields(attachment.modified_timestamp, attachment.modified_offset)?,
-                        mime: attachment.mime.parse::<mime::Mime>()?,
-                        size: attachment.size as u64,
-                        orientation: data::Orientation::from_db_field(attachment.orientation)?,
-                        dimensions: data::Dimensions::from_db_fields(attachment.width, attachment.height),
-                        duration: data::Duration::from_db_field(attachment.duration)?,
-                        hash: attachment.hash,
-                    }
-                }
-                else
-                {
-                    return Err(Error::DatabaseConsistencyError
-                    {
-                        msg: format!("Object {} contains no attachment metadata", object.id.to_string()),
-                    });
-                }
-            };
+            let (mut attachments, previous_versions): (Vec<_>, Vec<_>) = ops.get_attachments_metadata(object.id)?
+                .into_iter()
+                .map(data::get::AttachmentMetadata::from_db_model)
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .partition(|a| !a.is_previous_version);
+
+            // Objects such as notes don't have any attachments
+
+            let attachment = if attachments.is_empty() { None } else { Some(attachments.remove(0)) };
+            let additional_attachments = attachments;
 
             let location = match object.location_source
             {
@@ -239,13 +229,7 @@ impl ApiMessage for GetObjectsRequest
                 {
                     let tag_data = ops.get_tag(*tag_id)?;
 
-                    tags.push(data::get::TagMetadata {
-                        tag_id: data::TagId::from_db_field(tag_data.tag_id),
-                        name: tag_data.tag_name,
-                        kind: data::TagKind::from_db_field(tag_data.tag_kind)?,
-                        rating: data::Rating::from_db_field(tag_data.tag_rating)?,
-                        censor: data::Censor::from_db_field(tag_data.tag_censor)?,
-                    });
+                    tags.push(data::get::TagMetadata::from_db_model(tag_data)?);
                 }
             }
             tags.sort_by(|a, b| crate::stem::cmp(&a.name, &b.name));
@@ -255,15 +239,19 @@ impl ApiMessage for GetObjectsRequest
                 id: data::ObjectId::from_db_field(object.id),
                 created_time: data::Date::from_db_fields(object.created_timestamp, object.created_offset)?,
                 modified_time: data::Date::from_db_fields(object.modified_timestamp, object.modified_offset)?,
-                activity_time: data::Date::from_db_fields(object.activity_timestamp, object.activity_offset)?,
+                activity_time: data::Date::from_db_fields_with_nanos(object.activity_timestamp, object.activity_nanos, object.activity_offset)?,
                 title: data::TitleMarkdown::from_db_field(object.title)?,
                 notes: data::NotesMarkdown::from_db_field(object.notes)?,
                 rating: data::Rating::from_db_field(object.rating)?,
                 censor: data::Censor::from_db_field(object.censor)?,
                 location: location,
                 attachment: attachment,
+                additional_attachments: additional_attachments,
+                previous_versions: previous_versions,
                 tags: tags,
                 ext_ref: data::ExternalReference::from_db_fields(object.ext_ref_type, object.ext_ref_id)?,
+                user_orientation: data::Orientation::from_db_field(object.user_orientation)?,
+                crop: data::Crop::from_db_fields(object.crop_left, object.crop_top, object.crop_width, object.crop_height)?,
             });
         }
 
@@ -354,16 +342,36 @@ impl ApiMessage for GetTagRequest
     {
         let tag_data = ops.get_tag(self.tag_id.to_db_field())?;
 
-        let tag = data::get::TagMetadata
+        let tag = data::get::TagMetadata::from_db_model(tag_data)?;
+
+        let mut ancestors = Vec::new();
+        let mut parent_id = tag.parent_id.clone();
+
+        while let Some(id) = parent_id
         {
-            tag_id: data::TagId::from_db_field(tag_data.tag_id),
-            name: tag_data.tag_name,
-            kind: data::TagKind::from_db_field(tag_data.tag_kind)?,
-            rating: data::Rating::from_db_field(tag_data.tag_rating)?,
-            censor: data::Censor::from_db_field(tag_data.tag_censor)?,
-        };
+            if id == tag.tag_id
+                || ancestors.iter().any(|a: &data::get::TagMetadata| a.tag_id == id)
+            {
+                break;
+            }
+
+            let parent = data::get::TagMetadata::from_db_model(ops.get_tag(id.to_db_field())?)?;
+            parent_id = parent.parent_id.clone();
+            ancestors.insert(0, parent);
+        }
+
+        let mut children = Vec::new();
+
+        for child_data in ops.get_tag_children(tag.tag_id.to_db_field())?
+        {
+            children.push(data::get::TagMetadata::from_db_model(child_data)?);
+        }
+
+        children.sort_by(|a, b| crate::stem::cmp(&a.name, &b.name));
 
-        Ok(GetTagResponse{ tag })
+        let aliases = ops.get_tag_aliases(tag.tag_id.to_db_field())?;
+
+        Ok(GetTagResponse{ tag, ancestors, children, aliases })
     }
 }
 
@@ -371,6 +379,60 @@ impl ApiMessage for GetTagRequest
 pub struct GetTagResponse
 {
     pub tag: data::get::TagMetadata,
+    /// The tag's parents, starting from the top
+    /// of the hierarchy
+    pub ancestors: Vec<data::get::TagMetadata>,
+    pub children: Vec<data::get::TagMetadata>,
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GetTagTreeRequest
+{
+}
+
+impl ApiMessage for GetTagTreeRequest
+{
+    type Response = GetTagTreeResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let mut tags = Vec::new();
+
+        for tag_data in ops.get_all_tags()?
+        {
+            tags.push(data::get::TagMetadata::from_db_model(tag_data)?);
+        }
+
+        tags.sort_by(|a, b| crate::stem::cmp(&a.name, &b.name));
+
+        // Any tag whose parent is missing is treated
+        // as a root, so every tag appears once
+
+        let ids: std::collections::HashSet<i64> = tags.iter().map(|t| t.tag_id.to_db_field()).collect();
+
+        let mut by_parent: HashMap<Option<i64>, Vec<data::get::TagMetadata>> = HashMap::new();
+
+        for tag in tags
+        {
+            let parent = tag.parent_id.as_ref()
+                .map(|p| p.to_db_field())
+                .filter(|p| ids.contains(p));
+
+            by_parent.entry(parent).or_insert_with(Vec::new).push(tag);
+        }
+
+        let roots = build_tag_tree(&mut by_parent, None);
+
+        Ok(GetTagTreeResponse{ roots })
+    }
+}
+
+#[derive(Debug)]
+pub struct GetTagTreeResponse
+{
+    pub roots: Vec<data::get::TagTreeNode>,
 }
 
 #[derive(Debug)]
@@ -393,14 +455,7 @@ impl ApiMessage for SearchTagsRequest
 
         for tag_data in db_tags
         {
-            tags.push(data::get::TagMetadata
-            {
-                tag_id: data::TagId::from_db_field(tag_data.tag_id),
-                name: tag_data.tag_name,
-                kind: data::TagKind::from_db_field(tag_data.tag_kind)?,
-                rating: data::Rating::from_db_field(tag_data.tag_rating)?,
-                censor: data::Censor::from_db_field(tag_data.tag_censor)?,
-            });
+            tags.push(data::get::TagMetadata::from_db_model(tag_data)?);
         }
 
         tags.sort_by(|a, b| crate::stem::cmp(&a.name, &b.name));
@@ -415,6 +470,202 @@ pub struct SearchTagsResponse
     pub tags: Vec<data::get::TagMetadata>,
 }
 
+#[derive(Debug)]
+pub struct GetObjectLinksRequest
+{
+    pub object_id: data::ObjectId,
+}
+
+impl ApiMessage for GetObjectLinksRequest
+{
+    type Response = GetObjectLinksResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let get_object = |obj_id: data::ObjectId| -> Result<Option<data::get::ObjectMetadata>, Error>
+        {
+            let response = GetObjectsRequest
+            {
+                query: data::get::GetObjectsQuery::ByObjectId(obj_id),
+                pagination: None,
+            }.execute(ops)?;
+
+            Ok(response.objects.into_iter().next())
+        };
+
+        let notes = get_object(self.object_id.clone())?.and_then(|o| o.notes);
+
+        // Links to objects or tags that don't exist
+        // are left out, and so aren't resolved
+
+        let mut linked_objects = Vec::new();
+        let mut linked_tags = Vec::new();
+
+        for link in notes.map(|n| n.get_wiki_links()).unwrap_or_default()
+        {
+            match link
+            {
+                data::WikiLink::Object(obj_id) =>
+                {
+                    if let Some(object) = get_object(obj_id)?
+                    {
+                        linked_objects.push(object);
+                    }
+                },
+                data::WikiLink::Tag(name) =>
+                {
+                    if let Some(tag_data) = ops.get_tag_by_name(&name)?
+                    {
+                        linked_tags.push((name, data::get::TagMetadata::from_db_model(tag_data)?));
+                    }
+                },
+            }
+        }
+
+        let mut referenced_by = Vec::new();
+
+        for obj_id in ops.get_obj_ids_linking_to(self.object_id.to_db_field())?
+        {
+            if let Some(object) = get_object(data::ObjectId::from_db_field(obj_id))?
+            {
+                referenced_by.push(object);
+            }
+        }
+
+        Ok(GetObjectLinksResponse
+        {
+            linked_objects,
+            linked_tags,
+            referenced_by,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GetObjectLinksResponse
+{
+    pub linked_objects: Vec<data::get::ObjectMetadata>,
+    /// The tags, along with the name used in the link,
+    /// which may be one of the tag's aliases
+    pub linked_tags: Vec<(String, data::get::TagMetadata)>,
+    pub referenced_by: Vec<data::get::ObjectMetadata>,
+}
+
+#[derive(Debug)]
+pub struct GetObjectRelationsRequest
+{
+    pub object_id: data::ObjectId,
+}
+
+impl ApiMessage for GetObjectRelationsRequest
+{
+    type Response = GetObjectRelationsResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let obj_id = self.object_id.to_db_field();
+        let mut relations = Vec::new();
+
+        for relation in ops.get_object_relations(obj_id)?
+        {
+            let outgoing = relation.from_obj_id == obj_id;
+            let other_id = if outgoing { relation.to_obj_id } else { relation.from_obj_id };
+
+            let other = GetObjectsRequest
+            {
+                query: data::get::GetObjectsQuery::ByObjectId(data::ObjectId::from_db_field(other_id)),
+                pagination: None,
+            }.execute(ops)?;
+
+            if let Some(object) = other.objects.into_iter().next()
+            {
+                relations.push(data::get::RelatedObject
+                {
+                    kind: data::RelationKind::from_db_field(relation.relation_kind)?,
+                    outgoing,
+                    object,
+                });
+            }
+        }
+
+        Ok(GetObjectRelationsResponse{ relations })
+    }
+}
+
+#[derive(Debug)]
+pub struct GetObjectRelationsResponse
+{
+    pub relations: Vec<data::get::RelatedObject>,
+}
+
+#[derive(Debug)]
+pub struct AddObjectRelationRequest
+{
+    pub from: data::ObjectId,
+    pub to: data::ObjectId,
+    pub kind: data::RelationKind,
+}
+
+impl ApiMessage for AddObjectRelationRequest
+{
+    type Response = AddObjectRelationResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        if self.from == self.to
+        {
+            return Ok(AddObjectRelationResponse::SameObject);
+        }
+
+        if ops.get_object_by_id(self.from.to_db_field())?.is_none()
+            || ops.get_object_by_id(self.to.to_db_field())?.is_none()
+        {
+            return Ok(AddObjectRelationResponse::ObjectNotFound);
+        }
+
+        ops.add_object_relation(self.from.to_db_field(), self.to.to_db_field(), self.kind.clone())?;
+
+        Ok(AddObjectRelationResponse::Added)
+    }
+}
+
+#[derive(Debug)]
+pub enum AddObjectRelationResponse
+{
+    ObjectNotFound,
+    SameObject,
+    Added,
+}
+
+#[derive(Debug)]
+pub struct RemoveObjectRelationRequest
+{
+    pub from: data::ObjectId,
+    pub to: data::ObjectId,
+    pub kind: data::RelationKind,
+}
+
+impl ApiMessage for RemoveObjectRelationRequest
+{
+    type Response = RemoveObjectRelationResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        ops.remove_object_relation(self.from.to_db_field(), self.to.to_db_field(), self.kind.clone())?;
+
+        Ok(RemoveObjectRelationResponse{})
+    }
+}
+
+#[derive(Debug)]
+pub struct RemoveObjectRelationResponse
+{
+}
+
 #[derive(Debug)]
 pub struct AddObjectRequest
 {
@@ -432,11 +683,7 @@ impl ApiMessage for AddObjectRequest
 
         for t in self.data.tags.iter()
         {
-            let tag_id = ops.find_or_add_tag(
-                t.name.clone(),
-                t.kind.clone(),
-                t.rating.clone(),
-                t.censor.clone())?;
+            let tag_id = find_or_add_tag_with_parent(ops, t)?;
 
             tag_ids.insert(tag_id);
         }
@@ -451,18 +698,52 @@ impl ApiMessage for AddObjectRequest
             self.data.censor.clone(),
             self.data.location.clone(),
             data::TagSet::from_db_set(&tag_ids),
-            self.data.ext_ref.clone())?;
+            self.data.ext_ref.clone(),
+            self.data.user_orientation.clone(),
+            self.data.crop.clone())?;
 
-        ops.add_attachment(
-            object_id.to_db_field(),
-            self.data.attachment.filename.clone(),
-            self.data.attachment.created.clone(),
-            self.data.attachment.modified.clone(),
-            self.data.attachment.mime.to_string(),
-            self.data.attachment.orientation.clone(),
-            self.data.attachment.dimensions.clone(),
-            self.data.attachment.duration.clone(),
-            self.data.attachment.bytes.clone())?;
+        // The primary attachment is always first, then any additional
+        // attachments (such as RAW files or live photo movies) in order.
+        // Notes don't have any attachments at all.
+
+        let all_attachments = self.data.attachment.iter()
+            .chain(self.data.additional_attachments.iter());
+
+        for (att_index, attachment) in all_attachments.enumerate()
+        {
+            ops.add_attachment(
+                object_id.to_db_field(),
+                att_index as i32,
+                attachment.role.clone(),
+                attachment.filename.clone(),
+                attachment.created.clone(),
+                attachment.modified.clone(),
+                attachment.mime.to_string(),
+                attachment.orientation.clone(),
+                attachment.dimensions.clone(),
+                attachment.duration.clone(),
+                attachment.bytes.clone())?;
+        }
+
+        // The searchable text comes from every document
+        // attachment, starting with the primary attachment
+
+        let texts = self.data.attachment.iter()
+            .chain(self.data.additional_attachments.iter())
+            .filter_map(|a| a.text.clone())
+            .collect::<Vec<_>>();
+
+        if !texts.is_empty()
+        {
+            ops.set_document_text(object_id.to_db_field(), Some(texts.join("\n\n")))?;
+        }
+
+        if let Some(exif) = self.data.attachment.as_ref().and_then(|a| a.exif.clone())
+        {
+            ops.set_attachment_exif(
+                object_id.to_db_field(),
+                Some(exif))?;
+        }
 
         for tag_id in tag_ids
         {
@@ -493,37 +774,31 @@ impl ApiMessage for GetAttachmentDataRequest
 
     fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
     {
-        let metadata = ops.get_attachment_metadata(self.object_id.to_db_field())?;
-        match metadata
+        let attachments = ops.get_attachments_metadata(self.object_id.to_db_field())?;
+
+        if attachments.is_empty()
+        {
+            return Ok(GetAttachmentDataResponse::ObjectNotFound);
+        }
+
+        // Without a specific hash, the primary attachment is returned
+
+        let found = attachments.into_iter()
+            .find(|a| self.specific_hash.is_none()
+                || (*self.specific_hash.as_ref().unwrap() == a.hash));
+
+        match found
         {
-            None => Ok(GetAttachmentDataResponse::ObjectNotFound),
+            None => Ok(GetAttachmentDataResponse::HashNotFound),
             Some(metadata) =>
             {
-                let metadata = data::get::AttachmentMetadata
-                {
-                    filename: metadata.filename,
-                    created: data::Date::from_db_fields(metadata.created_timestamp, metadata.created_offset)?,
-                    modified: data::Date::from_db_fields(metadata.modified_timestamp, metadata.modified_offset)?,
-                    mime: metadata.mime.parse::<mime::Mime>()?,
-                    size: metadata.size as u64,
-                    orientation: data::Orientation::from_db_field(metadata.orientation)?,
-                    dimensions: data::Dimensions::from_db_fields(metadata.width, metadata.height),
-                    duration: data::Duration::from_db_field(metadata.duration)?,
-                    hash: metadata.hash,
-                };
-
-                if self.specific_hash.is_none()
-                    || (*self.specific_hash.as_ref().unwrap() == metadata.hash)
-                {
-                    let bytes = ops.get_attachment_data(self.object_id.to_db_field())?
-                        .ok_or(Error::DatabaseConsistencyError{ msg: format!("Object {} contains attachment metadata but no attachment data", self.object_id.to_db_field()) })?;
-                    
-                    Ok(GetAttachmentDataResponse::Found{metadata, bytes})
-                }
-                else
-                {
-                    Ok(GetAttachmentDataResponse::HashNotFound)
-                }
+                let att_index = metadata.att_index;
+                let metadata = data::get::AttachmentMetadata::from_db_model(metadata)?;
+
+                let bytes = ops.get_attachment_data(self.object_id.to_db_field(), att_index)?
+                    .ok_or(Error::DatabaseConsistencyError{ msg: format!("Object {} contains attachment metadata but no attachment data", self.object_id.to_db_field()) })?;
+
+                Ok(GetAttachmentDataResponse::Found{metadata, bytes})
             },
         }
     }
@@ -542,104 +817,562 @@ pub enum GetAttachmentDataResponse
 }
 
 #[derive(Debug)]
-pub struct UpdateObjectRequest
+pub struct GetAttachmentExifRequest
 {
     pub object_id: data::ObjectId,
-    pub activity_time: data::Date,
-    pub title: Option<data::TitleMarkdown>,
-    pub notes: Option<data::NotesMarkdown>,
-    pub rating: data::Rating,
-    pub censor: data::Censor,
-    pub location: Option<data::Location>,
 }
 
-impl ApiMessage for UpdateObjectRequest
+impl ApiMessage for GetAttachmentExifRequest
 {
-    type Response = UpdateObjectResponse;
+    type Response = GetAttachmentExifResponse;
     type Error = Error;
 
     fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
     {
-        ops.update_object(
-            self.object_id.to_db_field(),
-            self.activity_time.clone(),
-            self.title.clone(),
-            self.notes.clone(),
-            self.rating.clone(),
-            self.censor.clone(),
-            self.location.clone())?;
+        let exif = ops.get_attachment_exif(self.object_id.to_db_field())?
+            .map(data::AttachmentExif::from_db_model);
 
-        Ok(UpdateObjectResponse{})
+        Ok(GetAttachmentExifResponse{ exif })
     }
 }
 
 #[derive(Debug)]
-pub struct UpdateObjectResponse
+pub struct GetAttachmentExifResponse
 {
+    pub exif: Option<data::AttachmentExif>,
 }
 
 #[derive(Debug)]
-pub struct UpdateTagRequest
+pub struct SetAttachmentExifRequest
 {
-    pub tag_id: data::TagId,
-    pub name: String,
-    pub rating: data::Rating,
-    pub censor: data::Censor,
-    pub kind: data::TagKind,
+    pub object_id: data::ObjectId,
+    pub exif: Option<data::AttachmentExif>,
 }
 
-impl ApiMessage for UpdateTagRequest
+impl ApiMessage for SetAttachmentExifRequest
 {
-    type Response = UpdateTagResponse;
+    type Response = SetAttachmentExifResponse;
     type Error = Error;
 
     fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
     {
-        ops.update_tag(
-            self.tag_id.clone(),
-            self.name.clone(),
-            self.rating.clone(),
-            self.censor.clone(),
-            self.kind.clone())?;
+        ops.set_attachment_exif(self.object_id.to_db_field(), self.exif.clone())?;
 
-        Ok(UpdateTagResponse{})
+        Ok(SetAttachmentExifResponse{})
     }
 }
 
 #[derive(Debug)]
-pub struct UpdateTagResponse
+pub struct SetAttachmentExifResponse
 {
 }
 
 #[derive(Debug)]
-pub struct UpdateObjectTagsRequest
+pub struct SetDocumentTextRequest
 {
     pub object_id: data::ObjectId,
-    pub remove: Vec<data::TagId>,
-    pub add: Vec<data::add::Tag>,
+    pub text: Option<String>,
 }
 
-impl ApiMessage for UpdateObjectTagsRequest
+impl ApiMessage for SetDocumentTextRequest
 {
-    type Response = UpdateObjectTagsResponse;
+    type Response = SetDocumentTextResponse;
     type Error = Error;
 
     fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
     {
-        // First, get the object
+        ops.set_document_text(self.object_id.to_db_field(), self.text.clone())?;
 
-        let object = match ops.get_object_by_id(self.object_id.to_db_field())?
-        {
-            None => return Err(Error::DatabaseConsistencyError{ msg: format!("Object {:?} doesn't exist", self.object_id) }),
-            Some(object) => object,
-        };
+        Ok(SetDocumentTextResponse{})
+    }
+}
 
-        let orig_tag_set = data::TagSet::from_db_field(object.tag_set)?.to_db_set();
-        let mut new_tag_set = orig_tag_set.clone();
+#[derive(Debug)]
+pub struct SetDocumentTextResponse
+{
+}
 
-        // Remove the specified tags from the object,
-        // deleting the tag if this is the last object
-        // that has the tag
+#[derive(Debug)]
+pub struct ReplaceAttachmentRequest
+{
+    pub object_id: data::ObjectId,
+    pub hash: String,
+    pub attachment: data::add::Attachment,
+}
+
+impl ApiMessage for ReplaceAttachmentRequest
+{
+    type Response = ReplaceAttachmentResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let obj_id = self.object_id.to_db_field();
+        let attachments = ops.get_attachments_metadata(obj_id)?;
+
+        if attachments.is_empty()
+        {
+            return Ok(ReplaceAttachmentResponse::ObjectNotFound);
+        }
+
+        let slot = match attachments.iter()
+            .find(|a| a.version_of.is_none() && a.hash == self.hash)
+        {
+            Some(current) => current.att_index,
+            None => return Ok(ReplaceAttachmentResponse::HashNotFound),
+        };
+
+        // The current attachment is kept as a previous
+        // version, moved past the end of the existing
+        // attachments, and the new one takes its place
+
+        let history_index = attachments.iter().map(|a| a.att_index).max().unwrap_or(0) + 1;
+
+        ops.move_attachment(obj_id, slot, history_index, Some(slot))?;
+
+        ops.add_attachment(
+            obj_id,
+            slot,
+            data::AttachmentRole::Edited,
+            self.attachment.filename.clone(),
+            self.attachment.created.clone(),
+            self.attachment.modified.clone(),
+            self.attachment.mime.to_string(),
+            self.attachment.orientation.clone(),
+            self.attachment.dimensions.clone(),
+            self.attachment.duration.clone(),
+            self.attachment.bytes.clone())?;
+
+        if slot == 0
+        {
+            ops.set_attachment_exif(obj_id, self.attachment.exif.clone())?;
+            ops.set_document_text(obj_id, self.attachment.text.clone())?;
+        }
+
+        Ok(ReplaceAttachmentResponse::Replaced)
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplaceAttachmentResponse
+{
+    ObjectNotFound,
+    HashNotFound,
+    Replaced,
+}
+
+#[derive(Debug)]
+pub struct RevertAttachmentRequest
+{
+    pub object_id: data::ObjectId,
+    pub hash: String,
+    pub exif: Option<data::AttachmentExif>,
+    pub text: Option<String>,
+}
+
+impl ApiMessage for RevertAttachmentRequest
+{
+    type Response = RevertAttachmentResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let obj_id = self.object_id.to_db_field();
+        let attachments = ops.get_attachments_metadata(obj_id)?;
+
+        if attachments.is_empty()
+        {
+            return Ok(RevertAttachmentResponse::ObjectNotFound);
+        }
+
+        let (version_index, slot) = match attachments.iter()
+            .find(|a| a.version_of.is_some() && a.hash == self.hash)
+        {
+            Some(version) => (version.att_index, version.version_of.unwrap()),
+            None => return Ok(RevertAttachmentResponse::HashNotFound),
+        };
+
+        // The attachment currently in the slot becomes
+        // a previous version itself, so nothing is lost
+
+        let history_index = attachments.iter().map(|a| a.att_index).max().unwrap_or(0) + 1;
+
+        ops.move_attachment(obj_id, slot, history_index, Some(slot))?;
+        ops.move_attachment(obj_id, version_index, slot, None)?;
+
+        if slot == 0
+        {
+            ops.set_attachment_exif(obj_id, self.exif.clone())?;
+            ops.set_document_text(obj_id, self.text.clone())?;
+        }
+
+        Ok(RevertAttachmentResponse::Reverted)
+    }
+}
+
+#[derive(Debug)]
+pub enum RevertAttachmentResponse
+{
+    ObjectNotFound,
+    HashNotFound,
+    Reverted,
+}
+
+#[derive(Debug)]
+pub struct GetExifCamerasRequest
+{
+}
+
+impl ApiMessage for GetExifCamerasRequest
+{
+    type Response = GetExifCamerasResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let cameras = ops.get_exif_cameras()?
+            .into_iter()
+            .map(|(make, model, num)| data::get::CameraSummary
+            {
+                make,
+                model,
+                num_objects: num as u64,
+            })
+            .collect();
+
+        Ok(GetExifCamerasResponse{ cameras })
+    }
+}
+
+#[derive(Debug)]
+pub struct GetExifCamerasResponse
+{
+    pub cameras: Vec<data::get::CameraSummary>,
+}
+
+#[derive(Debug)]
+pub struct UpdateObjectRequest
+{
+    pub object_id: data::ObjectId,
+    pub activity_time: data::Date,
+    pub title: Option<data::TitleMarkdown>,
+    pub notes: Option<data::NotesMarkdown>,
+    pub rating: data::Rating,
+    pub censor: data::Censor,
+    pub location: Option<data::Location>,
+}
+
+impl ApiMessage for UpdateObjectRequest
+{
+    type Response = UpdateObjectResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        ops.update_object(
+            self.object_id.to_db_field(),
+            self.activity_time.clone(),
+            self.title.clone(),
+            self.notes.clone(),
+            self.rating.clone(),
+            self.censor.clone(),
+            self.location.clone())?;
+
+        Ok(UpdateObjectResponse{})
+    }
+}
+
+#[derive(Debug)]
+pub struct UpdateObjectResponse
+{
+}
+
+/// Re-indexes the ingredients of an object's recipe, for
+/// objects whose notes were written before recipes were indexed.
+#[derive(Debug)]
+pub struct ReindexRecipeRequest
+{
+    pub object_id: data::ObjectId,
+}
+
+impl ApiMessage for ReindexRecipeRequest
+{
+    type Response = ReindexRecipeResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let is_recipe = ops.reindex_recipe(self.object_id.to_db_field())?;
+
+        Ok(ReindexRecipeResponse{ is_recipe })
+    }
+}
+
+#[derive(Debug)]
+pub struct ReindexRecipeResponse
+{
+    pub is_recipe: bool,
+}
+
+#[derive(Debug)]
+pub struct UpdateTagRequest
+{
+    pub tag_id: data::TagId,
+    pub name: String,
+    pub rating: data::Rating,
+    pub censor: data::Censor,
+    pub kind: data::TagKind,
+}
+
+impl ApiMessage for UpdateTagRequest
+{
+    type Response = UpdateTagResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let name = self.name.trim().to_owned();
+
+        // A tag can't be renamed to the name or alias of
+        // another tag - they need to be merged instead
+
+        if let Some(existing) = ops.get_tag_by_name(&name)?
+        {
+            if existing.tag_id != self.tag_id.to_db_field()
+            {
+                return Ok(UpdateTagResponse::NameConflict{ existing: data::get::TagMetadata::from_db_model(existing)? });
+            }
+        }
+
+        // If the new name was one of this tag's
+        // aliases, it's not needed any more
+
+        ops.remove_tag_alias(&name)?;
+
+        ops.update_tag(
+            self.tag_id.clone(),
+            name,
+            self.rating.clone(),
+            self.censor.clone(),
+            self.kind.clone())?;
+
+        Ok(UpdateTagResponse::Updated)
+    }
+}
+
+#[derive(Debug)]
+pub enum UpdateTagResponse
+{
+    Updated,
+    NameConflict{ existing: data::get::TagMetadata },
+}
+
+/// Moves every object from one tag to another, and then
+/// deletes the old tag. The old tag's name is kept as
+/// an alias of the new one.
+#[derive(Debug)]
+pub struct MergeTagsRequest
+{
+    pub from: data::TagId,
+    pub into: data::TagId,
+}
+
+impl ApiMessage for MergeTagsRequest
+{
+    type Response = MergeTagsResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        if self.from == self.into
+        {
+            return Ok(MergeTagsResponse::SameTag);
+        }
+
+        let from_parent_id = ops.get_tag(self.from.to_db_field())?.tag_parent_id;
+
+        ops.merge_tags(self.from.to_db_field(), self.into.to_db_field())?;
+
+        delete_unused_parent_tags(ops, from_parent_id)?;
+
+        Ok(MergeTagsResponse::Merged)
+    }
+}
+
+#[derive(Debug)]
+pub enum MergeTagsResponse
+{
+    Merged,
+    SameTag,
+}
+
+#[derive(Debug)]
+pub struct AddTagAliasRequest
+{
+    pub tag_id: data::TagId,
+    pub alias: String,
+}
+
+impl ApiMessage for AddTagAliasRequest
+{
+    type Response = AddTagAliasResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let alias = self.alias.trim().to_owned();
+
+        if let Some(existing) = ops.get_tag_by_name(&alias)?
+        {
+            if existing.tag_id != self.tag_id.to_db_field()
+            {
+                return Ok(AddTagAliasResponse::NameConflict{ existing: data::get::TagMetadata::from_db_model(existing)? });
+            }
+
+            // Already the tag's name or one of its aliases
+
+            return Ok(AddTagAliasResponse::Added);
+        }
+
+        ops.add_tag_alias(alias, self.tag_id.to_db_field())?;
+
+        Ok(AddTagAliasResponse::Added)
+    }
+}
+
+#[derive(Debug)]
+pub enum AddTagAliasResponse
+{
+    Added,
+    NameConflict{ existing: data::get::TagMetadata },
+}
+
+#[derive(Debug)]
+pub struct RemoveTagAliasRequest
+{
+    pub alias: String,
+}
+
+impl ApiMessage for RemoveTagAliasRequest
+{
+    type Response = RemoveTagAliasResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        ops.remove_tag_alias(&self.alias)?;
+
+        Ok(RemoveTagAliasResponse{})
+    }
+}
+
+#[derive(Debug)]
+pub struct RemoveTagAliasResponse
+{
+}
+
+#[derive(Debug)]
+pub struct SetTagParentRequest
+{
+    pub tag_id: data::TagId,
+    pub parent_id: Option<data::TagId>,
+}
+
+impl ApiMessage for SetTagParentRequest
+{
+    type Response = SetTagParentResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        if let Some(parent_id) = &self.parent_id
+        {
+            // The new parent can't be the tag itself,
+            // or anything below it
+
+            if query_tag_ids(ops, &self.tag_id, true)?.contains(&parent_id.to_db_field())
+            {
+                return Ok(SetTagParentResponse::WouldCreateCycle);
+            }
+
+            ops.get_tag(parent_id.to_db_field())?;
+        }
+
+        ops.set_tag_parent(self.tag_id.to_db_field(), self.parent_id.as_ref().map(|p| p.to_db_field()))?;
+
+        Ok(SetTagParentResponse::Updated)
+    }
+}
+
+#[derive(Debug)]
+pub enum SetTagParentResponse
+{
+    Updated,
+    WouldCreateCycle,
+}
+
+/// Deletes a tag that no objects are tagged with.
+/// Any child tags are moved up to the deleted
+/// tag's parent, and parents left without any
+/// objects or children are deleted too.
+#[derive(Debug)]
+pub struct DeleteTagRequest
+{
+    pub tag_id: data::TagId,
+}
+
+impl ApiMessage for DeleteTagRequest
+{
+    type Response = DeleteTagResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        let parent_id = ops.get_tag(self.tag_id.to_db_field())?.tag_parent_id;
+
+        ops.delete_tag(&self.tag_id)?;
+
+        delete_unused_parent_tags(ops, parent_id)?;
+
+        Ok(DeleteTagResponse{})
+    }
+}
+
+#[derive(Debug)]
+pub struct DeleteTagResponse
+{
+}
+
+#[derive(Debug)]
+pub struct UpdateObjectTagsRequest
+{
+    pub object_id: data::ObjectId,
+    pub remove: Vec<data::TagId>,
+    pub add: Vec<data::add::Tag>,
+}
+
+impl ApiMessage for UpdateObjectTagsRequest
+{
+    type Response = UpdateObjectTagsResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        // First, get the object
+
+        let object = match ops.get_object_by_id(self.object_id.to_db_field())?
+        {
+            None => return Err(Error::DatabaseConsistencyError{ msg: format!("Object {:?} doesn't exist", self.object_id) }),
+            Some(object) => object,
+        };
+
+        let orig_tag_set = data::TagSet::from_db_field(object.tag_set)?.to_db_set();
+        let mut new_tag_set = orig_tag_set.clone();
+
+        // Remove the specified tags from the object,
+        // deleting the tag if this is the last object
+        // that has the tag. Tags with children are kept,
+        // as they still group the objects below them
 
         for tag_id in &self.remove
         {
@@ -647,9 +1380,14 @@ impl ApiMessage for UpdateObjectTagsRequest
             {
                 ops.remove_object_tag(self.object_id.to_db_field(), tag_id.to_db_field())?;
 
-                if ops.get_num_objects_with_tag(tag_id.to_db_field())? == 0
+                if ops.get_num_objects_with_tags(&[tag_id.to_db_field()])? == 0
+                    && ops.get_tag_children(tag_id.to_db_field())?.is_empty()
                 {
+                    let parent_id = ops.get_tag(tag_id.to_db_field())?.tag_parent_id;
+
                     ops.delete_tag(tag_id)?;
+
+                    delete_unused_parent_tags(ops, parent_id)?;
                 }
 
                 new_tag_set.remove(&tag_id.to_db_field());
@@ -660,7 +1398,7 @@ impl ApiMessage for UpdateObjectTagsRequest
 
         for tag in &self.add
         {
-            let tag_id = ops.find_or_add_tag(tag.name.clone(), tag.kind.clone(), tag.rating.clone(), tag.censor.clone())?;
+            let tag_id = find_or_add_tag_with_parent(ops, tag)?;
 
             ops.add_object_tag(self.object_id.to_db_field(), tag_id)?;
 
@@ -681,6 +1419,39 @@ pub struct UpdateObjectTagsResponse
 {
 }
 
+#[derive(Debug)]
+pub struct UpdateObjectAdjustmentsRequest
+{
+    pub object_id: data::ObjectId,
+    pub user_orientation: Option<data::Orientation>,
+    pub crop: Option<data::Crop>,
+}
+
+impl ApiMessage for UpdateObjectAdjustmentsRequest
+{
+    type Response = UpdateObjectAdjustmentsResponse;
+    type Error = Error;
+
+    fn execute(&self, ops: &dyn WriteOps) -> Result<Self::Response, Self::Error>
+    {
+        // A crop covering the whole image is the same as no crop
+
+        let crop = self.crop.clone().filter(|c| !c.is_full_image());
+
+        ops.update_object_adjustments(
+            self.object_id.to_db_field(),
+            self.user_orientation.clone(),
+            crop)?;
+
+        Ok(UpdateObjectAdjustmentsResponse{})
+    }
+}
+
+#[derive(Debug)]
+pub struct UpdateObjectAdjustmentsResponse
+{
+}
+
 #[derive(Debug)]
 pub struct EditObjectRequest
 {
@@ -713,3 +1484,90 @@ impl ApiMessage for EditObjectRequest
 pub struct EditObjectResponse
 {
 }
+
+fn build_tag_tree(by_parent: &mut HashMap<Option<i64>, Vec<data::get::TagMetadata>>, parent: Option<i64>) -> Vec<data::get::TagTreeNode>
+{
+    let mut result = Vec::new();
+
+    for tag in by_parent.remove(&parent).unwrap_or_default()
+    {
+        let children = build_tag_tree(by_parent, Some(tag.tag_id.to_db_field()));
+
+        result.push(data::get::TagTreeNode{ tag, children });
+    }
+
+    result
+}
+
+/// Returns the tag, and if requested all the tags
+/// below it in the hierarchy
+fn query_tag_ids(ops: &dyn WriteOps, tag_id: &data::TagId, include_descendants: bool) -> Result<Vec<i64>, Error>
+{
+    let mut result = vec![tag_id.to_db_field()];
+
+    if include_descendants
+    {
+        let mut next = 0;
+
+        while next < result.len()
+        {
+            for child in ops.get_tag_children(result[next])?
+            {
+                if !result.contains(&child.tag_id)
+                {
+                    result.push(child.tag_id);
+                }
+            }
+
+            next += 1;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Deletes the parent of a deleted tag if it no longer has
+/// any objects or children, and likewise up the hierarchy -
+/// such as parents only added to group the tags below them.
+fn delete_unused_parent_tags(ops: &dyn WriteOps, parent_id: Option<i64>) -> Result<(), Error>
+{
+    let mut parent_id = parent_id;
+
+    while let Some(tag_id) = parent_id
+    {
+        if ops.get_num_objects_with_tags(&[tag_id])? != 0
+            || !ops.get_tag_children(tag_id)?.is_empty()
+        {
+            break;
+        }
+
+        parent_id = ops.get_tag(tag_id)?.tag_parent_id;
+
+        ops.delete_tag(&data::TagId::from_db_field(tag_id))?;
+    }
+
+    Ok(())
+}
+
+/// Finds or adds a tag, and its parent tag if one is named.
+/// The parent is only set if the tag doesn't already have
+/// one, so existing hierarchies aren't rearranged.
+fn find_or_add_tag_with_parent(ops: &dyn WriteOps, tag: &data::add::Tag) -> Result<i64, Error>
+{
+    let tag_id = ops.find_or_add_tag(tag.name.trim().to_owned(), tag.kind.clone(), tag.rating.clone(), tag.censor.clone())?;
+
+    if let Some(parent_name) = &tag.parent
+    {
+        if ops.get_tag(tag_id)?.tag_parent_id.is_none()
+        {
+            let parent_id = ops.find_or_add_tag(parent_name.clone(), tag.kind.clone(), tag.rating.clone(), tag.censor.clone())?;
+
+            if !query_tag_ids(ops, &data::TagId::from_db_field(tag_id), true)?.contains(&parent_id)
+            {
+                ops.set_tag_parent(tag_id, Some(parent_id))?;
+            }
+        }
+    }
+
+    Ok(tag_id)
+}
@@ -33,14 +33,20 @@ impl DbConnection
         let db_connection = SqliteConnection::establish(path)
             .context(LowerDbConnectionSnafu{path: path.to_owned() })?;
 
-        if schema::db_properties::table
+        let is_new_database = schema::db_properties::table
             .load::<models::DbProperty>(&db_connection)
             .context(LowerDbPropertiesSnafu{})
-            .is_err()
-        {
-            embedded_migrations::run(&db_connection)
-                .context(LowerDbMigrationSnafu{})?;
+            .is_err();
+
+        // Run any pending migrations - this creates the tables
+        // for a new database, and adds any newer tables
+        // to an existing database
 
+        embedded_migrations::run(&db_connection)
+            .context(LowerDbMigrationSnafu{})?;
+
+        if is_new_database
+        {
             let name = "version".to_owned();
             let value = "2020-07-05".to_owned();
             
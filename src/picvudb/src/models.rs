@@ -31,6 +31,12 @@ pub struct Object
     pub tag_set: Option<String>,
     pub ext_ref_type: Option<String>,
     pub ext_ref_id: Option<String>,
+    pub activity_nanos: i32,
+    pub user_orientation: Option<i32>,
+    pub crop_left: Option<f64>,
+    pub crop_top: Option<f64>,
+    pub crop_width: Option<f64>,
+    pub crop_height: Option<f64>,
 }
 
 #[derive(Insertable)]
@@ -54,6 +60,12 @@ pub struct InsertableObject
     pub tag_set: Option<String>,
     pub ext_ref_type: Option<String>,
     pub ext_ref_id: Option<String>,
+    pub activity_nanos: i32,
+    pub user_orientation: Option<i32>,
+    pub crop_left: Option<f64>,
+    pub crop_top: Option<f64>,
+    pub crop_width: Option<f64>,
+    pub crop_height: Option<f64>,
 }
 
 #[derive(QueryableByName)]
@@ -63,6 +75,17 @@ pub struct NewId
     pub new_id: i64
 }
 
+#[derive(QueryableByName)]
+pub struct CameraCount
+{
+    #[sql_type = "diesel::sql_types::Text"]
+    pub make: String,
+    #[sql_type = "diesel::sql_types::Text"]
+    pub model: String,
+    #[sql_type = "diesel::sql_types::BigInt"]
+    pub num_objects: i64,
+}
+
 #[derive(Identifiable)]
 #[table_name="objects"]
 pub struct UpdateObjectId
@@ -79,6 +102,7 @@ pub struct UpdateObjectChangeset
     pub modified_offset: Option<i32>,
     pub activity_timestamp: i64,
     pub activity_offset: Option<i32>,
+    pub activity_nanos: i32,
     pub title: Option<String>,
     pub notes: Option<String>,
     pub rating: Option<i32>,
@@ -99,6 +123,20 @@ pub struct UpdateObjectTagsetChangeset
     pub tag_set: Option<String>,
 }
 
+#[derive(AsChangeset)]
+#[table_name="objects"]
+#[changeset_options(treat_none_as_null="true")]
+pub struct UpdateObjectAdjustmentsChangeset
+{
+    pub modified_timestamp: i64,
+    pub modified_offset: Option<i32>,
+    pub user_orientation: Option<i32>,
+    pub crop_left: Option<f64>,
+    pub crop_top: Option<f64>,
+    pub crop_width: Option<f64>,
+    pub crop_height: Option<f64>,
+}
+
 #[derive(Queryable)]
 #[derive(Insertable)]
 #[table_name="attachments_metadata"]
@@ -117,6 +155,9 @@ pub struct AttachmentMetadata
     pub height: Option<i32>,
     pub duration: Option<i32>,
     pub hash: String,
+    pub att_index: i32,
+    pub role: i32,
+    pub version_of: Option<i32>,
 }
 
 #[derive(Queryable)]
@@ -127,6 +168,50 @@ pub struct AttachmentData
     pub obj_id: i64,
     pub offset: i64,
     pub bytes: Vec<u8>,
+    pub att_index: i32,
+}
+
+#[derive(Queryable)]
+#[derive(Insertable)]
+#[table_name="attachment_exif"]
+pub struct AttachmentExif
+{
+    pub obj_id: i64,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub exposure_time: Option<String>,
+    pub aperture: Option<f64>,
+    pub focal_length: Option<f64>,
+    pub iso: Option<i32>,
+    pub gps_dop: Option<f64>,
+    pub entries: String,
+    pub xmp: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name="recipe_ingredients"]
+pub struct RecipeIngredient
+{
+    pub obj_id: i64,
+    pub name: String,
+}
+
+#[derive(Insertable)]
+#[table_name="object_links"]
+pub struct ObjectLink
+{
+    pub obj_id: i64,
+    pub linked_obj_id: i64,
+}
+
+#[derive(Queryable)]
+#[derive(Insertable)]
+#[table_name="object_relations"]
+pub struct ObjectRelation
+{
+    pub from_obj_id: i64,
+    pub to_obj_id: i64,
+    pub relation_kind: i32,
 }
 
 #[derive(Insertable)]
@@ -136,6 +221,7 @@ pub struct InsertableObjectsFts
     pub rowid: i64,
     pub title: Option<String>,
     pub notes: Option<String>,
+    pub content: Option<String>,
 }
 
 #[derive(Queryable)]
@@ -168,6 +254,7 @@ pub struct Tag
     pub tag_kind: i32,
     pub tag_rating: Option<i32>,
     pub tag_censor: i32,
+    pub tag_parent_id: Option<i64>,
 }
 
 #[derive(Queryable)]
@@ -179,6 +266,15 @@ pub struct ObjectTags
     pub tag_id: i64,
 }
 
+#[derive(Queryable)]
+#[derive(Insertable)]
+#[table_name="tag_aliases"]
+pub struct TagAlias
+{
+    pub alias_name: String,
+    pub tag_id: i64,
+}
+
 #[derive(Insertable)]
 #[table_name="tags_fts_insert"]
 pub struct InsertableTagsFts
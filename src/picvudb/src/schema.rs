@@ -25,11 +25,17 @@ table! {
         tag_set -> Nullable<Text>,
         ext_ref_type -> Nullable<Text>,
         ext_ref_id -> Nullable<Text>,
+        activity_nanos -> Integer,
+        user_orientation -> Nullable<Integer>,
+        crop_left -> Nullable<Double>,
+        crop_top -> Nullable<Double>,
+        crop_width -> Nullable<Double>,
+        crop_height -> Nullable<Double>,
     }
 }
 
 table! {
-    attachments_metadata (obj_id) {
+    attachments_metadata (obj_id, att_index) {
         obj_id -> BigInt,
         filename -> Text,
         created_timestamp -> BigInt,
@@ -43,14 +49,33 @@ table! {
         height -> Nullable<Integer>,
         duration -> Nullable<Integer>,
         hash -> Text,
+        att_index -> Integer,
+        role -> Integer,
+        version_of -> Nullable<Integer>,
     }
 }
 
 table! {
-    attachments_data (obj_id, offset) {
+    attachment_exif (obj_id) {
+        obj_id -> BigInt,
+        make -> Nullable<Text>,
+        model -> Nullable<Text>,
+        exposure_time -> Nullable<Text>,
+        aperture -> Nullable<Double>,
+        focal_length -> Nullable<Double>,
+        iso -> Nullable<Integer>,
+        gps_dop -> Nullable<Double>,
+        entries -> Text,
+        xmp -> Nullable<Text>,
+    }
+}
+
+table! {
+    attachments_data (obj_id, att_index, offset) {
         obj_id -> BigInt,
         offset -> BigInt,
         bytes -> Blob,
+        att_index -> Integer,
     }
 }
 
@@ -60,6 +85,7 @@ table! {
         rowid -> BigInt,
         title -> Nullable<Text>,
         notes -> Nullable<Text>,
+        content -> Nullable<Text>,
     }
 }
 
@@ -91,6 +117,7 @@ table! {
         tag_kind -> Integer,
         tag_rating -> Nullable<Integer>,
         tag_censor -> Integer,
+        tag_parent_id -> Nullable<BigInt>,
     }
 }
 
@@ -101,6 +128,13 @@ table! {
     }
 }
 
+table! {
+    tag_aliases (alias_name) {
+        alias_name -> Text,
+        tag_id -> BigInt,
+    }
+}
+
 table! {
     #[sql_name = "tags_fts"]
     tags_fts_insert (rowid) {
@@ -120,9 +154,34 @@ table! {
     }
 }
 
+table! {
+    recipe_ingredients (obj_id, name) {
+        obj_id -> BigInt,
+        name -> Text,
+    }
+}
+
+table! {
+    object_links (obj_id, linked_obj_id) {
+        obj_id -> BigInt,
+        linked_obj_id -> BigInt,
+    }
+}
+
+table! {
+    object_relations (from_obj_id, to_obj_id, relation_kind) {
+        from_obj_id -> BigInt,
+        to_obj_id -> BigInt,
+        relation_kind -> Integer,
+    }
+}
+
 joinable!(attachments_metadata -> objects (obj_id));
 allow_tables_to_appear_in_same_query!(objects, attachments_metadata);
 
+joinable!(attachment_exif -> objects (obj_id));
+allow_tables_to_appear_in_same_query!(objects, attachment_exif);
+
 joinable!(objects_location -> objects (id));
 allow_tables_to_appear_in_same_query!(objects, objects_location);
 
@@ -134,3 +193,5 @@ allow_tables_to_appear_in_same_query!(objects, object_tags);
 
 joinable!(tags_fts_query -> tags (rowid));
 allow_tables_to_appear_in_same_query!(tags, tags_fts_query);
+
+allow_tables_to_appear_in_same_query!(objects, object_links);
@@ -12,8 +12,10 @@ pub trait ReadOps
     fn get_num_objects_with_attachments(&self) -> Result<u64, Error>;
     fn get_num_objects_near_location(&self, latitude: f64, longitude: f64, radius_meters: f64) -> Result<u64, Error>;
     fn get_num_objects_for_text_search(&self, search: &data::get::SearchString) -> Result<u64, Error>;
-    fn get_num_objects_with_tag(&self, tag: i64) -> Result<u64, Error>;
+    fn get_num_objects_with_tags(&self, tag_ids: &[i64]) -> Result<u64, Error>;
     fn get_num_objects_in_activity_date_range(&self, date_range: &data::DateRange) -> Result<u64, Error>;
+    fn get_num_objects_with_exif(&self, filter: &data::ExifFilter) -> Result<u64, Error>;
+    fn get_num_objects_with_ingredient(&self, ingredient: &str) -> Result<u64, Error>;
 
     fn get_object_by_id(&self, obj_id: i64) -> Result<Option<Object>, Error>;
     fn get_objects_by_activity_desc(&self, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>;
@@ -21,26 +23,48 @@ pub trait ReadOps
     fn get_objects_by_attachment_size_desc(&self, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>;
     fn get_objects_near_location_by_activity_desc(&self, latitude: f64, longitude: f64, radius_meters: f64, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>;
     fn get_objects_for_text_search(&self, search: &data::get::SearchString, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>;
-    fn get_objects_with_tag_by_activity_desc(&self, tag_id: i64, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>;
+    fn get_objects_with_tags_by_activity_desc(&self, tag_ids: &[i64], offset: u64, page_size: u64) -> Result<Vec<Object>, Error>;
     fn get_objects_in_activity_date_range(&self, date_range: &data::DateRange, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>;
+    fn get_objects_with_exif_by_activity_desc(&self, filter: &data::ExifFilter, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>;
+    fn get_objects_with_ingredient_by_activity_desc(&self, ingredient: &str, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>;
 
-    fn get_attachment_metadata(&self, obj_id: i64) -> Result<Option<AttachmentMetadata>, Error>;
-    fn get_attachment_data(&self, obj_id: i64) -> Result<Option<Vec<u8>>, Error>;
+    fn get_attachments_metadata(&self, obj_id: i64) -> Result<Vec<AttachmentMetadata>, Error>;
+    fn get_attachment_data(&self, obj_id: i64, att_index: i32) -> Result<Option<Vec<u8>>, Error>;
+    fn get_attachment_exif(&self, obj_id: i64) -> Result<Option<AttachmentExif>, Error>;
+    fn get_exif_cameras(&self) -> Result<Vec<(String, String, i64)>, Error>;
 
     fn get_tag(&self, tag_id: i64) -> Result<Tag, Error>;
     fn get_tags_for_text_search(&self, search: &data::get::SearchString) -> Result<Vec<Tag>, Error>;
+    fn get_tag_by_name(&self, name: &str) -> Result<Option<Tag>, Error>;
+    fn get_tag_children(&self, tag_id: i64) -> Result<Vec<Tag>, Error>;
+    fn get_tag_aliases(&self, tag_id: i64) -> Result<Vec<String>, Error>;
+    fn get_all_tags(&self) -> Result<Vec<Tag>, Error>;
+
+    fn get_obj_ids_linking_to(&self, obj_id: i64) -> Result<Vec<i64>, Error>;
+    fn get_object_relations(&self, obj_id: i64) -> Result<Vec<ObjectRelation>, Error>;
 }
 
 pub trait WriteOps: ReadOps
 {
     fn set_properties(&self, properties: &HashMap<String, String>) -> Result<(), Error>;
-    fn add_object(&self, created_time: Option<data::Date>, modified_time: Option<data::Date>, activity_time: Option<data::Date>, title: Option<data::TitleMarkdown>, notes: Option<data::NotesMarkdown>, rating: data::Rating, censor: data::Censor, location: Option<data::Location>, tag_set: data::TagSet, ext_ref: Option<data::ExternalReference>) -> Result<data::ObjectId, Error>;
-    fn add_attachment(&self, obj_id: i64, filename: String, created: data::Date, modified: data::Date, mime: String, orientation: Option<data::Orientation>, dimensions: Option<data::Dimensions>, duration: Option<data::Duration>, bytes: Vec<u8>) -> Result<(), Error>;
+    fn add_object(&self, created_time: Option<data::Date>, modified_time: Option<data::Date>, activity_time: Option<data::Date>, title: Option<data::TitleMarkdown>, notes: Option<data::NotesMarkdown>, rating: data::Rating, censor: data::Censor, location: Option<data::Location>, tag_set: data::TagSet, ext_ref: Option<data::ExternalReference>, user_orientation: Option<data::Orientation>, crop: Option<data::Crop>) -> Result<data::ObjectId, Error>;
+    fn add_attachment(&self, obj_id: i64, att_index: i32, role: data::AttachmentRole, filename: String, created: data::Date, modified: data::Date, mime: String, orientation: Option<data::Orientation>, dimensions: Option<data::Dimensions>, duration: Option<data::Duration>, bytes: Vec<u8>) -> Result<(), Error>;
+    fn move_attachment(&self, obj_id: i64, from_index: i32, to_index: i32, version_of: Option<i32>) -> Result<(), Error>;
+    fn set_attachment_exif(&self, obj_id: i64, exif: Option<data::AttachmentExif>) -> Result<(), Error>;
+    fn set_document_text(&self, obj_id: i64, text: Option<String>) -> Result<(), Error>;
     fn update_object(&self, obj_id: i64, activity_time: data::Date, title: Option<data::TitleMarkdown>, notes: Option<data::NotesMarkdown>, rating: data::Rating, censor: data::Censor, location: Option<data::Location>) -> Result<(), Error>;
     fn update_object_tagset(&self, obj_id: i64, tag_set: data::TagSet) -> Result<(), Error>;
+    fn update_object_adjustments(&self, obj_id: i64, user_orientation: Option<data::Orientation>, crop: Option<data::Crop>) -> Result<(), Error>;
+    fn reindex_recipe(&self, obj_id: i64) -> Result<bool, Error>;
     fn update_tag(&self, tag_id: data::TagId, name: String, rating: data::Rating, censor: data::Censor, kind: data::TagKind) -> Result<(), Error>;
+    fn set_tag_parent(&self, tag_id: i64, parent_id: Option<i64>) -> Result<(), Error>;
     fn delete_tag(&self, tag_id: &data::TagId) -> Result<(), Error>;
     fn find_or_add_tag(&self, name: String, kind: data::TagKind, rating: data::Rating, censor: data::Censor) -> Result<i64, Error>;
+    fn merge_tags(&self, from_tag_id: i64, into_tag_id: i64) -> Result<(), Error>;
+    fn add_tag_alias(&self, alias: String, tag_id: i64) -> Result<(), Error>;
+    fn remove_tag_alias(&self, alias: &str) -> Result<(), Error>;
     fn add_object_tag(&self, obj_id: i64, tag_id: i64) -> Result<(), Error>;
     fn remove_object_tag(&self, obj_id: i64, tag_id: i64) -> Result<(), Error>;
+    fn add_object_relation(&self, from_obj_id: i64, to_obj_id: i64, kind: data::RelationKind) -> Result<(), Error>;
+    fn remove_object_relation(&self, from_obj_id: i64, to_obj_id: i64, kind: data::RelationKind) -> Result<(), Error>;
 }
@@ -49,6 +49,7 @@ impl<'a> ReadOps for Transaction<'a>
         use diesel::dsl::count_star;
 
         let num: u64 = attachments_metadata
+            .filter(att_index.eq(0))
             .select(count_star())
             .first::<i64>(self.connection)?
             .to_u64()
@@ -110,14 +111,14 @@ impl<'a> ReadOps for Transaction<'a>
         Ok(num)
     }
 
-    fn get_num_objects_with_tag(&self, tag: i64) -> Result<u64, Error>
+    fn get_num_objects_with_tags(&self, tag_ids: &[i64]) -> Result<u64, Error>
     {
-        use schema::object_tags::dsl::*;
         use diesel::dsl::count_star;
 
-        let num: u64 = object_tags
+        let num: u64 = schema::objects::table
             .select(count_star())
-            .filter(tag_id.eq(tag))
+            .filter(schema::objects::id.eq_any(
+                    schema::object_tags::table.select(schema::object_tags::obj_id).filter(schema::object_tags::tag_id.eq_any(tag_ids))))
             .first::<i64>(self.connection)?
             .to_u64()
             .ok_or(Error::DatabaseConsistencyError{ msg: "More than 2^64 objects in database".to_owned() })?;
@@ -149,6 +150,39 @@ impl<'a> ReadOps for Transaction<'a>
         Ok(num)
     }
 
+    fn get_num_objects_with_exif(&self, filter: &data::ExifFilter) -> Result<u64, Error>
+    {
+        use diesel::dsl::count_star;
+
+        let num = schema::objects::table
+            .select(count_star())
+            .filter(schema::objects::id.eq_any(exif_filter_obj_ids(filter)))
+            .first::<i64>(self.connection)?
+            .to_u64()
+            .ok_or(Error::DatabaseConsistencyError{ msg: "More than 2^64 objects in database".to_owned() })?;
+
+        Ok(num)
+    }
+
+    fn get_num_objects_with_ingredient(&self, ingredient: &str) -> Result<u64, Error>
+    {
+        use diesel::dsl::count_star;
+
+        if ingredient.trim().is_empty()
+        {
+            return Ok(0);
+        }
+
+        let num = schema::objects::table
+            .select(count_star())
+            .filter(schema::objects::id.eq_any(ingredient_obj_ids(ingredient)))
+            .first::<i64>(self.connection)?
+            .to_u64()
+            .ok_or(Error::DatabaseConsistencyError{ msg: "More than 2^64 objects in database".to_owned() })?;
+
+        Ok(num)
+    }
+
     fn get_object_by_id(&self, obj_id: i64) -> Result<Option<Object>, Error>
     {
         use schema::objects::dsl::*;
@@ -166,7 +200,7 @@ impl<'a> ReadOps for Transaction<'a>
         use schema::objects::dsl::*;
 
         let results = objects
-            .order_by(activity_timestamp.desc())
+            .order_by((activity_timestamp.desc(), activity_nanos.desc()))
             .offset(offset as i64)
             .limit(page_size as i64)
             .load::<Object>(self.connection)?;
@@ -193,6 +227,7 @@ impl<'a> ReadOps for Transaction<'a>
 
         let results = objects
             .inner_join(schema::attachments_metadata::table)
+            .filter(schema::attachments_metadata::att_index.eq(0))
             .select(schema::objects::all_columns)
             .order_by(schema::attachments_metadata::size.desc())
             .offset(offset as i64)
@@ -224,7 +259,7 @@ impl<'a> ReadOps for Transaction<'a>
                 .and(max_lat.le(q_max_lat))
                 .and(min_long.ge(q_min_long))
                 .and(max_long.le(q_max_long)))
-            .order_by(schema::objects::activity_timestamp.desc())
+            .order_by((schema::objects::activity_timestamp.desc(), schema::objects::activity_nanos.desc()))
             .offset(offset as i64)
             .limit(page_size as i64)
             .load::<(Object, ObjectsLocation)>(self.connection)?
@@ -250,7 +285,7 @@ impl<'a> ReadOps for Transaction<'a>
                     schema::attachments_metadata::table
                     .select(schema::attachments_metadata::obj_id)
                     .filter(schema::attachments_metadata::dsl::filename.eq(&literal_text)))))
-            .order_by(schema::objects::activity_timestamp.desc())
+            .order_by((schema::objects::activity_timestamp.desc(), schema::objects::activity_nanos.desc()))
             .offset(offset as i64)
             .limit(page_size as i64)
             .load::<Object>(self.connection)?;
@@ -258,12 +293,12 @@ impl<'a> ReadOps for Transaction<'a>
         Ok(results)
     }
 
-    fn get_objects_with_tag_by_activity_desc(&self, tag_id: i64, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>
+    fn get_objects_with_tags_by_activity_desc(&self, tag_ids: &[i64], offset: u64, page_size: u64) -> Result<Vec<Object>, Error>
     {
         let results = schema::objects::table
             .filter(schema::objects::id.eq_any(
-                    schema::object_tags::table.select(schema::object_tags::obj_id).filter(schema::object_tags::tag_id.eq(tag_id))))
-            .order_by(schema::objects::activity_timestamp.desc())
+                    schema::object_tags::table.select(schema::object_tags::obj_id).filter(schema::object_tags::tag_id.eq_any(tag_ids))))
+            .order_by((schema::objects::activity_timestamp.desc(), schema::objects::activity_nanos.desc()))
             .offset(offset as i64)
             .limit(page_size as i64)
             .load::<Object>(self.connection)?;
@@ -284,7 +319,7 @@ impl<'a> ReadOps for Transaction<'a>
                 .and(schema::objects::activity_timestamp.le(end_ts_utc))
                 .and((schema::objects::activity_timestamp + coalesce(schema::objects::activity_offset, 36000)).ge(start_ts_local))
                 .and((schema::objects::activity_timestamp + coalesce(schema::objects::activity_offset, 36000)).le(end_ts_local)))
-            .order_by(schema::objects::activity_timestamp.desc())
+            .order_by((schema::objects::activity_timestamp.desc(), schema::objects::activity_nanos.desc()))
             .offset(offset as i64)
             .limit(page_size as i64)
             .load::<Object>(self.connection)?;
@@ -292,25 +327,54 @@ impl<'a> ReadOps for Transaction<'a>
         Ok(results)
     }
 
-    fn get_attachment_metadata(&self, q_obj_id: i64) -> Result<Option<AttachmentMetadata>, Error>
+    fn get_objects_with_exif_by_activity_desc(&self, filter: &data::ExifFilter, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>
+    {
+        let results = schema::objects::table
+            .filter(schema::objects::id.eq_any(exif_filter_obj_ids(filter)))
+            .order_by((schema::objects::activity_timestamp.desc(), schema::objects::activity_nanos.desc()))
+            .offset(offset as i64)
+            .limit(page_size as i64)
+            .load::<Object>(self.connection)?;
+
+        Ok(results)
+    }
+
+    fn get_objects_with_ingredient_by_activity_desc(&self, ingredient: &str, offset: u64, page_size: u64) -> Result<Vec<Object>, Error>
+    {
+        if ingredient.trim().is_empty()
+        {
+            return Ok(Vec::new());
+        }
+
+        let results = schema::objects::table
+            .filter(schema::objects::id.eq_any(ingredient_obj_ids(ingredient)))
+            .order_by((schema::objects::activity_timestamp.desc(), schema::objects::activity_nanos.desc()))
+            .offset(offset as i64)
+            .limit(page_size as i64)
+            .load::<Object>(self.connection)?;
+
+        Ok(results)
+    }
+
+    fn get_attachments_metadata(&self, q_obj_id: i64) -> Result<Vec<AttachmentMetadata>, Error>
     {
         use schema::attachments_metadata::dsl::*;
 
         let metadata = attachments_metadata
             .filter(obj_id.eq(q_obj_id))
-            .load::<AttachmentMetadata>(self.connection)?
-            .into_iter()
-            .nth(0);
+            .order_by(att_index.asc())
+            .load::<AttachmentMetadata>(self.connection)?;
 
         Ok(metadata)
     }
 
-    fn get_attachment_data(&self, q_obj_id: i64) -> Result<Option<Vec<u8>>, Error>
+    fn get_attachment_data(&self, q_obj_id: i64, q_att_index: i32) -> Result<Option<Vec<u8>>, Error>
     {
         use schema::attachments_data::dsl::*;
 
         let mut data = attachments_data
             .filter(obj_id.eq(q_obj_id))
+            .filter(att_index.eq(q_att_index))
             .order_by(offset.asc())
             .load::<AttachmentData>(self.connection)?;
 
@@ -351,6 +415,29 @@ impl<'a> ReadOps for Transaction<'a>
         Ok(Some(collected_bytes))
     }
 
+    fn get_attachment_exif(&self, q_obj_id: i64) -> Result<Option<AttachmentExif>, Error>
+    {
+        use schema::attachment_exif::dsl::*;
+
+        let exif = attachment_exif
+            .filter(obj_id.eq(q_obj_id))
+            .first::<AttachmentExif>(self.connection)
+            .optional()?;
+
+        Ok(exif)
+    }
+
+    fn get_exif_cameras(&self) -> Result<Vec<(String, String, i64)>, Error>
+    {
+        let results = diesel::sql_query("SELECT make, model, COUNT(*) AS num_objects FROM attachment_exif WHERE make IS NOT NULL AND model IS NOT NULL GROUP BY make, model ORDER BY make, model")
+            .load::<CameraCount>(self.connection)?
+            .into_iter()
+            .map(|c| (c.make, c.model, c.num_objects))
+            .collect();
+
+        Ok(results)
+    }
+
     fn get_tag(&self, tag_id: i64) -> Result<Tag, Error>
     {
         match schema::tags::table
@@ -382,6 +469,84 @@ impl<'a> ReadOps for Transaction<'a>
 
         Ok(results)
     }
+
+    fn get_tag_by_name(&self, name: &str) -> Result<Option<Tag>, Error>
+    {
+        let result = schema::tags::table
+            .filter(schema::tags::tag_name.eq(name))
+            .get_result(self.connection)
+            .optional()?;
+
+        if result.is_some()
+        {
+            return Ok(result);
+        }
+
+        // Otherwise, the name may be an alias
+
+        match self.get_tag_alias(name)?
+        {
+            Some(alias) => Ok(Some(self.get_tag(alias.tag_id)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_tag_children(&self, tag_id: i64) -> Result<Vec<Tag>, Error>
+    {
+        let results = schema::tags::table
+            .filter(schema::tags::tag_parent_id.eq(tag_id))
+            .load::<Tag>(self.connection)?;
+
+        Ok(results)
+    }
+
+    fn get_tag_aliases(&self, q_tag_id: i64) -> Result<Vec<String>, Error>
+    {
+        use schema::tag_aliases::dsl::*;
+
+        let results = tag_aliases
+            .select(alias_name)
+            .filter(tag_id.eq(q_tag_id))
+            .order_by(alias_name.asc())
+            .load::<String>(self.connection)?;
+
+        Ok(results)
+    }
+
+    fn get_all_tags(&self) -> Result<Vec<Tag>, Error>
+    {
+        let results = schema::tags::table
+            .load::<Tag>(self.connection)?;
+
+        Ok(results)
+    }
+
+    fn get_obj_ids_linking_to(&self, q_obj_id: i64) -> Result<Vec<i64>, Error>
+    {
+        let linking = schema::object_links::table
+            .select(schema::object_links::obj_id)
+            .filter(schema::object_links::linked_obj_id.eq(q_obj_id));
+
+        let results = schema::objects::table
+            .select(schema::objects::id)
+            .filter(schema::objects::id.eq_any(linking))
+            .order_by((schema::objects::activity_timestamp.desc(), schema::objects::activity_nanos.desc()))
+            .load::<i64>(self.connection)?;
+
+        Ok(results)
+    }
+
+    fn get_object_relations(&self, q_obj_id: i64) -> Result<Vec<ObjectRelation>, Error>
+    {
+        use schema::object_relations::dsl::*;
+
+        let results = object_relations
+            .filter(from_obj_id.eq(q_obj_id).or(to_obj_id.eq(q_obj_id)))
+            .order_by((relation_kind.asc(), from_obj_id.asc(), to_obj_id.asc()))
+            .load::<ObjectRelation>(self.connection)?;
+
+        Ok(results)
+    }
 }
 
 impl<'a> WriteOps for Transaction<'a>
@@ -413,7 +578,7 @@ impl<'a> WriteOps for Transaction<'a>
         Ok(())
     }
 
-    fn add_object(&self, created_time: Option<data::Date>, modified_time: Option<data::Date>, activity_time: Option<data::Date>, title: Option<data::TitleMarkdown>, notes: Option<data::NotesMarkdown>, rating: data::Rating, censor: data::Censor, location: Option<data::Location>, tag_set: data::TagSet, ext_ref: Option<data::ExternalReference>) -> Result<data::ObjectId, Error>
+    fn add_object(&self, created_time: Option<data::Date>, modified_time: Option<data::Date>, activity_time: Option<data::Date>, title: Option<data::TitleMarkdown>, notes: Option<data::NotesMarkdown>, rating: data::Rating, censor: data::Censor, location: Option<data::Location>, tag_set: data::TagSet, ext_ref: Option<data::ExternalReference>, user_orientation: Option<data::Orientation>, crop: Option<data::Crop>) -> Result<data::ObjectId, Error>
     {
         let modified_time = modified_time.unwrap_or(data::Date::now());
         let created_time = created_time.unwrap_or(modified_time.clone());
@@ -432,6 +597,7 @@ impl<'a> WriteOps for Transaction<'a>
             modified_offset: modified_time.to_db_offset(),
             activity_timestamp: activity_time.to_db_timestamp(),
             activity_offset: activity_time.to_db_offset(),
+            activity_nanos: activity_time.to_db_nanos(),
             title: title.clone().map(|m| m.get_markdown()),
             notes: notes.clone().map(|m| m.get_markdown()),
             rating: rating.to_db_field(),
@@ -443,6 +609,11 @@ impl<'a> WriteOps for Transaction<'a>
             tag_set: tag_set.to_db_field(),
             ext_ref_type: ext_ref.clone().map(|e| e.to_db_field_type()),
             ext_ref_id: ext_ref.clone().map(|e| e.to_db_field_id()),
+            user_orientation: user_orientation.map(|o| o.to_db_field()),
+            crop_left: crop.clone().map(|c| c.left),
+            crop_top: crop.clone().map(|c| c.top),
+            crop_width: crop.clone().map(|c| c.width),
+            crop_height: crop.clone().map(|c| c.height),
         };
 
         diesel::insert_into(schema::objects::table)
@@ -454,6 +625,9 @@ impl<'a> WriteOps for Transaction<'a>
 
         // Now add data to the extra search indexes
 
+        self.set_recipe_ingredients(new_id.new_id, &notes)?;
+        self.set_object_links(new_id.new_id, &notes)?;
+
         if title.is_some() || notes.is_some()
         {
             let fts_insert_value = InsertableObjectsFts
@@ -461,6 +635,7 @@ impl<'a> WriteOps for Transaction<'a>
                 rowid: new_id.new_id,
                 title: title.map(|m| m.get_search_text()),
                 notes: notes.map(|m| m.get_search_text()),
+                content: None,
             };
 
             diesel::insert_into(schema::objects_fts_insert::table)
@@ -489,7 +664,7 @@ impl<'a> WriteOps for Transaction<'a>
         Ok(data::ObjectId::from_db_field(new_id.new_id))
     }
 
-    fn add_attachment(&self, obj_id: i64, filename: String, created: data::Date, modified: data::Date, mime: String, orientation: Option<data::Orientation>, dimensions: Option<data::Dimensions>, duration: Option<data::Duration>, bytes: Vec<u8>) -> Result<(), Error>
+    fn add_attachment(&self, obj_id: i64, att_index: i32, role: data::AttachmentRole, filename: String, created: data::Date, modified: data::Date, mime: String, orientation: Option<data::Orientation>, dimensions: Option<data::Dimensions>, duration: Option<data::Duration>, bytes: Vec<u8>) -> Result<(), Error>
     {
         if bytes.is_empty()
         {
@@ -520,6 +695,9 @@ impl<'a> WriteOps for Transaction<'a>
             height: dimensions.clone().map(|d| d.to_db_field_height()),
             duration: duration.map(|d| d.to_db_field()),
             hash: hash,
+            att_index: att_index,
+            role: role.to_db_field(),
+            version_of: None,
         };
 
         diesel::insert_into(schema::attachments_metadata::table)
@@ -537,6 +715,7 @@ impl<'a> WriteOps for Transaction<'a>
                 obj_id: obj_id.clone(),
                 offset: offset as i64,
                 bytes: bytes[offset..(offset + this_time)].to_vec(),
+                att_index: att_index,
             };
     
             diesel::insert_into(schema::attachments_data::table)
@@ -549,6 +728,77 @@ impl<'a> WriteOps for Transaction<'a>
         Ok(())
     }
 
+    fn move_attachment(&self, q_obj_id: i64, from_index: i32, to_index: i32, q_version_of: Option<i32>) -> Result<(), Error>
+    {
+        {
+            use schema::attachments_metadata::dsl::*;
+
+            diesel::update(attachments_metadata.filter(obj_id.eq(q_obj_id)).filter(att_index.eq(from_index)))
+                .set((att_index.eq(to_index), version_of.eq(q_version_of)))
+                .execute(self.connection)?;
+        }
+
+        {
+            use schema::attachments_data::dsl::*;
+
+            diesel::update(attachments_data.filter(obj_id.eq(q_obj_id)).filter(att_index.eq(from_index)))
+                .set(att_index.eq(to_index))
+                .execute(self.connection)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_attachment_exif(&self, q_obj_id: i64, exif: Option<data::AttachmentExif>) -> Result<(), Error>
+    {
+        // Always delete, then re-add if there
+        // is any new EXIF data
+
+        diesel::delete(schema::attachment_exif::table.filter(schema::attachment_exif::obj_id.eq(q_obj_id)))
+            .execute(self.connection)?;
+
+        if let Some(exif) = exif
+        {
+            diesel::insert_into(schema::attachment_exif::table)
+                .values(&exif.to_db_model(q_obj_id))
+                .execute(self.connection)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_document_text(&self, obj_id: i64, text: Option<String>) -> Result<(), Error>
+    {
+        // Keep the title and notes, and re-create
+        // the full text search row with the new text
+
+        let (title, notes, _) = self.get_fts_row(obj_id)?;
+
+        {
+            use schema::objects_fts_query::dsl::*;
+
+            diesel::delete(objects_fts_query.filter(rowid.eq(obj_id)))
+                .execute(self.connection)?;
+        }
+
+        if title.is_some() || notes.is_some() || text.is_some()
+        {
+            let fts_insert_value = InsertableObjectsFts
+            {
+                rowid: obj_id,
+                title,
+                notes,
+                content: text,
+            };
+
+            diesel::insert_into(schema::objects_fts_insert::table)
+                .values(vec![fts_insert_value])
+                .execute(self.connection)?;
+        }
+
+        Ok(())
+    }
+
     fn update_object(&self, obj_id: i64, activity_time: data::Date, title: Option<data::TitleMarkdown>, notes: Option<data::NotesMarkdown>, rating: data::Rating, censor: data::Censor, location: Option<data::Location>) -> Result<(), Error>
     {
         let object = UpdateObjectId
@@ -564,6 +814,7 @@ impl<'a> WriteOps for Transaction<'a>
             modified_offset: modified.to_db_offset(),
             activity_timestamp: activity_time.to_db_timestamp(),
             activity_offset: activity_time.to_db_offset(),
+            activity_nanos: activity_time.to_db_nanos(),
             title: title.clone().map(|m| m.get_markdown()),
             notes: notes.clone().map(|m| m.get_markdown()),
             rating: rating.to_db_field(),
@@ -581,7 +832,15 @@ impl<'a> WriteOps for Transaction<'a>
         // still data. The SQLite VIRTUAL TABLE implementations
         // don't seem to handle diesel "update_into" very well.
 
-        // Full text search
+        // Recipe ingredients and wiki links
+
+        self.set_recipe_ingredients(obj_id, &notes)?;
+        self.set_object_links(obj_id, &notes)?;
+
+        // Full text search - any document text
+        // isn't changed by editing the object
+
+        let document_text = self.get_fts_row(obj_id)?.2;
 
         {
             use schema::objects_fts_query::dsl::*;
@@ -590,13 +849,14 @@ impl<'a> WriteOps for Transaction<'a>
                 .execute(self.connection)?;
         }
 
-        if title.is_some() || notes.is_some()
+        if title.is_some() || notes.is_some() || document_text.is_some()
         {
             let fts_insert_value = InsertableObjectsFts
             {
                 rowid: obj_id,
                 title: title.map(|m| m.get_search_text()),
                 notes: notes.map(|m| m.get_search_text()),
+                content: document_text,
             };
 
             diesel::insert_into(schema::objects_fts_insert::table)
@@ -653,6 +913,43 @@ impl<'a> WriteOps for Transaction<'a>
         Ok(())
     }
 
+    fn update_object_adjustments(&self, obj_id: i64, user_orientation: Option<data::Orientation>, crop: Option<data::Crop>) -> Result<(), Error>
+    {
+        let object = UpdateObjectId
+        {
+            id: obj_id,
+        };
+
+        let modified = data::Date::now();
+
+        let changeset = UpdateObjectAdjustmentsChangeset
+        {
+            modified_timestamp: modified.to_db_timestamp(),
+            modified_offset: modified.to_db_offset(),
+            user_orientation: user_orientation.map(|o| o.to_db_field()),
+            crop_left: crop.clone().map(|c| c.left),
+            crop_top: crop.clone().map(|c| c.top),
+            crop_width: crop.clone().map(|c| c.width),
+            crop_height: crop.clone().map(|c| c.height),
+        };
+
+        diesel::update(&object).set(changeset).execute(self.connection)?;
+
+        Ok(())
+    }
+
+    fn reindex_recipe(&self, obj_id: i64) -> Result<bool, Error>
+    {
+        let object = self.get_object_by_id(obj_id)?
+            .ok_or(Error::DatabaseConsistencyError{ msg: format!("Object {} doesn't exist", obj_id) })?;
+
+        let notes = data::NotesMarkdown::from_db_field(object.notes)?;
+
+        self.set_recipe_ingredients(obj_id, &notes)?;
+
+        Ok(notes.as_ref().and_then(data::Recipe::from_notes).is_some())
+    }
+
     fn update_tag(&self, tag_id: data::TagId, name: String, rating: data::Rating, censor: data::Censor, kind: data::TagKind) -> Result<(), Error>
     {
         // First, check there's no other tag with this name
@@ -691,20 +988,16 @@ impl<'a> WriteOps for Transaction<'a>
 
         // Update the full-text search
 
-        {
-            diesel::delete(schema::tags_fts_query::table.filter(schema::tags_fts_query::dsl::rowid.eq(tag_id.to_db_field())))
-                .execute(self.connection)?;
+        self.set_tag_fts(tag_id.to_db_field())?;
 
-            let fts_insert_value = InsertableTagsFts
-            {
-                rowid: tag_id.to_db_field(),
-                tag_name: name.clone(),
-            };
+        Ok(())
+    }
 
-            diesel::insert_into(schema::tags_fts_insert::table)
-                .values(vec![fts_insert_value])
-                .execute(self.connection)?;
-        }
+    fn set_tag_parent(&self, tag_id: i64, parent_id: Option<i64>) -> Result<(), Error>
+    {
+        diesel::update(schema::tags::table.filter(schema::tags::tag_id.eq(tag_id)))
+            .set(schema::tags::tag_parent_id.eq(parent_id))
+            .execute(self.connection)?;
 
         Ok(())
     }
@@ -721,12 +1014,24 @@ impl<'a> WriteOps for Transaction<'a>
             return Err(Error::DatabaseConsistencyError{ msg: format!("Tag {:?} can't be deleted - some objects are still tagged with it", tag_id) });
         }
 
-        // Delete the tag
+        // Move any child tags up to the
+        // deleted tag's parent
+
+        let tag = self.get_tag(tag_id.to_db_field())?;
+
+        diesel::update(schema::tags::table.filter(schema::tags::tag_parent_id.eq(tag_id.to_db_field())))
+            .set(schema::tags::tag_parent_id.eq(tag.tag_parent_id))
+            .execute(self.connection)?;
+
+        // Delete the tag, its aliases
         // and the Full-Text-Search entry
 
         diesel::delete(schema::tags::table.filter(schema::tags::dsl::tag_id.eq(tag_id.to_db_field())))
             .execute(self.connection)?;
 
+        diesel::delete(schema::tag_aliases::table.filter(schema::tag_aliases::tag_id.eq(tag_id.to_db_field())))
+            .execute(self.connection)?;
+
         diesel::delete(schema::tags_fts_query::table.filter(schema::tags_fts_query::dsl::rowid.eq(tag_id.to_db_field())))
             .execute(self.connection)?;
 
@@ -743,6 +1048,14 @@ impl<'a> WriteOps for Transaction<'a>
             },
             None =>
             {
+                // Adding an alias just finds the
+                // tag it belongs to
+
+                if let Some(alias) = self.get_tag_alias(&name)?
+                {
+                    return Ok(alias.tag_id);
+                }
+
                 let tag = InsertableTag
                 {
                     tag_name: name.clone(),
@@ -773,6 +1086,103 @@ impl<'a> WriteOps for Transaction<'a>
         }
     }
 
+    fn merge_tags(&self, from_tag_id: i64, into_tag_id: i64) -> Result<(), Error>
+    {
+        let from_tag = self.get_tag(from_tag_id)?;
+        let into_tag = self.get_tag(into_tag_id)?;
+
+        // Move each object over to the new tag,
+        // updating both the object_tags entries and
+        // the TagSet stored with the object
+
+        let obj_ids = schema::object_tags::table
+            .select(schema::object_tags::obj_id)
+            .filter(schema::object_tags::tag_id.eq(from_tag_id))
+            .load::<i64>(self.connection)?;
+
+        for obj_id in obj_ids
+        {
+            let object = self.get_object_by_id(obj_id)?
+                .ok_or(Error::DatabaseConsistencyError{ msg: format!("Object {} is tagged but doesn't exist", obj_id) })?;
+
+            let mut tag_set = data::TagSet::from_db_field(object.tag_set)?.to_db_set();
+
+            self.remove_object_tag(obj_id, from_tag_id)?;
+            tag_set.remove(&from_tag_id);
+
+            if tag_set.insert(into_tag_id)
+            {
+                self.add_object_tag(obj_id, into_tag_id)?;
+            }
+
+            self.update_object_tagset(obj_id, data::TagSet::from_db_set(&tag_set))?;
+        }
+
+        // Sub-tags move under the new tag - unless
+        // it's below the old tag, when it first takes
+        // the old tag's place in the hierarchy
+
+        let mut ancestor_id = into_tag.tag_parent_id;
+
+        while let Some(id) = ancestor_id
+        {
+            if id == from_tag_id
+            {
+                self.set_tag_parent(into_tag_id, from_tag.tag_parent_id)?;
+                break;
+            }
+
+            ancestor_id = self.get_tag(id)?.tag_parent_id;
+        }
+
+        diesel::update(schema::tags::table.filter(schema::tags::tag_parent_id.eq(from_tag_id)))
+            .set(schema::tags::tag_parent_id.eq(into_tag_id))
+            .execute(self.connection)?;
+
+        // The old tag's aliases, and its name,
+        // now find the new tag
+
+        diesel::update(schema::tag_aliases::table.filter(schema::tag_aliases::tag_id.eq(from_tag_id)))
+            .set(schema::tag_aliases::tag_id.eq(into_tag_id))
+            .execute(self.connection)?;
+
+        self.delete_tag(&data::TagId::from_db_field(from_tag_id))?;
+
+        self.add_tag_alias(from_tag.tag_name, into_tag_id)?;
+
+        Ok(())
+    }
+
+    fn add_tag_alias(&self, alias: String, tag_id: i64) -> Result<(), Error>
+    {
+        let entry = TagAlias
+        {
+            alias_name: alias,
+            tag_id,
+        };
+
+        diesel::replace_into(schema::tag_aliases::table)
+            .values(&entry)
+            .execute(self.connection)?;
+
+        self.set_tag_fts(tag_id)?;
+
+        Ok(())
+    }
+
+    fn remove_tag_alias(&self, alias: &str) -> Result<(), Error>
+    {
+        if let Some(existing) = self.get_tag_alias(alias)?
+        {
+            diesel::delete(schema::tag_aliases::table.filter(schema::tag_aliases::alias_name.eq(alias)))
+                .execute(self.connection)?;
+
+            self.set_tag_fts(existing.tag_id)?;
+        }
+
+        Ok(())
+    }
+
     fn add_object_tag(&self, obj_id: i64, tag_id: i64) -> Result<(), Error>
     {
         let entry = ObjectTags
@@ -798,4 +1208,206 @@ impl<'a> WriteOps for Transaction<'a>
 
         Ok(())
     }
+
+    fn add_object_relation(&self, from_obj_id: i64, to_obj_id: i64, kind: data::RelationKind) -> Result<(), Error>
+    {
+        let entry = ObjectRelation
+        {
+            from_obj_id,
+            to_obj_id,
+            relation_kind: kind.to_db_field(),
+        };
+
+        diesel::replace_into(schema::object_relations::table)
+            .values(&entry)
+            .execute(self.connection)?;
+
+        Ok(())
+    }
+
+    fn remove_object_relation(&self, q_from_obj_id: i64, q_to_obj_id: i64, kind: data::RelationKind) -> Result<(), Error>
+    {
+        use schema::object_relations::dsl::*;
+
+        diesel::delete(object_relations.filter(
+            from_obj_id.eq(q_from_obj_id)
+            .and(to_obj_id.eq(q_to_obj_id))
+            .and(relation_kind.eq(kind.to_db_field()))))
+        .execute(self.connection)?;
+
+        Ok(())
+    }
+}
+
+impl<'a> Transaction<'a>
+{
+    fn get_tag_alias(&self, name: &str) -> Result<Option<TagAlias>, Error>
+    {
+        let result = schema::tag_aliases::table
+            .filter(schema::tag_aliases::alias_name.eq(name))
+            .get_result::<TagAlias>(self.connection)
+            .optional()?;
+
+        Ok(result)
+    }
+
+    /// Aliases are searched along with the name,
+    /// so they share the tag's full-text search row
+    fn set_tag_fts(&self, tag_id: i64) -> Result<(), Error>
+    {
+        let tag = self.get_tag(tag_id)?;
+
+        let mut names = vec![tag.tag_name];
+        names.extend(self.get_tag_aliases(tag_id)?);
+
+        diesel::delete(schema::tags_fts_query::table.filter(schema::tags_fts_query::dsl::rowid.eq(tag_id)))
+            .execute(self.connection)?;
+
+        let fts_insert_value = InsertableTagsFts
+        {
+            rowid: tag_id,
+            tag_name: names.join("\n"),
+        };
+
+        diesel::insert_into(schema::tags_fts_insert::table)
+            .values(vec![fts_insert_value])
+            .execute(self.connection)?;
+
+        Ok(())
+    }
+
+    fn get_fts_row(&self, obj_id: i64) -> Result<(Option<String>, Option<String>, Option<String>), Error>
+    {
+        use schema::objects_fts_insert::dsl::*;
+
+        let row = objects_fts_insert
+            .filter(rowid.eq(obj_id))
+            .select((title, notes, content))
+            .first::<(Option<String>, Option<String>, Option<String>)>(self.connection)
+            .optional()?;
+
+        Ok(row.unwrap_or((None, None, None)))
+    }
+
+    fn set_recipe_ingredients(&self, q_obj_id: i64, notes: &Option<data::NotesMarkdown>) -> Result<(), Error>
+    {
+        {
+            use schema::recipe_ingredients::dsl::*;
+
+            diesel::delete(recipe_ingredients.filter(obj_id.eq(q_obj_id)))
+                .execute(self.connection)?;
+        }
+
+        let values = notes.as_ref()
+            .and_then(data::Recipe::from_notes)
+            .map(|recipe| recipe.get_search_names())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|name| RecipeIngredient{ obj_id: q_obj_id, name })
+            .collect::<Vec<_>>();
+
+        if !values.is_empty()
+        {
+            diesel::insert_into(schema::recipe_ingredients::table)
+                .values(values)
+                .execute(self.connection)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_object_links(&self, q_obj_id: i64, notes: &Option<data::NotesMarkdown>) -> Result<(), Error>
+    {
+        {
+            use schema::object_links::dsl::*;
+
+            diesel::delete(object_links.filter(obj_id.eq(q_obj_id)))
+                .execute(self.connection)?;
+        }
+
+        // Only links to other objects are indexed - tag
+        // links are looked up by name when they're shown
+
+        let values = notes.as_ref()
+            .map(|n| n.get_wiki_links())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|link| match link
+            {
+                data::WikiLink::Object(linked) => Some(linked.to_db_field()),
+                data::WikiLink::Tag(_) => None,
+            })
+            .filter(|linked| *linked != q_obj_id)
+            .map(|linked| ObjectLink{ obj_id: q_obj_id, linked_obj_id: linked })
+            .collect::<Vec<_>>();
+
+        if !values.is_empty()
+        {
+            diesel::insert_into(schema::object_links::table)
+                .values(values)
+                .execute(self.connection)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn ingredient_obj_ids<'a>(ingredient: &'a str) -> schema::recipe_ingredients::BoxedQuery<'a, diesel::sqlite::Sqlite, diesel::sql_types::BigInt>
+{
+    use schema::recipe_ingredients::dsl::*;
+
+    // Ingredients are matched anywhere in their
+    // name, so "flour" also finds "plain flour".
+    // Any wildcards in the search are escaped.
+
+    let escaped = ingredient.trim().to_lowercase()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    recipe_ingredients
+        .select(obj_id)
+        .filter(name.like(format!("%{}%", escaped)).escape('\\'))
+        .into_boxed()
+}
+
+fn exif_filter_obj_ids<'a>(filter: &'a data::ExifFilter) -> schema::attachment_exif::BoxedQuery<'a, diesel::sqlite::Sqlite, diesel::sql_types::BigInt>
+{
+    use schema::attachment_exif::dsl::*;
+
+    let mut query = attachment_exif
+        .select(obj_id)
+        .into_boxed();
+
+    if let Some(q_make) = &filter.make
+    {
+        query = query.filter(make.eq(q_make));
+    }
+
+    if let Some(q_model) = &filter.model
+    {
+        query = query.filter(model.eq(q_model));
+    }
+
+    if let Some(min_iso) = filter.min_iso
+    {
+        query = query.filter(iso.ge(min_iso as i32));
+    }
+
+    if let Some(max_iso) = filter.max_iso
+    {
+        query = query.filter(iso.le(max_iso as i32));
+    }
+
+    if let Some(min_focal_length) = filter.min_focal_length
+    {
+        query = query.filter(focal_length.ge(min_focal_length));
+    }
+
+    if let Some(max_focal_length) = filter.max_focal_length
+    {
+        query = query.filter(focal_length.le(max_focal_length));
+    }
+
+    query
 }
@@ -1,5 +1,334 @@
+use crate::ApiMessage;
+use crate::Error;
+use crate::StoreAccess;
+use crate::data;
+use crate::msgs;
+use crate::store::WriteOps;
+
 #[test]
 fn test_open_empty_database()
 {
     let _connection = crate::Store::new(":memory:").expect("Could not open connection");
 }
+
+fn text_attachment(filename: &str, role: data::AttachmentRole, text: Option<&str>) -> data::add::Attachment
+{
+    data::add::Attachment
+    {
+        filename: filename.to_owned(),
+        created: data::Date::now(),
+        modified: data::Date::now(),
+        mime: mime::TEXT_PLAIN,
+        orientation: None,
+        dimensions: None,
+        duration: None,
+        exif: None,
+        text: text.map(|t| t.to_owned()),
+        role,
+        bytes: filename.as_bytes().to_vec(),
+    }
+}
+
+#[test]
+fn test_document_text_search()
+{
+    let store = crate::Store::new(":memory:").expect("Could not open connection");
+
+    store.write_transaction(|ops| -> Result<(), Error>
+    {
+        let search = |text: &str| ops.get_num_objects_for_text_search(&data::get::SearchString::FullSearch(text.to_owned()));
+
+        let object_id = msgs::AddObjectRequest
+        {
+            data: data::add::ObjectData
+            {
+                title: None,
+                notes: None,
+                rating: data::Rating::NotRated,
+                censor: data::Censor::FamilyFriendly,
+                created_time: None,
+                modified_time: None,
+                activity_time: None,
+                location: None,
+                attachment: Some(text_attachment("receipt.txt", data::AttachmentRole::Primary, Some("hardware store receipt"))),
+                additional_attachments: vec![text_attachment("warranty.txt", data::AttachmentRole::Sidecar, Some("two year warranty"))],
+                tags: Vec::new(),
+                ext_ref: None,
+                user_orientation: None,
+                crop: None,
+            },
+        }.execute(ops)?.object_id;
+
+        // The text of every attachment is searchable
+
+        assert_eq!(search("hardware")?, 1);
+        assert_eq!(search("warranty")?, 1);
+
+        msgs::SetDocumentTextRequest
+        {
+            object_id,
+            text: Some("instruction manual".to_owned()),
+        }.execute(ops)?;
+
+        assert_eq!(search("manual")?, 1);
+        assert_eq!(search("warranty")?, 0);
+
+        Ok(())
+    }).expect("Document text search failed");
+}
+
+#[test]
+fn test_ingredient_search()
+{
+    let store = crate::Store::new(":memory:").expect("Could not open connection");
+
+    store.write_transaction(|ops| -> Result<(), Error>
+    {
+        let notes = data::NotesMarkdown::parse("## Ingredients\n\n- 500g plain flour\n- salt\n".to_owned())?;
+
+        let recipe = ops.add_object(None, None, None, None, Some(notes), data::Rating::NotRated, data::Censor::FamilyFriendly, None, data::TagSet::from_db_set(&Default::default()), None, None, None)?;
+
+        assert_eq!(ops.get_num_objects_with_ingredient("Flour")?, 1);
+        assert_eq!(ops.get_objects_with_ingredient_by_activity_desc("flour", 0, 10)?.len(), 1);
+
+        // Wildcards are matched literally, and an
+        // empty search doesn't match every recipe
+
+        assert_eq!(ops.get_num_objects_with_ingredient("_lour")?, 0);
+        assert_eq!(ops.get_num_objects_with_ingredient("%")?, 0);
+        assert_eq!(ops.get_num_objects_with_ingredient(" ")?, 0);
+        assert!(ops.get_objects_with_ingredient_by_activity_desc("", 0, 10)?.is_empty());
+
+        assert!(ops.reindex_recipe(recipe.to_db_field())?);
+        assert_eq!(ops.get_num_objects_with_ingredient("salt")?, 1);
+
+        Ok(())
+    }).expect("Ingredient search failed");
+}
+
+fn add_object(ops: &dyn WriteOps) -> Result<data::ObjectId, Error>
+{
+    ops.add_object(None, None, None, None, None, data::Rating::NotRated, data::Censor::FamilyFriendly, None, data::TagSet::from_db_set(&Default::default()), None, None, None)
+}
+
+fn tag_object(ops: &dyn WriteOps, object_id: &data::ObjectId, name: &str, parent: Option<&str>) -> Result<(), Error>
+{
+    msgs::UpdateObjectTagsRequest
+    {
+        object_id: object_id.clone(),
+        remove: Vec::new(),
+        add: vec![data::add::Tag
+        {
+            name: name.to_owned(),
+            kind: data::TagKind::Label,
+            rating: data::Rating::NotRated,
+            censor: data::Censor::FamilyFriendly,
+            parent: parent.map(|p| p.to_owned()),
+        }],
+    }.execute(ops)?;
+
+    Ok(())
+}
+
+fn tag_id(ops: &dyn WriteOps, name: &str) -> Result<i64, Error>
+{
+    Ok(ops.get_tag_by_name(name)?.expect("Tag not found").tag_id)
+}
+
+fn num_objects_with_tag(ops: &dyn WriteOps, tag_id: i64, include_descendants: bool) -> Result<u64, Error>
+{
+    let query = data::get::GetObjectsQuery::TagByActivityDesc{ tag_id: data::TagId::from_db_field(tag_id), include_descendants };
+
+    Ok(msgs::GetNumObjectsRequest{ query }.execute(ops)?.num_objects)
+}
+
+fn tree_names(nodes: &[data::get::TagTreeNode]) -> Vec<String>
+{
+    nodes.iter().map(|n| n.tag.name.clone()).collect()
+}
+
+#[test]
+fn test_tag_hierarchy()
+{
+    let store = crate::Store::new(":memory:").expect("Could not open connection");
+
+    store.write_transaction(|ops| -> Result<(), Error>
+    {
+        let beach = add_object(ops)?;
+        let mountain = add_object(ops)?;
+        let sand = add_object(ops)?;
+
+        tag_object(ops, &beach, "Beach", Some("Places"))?;
+        tag_object(ops, &mountain, "Mountain", Some("Places"))?;
+        tag_object(ops, &sand, "Sand", Some("Beach"))?;
+
+        let places_id = tag_id(ops, "Places")?;
+        let beach_id = tag_id(ops, "Beach")?;
+        let mountain_id = tag_id(ops, "Mountain")?;
+        let sand_id = tag_id(ops, "Sand")?;
+
+        // Descendants are only included when asked for
+
+        assert_eq!(num_objects_with_tag(ops, places_id, false)?, 0);
+        assert_eq!(num_objects_with_tag(ops, places_id, true)?, 3);
+        assert_eq!(num_objects_with_tag(ops, beach_id, false)?, 1);
+        assert_eq!(num_objects_with_tag(ops, beach_id, true)?, 2);
+
+        let roots = msgs::GetTagTreeRequest{}.execute(ops)?.roots;
+
+        assert_eq!(tree_names(&roots), vec!["Places".to_owned()]);
+        assert_eq!(tree_names(&roots[0].children), vec!["Beach".to_owned(), "Mountain".to_owned()]);
+        assert_eq!(tree_names(&roots[0].children[0].children), vec!["Sand".to_owned()]);
+
+        // A tag can't be moved below itself or its descendants
+
+        let set_parent = |tag: i64, parent: i64| msgs::SetTagParentRequest
+        {
+            tag_id: data::TagId::from_db_field(tag),
+            parent_id: Some(data::TagId::from_db_field(parent)),
+        }.execute(ops);
+
+        assert!(matches!(set_parent(places_id, places_id)?, msgs::SetTagParentResponse::WouldCreateCycle));
+        assert!(matches!(set_parent(places_id, sand_id)?, msgs::SetTagParentResponse::WouldCreateCycle));
+        assert_eq!(ops.get_tag(places_id)?.tag_parent_id, None);
+
+        assert!(matches!(set_parent(mountain_id, beach_id)?, msgs::SetTagParentResponse::Updated));
+        assert_eq!(num_objects_with_tag(ops, beach_id, true)?, 3);
+
+        Ok(())
+    }).expect("Tag hierarchy failed");
+}
+
+#[test]
+fn test_delete_unused_parent_tags()
+{
+    let store = crate::Store::new(":memory:").expect("Could not open connection");
+
+    store.write_transaction(|ops| -> Result<(), Error>
+    {
+        let sand = add_object(ops)?;
+        let forest = add_object(ops)?;
+
+        tag_object(ops, &sand, "Beach", Some("Places"))?;
+        tag_object(ops, &sand, "Sand", Some("Beach"))?;
+        tag_object(ops, &forest, "Forest", Some("Parks"))?;
+        tag_object(ops, &forest, "Parks", None)?;
+
+        let remove = |object_id: &data::ObjectId, name: &str| -> Result<(), Error>
+        {
+            msgs::UpdateObjectTagsRequest
+            {
+                object_id: object_id.clone(),
+                remove: vec![data::TagId::from_db_field(tag_id(ops, name)?)],
+                add: Vec::new(),
+            }.execute(ops)?;
+
+            Ok(())
+        };
+
+        // Tags with children are kept, until the
+        // last child is deleted
+
+        remove(&sand, "Beach")?;
+        assert!(ops.get_tag_by_name("Beach")?.is_some());
+
+        remove(&sand, "Sand")?;
+        assert!(ops.get_tag_by_name("Sand")?.is_none());
+        assert!(ops.get_tag_by_name("Beach")?.is_none());
+        assert!(ops.get_tag_by_name("Places")?.is_none());
+
+        // Parents that have objects are kept
+
+        remove(&forest, "Forest")?;
+        assert!(ops.get_tag_by_name("Forest")?.is_none());
+        assert!(ops.get_tag_by_name("Parks")?.is_some());
+
+        Ok(())
+    }).expect("Deleting tags failed");
+}
+
+fn object_tags(ops: &dyn WriteOps, object_id: &data::ObjectId) -> Result<Vec<i64>, Error>
+{
+    let object = ops.get_object_by_id(object_id.to_db_field())?.expect("Object not found");
+
+    Ok(data::TagSet::from_db_field(object.tag_set)?.to_db_set().into_iter().collect())
+}
+
+#[test]
+fn test_merge_tags()
+{
+    let store = crate::Store::new(":memory:").expect("Could not open connection");
+
+    store.write_transaction(|ops| -> Result<(), Error>
+    {
+        let both = add_object(ops)?;
+        let only_from = add_object(ops)?;
+
+        tag_object(ops, &both, "Holiday", None)?;
+        tag_object(ops, &both, "Vacation", None)?;
+        tag_object(ops, &only_from, "Vacation", None)?;
+
+        let from = tag_id(ops, "Vacation")?;
+        let into = tag_id(ops, "Holiday")?;
+
+        let response = msgs::MergeTagsRequest
+        {
+            from: data::TagId::from_db_field(from),
+            into: data::TagId::from_db_field(into),
+        }.execute(ops)?;
+
+        assert!(matches!(response, msgs::MergeTagsResponse::Merged));
+
+        // Both objects now carry just the new tag
+
+        assert_eq!(object_tags(ops, &both)?, vec![into]);
+        assert_eq!(object_tags(ops, &only_from)?, vec![into]);
+        assert_eq!(ops.get_num_objects_with_tags(&[into])?, 2);
+        assert!(ops.get_tag(from).is_err());
+
+        // The old name is an alias of the new tag
+
+        assert_eq!(tag_id(ops, "Vacation")?, into);
+        assert_eq!(ops.get_tag_aliases(into)?, vec!["Vacation".to_owned()]);
+        assert_eq!(ops.find_or_add_tag("Vacation".to_owned(), data::TagKind::Label, data::Rating::NotRated, data::Censor::FamilyFriendly)?, into);
+
+        Ok(())
+    }).expect("Merge failed");
+}
+
+#[test]
+fn test_merge_tag_into_descendant()
+{
+    let store = crate::Store::new(":memory:").expect("Could not open connection");
+
+    store.write_transaction(|ops| -> Result<(), Error>
+    {
+        let object = add_object(ops)?;
+
+        tag_object(ops, &object, "Places", None)?;
+        tag_object(ops, &object, "Beach", Some("Places"))?;
+        tag_object(ops, &object, "Sand", Some("Places"))?;
+
+        let from = tag_id(ops, "Places")?;
+        let into = tag_id(ops, "Beach")?;
+        let sibling = tag_id(ops, "Sand")?;
+
+        msgs::MergeTagsRequest
+        {
+            from: data::TagId::from_db_field(from),
+            into: data::TagId::from_db_field(into),
+        }.execute(ops)?;
+
+        // The descendant takes the old tag's place, and
+        // the old tag's other children move below it
+
+        assert_eq!(ops.get_tag(into)?.tag_parent_id, None);
+        assert_eq!(ops.get_tag(sibling)?.tag_parent_id, Some(into));
+        assert_eq!(object_tags(ops, &object)?, vec![into, sibling]);
+
+        assert_eq!(tag_id(ops, "Places")?, into);
+        assert_eq!(ops.find_or_add_tag("Places".to_owned(), data::TagKind::Label, data::Rating::NotRated, data::Censor::FamilyFriendly)?, into);
+
+        Ok(())
+    }).expect("Merge failed");
+}